@@ -0,0 +1,92 @@
+// Throughput of the CborWF decoders. The wire holds a batch of frames that are all available at once, like
+// on a busy connection.
+//
+// Run with: `cargo bench --bench decoder`
+//
+use
+{
+	criterion      :: { criterion_group, criterion_main, Criterion, BenchmarkId, Throughput } ,
+	futures        :: { executor::block_on, io::Cursor, Stream, StreamExt                  } ,
+	thespis_remote :: { CborWF, Decoder, DecoderNoHeap, DecoderBuffered, WireErr           } ,
+};
+
+
+// The number of frames in a batch.
+//
+const FRAMES: usize = 1000;
+
+// The size of the header of CborWF: length, sid and cid.
+//
+const HEADER: usize = 32;
+
+
+// A batch of frames with a payload of `size` bytes.
+//
+fn wire( size: usize ) -> Vec<u8>
+{
+	let mut wire = Vec::with_capacity( FRAMES * ( HEADER + size ) );
+
+	for i in 0..FRAMES as u64
+	{
+		wire.extend_from_slice( &( ( HEADER + size ) as u64 ).to_le_bytes() );
+		wire.extend_from_slice( &7u128.to_le_bytes()                        );
+		wire.extend_from_slice( &( i + 1 ).to_le_bytes()                    );
+		wire.extend( std::iter::repeat_n( i as u8, size )                   );
+	}
+
+	wire
+}
+
+
+// Decode all frames and verify we got them.
+//
+fn decode( mut decoder: impl Stream< Item = Result<CborWF, WireErr> > + Unpin )
+{
+	block_on( async
+	{
+		let mut count = 0;
+
+		while let Some( frame ) = decoder.next().await
+		{
+			frame.expect( "decode frame" );
+			count += 1;
+		}
+
+		assert_eq!( FRAMES, count );
+	});
+}
+
+
+fn decoders( c: &mut Criterion )
+{
+	let mut group = c.benchmark_group( "decoder" );
+
+	for size in [ 64, 1024, 16 * 1024 ]
+	{
+		let wire     = wire( size );
+		let max_size = HEADER + size;
+
+		group.throughput( Throughput::Bytes( wire.len() as u64 ) );
+
+		group.bench_with_input( BenchmarkId::new( "Decoder", size ), &wire, |b, wire|
+		{
+			b.iter( || decode( Decoder::new( Cursor::new( wire.clone() ), max_size ) ) )
+		});
+
+		group.bench_with_input( BenchmarkId::new( "DecoderNoHeap", size ), &wire, |b, wire|
+		{
+			b.iter( || decode( DecoderNoHeap::new( Cursor::new( wire.clone() ), max_size ) ) )
+		});
+
+		group.bench_with_input( BenchmarkId::new( "DecoderBuffered", size ), &wire, |b, wire|
+		{
+			b.iter( || decode( DecoderBuffered::new( Cursor::new( wire.clone() ), max_size ) ) )
+		});
+	}
+
+	group.finish();
+}
+
+
+criterion_group!( benches, decoders );
+criterion_main! ( benches           );
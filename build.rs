@@ -4,6 +4,8 @@ use rustc_version::{ version_meta, Channel };
 
 fn main()
 {
+	println!( "cargo:rustc-check-cfg=cfg(stable, beta, nightly, rustc_dev)" );
+
 	// Set cfg flags depending on release channel
 	//
 	match version_meta().unwrap().channel
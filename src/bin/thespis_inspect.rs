@@ -0,0 +1,106 @@
+//! Decode captures written by `Recorder` and raw byte streams of `CborWF` into JSON, one frame per line.
+//!
+//! ```text
+//! thespis_inspect [--names <table>] [--max-size <bytes>] <file>
+//! ```
+//!
+//! Use `-` as file to read from stdin. The table of service names can be written by your application with
+//! `ServiceID::export_names`. Without it, only the services reserved by thespis_remote have names.
+//
+#![ allow( clippy::suspicious_else_formatting ) ]
+
+use
+{
+	thespis_remote :: { Capture, ServiceID, inspect_record, inspect_stream               } ,
+	std            :: { fs::File, io::{ self, BufReader, Read, Write }, process::ExitCode } ,
+};
+
+
+const USAGE: &str = "usage: thespis_inspect [--names <table>] [--max-size <bytes>] <file>";
+
+/// The default max size of frames in byte streams.
+//
+const MAX_SIZE: usize = 16 * 1024 * 1024;
+
+
+
+fn main() -> ExitCode
+{
+	match run()
+	{
+		Ok (()) => ExitCode::SUCCESS,
+
+		Err(e) =>
+		{
+			eprintln!( "thespis_inspect: {e}" );
+			ExitCode::FAILURE
+		}
+	}
+}
+
+
+
+fn run() -> Result<(), String>
+{
+	let mut names    = None;
+	let mut max_size = MAX_SIZE;
+	let mut input    = None;
+
+	let mut args = std::env::args().skip( 1 );
+
+	while let Some( arg ) = args.next()
+	{
+		match arg.as_str()
+		{
+			"--names"       => names    = Some( args.next().ok_or( USAGE )? ),
+			"--max-size"    => max_size = args.next().ok_or( USAGE )?.parse().map_err( |_| USAGE )?,
+			"-h" | "--help" => { println!( "{USAGE}" ); return Ok(()) }
+
+			_ if input.is_none() => input = Some( arg ),
+			_                    => return Err( USAGE.to_string() ),
+		}
+	}
+
+	let input = input.ok_or( USAGE )?;
+
+	if let Some( names ) = names
+	{
+		let table = File::open( &names ).map_err( |e| format!( "{names}: {e}" ) )?;
+
+		ServiceID::import_names( BufReader::new( table ) ).map_err( |e| format!( "{names}: {e}" ) )?;
+	}
+
+	let mut data = Vec::new();
+
+	match input.as_str()
+	{
+		"-" => io::stdin().read_to_end( &mut data ),
+		_   => File::open( &input ).and_then( |mut f| f.read_to_end( &mut data ) ),
+	}
+	.map_err( |e| format!( "{input}: {e}" ) )?;
+
+
+	let frames = if Capture::is_capture( &data )
+	{
+		let capture = Capture::read( data.as_slice() ).map_err( |e| format!( "{input}: {e}" ) )?;
+
+		capture.records().iter().map( inspect_record ).collect()
+	}
+
+	else
+	{
+		inspect_stream( data, max_size )
+	};
+
+
+	let mut out = io::stdout().lock();
+
+	for frame in frames
+	{
+		let frame = frame.map_err( |e| format!( "{input}: {e}" ) )?;
+
+		writeln!( out, "{frame}" ).map_err( |e| e.to_string() )?;
+	}
+
+	Ok(())
+}
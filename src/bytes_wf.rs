@@ -0,0 +1,415 @@
+use
+{
+	crate :: { import::*, Peer, PeerErr, PeerExec, BoundsIn, BoundsOut, wire_format::*                } ,
+	crate :: { cbor_wf::{ LEN_LEN, LEN_SID, LEN_CID, IDX_LEN, IDX_SID, IDX_CID, IDX_MSG, LEN_HEADER } } ,
+	bytes :: { Bytes, BytesMut, BufMut                                                                } ,
+};
+
+
+mod encoder;
+mod decoder;
+
+pub use encoder::*;
+pub use decoder::*;
+
+
+
+/// A wire format with the same layout as [`CborWF`](crate::CborWF), backed by [`Bytes`].
+///
+/// Messages that come in from the network are frozen. Cloning them only bumps a reference count, which
+/// makes fanning out a message, like [`PubSub`](crate::PubSub) does, cheap. [`BytesWF::msg_bytes`] gives you
+/// the payload without copying.
+///
+/// Messages you build are mutable until you call [`BytesWF::freeze`]. Cloning those copies the buffer.
+/// Writing to or changing the header of a frozen message copies it only if it is shared.
+///
+/// The two wire formats are compatible on the wire, so a peer using `BytesWF` can talk to a peer using
+/// `CborWF`. The codec of the service maps is unrelated to the wire format.
+///
+/// Compression is not supported, [`WireFormat::set_compression`] is ignored. The handshake advertises no compression
+/// algorithm, so a `CborWF` remote won't compress the frames it sends to us.
+//
+#[ derive( Debug, Clone ) ]
+//
+pub struct BytesWF
+{
+	data: Data,
+}
+
+
+#[ derive( Debug, Clone ) ]
+//
+enum Data
+{
+	Building( BytesMut ),
+	Frozen  ( Bytes    ),
+}
+
+
+
+impl Message for BytesWF
+{
+	type Return = Result<(), PeerErr>;
+}
+
+
+impl BytesWF
+{
+	/// Get direct access to the buffer.
+	//
+	pub(crate) fn as_buf( &self ) -> &[u8]
+	{
+		match &self.data
+		{
+			Data::Building( b ) => b,
+			Data::Frozen  ( b ) => b,
+		}
+	}
+
+
+	/// Get mutable access to the buffer. Copies the data if it's frozen and shared.
+	//
+	fn as_mut( &mut self ) -> &mut BytesMut
+	{
+		if let Data::Frozen( bytes ) = &mut self.data
+		{
+			let buf = match std::mem::take( bytes ).try_into_mut()
+			{
+				Ok ( buf   ) => buf,
+				Err( bytes ) => BytesMut::from( &bytes[..] ),
+			};
+
+			self.data = Data::Building( buf );
+		}
+
+		match &mut self.data
+		{
+			Data::Building( b ) => b,
+			Data::Frozen  ( _ ) => unreachable!(),
+		}
+	}
+
+
+	fn set_len( &mut self, len: u64 ) -> &mut Self
+	{
+		self.as_mut()[ IDX_LEN..IDX_LEN+LEN_LEN ].copy_from_slice( &len.to_le_bytes() );
+		self
+	}
+
+
+	fn read_u64( &self, idx: usize ) -> u64
+	{
+		let mut field = [0u8; 8];
+		field.copy_from_slice( &self.as_buf()[ idx..idx+8 ] );
+
+		u64::from_le_bytes( field )
+	}
+
+
+	/// Make the message immutable, so clones are cheap. Messages coming in from the network are frozen already.
+	//
+	pub fn freeze( &mut self )
+	{
+		if let Data::Building( buf ) = &mut self.data
+		{
+			self.data = Data::Frozen( buf.split().freeze() );
+		}
+	}
+
+
+	/// The serialized payload. If the message is frozen, this doesn't copy.
+	//
+	pub fn msg_bytes( &self ) -> Bytes
+	{
+		match &self.data
+		{
+			Data::Building( b ) => Bytes::copy_from_slice( &b[ IDX_MSG.. ] ),
+			Data::Frozen  ( b ) => b.slice( IDX_MSG.. ),
+		}
+	}
+
+
+	/// The entire message, header included. If the message is frozen, this doesn't copy.
+	//
+	pub fn into_bytes( self ) -> Bytes
+	{
+		match self.data
+		{
+			Data::Building( b ) => b.freeze(),
+			Data::Frozen  ( b ) => b,
+		}
+	}
+}
+
+
+
+impl WireFormat for BytesWF
+{
+	/// Create a Peer directly from an asynchronous stream. See [`CborWF::create_peer`](crate::CborWF::create_peer).
+	//
+	fn create_peer
+	(
+		name          : impl AsRef<str>                                      ,
+		socket        : impl AsyncRead + AsyncWrite + Unpin + Send + 'static ,
+		max_size_read : usize                                                ,
+		max_size_write: usize                                                ,
+		exec          : impl PeerExec<BytesWF>                               ,
+		bp            : Option< Arc<Semaphore> >                             ,
+		grace_period  : Option< Duration       >                             ,
+	)
+
+		-> Result< (Peer<BytesWF>, Mailbox<Peer<BytesWF>>, WeakAddr<Peer<BytesWF>>), PeerErr >
+
+	{
+		let (stream, sink) = Self::frame( socket, max_size_read, max_size_write );
+
+		Peer::from_framed( name, stream, sink, exec, bp, grace_period )
+	}
+
+
+	/// Frame the connection with [BytesDecoder] and [BytesEncoder].
+	//
+	fn frame
+	(
+		socket        : impl AsyncRead + AsyncWrite + Unpin + Send + 'static ,
+		max_size_read : usize                                                ,
+		max_size_write: usize                                                ,
+	)
+
+		-> ( impl BoundsIn<Self>, impl BoundsOut<Self> )
+
+	{
+		let (reader, writer) = socket.split();
+
+		let stream = BytesDecoder::new( reader, max_size_read  );
+		let sink   = BytesEncoder::new( writer, max_size_write );
+
+		(stream, sink)
+	}
+
+
+	fn sid( &self ) -> ServiceID
+	{
+		let mut field = [0u8; LEN_SID];
+		field.copy_from_slice( &self.as_buf()[ IDX_SID..IDX_SID+LEN_SID ] );
+
+		u128::from_le_bytes( field ).into()
+	}
+
+
+	fn set_sid( &mut self, sid: ServiceID ) -> &mut Self
+	{
+		self.as_mut()[ IDX_SID..IDX_SID+LEN_SID ].copy_from_slice( &u128::from( sid ).to_le_bytes() );
+		self
+	}
+
+
+	fn cid( &self ) -> ConnID
+	{
+		self.read_u64( IDX_CID ).into()
+	}
+
+
+	fn set_cid( &mut self, cid: ConnID ) -> &mut Self
+	{
+		self.as_mut()[ IDX_CID..IDX_CID+LEN_CID ].copy_from_slice( &u64::from( cid ).to_le_bytes() );
+		self
+	}
+
+
+	fn msg( &self ) -> &[u8]
+	{
+		&self.as_buf()[ IDX_MSG.. ]
+	}
+
+
+	fn len( &self ) -> u64
+	{
+		self.read_u64( IDX_LEN )
+	}
+
+
+	fn with_capacity( size: usize ) -> Self
+	{
+		let mut buf = BytesMut::with_capacity( size + LEN_HEADER );
+		buf.put_bytes( 0, LEN_HEADER );
+
+		let mut wf = Self{ data: Data::Building( buf ) };
+		wf.set_len( LEN_HEADER as u64 );
+
+		wf
+	}
+}
+
+
+
+impl io::Write for BytesWF
+{
+	fn write( &mut self, buf: &[u8] ) -> io::Result<usize>
+	{
+		self.as_mut().extend_from_slice( buf );
+
+		let len = self.as_buf().len() as u64;
+		self.set_len( len );
+
+		Ok( buf.len() )
+	}
+
+	fn flush( &mut self ) -> io::Result<()>
+	{
+		Ok(())
+	}
+}
+
+
+
+impl Default for BytesWF
+{
+	/// An empty message with sid and cid zeroed.
+	//
+	fn default() -> Self
+	{
+		Self::with_capacity( LEN_HEADER )
+	}
+}
+
+
+
+impl PartialEq for BytesWF
+{
+	fn eq( &self, other: &Self ) -> bool
+	{
+		self.as_buf() == other.as_buf()
+	}
+}
+
+impl Eq for BytesWF {}
+
+
+
+impl TryFrom< Bytes > for BytesWF
+{
+	type Error = WireErr;
+
+	/// The length field must match the length of `data`.
+	//
+	fn try_from( data: Bytes ) -> Result< Self, WireErr >
+	{
+		if data.len() < LEN_HEADER
+		{
+			return Err( WireErr::Deserialize{ context: "BytesWF: not enough bytes even for the header.".to_string() } );
+		}
+
+		let wf = Self{ data: Data::Frozen( data ) };
+
+		if wf.len() != wf.as_buf().len() as u64
+		{
+			return Err( WireErr::Deserialize{ context: "BytesWF: length field doesn't match the data.".to_string() } );
+		}
+
+		Ok( wf )
+	}
+}
+
+
+
+#[ cfg(test) ]
+//
+mod tests
+{
+	// Tests:
+	//
+	// - header fields survive freezing and thawing
+	// - clones of frozen messages share the buffer
+	// - TestSuite for BytesEncoder/BytesDecoder
+	//
+	use
+	{
+		super           :: { *, assert_eq                      } ,
+		crate           :: { TestSuite, MockConnection         } ,
+		futures::io     :: { WriteHalf, ReadHalf, AsyncReadExt } ,
+		async_executors :: { AsyncStd                          } ,
+		std::io         :: { Write                             } ,
+	};
+
+
+	#[test]
+	//
+	fn header_fields()
+	{
+		let mut wf  = BytesWF::default();
+		let     sid = ServiceID::from_seed( &[ 1, 2, 3 ] );
+		let     cid = ConnID::random();
+
+		assert_eq!( LEN_HEADER as u64, wf.len() );
+		assert!( wf.sid().is_null() );
+		assert!( wf.cid().is_null() );
+
+		wf.set_sid( sid );
+		wf.set_cid( cid );
+		wf.write_all( b"payload" ).expect( "write payload" );
+		wf.freeze();
+
+		assert_eq!( sid                             , wf.sid()       );
+		assert_eq!( cid                             , wf.cid()       );
+		assert_eq!( ( LEN_HEADER + 7 ) as u64       , wf.len()       );
+		assert_eq!( &b"payload"[..]                 , wf.msg()       );
+		assert_eq!( Bytes::from_static( b"payload" ), wf.msg_bytes() );
+
+		// Thaw it again.
+		//
+		let cid2 = ConnID::random();
+		wf.set_cid( cid2 );
+
+		assert_eq!( sid , wf.sid() );
+		assert_eq!( cid2, wf.cid() );
+		assert_eq!( &b"payload"[..], wf.msg() );
+	}
+
+
+	#[test]
+	//
+	fn cheap_clone()
+	{
+		let mut wf = BytesWF::default();
+		wf.write_all( b"payload" ).expect( "write payload" );
+		wf.freeze();
+
+		let mut clone = wf.clone();
+
+		assert_eq!( wf.as_buf().as_ptr(), clone.as_buf().as_ptr() );
+
+		// Changing a shared message doesn't affect the original.
+		//
+		clone.set_cid( ConnID::random() );
+
+		assert_ne!( wf.as_buf().as_ptr(), clone.as_buf().as_ptr() );
+		assert!( wf.cid().is_null() );
+	}
+
+
+
+	type Reader = ReadHalf <Box<dyn MockConnection>>;
+	type Writer = WriteHalf<Box<dyn MockConnection>>;
+
+
+	fn frame( socket: Box<dyn MockConnection>, max_size: usize ) -> (BytesEncoder<Writer>, BytesDecoder<Reader>)
+	{
+		let (reader, writer) = socket.split();
+
+		let stream = BytesDecoder::new( reader, max_size );
+		let sink   = BytesEncoder::new( writer, max_size );
+
+		(sink, stream)
+	}
+
+
+	#[async_std::test]
+	//
+	async fn bytes_decoder_encoder()
+	{
+		let test_suite = TestSuite::new( frame );
+
+		test_suite.run( AsyncStd ).await;
+	}
+}
@@ -0,0 +1,163 @@
+use
+{
+	crate :: { import::*, BytesWF, WireErr, cbor_wf::{ LEN_LEN, LEN_HEADER } } ,
+	bytes :: { BytesMut                                                      } ,
+};
+
+
+// How many bytes we try to read at once, unless the frame we are reading is bigger.
+//
+const READ_SIZE: usize = 8 * 1024;
+
+
+
+/// Decodes a stream of bytes into a stream of [BytesWF] messages.
+///
+/// All messages are read into one buffer, which is reused once the messages split off of it have been
+/// dropped. The messages returned are frozen, so they are cheap to clone.
+///
+/// [AsyncRead] needs an initialized buffer. The part of the buffer after the data we have read is kept
+/// between reads, so every byte only needs to be zeroed once, not every time we poll.
+//
+#[ derive(Debug) ]
+//
+pub struct BytesDecoder<T>
+{
+	byte_stream: T        ,
+	buffer     : BytesMut ,
+	filled     : usize    ,
+	closed     : bool     ,
+	max_size   : usize    ,
+}
+
+
+impl<T> BytesDecoder<T>
+{
+	/// Create a new decoder.
+	//
+	pub fn new( byte_stream: T, max_size: usize ) -> Self
+	{
+		Self
+		{
+			byte_stream                                         ,
+			max_size                                            ,
+			buffer     : BytesMut::with_capacity( READ_SIZE )   ,
+			filled     : 0                                      ,
+			closed     : false                                  ,
+		}
+	}
+
+
+	/// The length of the frame at the start of the buffer, if we have read the length field.
+	//
+	fn frame_len( &self ) -> Option<usize>
+	{
+		if self.filled < LEN_LEN { return None }
+
+		let mut field = [0u8; LEN_LEN];
+		field.copy_from_slice( &self.buffer[ ..LEN_LEN ] );
+
+		// If it doesn't fit in usize, it's definitely bigger than max_size.
+		//
+		Some( usize::try_from( u64::from_le_bytes( field ) ).unwrap_or( usize::MAX ) )
+	}
+}
+
+
+
+impl<T> Stream for BytesDecoder<T>
+
+	where T: AsyncRead + Unpin
+{
+	type Item = Result<BytesWF, WireErr>;
+
+
+	fn poll_next( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll< Option<Self::Item> >
+	{
+		if self.closed
+		{
+			return Poll::Ready( None );
+		}
+
+		loop
+		{
+			let len = self.frame_len();
+
+			if let Some( len ) = len
+			{
+				if len < LEN_HEADER
+				{
+					self.closed = true;
+
+					let context = format!( "BytesDecoder: length field ({len}) is smaller than the header." );
+
+					return Poll::Ready( Some(Err( WireErr::Deserialize{ context } )) );
+				}
+
+				if len > self.max_size
+				{
+					self.closed = true;
+
+					let err = WireErr::MessageSizeExceeded
+					{
+						size    : len                        ,
+						max_size: self.max_size              ,
+						context : "BytesDecoder".to_string() ,
+					};
+
+					return Poll::Ready( Some(Err( err )) );
+				}
+
+				// We have an entire frame.
+				//
+				if self.filled >= len
+				{
+					let frame = self.buffer.split_to( len ).freeze();
+					self.filled -= len;
+
+					return Poll::Ready( Some( BytesWF::try_from( frame ) ) );
+				}
+			}
+
+
+			// Read more. Make sure there is room for at least the rest of the current frame. Only the part of
+			// the buffer that wasn't initialized before gets zeroed.
+			//
+			let filled = self.filled;
+			let wanted = len.unwrap_or( LEN_LEN ).saturating_sub( filled ).max( READ_SIZE );
+
+			if self.buffer.len() < filled + wanted
+			{
+				self.buffer.resize( filled + wanted, 0 );
+			}
+
+			let this = &mut *self;
+
+			match Pin::new( &mut this.byte_stream ).poll_read( cx, &mut this.buffer[ filled.. ] )
+			{
+				Poll::Pending => return Poll::Pending,
+
+				// The connection was closed. If it was in the middle of a frame, the frame is lost.
+				//
+				Poll::Ready( Ok(0) ) =>
+				{
+					self.closed = true;
+
+					return Poll::Ready( None );
+				}
+
+				Poll::Ready( Ok(read) ) =>
+				{
+					self.filled += read;
+				}
+
+				Poll::Ready( Err(e) ) =>
+				{
+					self.closed = true;
+
+					return Poll::Ready( Some(Err( WireErr::from(e) )) );
+				}
+			}
+		}
+	}
+}
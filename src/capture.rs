@@ -0,0 +1,626 @@
+//! Record the frames of a connection to a capture file and replay them into a fresh peer later.
+//!
+//! A [Recorder] wraps the incoming stream and outgoing sink that are handed to [`Peer::from_framed`](crate::Peer::from_framed).
+//! Every frame that passes is written to the capture with the time elapsed since the recorder was created. The
+//! capture only contains the fields of the [WireFormat] trait, so it can be replayed with any wire format:
+//!
+//! ```text
+//! magic   | direction | micros since start | sid     | connID | length of payload | payload  |
+//! 8 bytes | u8        | u64 LE             | u128 LE | u64 LE | u32 LE            | variable |
+//!           ^------------------------ repeated for every frame ------------------------------^
+//! ```
+//!
+//! [`Capture::replay`] feeds the incoming frames of a capture back into a peer. To be deterministic it waits until the peer
+//! has sent as many frames as it had sent at that point of the recording before feeding the next incoming frame, and the
+//! stream only ends once the peer has sent all the frames from the recording. The frames the peer sends are available
+//! through [Replayed] so they can be compared with the recording.
+//
+use
+{
+	crate :: { import::*, WireFormat, WireErr, ServiceID, ConnID } ,
+	std   :: { sync::mpsc as mpsc_std, thread                    } ,
+};
+
+
+/// The first bytes of every capture file. The last byte is the version of the format.
+//
+const MAGIC: [u8; 8] = *b"THESCAP\x01";
+
+const LEN_RECORD_HEADER: usize = 1 + 8 + 16 + 8 + 4;
+
+
+
+/// Whether a frame was received or sent by the peer that was recorded.
+//
+#[ derive( Debug, Clone, Copy, PartialEq, Eq, Hash ) ]
+//
+pub enum Direction
+{
+	/// The frame came from the remote.
+	//
+	Incoming,
+
+	/// The frame was sent to the remote.
+	//
+	Outgoing,
+}
+
+
+impl Direction
+{
+	fn to_u8( self ) -> u8
+	{
+		match self
+		{
+			Direction::Incoming => 0,
+			Direction::Outgoing => 1,
+		}
+	}
+
+
+	fn from_u8( byte: u8 ) -> Result<Self, WireErr>
+	{
+		match byte
+		{
+			0 => Ok( Direction::Incoming ),
+			1 => Ok( Direction::Outgoing ),
+
+			_ => Err( WireErr::Deserialize{ context: format!( "Capture: invalid direction: {byte}" ) } ),
+		}
+	}
+}
+
+
+
+/// One frame in a capture.
+//
+#[ derive( Debug, Clone, PartialEq, Eq ) ]
+//
+pub struct Record
+{
+	/// Whether the frame was received or sent.
+	//
+	pub direction: Direction,
+
+	/// Time elapsed since the recorder was created. Always zero for frames that come from a replay.
+	//
+	pub elapsed: Duration,
+
+	/// The service id of the frame.
+	//
+	pub sid: ServiceID,
+
+	/// The connection id of the frame.
+	//
+	pub cid: ConnID,
+
+	/// The serialized message.
+	//
+	pub msg: Vec<u8>,
+}
+
+
+impl Record
+{
+	fn from_wf( direction: Direction, elapsed: Duration, wf: &impl WireFormat ) -> Self
+	{
+		Self { direction, elapsed, sid: wf.sid(), cid: wf.cid(), msg: wf.msg().to_vec() }
+	}
+
+
+	pub(crate) fn to_wf<Wf: WireFormat>( &self ) -> Result<Wf, WireErr>
+	{
+		let mut wf = Wf::with_capacity( self.msg.len() );
+
+		wf.set_sid( self.sid );
+		wf.set_cid( self.cid );
+		wf.write_all( &self.msg )?;
+
+		Ok( wf )
+	}
+
+
+	fn write( &self, out: &mut impl io::Write ) -> io::Result<()>
+	{
+		let len = u32::try_from( self.msg.len() ).map_err( |_| io::Error::from( io::ErrorKind::InvalidInput ) )?;
+
+		let mut buf = Vec::with_capacity( LEN_RECORD_HEADER + self.msg.len() );
+
+		let micros = u64::try_from( self.elapsed.as_micros() ).unwrap_or( u64::MAX );
+
+		buf.write_u8                  ( self.direction.to_u8() )?;
+		buf.write_u64 ::<LittleEndian>( micros                 )?;
+		buf.write_u128::<LittleEndian>( self.sid.into()        )?;
+		buf.write_u64 ::<LittleEndian>( self.cid.into()        )?;
+		buf.write_u32 ::<LittleEndian>( len                    )?;
+		buf.extend_from_slice         ( &self.msg              );
+
+		out.write_all( &buf )
+	}
+
+
+	fn read( data: &mut &[u8] ) -> Result<Self, WireErr>
+	{
+		if data.len() < LEN_RECORD_HEADER
+		{
+			return Err( WireErr::Deserialize{ context: "Capture: truncated record header.".to_string() } );
+		}
+
+		let direction = Direction::from_u8( data.read_u8()? )?;
+		let elapsed   = Duration::from_micros( data.read_u64::<LittleEndian>()? );
+		let sid       = data.read_u128::<LittleEndian>()?.into();
+		let cid       = data.read_u64::<LittleEndian>()?.into();
+		let len       = data.read_u32::<LittleEndian>()? as usize;
+
+		if data.len() < len
+		{
+			return Err( WireErr::Deserialize{ context: "Capture: truncated record payload.".to_string() } );
+		}
+
+		let (msg, rest) = data.split_at( len );
+		let msg         = msg.to_vec();
+		*data           = rest;
+
+		Ok( Self { direction, elapsed, sid, cid, msg } )
+	}
+}
+
+
+
+// What the recorder asks of the writer thread.
+//
+enum Command
+{
+	Record( Record ),
+
+	// Flush the output, then notify the sender if it wants to know.
+	//
+	Flush( Option< oneshot::Sender<()> > ),
+}
+
+
+
+// Runs on the writer thread until all recorders are dropped.
+//
+fn write_capture( mut out: Box< dyn io::Write + Send + 'static >, commands: mpsc_std::Receiver<Command> )
+{
+	let flush = |out: &mut dyn io::Write|
+	{
+		if let Err( e ) = out.flush()
+		{
+			error!( "Recorder: failed to flush capture: {e}" );
+		}
+	};
+
+	for command in commands
+	{
+		match command
+		{
+			// A failing capture shouldn't take down the connection it is supposed to help debug.
+			//
+			Command::Record( record ) => if let Err( e ) = record.write( &mut out )
+			{
+				error!( "Recorder: failed to write frame to capture: {e}" );
+			}
+
+			Command::Flush( done ) =>
+			{
+				flush( &mut out );
+
+				if let Some( done ) = done
+				{
+					let _ = done.send(());
+				}
+			}
+		}
+	}
+
+	flush( &mut out );
+}
+
+
+
+/// Records the frames of a connection to a capture. Cheap to clone, clones write to the same capture. Wrap the output in a
+/// [BufWriter](std::io::BufWriter) if it's a file, the capture is flushed every time the outgoing sink is flushed.
+///
+/// The output is written by a dedicated thread, so a slow disk doesn't hold up the connection. Frames wait in memory
+/// until the thread catches up. Use [`Recorder::flush`] to wait until everything recorded so far is written. The thread
+/// stops once the recorder and all the taps are dropped.
+///
+/// ```ignore
+/// let recorder             = Recorder::new( BufWriter::new( File::create( "peer.capture" )? ) )?;
+/// let (incoming, outgoing) = recorder.tap( incoming, outgoing );
+///
+/// let (peer, peer_mb, peer_addr) = Peer::from_framed( "server", incoming, outgoing, exec, None, None )?;
+/// ```
+//
+#[ derive( Clone ) ]
+//
+pub struct Recorder
+{
+	start   : Instant                                    ,
+	commands: Arc< Mutex< mpsc_std::Sender<Command> > > ,
+}
+
+
+impl Recorder
+{
+	/// Create a recorder that writes to `out`. Writes the header of the capture right away and starts the writer thread.
+	//
+	pub fn new( mut out: impl io::Write + Send + 'static ) -> Result<Self, WireErr>
+	{
+		out.write_all( &MAGIC )?;
+
+		let (tx, rx) = mpsc_std::channel();
+		let out      = Box::new( out );
+
+		thread::Builder::new()
+
+			.name( "thespis_recorder".to_string() )
+			.spawn( move || write_capture( out, rx ) )?
+		;
+
+		Ok( Self { start: Instant::now(), commands: Arc::new( Mutex::new( tx ) ) } )
+	}
+
+
+	/// Wrap the incoming stream and outgoing sink of a connection so every frame gets recorded.
+	//
+	pub fn tap<In, Out>( &self, incoming: In, outgoing: Out ) -> (TapIn<In>, TapOut<Out>)
+	{
+		( TapIn{ inner: incoming, recorder: self.clone() }, TapOut{ inner: outgoing, recorder: self.clone() } )
+	}
+
+
+	/// Wait until all the frames recorded so far are written and the output is flushed.
+	//
+	pub async fn flush( &self )
+	{
+		let (tx, rx) = oneshot::channel();
+
+		self.send( Command::Flush( Some(tx) ) );
+
+		// If the writer thread is gone, there is nothing to wait for.
+		//
+		let _ = rx.await;
+	}
+
+
+	fn record( &self, direction: Direction, wf: &impl WireFormat )
+	{
+		let mut record = Record::from_wf( direction, Duration::ZERO, wf );
+
+		// Take the time under the lock, so the records of the incoming and outgoing side are in order.
+		//
+		let commands   = self.commands.lock();
+		record.elapsed = self.start.elapsed();
+
+		if commands.send( Command::Record( record ) ).is_err()
+		{
+			error!( "Recorder: the writer thread is gone, frame not recorded." );
+		}
+	}
+
+
+	fn send( &self, command: Command )
+	{
+		if self.commands.lock().send( command ).is_err()
+		{
+			error!( "Recorder: the writer thread is gone." );
+		}
+	}
+}
+
+
+impl fmt::Debug for Recorder
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		write!( f, "Recorder" )
+	}
+}
+
+
+
+/// Incoming stream that records every frame to a capture. See [`Recorder::tap`].
+//
+#[ derive( Debug ) ]
+//
+pub struct TapIn<S>
+{
+	inner   : S        ,
+	recorder: Recorder ,
+}
+
+
+impl<S, Wf> Stream for TapIn<S>
+
+	where S : Stream< Item = Result<Wf, WireErr> > + Unpin ,
+	      Wf: WireFormat                                  ,
+
+{
+	type Item = Result<Wf, WireErr>;
+
+
+	fn poll_next( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll< Option<Self::Item> >
+	{
+		let item = ready!( Pin::new( &mut self.inner ).poll_next( cx ) );
+
+		if let Some( Ok(wf) ) = &item
+		{
+			self.recorder.record( Direction::Incoming, wf );
+		}
+
+		Poll::Ready( item )
+	}
+}
+
+
+
+/// Outgoing sink that records every frame to a capture. See [`Recorder::tap`].
+//
+#[ derive( Debug ) ]
+//
+pub struct TapOut<S>
+{
+	inner   : S        ,
+	recorder: Recorder ,
+}
+
+
+impl<S, Wf> Sink<Wf> for TapOut<S>
+
+	where S : Sink< Wf, Error = WireErr > + Unpin ,
+	      Wf: WireFormat                         ,
+
+{
+	type Error = WireErr;
+
+
+	fn poll_ready( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll< Result<(), Self::Error> >
+	{
+		Pin::new( &mut self.inner ).poll_ready( cx )
+	}
+
+
+	fn start_send( mut self: Pin<&mut Self>, msg: Wf ) -> Result<(), Self::Error>
+	{
+		self.recorder.record( Direction::Outgoing, &msg );
+
+		Pin::new( &mut self.inner ).start_send( msg )
+	}
+
+
+	fn poll_flush( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll<Result<(), Self::Error>>
+	{
+		let res = ready!( Pin::new( &mut self.inner ).poll_flush( cx ) );
+
+		self.recorder.send( Command::Flush( None ) );
+
+		Poll::Ready( res )
+	}
+
+
+	fn poll_close( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll<Result<(), Self::Error>>
+	{
+		let res = ready!( Pin::new( &mut self.inner ).poll_close( cx ) );
+
+		self.recorder.send( Command::Flush( None ) );
+
+		Poll::Ready( res )
+	}
+}
+
+
+
+/// The frames of a recorded connection.
+//
+#[ derive( Debug, Clone, PartialEq, Eq ) ]
+//
+pub struct Capture
+{
+	records: Vec<Record>,
+}
+
+
+impl Capture
+{
+	/// Read a capture that was written by a [Recorder].
+	//
+	pub fn read( mut input: impl io::Read ) -> Result<Self, WireErr>
+	{
+		let mut buf = Vec::new();
+		input.read_to_end( &mut buf )?;
+
+		if buf.len() < MAGIC.len() || buf[ ..MAGIC.len() ] != MAGIC
+		{
+			return Err( WireErr::Deserialize{ context: "Capture: not a capture file or unsupported version.".to_string() } );
+		}
+
+		let mut data    = &buf[ MAGIC.len().. ];
+		let mut records = Vec::new();
+
+		while !data.is_empty()
+		{
+			records.push( Record::read( &mut data )? );
+		}
+
+		Ok( Self { records } )
+	}
+
+
+	/// Whether `data` starts with the header of a capture, to tell captures apart from other files.
+	//
+	pub fn is_capture( data: &[u8] ) -> bool
+	{
+		data.starts_with( &MAGIC[ ..MAGIC.len()-1 ] )
+	}
+
+
+	/// The frames in the order they were recorded.
+	//
+	pub fn records( &self ) -> &[Record]
+	{
+		&self.records
+	}
+
+
+	/// Feed the incoming frames of this capture into a peer. Pass the stream and sink to
+	/// [`Peer::from_framed`](crate::Peer::from_framed). The frames the peer sends can be inspected with the returned
+	/// [Replayed].
+	///
+	/// If the peer sends fewer frames than it did in the recording, the stream never ends, so tests should use a timeout.
+	///
+	/// Only the incoming side is replayed, so this works for a peer that responds to the remote, like a server. It can't
+	/// work for a peer that makes calls of its own. Those calls get a new random [ConnID], which won't match the responses
+	/// in the capture, so they are never resolved and the frames the peer sends differ from the recording.
+	//
+	pub fn replay<Wf>( &self ) -> (ReplayStream<Wf>, ReplaySink<Wf>, Replayed)
+	{
+		let shared = Arc::new( Mutex::new( ReplayShared::default() ) );
+
+		let mut sent     = 0;
+		let mut incoming = VecDeque::new();
+
+		for record in &self.records
+		{
+			match record.direction
+			{
+				Direction::Incoming => incoming.push_back( (sent, record.clone()) ),
+				Direction::Outgoing => sent += 1,
+			}
+		}
+
+		let stream = ReplayStream{ incoming, total_sent: sent, shared: shared.clone(), _phantom: PhantomData };
+		let sink   = ReplaySink  { shared: shared.clone(), _phantom: PhantomData };
+
+		( stream, sink, Replayed{ shared } )
+	}
+}
+
+
+
+#[ derive( Debug, Default ) ]
+//
+struct ReplayShared
+{
+	sent : Vec<Record>   ,
+	waker: Option<Waker> ,
+}
+
+
+
+/// The frames a peer sent during a replay. See [`Capture::replay`].
+//
+#[ derive( Debug, Clone ) ]
+//
+pub struct Replayed
+{
+	shared: Arc< Mutex<ReplayShared> >,
+}
+
+
+impl Replayed
+{
+	/// The frames the peer has sent so far.
+	//
+	pub fn sent( &self ) -> Vec<Record>
+	{
+		self.shared.lock().sent.clone()
+	}
+}
+
+
+
+/// Incoming stream of a replay. See [`Capture::replay`].
+//
+#[ derive( Debug ) ]
+//
+pub struct ReplayStream<Wf>
+{
+	// The incoming frames with the number of frames that had been sent before it in the recording.
+	//
+	incoming  : VecDeque<(usize, Record)>  ,
+	total_sent: usize                      ,
+	shared    : Arc< Mutex<ReplayShared> > ,
+	_phantom  : PhantomData<fn() -> Wf>    ,
+}
+
+
+impl<Wf: WireFormat> Stream for ReplayStream<Wf>
+{
+	type Item = Result<Wf, WireErr>;
+
+
+	fn poll_next( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll< Option<Self::Item> >
+	{
+		let waiting_for = self.incoming.front().map( |(sent, _)| *sent ).unwrap_or( self.total_sent );
+
+		{
+			let mut shared = self.shared.lock();
+
+			if shared.sent.len() < waiting_for
+			{
+				shared.waker = Some( cx.waker().clone() );
+				return Poll::Pending;
+			}
+		}
+
+		match self.incoming.pop_front()
+		{
+			Some( (_, record) ) => Poll::Ready( Some( record.to_wf() ) ),
+			None                => Poll::Ready( None ),
+		}
+	}
+}
+
+
+
+/// Outgoing sink of a replay. See [`Capture::replay`].
+//
+#[ derive( Debug ) ]
+//
+pub struct ReplaySink<Wf>
+{
+	shared  : Arc< Mutex<ReplayShared> > ,
+	_phantom: PhantomData<fn(Wf)>        ,
+}
+
+
+impl<Wf: WireFormat> Sink<Wf> for ReplaySink<Wf>
+{
+	type Error = WireErr;
+
+
+	fn poll_ready( self: Pin<&mut Self>, _cx: &mut Context<'_> ) -> Poll< Result<(), Self::Error> >
+	{
+		Poll::Ready( Ok(()) )
+	}
+
+
+	fn start_send( self: Pin<&mut Self>, msg: Wf ) -> Result<(), Self::Error>
+	{
+		let mut shared = self.shared.lock();
+
+		shared.sent.push( Record::from_wf( Direction::Outgoing, Duration::ZERO, &msg ) );
+
+		if let Some( waker ) = shared.waker.take()
+		{
+			waker.wake();
+		}
+
+		Ok(())
+	}
+
+
+	fn poll_flush( self: Pin<&mut Self>, _cx: &mut Context<'_> ) -> Poll<Result<(), Self::Error>>
+	{
+		Poll::Ready( Ok(()) )
+	}
+
+
+	fn poll_close( self: Pin<&mut Self>, _cx: &mut Context<'_> ) -> Poll<Result<(), Self::Error>>
+	{
+		Poll::Ready( Ok(()) )
+	}
+}
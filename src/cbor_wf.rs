@@ -1,30 +1,30 @@
 use
 {
-	crate   :: { import::*, Peer, PeerErr, PeerExec, wire_format::* } ,
-	std::io :: { Write as _, Seek                                   } ,
-	futures :: { stream::{ select_with_strategy, PollNext }         } ,
+	crate   :: { import::*, Peer, PeerErr, PeerExec, BoundsIn, BoundsOut, Compression, Algorithms, compression, checksum, wire_format::* } ,
+	std::io :: { Write as _, Seek                                                                                   } ,
 };
 
 
 mod encoder;
 mod decoder;
 mod decoder_noheap;
+mod decoder_buffered;
 
 pub use encoder::*;
 pub use decoder::*;
 pub use decoder_noheap::*;
+pub use decoder_buffered::*;
 
-const LEN_LEN: usize = 8; // u64
-const LEN_SID: usize = 8; // u64
-const LEN_CID: usize = 8; // u64
+pub(crate) const LEN_LEN: usize = 8; // u64
+pub(crate) const LEN_SID: usize = 16; // u128
+pub(crate) const LEN_CID: usize = 8; // u64
 
-const IDX_LEN: usize = 0;
-const IDX_SID: usize = LEN_LEN;
-const IDX_CID: usize = IDX_SID + LEN_SID;
-const IDX_MSG: usize = IDX_CID + LEN_CID;
+pub(crate) const IDX_LEN: usize = 0;
+pub(crate) const IDX_SID: usize = LEN_LEN;
+pub(crate) const IDX_CID: usize = IDX_SID + LEN_SID;
+pub(crate) const IDX_MSG: usize = IDX_CID + LEN_CID;
 
-const LEN_HEADER: usize = IDX_MSG;
-const BOUNDED   : usize = 5;
+pub(crate) const LEN_HEADER: usize = IDX_MSG;
 
 
 
@@ -41,10 +41,10 @@ const BOUNDED   : usize = 5;
 /// message : the request message serialized with the specified codec
 ///
 /// ```text
-/// u64 length + payload ------------------------------------------|
-///              8 bytes sid | 8 bytes connID | serialized message |
-///              u64 LE      | u64 LE         | variable           |
-/// ----------------------------------------------------------------
+/// u64 length + payload -------------------------------------------|
+///              16 bytes sid | 8 bytes connID | serialized message |
+///              u128 LE      | u64 LE         | variable           |
+/// -----------------------------------------------------------------
 /// ```
 ///
 /// As soon as a codec determines from the length field that the entire message is read,
@@ -85,6 +85,14 @@ const BOUNDED   : usize = 5;
 pub struct CborWF
 {
 	data: io::Cursor< Vec<u8> >,
+
+	// How the encoder should compress this message. Not part of the frame.
+	//
+	compression: Option<Compression>,
+
+	// Whether the encoder should add a checksum to this message. Not part of the frame.
+	//
+	checksum: bool,
 }
 
 
@@ -99,11 +107,26 @@ impl CborWF
 {
 	/// Get direct access to the buffer.
 	//
-	fn as_buf( &self ) -> &[u8]
+	pub(crate) fn as_buf( &self ) -> &[u8]
 	{
 		self.data.get_ref()
 	}
 
+	/// Verify, decompress and parse a complete frame from the network. Frames that came with a checksum are marked,
+	/// see [`WireFormat::has_checksum`].
+	//
+	pub(crate) fn from_wire( frame: Vec<u8>, max_size: usize ) -> Result<Self, WireErr>
+	{
+		let sealed = checksum::sealed( &frame );
+		let frame  = checksum::verify( frame )?;
+		let frame  = compression::decompress( frame, max_size )?;
+
+		let mut wf  = Self::try_from( frame )?;
+		wf.checksum = sealed;
+
+		Ok( wf )
+	}
+
 	fn set_len( &mut self, len: u64 ) -> &mut Self
 	{
 		self.data.get_mut()[ IDX_LEN..IDX_LEN+LEN_LEN ].as_mut().write_u64::<LittleEndian>( len ).unwrap();
@@ -150,36 +173,30 @@ impl WireFormat for CborWF
 		-> Result< (Peer<CborWF>, Mailbox<Peer<CborWF>>, WeakAddr<Peer<CborWF>>), PeerErr >
 
 	{
-		let (reader, writer) = socket.split();
+		let (stream, sink) = Self::frame( socket, max_size_read, max_size_write );
 
-		let stream = decoder::Decoder::new( reader, max_size_read  );
-		let sink   = encoder::Encoder::new( writer, max_size_write );
+		Peer::from_framed( name, stream, sink, exec, bp, grace_period )
+	}
 
-		// We will create 2 separate channels to the same mailbox.
-		// One for high priority (outbound) and one for low priority
-		// (inbound).
-		//
-		// Both will be bounded, but delivering outbound work is always
-		// prioritized over taking more inbound work. This will keep the
-		// system from congesting.
-		//
-		let ( low_tx,  low_rx) = futures::channel::mpsc::channel( BOUNDED );
-		let (high_tx, high_rx) = futures::channel::mpsc::channel( BOUNDED );
 
-		let strategy = |_: &mut ()| PollNext::Left;
-		let rx = Box::new( select_with_strategy( high_rx, low_rx, strategy ) );
+	/// Frame the connection with [DecoderBuffered] and [Encoder].
+	//
+	fn frame
+	(
+		socket        : impl AsyncRead + AsyncWrite + Unpin + Send + 'static ,
+		max_size_read : usize                                                ,
+		max_size_write: usize                                                ,
+	)
 
-		let low_tx  = low_tx .sink_map_err( |e| -> DynError { Box::new(e) } );
-		let high_tx = high_tx.sink_map_err( |e| -> DynError { Box::new(e) } );
+		-> ( impl BoundsIn<Self>, impl BoundsOut<Self> )
 
-		let mb       = Mailbox::new( name, rx );
-		let addr_in  = mb.addr( Box::new( low_tx  ) );
-		let addr_out = mb.addr( Box::new( high_tx ) );
-		let weak_out = addr_out.weak();
+	{
+		let (reader, writer) = socket.split();
 
-		let peer = Peer::new( addr_in, addr_out, stream, sink, Arc::new(exec), bp, grace_period )?;
+		let stream = DecoderBuffered::new( reader, max_size_read  );
+		let sink   = Encoder::new( writer, max_size_write );
 
-		Ok( (peer, mb, weak_out) )
+		(stream, sink)
 	}
 
 
@@ -192,13 +209,13 @@ impl WireFormat for CborWF
 	{
 		// TODO: is this the most efficient way?
 		//
-		self.data.get_ref()[ IDX_SID..IDX_SID+LEN_SID ].as_ref().read_u64::<LittleEndian>().unwrap().into()
+		self.data.get_ref()[ IDX_SID..IDX_SID+LEN_SID ].as_ref().read_u128::<LittleEndian>().unwrap().into()
 	}
 
 
 	fn set_sid( &mut self, sid: ServiceID ) -> &mut Self
 	{
-		self.data.get_mut()[ IDX_SID..IDX_SID+LEN_SID ].as_mut().write_u64::<LittleEndian>( sid.into() ).unwrap();
+		self.data.get_mut()[ IDX_SID..IDX_SID+LEN_SID ].as_mut().write_u128::<LittleEndian>( sid.into() ).unwrap();
 		self
 	}
 
@@ -233,6 +250,36 @@ impl WireFormat for CborWF
 		self.data.get_ref()[ IDX_LEN..IDX_LEN+LEN_LEN ].as_ref().read_u64::<LittleEndian>().unwrap()
 	}
 
+	/// The decoders support all the algorithms enabled in this build.
+	//
+	fn compression() -> Algorithms
+	{
+		Algorithms::supported()
+	}
+
+	/// The [Encoder] compresses the payload if it is at least as big as the threshold.
+	//
+	fn set_compression( &mut self, compression: Option<Compression> ) -> &mut Self
+	{
+		self.compression = compression;
+		self
+	}
+
+	/// The [Encoder] adds a checksum after compressing the message.
+	//
+	fn set_checksum( &mut self, checksum: bool ) -> &mut Self
+	{
+		self.checksum = checksum;
+		self
+	}
+
+	/// Set by the decoders when they verified a checksum.
+	//
+	fn has_checksum( &self ) -> bool
+	{
+		self.checksum
+	}
+
 	/// Make sure there is enough room for the serialized payload to avoid frequent re-allocation.
 	//
 	fn with_capacity( size: usize ) -> Self
@@ -241,7 +288,9 @@ impl WireFormat for CborWF
 
 		let mut wf = Self
 		{
-			data: io::Cursor::new( Vec::with_capacity( size + LEN_HEADER ) )
+			data       : io::Cursor::new( Vec::with_capacity( size + LEN_HEADER ) ) ,
+			compression: None                                                        ,
+			checksum   : false                                                       ,
 		};
 
 		wf.data.write_all( &[0u8; LEN_HEADER] ).unwrap();
@@ -259,12 +308,11 @@ impl io::Write for CborWF
 	{
 		self.data.seek( io::SeekFrom::End(0) )?;
 
-		self.data.write( buf ).map(|written|
+		self.data.write( buf ).inspect( |&written|
 		{
 			trace!( "writing wf with mesg length: {}", self.len() + written as u64 - LEN_HEADER as u64 );
 
 			self.set_len( self.len() + written as u64 );
-			written
 		})
 	}
 
@@ -285,7 +333,9 @@ impl Default for CborWF
 	{
 		let mut wf = Self
 		{
-			data: io::Cursor::new( Vec::with_capacity( LEN_HEADER *2 ) )
+			data       : io::Cursor::new( Vec::with_capacity( LEN_HEADER *2 ) ) ,
+			compression: None                                                    ,
+			checksum   : false                                                   ,
 		};
 
 		wf.write_all( &[0u8; LEN_HEADER] ).unwrap();
@@ -311,7 +361,7 @@ impl TryFrom< Vec<u8> > for CborWF
 			return Err( WireErr::Deserialize{ context: "CborWF: not enough bytes even for the header.".to_string() } );
 		}
 
-		Ok( Self { data: io::Cursor::new(data) } )
+		Ok( Self { data: io::Cursor::new(data), compression: None, checksum: false } )
 	}
 }
 
@@ -329,13 +379,18 @@ mod tests
 	// - set_len/len equality and check the actual data
 	// - set_sid/sid equality and check the actual data
 	// - set_cid/cid equality and check the actual data
+	// - TestSuite for each decoder
+	// - DecoderBuffered reads several frames at once
+	// - DecoderBuffered reads frames bigger than its buffer in between small ones
+	// - Encoder writes queued frames at once
 	//
 	use
 	{
-		super           :: { *, assert_eq                      } ,
-		crate           :: { TestSuite, MockConnection         } ,
-		futures::io     :: { WriteHalf, ReadHalf, AsyncReadExt } ,
-		async_executors :: { AsyncStd                          } ,
+		super           :: { *, assert_eq                              } ,
+		crate           :: { TestSuite, MockConnection                 } ,
+		futures::io     :: { WriteHalf, ReadHalf, AsyncReadExt, Cursor } ,
+		async_executors :: { AsyncStd                                  } ,
+		futures::io     :: { IoSlice                                   } ,
 	};
 
 
@@ -453,6 +508,174 @@ mod tests
 
 		test_suite.run( AsyncStd ).await;
 	}
-}
 
 
+
+	fn frame_buffered( socket: Box<dyn MockConnection>, max_size: usize ) -> (Encoder<Writer>, DecoderBuffered<Reader>)
+	{
+		let (reader, writer) = socket.split();
+
+		let stream = DecoderBuffered::new( reader, max_size );
+		let sink   = Encoder::new( writer, max_size );
+
+		(sink, stream)
+	}
+
+
+	#[async_std::test]
+	//
+	async fn decoder_encoder_buffered()
+	{
+		let test_suite = TestSuite::new( frame_buffered );
+
+		test_suite.run( AsyncStd ).await;
+	}
+
+
+	// Counts the reads, to verify that the decoder reads all available frames at once.
+	//
+	struct Reads<T>{ inner: T, reads: Arc<AtomicUsize> }
+
+	impl<T: AsyncRead + Unpin> AsyncRead for Reads<T>
+	{
+		fn poll_read( mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8] ) -> Poll< io::Result<usize> >
+		{
+			self.reads.fetch_add( 1, Relaxed );
+			Pin::new( &mut self.inner ).poll_read( cx, buf )
+		}
+	}
+
+
+	#[async_std::test]
+	//
+	async fn buffered_reads_at_once()
+	{
+		let mut wire = Vec::new();
+
+		for i in 0..10u8
+		{
+			let mut wf = CborWF::with_capacity( 100 );
+			wf.set_cid( ConnID::from( u64::from( i ) + 1 ) );
+			wf.write_all( &[ i; 100 ] ).expect( "write to wf" );
+
+			wire.extend_from_slice( wf.as_buf() );
+		}
+
+		let     reads   = Arc::new( AtomicUsize::new(0) );
+		let mut decoder = DecoderBuffered::new( Reads{ inner: Cursor::new( wire ), reads: reads.clone() }, 1024 );
+
+		for i in 0..10u8
+		{
+			let wf = decoder.next().await.expect( "a frame" ).expect( "decode frame" );
+
+			assert_eq!( ConnID::from( u64::from( i ) + 1 ), wf.cid() );
+			assert_eq!( &[ i; 100 ][..]                   , wf.msg() );
+		}
+
+		assert_eq!( 1, reads.load( Relaxed ) );
+
+		assert!( decoder.next().await.is_none() );
+	}
+
+
+
+	#[async_std::test]
+	//
+	async fn buffered_large_frames()
+	{
+		let sizes = [ 100, 20_000, 50, 9_000, 8_000, 30 ];
+		let mut wire = Vec::new();
+
+		for (i, size) in sizes.iter().enumerate()
+		{
+			let mut wf = CborWF::with_capacity( *size );
+			wf.set_cid( ConnID::from( i as u64 + 1 ) );
+			wf.write_all( &vec![ i as u8; *size ] ).expect( "write to wf" );
+
+			wire.extend_from_slice( wf.as_buf() );
+		}
+
+		let mut decoder = DecoderBuffered::new( Cursor::new( wire ), 32 * 1024 );
+
+		for (i, size) in sizes.iter().enumerate()
+		{
+			let wf = decoder.next().await.expect( "a frame" ).expect( "decode frame" );
+
+			assert_eq!( ConnID::from( i as u64 + 1 ), wf.cid() );
+			assert_eq!( &vec![ i as u8; *size ][..]  , wf.msg() );
+		}
+
+		assert!( decoder.next().await.is_none() );
+	}
+
+
+
+	// Counts the writes, to verify that the encoder writes all queued frames at once.
+	//
+	struct Writes<T>{ inner: T, writes: Arc<AtomicUsize> }
+
+	impl<T: AsyncWrite + Unpin> AsyncWrite for Writes<T>
+	{
+		fn poll_write( mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8] ) -> Poll< io::Result<usize> >
+		{
+			self.writes.fetch_add( 1, Relaxed );
+			Pin::new( &mut self.inner ).poll_write( cx, buf )
+		}
+
+		fn poll_write_vectored( mut self: Pin<&mut Self>, cx: &mut Context<'_>, bufs: &[IoSlice<'_>] ) -> Poll< io::Result<usize> >
+		{
+			self.writes.fetch_add( 1, Relaxed );
+			Pin::new( &mut self.inner ).poll_write_vectored( cx, bufs )
+		}
+
+		fn poll_flush( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll< io::Result<()> >
+		{
+			Pin::new( &mut self.inner ).poll_flush( cx )
+		}
+
+		fn poll_close( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll< io::Result<()> >
+		{
+			Pin::new( &mut self.inner ).poll_close( cx )
+		}
+	}
+
+
+	#[async_std::test]
+	//
+	async fn encoder_writes_at_once()
+	{
+		let mut wire    = Cursor::new( Vec::new() );
+		let     writes  = Arc::new( AtomicUsize::new(0) );
+		let mut encoder = Encoder::new( Writes{ inner: &mut wire, writes: writes.clone() }, 1024 );
+
+		for i in 0..10u8
+		{
+			let mut wf = CborWF::with_capacity( 100 );
+			wf.set_cid( ConnID::from( u64::from( i ) + 1 ) );
+			wf.write_all( &[ i; 100 ] ).expect( "write to wf" );
+
+			encoder.feed( wf ).await.expect( "queue frame" );
+		}
+
+		assert_eq!( 0, writes.load( Relaxed ) );
+
+		encoder.flush().await.expect( "flush encoder" );
+
+		assert_eq!( 1, writes.load( Relaxed ) );
+
+
+		drop( encoder );
+
+		let mut decoder = DecoderBuffered::new( Cursor::new( wire.into_inner() ), 1024 );
+
+		for i in 0..10u8
+		{
+			let wf = decoder.next().await.expect( "a frame" ).expect( "decode frame" );
+
+			assert_eq!( ConnID::from( u64::from( i ) + 1 ), wf.cid() );
+			assert_eq!( &[ i; 100 ][..]                   , wf.msg() );
+		}
+
+		assert!( decoder.next().await.is_none() );
+	}
+}
@@ -1,6 +1,6 @@
 use
 {
-	crate     :: { CborWF                      } ,
+	crate     :: { CborWF, compression, checksum } ,
 	super     :: { *                           } ,
 	byteorder :: { ReadBytesExt, LittleEndian  } ,
 	std       :: { future::Future              } ,
@@ -119,7 +119,7 @@ impl<T> Stream for Decoder<T>
 					Poll::Ready( (transport, Ok(all)) ) =>
 					{
 						self.byte_stream = Some(transport);
-						let thes_wf = CborWF::try_from( all )?;
+						let thes_wf = CborWF::from_wire( all, self.max_size )?;
 
 						return Poll::Ready( Some(Ok( thes_wf )) );
 					}
@@ -153,12 +153,26 @@ impl<T> Stream for Decoder<T>
 
 					Poll::Ready( (mut transport, Ok(buf)) ) =>
 					{
-						let len: usize = buf[ 0..LEN_LEN ].as_ref().read_u64::<LittleEndian>().unwrap().try_into().unwrap();
+						// The length field can have a compression and a checksum flag.
+						//
+						let (len, flags) = compression::split_len( buf[ 0..LEN_LEN ].as_ref().read_u64::<LittleEndian>().unwrap() );
+						let len: usize   = len.try_into().unwrap();
+
+						if len < LEN_HEADER
+						{
+							self.closed      = true;
+							self.byte_stream = Some(transport);
 
-						debug_assert!( len >= LEN_HEADER );
+							let context = format!( "CborWF Decoder: length field ({len}) is smaller than the header." );
 
-						if len > self.max_size
+							return Poll::Ready( Some(Err( WireErr::Deserialize{ context } )) );
+						}
+
+						if len > self.max_size.saturating_add( checksum::overhead( flags ) )
 						{
+							self.closed      = true;
+							self.byte_stream = Some(transport);
+
 							let err = WireErr::MessageSizeExceeded
 							{
 								size    : len                          ,
@@ -0,0 +1,273 @@
+use
+{
+	crate :: { import::*, CborWF, WireErr, compression, checksum, cbor_wf::{ LEN_LEN, LEN_HEADER } } ,
+};
+
+
+// How many bytes we try to read at once, unless the frame we are reading is bigger.
+//
+const READ_SIZE: usize = 8 * 1024;
+
+
+
+/// Decodes a stream of bytes into a stream of CborWF messages.
+///
+/// This is a state machine over one buffer that is reused for all frames. Each read asks for as many bytes as
+/// fit in the buffer, so when several frames are available, they are all read at once and the next calls to
+/// `poll_next` return them without touching the underlying reader.
+///
+/// Frames that don't fit in the buffer get their own buffer of exactly their size. The rest of the frame is read
+/// straight into it and it becomes the [CborWF] that is returned, so big frames are never copied. It is allocated
+/// zeroed, which for big buffers the allocator usually gets for free from the OS.
+///
+/// The only allocation per frame is the buffer of the [CborWF] that is returned.
+//
+#[ derive(Debug) ]
+//
+pub struct DecoderBuffered<T>
+{
+	byte_stream: T       ,
+	buffer     : Vec<u8> ,
+	state      : State   ,
+	max_size   : usize   ,
+
+	// The buffer of a frame that doesn't fit in `buffer`.
+	//
+	large: Vec<u8>,
+
+	// The bytes in `buffer[ start..end ]` have been read but not yet decoded.
+	//
+	start: usize,
+	end  : usize,
+}
+
+
+#[ derive( Debug, Clone, Copy, PartialEq, Eq ) ]
+//
+enum State
+{
+	/// Waiting for the length field.
+	//
+	Length,
+
+	/// Waiting for the rest of a frame of `len` bytes, length field included.
+	//
+	Frame{ len: usize },
+
+	/// Reading a frame that doesn't fit in the buffer into `large`. `filled` bytes have been read.
+	//
+	Large{ filled: usize },
+
+	/// The stream has ended or errored.
+	//
+	Closed,
+}
+
+
+impl<T> DecoderBuffered<T>
+{
+	/// Create a new decoder.
+	//
+	pub fn new( byte_stream: T, max_size: usize ) -> Self
+	{
+		Self
+		{
+			byte_stream                       ,
+			max_size                          ,
+			buffer     : vec![ 0; READ_SIZE ] ,
+			state      : State::Length        ,
+			large      : Vec::new()           ,
+			start      : 0                    ,
+			end        : 0                    ,
+		}
+	}
+
+
+	/// The number of bytes read but not decoded yet.
+	//
+	fn available( &self ) -> usize
+	{
+		self.end - self.start
+	}
+
+
+	/// Make room in the buffer for `len` bytes, starting at `start`. `len` is never bigger than the buffer.
+	//
+	fn reserve( &mut self, len: usize )
+	{
+		// Move the leftovers to the front, unless the frame fits behind them anyway.
+		//
+		if self.start + len > self.buffer.len() && self.start > 0
+		{
+			self.buffer.copy_within( self.start..self.end, 0 );
+			self.end  -= self.start;
+			self.start = 0;
+		}
+	}
+
+
+	/// Start reading a frame of `len` bytes that doesn't fit in the buffer into a buffer of its own.
+	//
+	fn start_large( &mut self, len: usize )
+	{
+		let filled = self.available();
+
+		self.large = vec![ 0; len ];
+		self.large[ ..filled ].copy_from_slice( &self.buffer[ self.start..self.end ] );
+
+		self.start = 0;
+		self.end   = 0;
+		self.state = State::Large{ filled };
+	}
+
+
+	/// Verify and decompress a complete frame.
+	//
+	fn finish( &mut self, frame: Vec<u8> ) -> Poll< Option<Result<CborWF, WireErr>> >
+	{
+		self.state = State::Length;
+
+		match CborWF::from_wire( frame, self.max_size )
+		{
+			Ok ( wf ) => Poll::Ready( Some(Ok( wf )) ),
+			Err( e  ) => self.fail( e ),
+		}
+	}
+
+
+	fn fail( &mut self, err: WireErr ) -> Poll< Option<Result<CborWF, WireErr>> >
+	{
+		self.state = State::Closed;
+
+		Poll::Ready( Some(Err( err )) )
+	}
+}
+
+
+
+impl<T> Stream for DecoderBuffered<T>
+
+	where T: AsyncRead + Unpin
+{
+	type Item = Result<CborWF, WireErr>;
+
+
+	fn poll_next( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll< Option<Self::Item> >
+	{
+		loop
+		{
+			// How many bytes we need in the buffer to make progress. For big frames, how much of their own buffer is
+			// filled.
+			//
+			let needed = match self.state
+			{
+				State::Closed => return Poll::Ready( None ),
+
+				State::Length if self.available() >= LEN_LEN =>
+				{
+					let mut field = [0u8; LEN_LEN];
+					field.copy_from_slice( &self.buffer[ self.start..self.start+LEN_LEN ] );
+
+					// The length field can have a compression and a checksum flag. If it doesn't fit in usize, it's
+					// definitely bigger than max_size.
+					//
+					let (len, flags) = compression::split_len( u64::from_le_bytes( field ) );
+					let len          = usize::try_from( len ).unwrap_or( usize::MAX );
+
+					if len < LEN_HEADER
+					{
+						let context = format!( "CborWF DecoderBuffered: length field ({len}) is smaller than the header." );
+
+						return self.fail( WireErr::Deserialize{ context } );
+					}
+
+					if len > self.max_size.saturating_add( checksum::overhead( flags ) )
+					{
+						let err = WireErr::MessageSizeExceeded
+						{
+							size    : len                                  ,
+							max_size: self.max_size                        ,
+							context : "CborWF DecoderBuffered".to_string() ,
+						};
+
+						return self.fail( err );
+					}
+
+					if len > self.buffer.len() { self.start_large( len ) }
+					else                       { self.state = State::Frame{ len } }
+
+					continue;
+				}
+
+				State::Length => LEN_LEN,
+
+				State::Frame{ len } if self.available() >= len =>
+				{
+					let frame = self.buffer[ self.start..self.start+len ].to_vec();
+
+					self.start += len;
+
+					// Start over at the front of the buffer when we have decoded everything we read.
+					//
+					if self.start == self.end
+					{
+						self.start = 0;
+						self.end   = 0;
+					}
+
+					return self.finish( frame );
+				}
+
+				State::Frame{ len } => len,
+
+				State::Large{ filled } if filled == self.large.len() =>
+				{
+					let frame = std::mem::take( &mut self.large );
+
+					return self.finish( frame );
+				}
+
+				State::Large{ filled } => filled,
+			};
+
+
+			let this = &mut *self;
+
+			// Big frames are read into their own buffer, only asking for the rest of the frame, so we don't read
+			// past it.
+			//
+			let read = match this.state
+			{
+				State::Large{..} => Pin::new( &mut this.byte_stream ).poll_read( cx, &mut this.large[ needed.. ] ),
+
+				_ =>
+				{
+					this.reserve( needed );
+					Pin::new( &mut this.byte_stream ).poll_read( cx, &mut this.buffer[ this.end.. ] )
+				}
+			};
+
+			match read
+			{
+				Poll::Pending => return Poll::Pending,
+
+				// The connection was closed. If it was in the middle of a frame, the frame is lost.
+				//
+				Poll::Ready( Ok(0) ) =>
+				{
+					self.state = State::Closed;
+
+					return Poll::Ready( None );
+				}
+
+				Poll::Ready( Ok(read) ) => match &mut self.state
+				{
+					State::Large{ filled } => *filled  += read,
+					_                      => self.end += read,
+				}
+
+				Poll::Ready( Err(e) ) => return self.fail( WireErr::from(e) ),
+			}
+		}
+	}
+}
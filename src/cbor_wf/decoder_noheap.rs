@@ -1,6 +1,6 @@
 use
 {
-	crate     :: { import::*, CborWF, WireErr, cbor_wf::{ LEN_LEN, LEN_HEADER }  } ,
+	crate     :: { import::*, CborWF, WireErr, compression, checksum, cbor_wf::{ LEN_LEN, LEN_HEADER } } ,
 	byteorder :: { ReadBytesExt, LittleEndian  } ,
 	std       :: { io::{ Write, Cursor }       } ,
 };
@@ -80,6 +80,16 @@ impl<T> Stream for DecoderNoHeap<T>
 						}
 
 
+						// The connection was closed. If it was in the middle of a frame, the frame is lost.
+						//
+						Poll::Ready(Ok( 0 )) =>
+						{
+							self.closed = true;
+
+							return Poll::Ready( None );
+						}
+
+
 						Poll::Ready(Ok( read )) =>
 						{
 							in_progress.set_position( in_progress.position() + read as u64 );
@@ -105,10 +115,24 @@ impl<T> Stream for DecoderNoHeap<T>
 				{
 					// TODO: this can truncate.
 					//
-					let len: usize = in_progress.get_ref()[ 0..LEN_LEN ].as_ref().read_u64::<LittleEndian>().unwrap().try_into().unwrap();
+					// The length field can have a compression and a checksum flag.
+					//
+					let (len, flags) = compression::split_len( in_progress.get_ref()[ 0..LEN_LEN ].as_ref().read_u64::<LittleEndian>().unwrap() );
+					let len: usize   = len.try_into().unwrap();
 
-					if len > self.max_size
+					if len < LEN_HEADER
 					{
+						self.closed = true;
+
+						let context = format!( "CborWF DecoderNoHeap: length field ({len}) is smaller than the header." );
+
+						return Poll::Ready( Some(Err( WireErr::Deserialize{ context } )) );
+					}
+
+					if len > self.max_size.saturating_add( checksum::overhead( flags ) )
+					{
+						self.closed = true;
+
 						let err = WireErr::MessageSizeExceeded
 						{
 							size    : len                          ,
@@ -119,16 +143,10 @@ impl<T> Stream for DecoderNoHeap<T>
 						return Poll::Ready( Some(Err( err )) );
 					}
 
-					// if we havent allocated to buffer yet on a previous run:
-					//
-					// TODO: do we get perf wins if we use debug_assert! here and get_unchecked_mut below?
-					//
-					assert!( len >= LEN_HEADER );
-
 					// Create a zeroed buffer of the size of the entire message.
 					// TODO: check the perf difference with an unzeroed buffer.
 					//
-					let mut tmp = io::Cursor::new( vec![0u8;len] );
+					let mut tmp = Cursor::new( vec![0u8;len] );
 
 					// put the length in the new buffer.
 					//
@@ -144,7 +162,8 @@ impl<T> Stream for DecoderNoHeap<T>
 				//
 				pos =>
 				{
-					let len     = in_progress.get_ref()[ 0..LEN_LEN ].as_ref().read_u64::<LittleEndian>().unwrap() as usize;
+					let (len, _) = compression::split_len( in_progress.get_ref()[ 0..LEN_LEN ].as_ref().read_u64::<LittleEndian>().unwrap() );
+					let len      = len as usize;
 					let to_read = len - pos;
 
 					match Pin::new( &mut self.byte_stream ).poll_read( cx, &mut in_progress.get_mut()[ pos..len ] )
@@ -155,6 +174,15 @@ impl<T> Stream for DecoderNoHeap<T>
 							return Poll::Pending;
 						}
 
+						// The connection was closed, the frame is lost.
+						//
+						Poll::Ready(Ok( 0 )) =>
+						{
+							self.closed = true;
+
+							return Poll::Ready( None );
+						}
+
 						Poll::Ready(Ok( read )) if read < to_read =>
 						{
 							in_progress.set_position( (pos + read) as u64 );
@@ -168,11 +196,18 @@ impl<T> Stream for DecoderNoHeap<T>
 							in_progress.set_position( in_progress.position() + read as u64 );
 							debug_assert_eq!( len as u64, in_progress.position() );
 
-							let thes_wf = CborWF::try_from( in_progress.into_inner() )?;
+							let thes_wf = CborWF::from_wire( in_progress.into_inner(), self.max_size )?;
 
 							return Poll::Ready( Some(Ok( thes_wf )) );
 						}
 
+						Poll::Ready( Err(e) ) =>
+						{
+							self.closed = true;
+
+							return Some(Err( WireErr::from(e) )).into();
+						}
+
 						_ => unreachable!( "read more bytes than buffer size" ),
 					}
 				}
@@ -1,15 +1,42 @@
-use crate::{ import::*, CborWF, WireErr, WireFormat };
+use crate::{ import::*, CborWF, WireErr, WireFormat, compression, checksum };
+
+
+// When this many bytes are queued, `poll_ready` writes them out before accepting more frames.
+//
+const MAX_QUEUED: usize = 64 * 1024;
+
+// The maximum number of frames we pass to one vectored write.
+//
+const MAX_SLICES: usize = 64;
+
 
 
 /// Serializes the CborWF format onto a stream of bytes.
+///
+/// Frames are queued by `start_send` and written when the sink is flushed, all at once with
+/// `poll_write_vectored`. So `feed` several frames and `flush` once to avoid a syscall per frame. When
+/// more than 64KiB are queued, `poll_ready` writes them out before accepting more. [Peer](crate::Peer)
+/// decides when to flush according to its [FlushPolicy](crate::FlushPolicy).
+///
+/// Messages for which [`WireFormat::set_compression`] was set are compressed if they are big enough.
+/// The max size applies to the uncompressed message. Messages for which [`WireFormat::set_checksum`] was set
+/// get a checksum after compression. The max size doesn't count the checksum.
 //
 #[ derive(Debug) ]
 //
 pub struct Encoder<T>
 {
-	out_bytes: T                         ,
-	buffer   : Option< (CborWF, usize) > ,
-	max_size : usize                     ,
+	out_bytes: T                 ,
+	queue    : VecDeque<CborWF>  ,
+	max_size : usize             ,
+
+	// How many bytes of the first frame in the queue have been written already.
+	//
+	pos: usize,
+
+	// How many bytes in the queue have not been written yet.
+	//
+	queued: usize,
 }
 
 
@@ -21,35 +48,107 @@ impl<T> Encoder<T>
 	{
 		Self
 		{
-			out_bytes    ,
-			max_size     ,
-			buffer: None ,
+			out_bytes               ,
+			max_size                ,
+			queue : VecDeque::new() ,
+			pos   : 0               ,
+			queued: 0               ,
 		}
 	}
 }
 
 
-impl<T> Sink<CborWF> for Encoder<T>
+impl<T> Encoder<T>
 
 	where T: AsyncWrite + Unpin
 
 {
-	type Error = WireErr;
+	/// Write out all queued frames.
+	//
+	fn poll_write_queue( &mut self, cx: &mut Context<'_> ) -> Poll<Result<(), WireErr>>
+	{
+		while self.queued > 0
+		{
+			let written =
+			{
+				let mut slices = Vec::with_capacity( self.queue.len().min( MAX_SLICES ) );
 
+				for (i, wf) in self.queue.iter().take( MAX_SLICES ).enumerate()
+				{
+					let start = if i == 0 { self.pos } else { 0 };
+
+					slices.push( io::IoSlice::new( &wf.as_buf()[ start.. ] ) );
+				}
+
+				match Pin::new( &mut self.out_bytes ).poll_write_vectored( cx, &slices )
+				{
+					Poll::Pending => return Poll::Pending,
 
-	fn poll_ready( self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll< Result<(), Self::Error> >
+					// Normally means the connection was closed.
+					//
+					Poll::Ready( Ok(0) ) =>
+					{
+						return Err( WireErr::from( io::Error::from( io::ErrorKind::ConnectionAborted ) )).into();
+					}
+
+					Poll::Ready( Ok(x) ) => x,
+
+					Poll::Ready( Err(e) ) => return Err( WireErr::from(e) ).into(),
+				}
+			};
+
+			self.advance( written );
+		}
+
+		Poll::Ready( Ok(()) )
+	}
+
+
+	/// Drop the frames that have been written.
+	//
+	fn advance( &mut self, mut written: usize )
 	{
-		self.poll_flush( cx )
+		self.queued -= written;
+
+		while let Some( wf ) = self.queue.front()
+		{
+			let left = wf.as_buf().len() - self.pos;
+
+			if written < left
+			{
+				self.pos += written;
+				return;
+			}
+
+			written -= left;
+			self.pos = 0;
+			self.queue.pop_front();
+		}
 	}
+}
 
 
-	fn start_send( mut self: Pin<&mut Self>, msg: CborWF ) -> Result<(), Self::Error>
+impl<T> Sink<CborWF> for Encoder<T>
+
+	where T: AsyncWrite + Unpin
+
+{
+	type Error = WireErr;
+
+
+	fn poll_ready( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll< Result<(), Self::Error> >
 	{
-		if self.buffer.is_some()
+		if self.queued < MAX_QUEUED
 		{
-			panic!( "call `poll_ready` before start_send" )
+			return Poll::Ready( Ok(()) );
 		}
 
+		self.poll_write_queue( cx )
+	}
+
+
+	fn start_send( mut self: Pin<&mut Self>, msg: CborWF ) -> Result<(), Self::Error>
+	{
 		let len = msg.len() as usize;
 
 		if len > self.max_size
@@ -62,59 +161,32 @@ impl<T> Sink<CborWF> for Encoder<T>
 			});
 		}
 
-		self.buffer = Some( (msg, 0) );
-
-		Ok(())
-	}
-
+		let sealed = msg.checksum;
 
-	fn poll_flush( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll<Result<(), Self::Error>>
-	{
-		loop { match self.buffer.take()
+		let msg = match msg.compression.as_ref().and_then( |c| compression::compress( msg.as_buf(), c ) )
 		{
-			None => return Poll::Ready( Ok(()) ),
+			Some( packed ) => CborWF::try_from( packed )?,
+			None           => msg,
+		};
 
-			Some( (msg, mut pos) ) =>
-			{
-				match Pin::new( &mut self.out_bytes ).poll_write( cx, &msg.as_buf()[pos..] )
-				{
-					Poll::Pending =>
-					{
-						self.buffer = Some( (msg, pos) );
-						return Poll::Pending;
-					}
-
-
-					Poll::Ready( Ok(0) ) => // TODO: what to do? normally means connection closed.
-					{
-						return Err( WireErr::from( io::Error::from( io::ErrorKind::ConnectionAborted ) )).into();
-					}
+		let msg = match sealed
+		{
+			true  => CborWF::try_from( checksum::seal( msg.data.into_inner() ) )?,
+			false => msg,
+		};
 
+		self.queued += msg.as_buf().len();
+		self.queue.push_back( msg );
 
-					Poll::Ready( Ok(x) ) =>
-					{
-						pos += x;
+		Ok(())
+	}
 
-						// we wrote all
-						//
-						if pos == msg.as_buf().len()
-						{
-							return Ok(()).into()
-						}
-
-						self.buffer = Some( (msg, pos) );
-					}
 
+	fn poll_flush( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll<Result<(), Self::Error>>
+	{
+		ready!( self.poll_write_queue( cx ) )?;
 
-					Poll::Ready( Err(e) ) =>
-					{
-						// TODO: are we still operational after an error?
-						//
-						return Err( WireErr::from(e) ).into()
-					}
-				}
-			}
-		}}
+		Pin::new( &mut self.out_bytes ).poll_flush( cx ).map_err( WireErr::from )
 	}
 
 
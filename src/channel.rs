@@ -0,0 +1,123 @@
+use crate::{ import::*, * };
+
+
+/// The message a handler for a bidirectional channel receives. `msg` is the message the caller opened the
+/// channel with and `incoming` has the items the caller sends on the channel. It ends when the caller closes
+/// or drops it's sink. The handler returns a stream of items that are sent back to the caller.
+///
+/// The `service_map!` macro takes care of creating these. See the `channels` parameter.
+//
+pub struct Channel<M, In>
+{
+	/// The message that opened the channel.
+	//
+	pub msg: M,
+
+	/// The items the caller sends on the channel.
+	//
+	pub incoming: Pin<Box< dyn Stream<Item=In> + Send >>,
+}
+
+
+impl<M, In> Message for Channel<M, In>
+
+	where M : Message        ,
+	      In: Send + 'static ,
+{
+	type Return = <M as Message>::Return;
+}
+
+
+impl<M: fmt::Debug, In> fmt::Debug for Channel<M, In>
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		f.debug_struct( "Channel" ).field( "msg", &self.msg ).finish_non_exhaustive()
+	}
+}
+
+
+
+/// The sending half of a bidirectional channel on the side of the caller. Frames are sent to the remote as
+/// items of the channel. Closing or dropping it tells the remote that no more items will follow.
+///
+/// Normally you don't use this directly, but use `RemoteAddr::open_channel` from the `service_map!` macro,
+/// which gives you a typed sink.
+//
+#[ derive( Debug ) ]
+//
+pub struct ChannelSink<Wf: WireFormat>
+{
+	peer : WeakAddr< Peer<Wf> >,
+	cid  : ConnID              ,
+
+	// When dropped, the peer will tell the remote the channel is closed.
+	//
+	close: Option< oneshot::Sender<()> >,
+}
+
+
+impl<Wf: WireFormat> ChannelSink<Wf>
+{
+	pub(crate) fn new( peer: WeakAddr< Peer<Wf> >, cid: ConnID, close: oneshot::Sender<()> ) -> Self
+	{
+		Self{ peer, cid, close: Some( close ) }
+	}
+
+
+	fn err( &self, _: ThesErr ) -> PeerErr
+	{
+		let ctx = Peer::err_ctx( &self.peer, None, self.cid, "Send item on bidirectional channel".to_string() );
+
+		PeerErr::PeerGone{ ctx }
+	}
+}
+
+
+impl<Wf: WireFormat + Send + 'static> Sink<Wf> for ChannelSink<Wf>
+{
+	type Error = PeerErr;
+
+
+	fn poll_ready( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll<Result<(), Self::Error>>
+	{
+		if self.close.is_none()
+		{
+			let ctx = Peer::err_ctx( &self.peer, None, self.cid, "Send item on closed bidirectional channel".to_string() );
+
+			return Poll::Ready( Err( PeerErr::ConnectionClosed{ ctx } ) );
+		}
+
+		Sink::<Wf>::poll_ready( Pin::new( &mut self.peer ), cx ).map_err( |e| self.err(e) )
+	}
+
+
+	fn start_send( mut self: Pin<&mut Self>, mut item: Wf ) -> Result<(), Self::Error>
+	{
+		item.set_sid( ServiceID::channel_item() );
+		item.set_cid( self.cid                  );
+
+		Pin::new( &mut self.peer ).start_send( item ).map_err( |e| self.err(e) )
+	}
+
+
+	fn poll_flush( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll<Result<(), Self::Error>>
+	{
+		Sink::<Wf>::poll_flush( Pin::new( &mut self.peer ), cx ).map_err( |e| self.err(e) )
+	}
+
+
+	/// Flushes the items and tells the remote there will be no more.
+	//
+	fn poll_close( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll<Result<(), Self::Error>>
+	{
+		if self.close.is_some()
+		{
+			futures::ready!( Sink::<Wf>::poll_flush( Pin::new( &mut self.peer ), cx ) ).map_err( |e| self.err(e) )?;
+
+			self.close = None;
+		}
+
+		Poll::Ready( Ok(()) )
+	}
+}
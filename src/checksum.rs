@@ -0,0 +1,173 @@
+//! Integrity checksums on frames. The encoder of the wire format appends the checksum, the decoder verifies and
+//! strips it, so it is transparent to the peer. See [`Peer::set_checksum`](crate::Peer::set_checksum).
+//!
+//! A frame with a checksum has a flag in the most significant bit of the length field, next to the compression
+//! flag. The checksum is the xxh3 64 bit hash of the entire frame before it, length field included, as a u64 LE.
+//! The length counts the checksum:
+//!
+//! ```text
+//! u8 flags | u56 length | 16 bytes sid | 8 bytes connID | payload | u64 LE xxh3 of everything before |
+//! ```
+//!
+//! The checksum is added after compression, so it covers the bytes on the wire. The max size of the decoder
+//! doesn't count the checksum.
+//
+use crate::{ import::*, WireErr, compression::split_len, cbor_wf::{ LEN_LEN, IDX_LEN, LEN_HEADER } };
+
+
+/// The length of the checksum at the end of the frame.
+//
+pub(crate) const LEN_CHECKSUM: usize = 8; // u64
+
+// The flag lives in the most significant bit of the length field.
+//
+const FLAG_CHECKSUM: u8  = 0b1000_0000;
+const SHIFT_FLAG   : u32 = 56;
+
+
+
+/// The number of bytes the checksum adds to a frame with these flags.
+//
+pub(crate) fn overhead( flags: u8 ) -> usize
+{
+	if flags & FLAG_CHECKSUM == 0 { 0 } else { LEN_CHECKSUM }
+}
+
+
+/// Whether `frame` has the checksum flag.
+//
+pub(crate) fn sealed( frame: &[u8] ) -> bool
+{
+	overhead( split_len( read_len( frame ) ).1 ) != 0
+}
+
+
+/// Append a checksum to `frame` and flag it in the length field.
+//
+pub(crate) fn seal( mut frame: Vec<u8> ) -> Vec<u8>
+{
+	let (len, flags) = split_len( read_len( &frame ) );
+	let field        = ( len + LEN_CHECKSUM as u64 ) | u64::from( flags | FLAG_CHECKSUM ) << SHIFT_FLAG;
+
+	frame[ IDX_LEN..IDX_LEN+LEN_LEN ].copy_from_slice( &field.to_le_bytes() );
+
+	let hash = xxh3::hash64( &frame );
+	frame.extend_from_slice( &hash.to_le_bytes() );
+
+	frame
+}
+
+
+/// Verify and strip the checksum of `frame` if it has the flag. Otherwise it is returned unchanged.
+//
+pub(crate) fn verify( mut frame: Vec<u8> ) -> Result< Vec<u8>, WireErr >
+{
+	let (len, flags) = split_len( read_len( &frame ) );
+
+	if flags & FLAG_CHECKSUM == 0
+	{
+		return Ok( frame );
+	}
+
+	if frame.len() < LEN_HEADER + LEN_CHECKSUM || len != frame.len() as u64
+	{
+		return Err( WireErr::Deserialize{ context: "Checksummed frame: not enough bytes for the checksum.".to_string() } );
+	}
+
+	let end = frame.len() - LEN_CHECKSUM;
+
+	let mut field = [0u8; LEN_CHECKSUM];
+	field.copy_from_slice( &frame[ end.. ] );
+
+	let expected = u64::from_le_bytes( field );
+	let actual   = xxh3::hash64( &frame[ ..end ] );
+
+	if expected != actual
+	{
+		return Err( WireErr::Checksum{ expected: Some(expected), actual: Some(actual) } );
+	}
+
+	frame.truncate( end );
+
+	let field = end as u64 | u64::from( flags & !FLAG_CHECKSUM ) << SHIFT_FLAG;
+	frame[ IDX_LEN..IDX_LEN+LEN_LEN ].copy_from_slice( &field.to_le_bytes() );
+
+	Ok( frame )
+}
+
+
+fn read_len( frame: &[u8] ) -> u64
+{
+	let mut field = [0u8; LEN_LEN];
+	field.copy_from_slice( &frame[ IDX_LEN..IDX_LEN+LEN_LEN ] );
+
+	u64::from_le_bytes( field )
+}
+
+
+
+#[ cfg(test) ]
+//
+mod tests
+{
+	// Tests:
+	//
+	// - sealed frames verify to the original
+	// - frames without the flag are returned unchanged
+	// - a flipped bit anywhere in the frame is detected
+	//
+	use
+	{
+		super :: { *, assert_eq                          } ,
+		crate :: { CborWF, WireFormat, ServiceID, ConnID } ,
+		std   :: { io::Write                             } ,
+	};
+
+
+	fn frame( payload: &[u8] ) -> CborWF
+	{
+		let mut wf = CborWF::with_capacity( payload.len() );
+
+		wf.set_sid( ServiceID::from_seed( &[ 1, 2, 3 ] ) );
+		wf.set_cid( ConnID::random() );
+		wf.write_all( payload ).expect( "write payload" );
+
+		wf
+	}
+
+
+	#[test]
+	//
+	fn roundtrip()
+	{
+		let wf     = frame( b"payload" );
+		let sealed = seal( wf.as_buf().to_vec() );
+
+		assert_eq!( wf.as_buf().len() + LEN_CHECKSUM, sealed.len() );
+		assert_eq!( LEN_CHECKSUM, overhead( split_len( read_len( &sealed ) ).1 ) );
+
+		assert_eq!( wf.as_buf(), &verify( sealed ).expect( "verify" )[..] );
+
+		// Without the flag.
+		//
+		assert_eq!( wf.as_buf(), &verify( wf.as_buf().to_vec() ).expect( "verify" )[..] );
+	}
+
+
+	#[test]
+	//
+	fn corrupt()
+	{
+		let sealed = seal( frame( b"payload" ).as_buf().to_vec() );
+
+		// Skip the length field, corrupting that changes the size of the frame, which the decoder catches before.
+		//
+		for i in LEN_LEN..sealed.len()
+		{
+			let mut corrupt = sealed.clone();
+			corrupt[i] ^= 0b0001_0000;
+
+			assert!( matches!( verify( corrupt ), Err( WireErr::Checksum{..} ) ), "byte {i}" );
+		}
+	}
+}
@@ -0,0 +1,173 @@
+//! The codec decides how the payload of a message is serialized. The `service_map!` macro takes it as
+//! a parameter, so both ends of a connection need to use the same codec for a given service map.
+//!
+//! The control frames of the peer itself (errors, handshake, discovery, ...) always use [CborCodec],
+//! so peers can talk to each other regardless of the codec of their service maps.
+//
+use crate::{ import::* };
+
+
+/// Serialize and deserialize the payload of messages.
+//
+pub trait Codec: fmt::Debug + Send + Sync + 'static
+{
+	/// The name of the codec, used in error messages.
+	//
+	const NAME: &'static str;
+
+	/// Serialize `value` into `writer`.
+	//
+	fn encode<T: Serialize>( writer: impl io::Write, value: &T ) -> Result<(), CodecErr>;
+
+	/// Deserialize a value from `bytes`.
+	//
+	fn decode<T: DeserializeOwned>( bytes: &[u8] ) -> Result<T, CodecErr>;
+}
+
+
+
+/// The codec failed to serialize or deserialize a value.
+//
+#[ derive( Debug, Clone, PartialEq, Eq ) ]
+//
+pub struct CodecErr
+{
+	/// The name of the codec.
+	//
+	pub codec: &'static str,
+
+	/// The error reported by the serialization library.
+	//
+	pub error: String,
+}
+
+
+impl CodecErr
+{
+	fn new<C: Codec>( error: impl fmt::Display ) -> Self
+	{
+		Self{ codec: C::NAME, error: error.to_string() }
+	}
+}
+
+
+impl std::error::Error for CodecErr {}
+
+
+impl fmt::Display for CodecErr
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		write!( f, "Codec {}: {}", self.codec, self.error )
+	}
+}
+
+
+
+/// CBOR, with [serde_cbor]. This is the default codec.
+//
+#[ derive( Debug, Clone, Copy, Default ) ]
+//
+pub struct CborCodec;
+
+impl Codec for CborCodec
+{
+	const NAME: &'static str = "CBOR";
+
+	fn encode<T: Serialize>( writer: impl io::Write, value: &T ) -> Result<(), CodecErr>
+	{
+		serde_cbor::to_writer( writer, value ).map_err( CodecErr::new::<Self> )
+	}
+
+	fn decode<T: DeserializeOwned>( bytes: &[u8] ) -> Result<T, CodecErr>
+	{
+		serde_cbor::from_slice( bytes ).map_err( CodecErr::new::<Self> )
+	}
+}
+
+
+
+/// Bincode, with [bincode](https://docs.rs/bincode). The most compact and the fastest, but it is not self
+/// describing, so it does not support types that rely on `deserialize_any`, like untagged enums.
+//
+#[ cfg( feature = "bincode" ) ]
+#[ cfg_attr( nightly, doc(cfg( feature = "bincode" )) ) ]
+//
+#[ derive( Debug, Clone, Copy, Default ) ]
+//
+pub struct BincodeCodec;
+
+#[ cfg( feature = "bincode" ) ]
+//
+impl Codec for BincodeCodec
+{
+	const NAME: &'static str = "bincode";
+
+	fn encode<T: Serialize>( writer: impl io::Write, value: &T ) -> Result<(), CodecErr>
+	{
+		bincode::serialize_into( writer, value ).map_err( CodecErr::new::<Self> )
+	}
+
+	fn decode<T: DeserializeOwned>( bytes: &[u8] ) -> Result<T, CodecErr>
+	{
+		bincode::deserialize( bytes ).map_err( CodecErr::new::<Self> )
+	}
+}
+
+
+
+/// MessagePack, with [rmp-serde](https://docs.rs/rmp-serde). Structs are serialized as maps, so fields
+/// can be added with `#[serde(default)]` without breaking older peers.
+//
+#[ cfg( feature = "msgpack" ) ]
+#[ cfg_attr( nightly, doc(cfg( feature = "msgpack" )) ) ]
+//
+#[ derive( Debug, Clone, Copy, Default ) ]
+//
+pub struct MsgPackCodec;
+
+#[ cfg( feature = "msgpack" ) ]
+//
+impl Codec for MsgPackCodec
+{
+	const NAME: &'static str = "MessagePack";
+
+	fn encode<T: Serialize>( mut writer: impl io::Write, value: &T ) -> Result<(), CodecErr>
+	{
+		rmp_serde::encode::write_named( &mut writer, value ).map_err( CodecErr::new::<Self> )
+	}
+
+	fn decode<T: DeserializeOwned>( bytes: &[u8] ) -> Result<T, CodecErr>
+	{
+		rmp_serde::from_slice( bytes ).map_err( CodecErr::new::<Self> )
+	}
+}
+
+
+
+/// JSON, with [serde_json](https://docs.rs/serde_json). The least compact, but you can read the payload
+/// when debugging.
+//
+#[ cfg( feature = "json" ) ]
+#[ cfg_attr( nightly, doc(cfg( feature = "json" )) ) ]
+//
+#[ derive( Debug, Clone, Copy, Default ) ]
+//
+pub struct JsonCodec;
+
+#[ cfg( feature = "json" ) ]
+//
+impl Codec for JsonCodec
+{
+	const NAME: &'static str = "JSON";
+
+	fn encode<T: Serialize>( writer: impl io::Write, value: &T ) -> Result<(), CodecErr>
+	{
+		serde_json::to_writer( writer, value ).map_err( CodecErr::new::<Self> )
+	}
+
+	fn decode<T: DeserializeOwned>( bytes: &[u8] ) -> Result<T, CodecErr>
+	{
+		serde_json::from_slice( bytes ).map_err( CodecErr::new::<Self> )
+	}
+}
@@ -0,0 +1,424 @@
+//! Compression of the payload of frames. Compression happens in the encoder of the wire format, so it is
+//! transparent to the codec and the service maps. See [`Peer::set_compression`](crate::Peer::set_compression).
+//!
+//! A compressed frame has a flag in the most significant byte of the length field, which tells the algorithm.
+//! The header stays uncompressed, so sid and cid can be read without decompressing. The payload starts with
+//! the size of the original payload as a u64 LE, followed by the compressed bytes:
+//!
+//! ```text
+//! u8 flag | u56 length | 16 bytes sid | 8 bytes connID | u64 LE original size | compressed payload |
+//! ```
+//!
+//! The max size of the decoder applies to the decompressed frame. The original size is checked before
+//! decompressing and the decompressor will not produce more than that, so a small frame on the wire can't
+//! be used to make the decoder allocate more than max size.
+//
+use crate::{ import::*, WireErr, cbor_wf::{ LEN_LEN, IDX_LEN, IDX_SID, IDX_MSG, LEN_HEADER } };
+
+
+// The length of the field holding the size of the original payload in compressed frames.
+//
+const LEN_ORIG: usize = 8; // u64
+
+// The flag lives in the most significant byte of the length field.
+//
+const SHIFT_FLAG: u32 = 56;
+const MASK_LEN  : u64 = u64::MAX >> 8;
+
+const FLAG_LZ4 : u8 = 1;
+const FLAG_ZSTD: u8 = 2;
+
+
+
+/// The compression algorithm for the payload of frames. Each algorithm needs the feature with the same name.
+/// Both peers need to enable it, otherwise the remote will fail to decode compressed frames.
+//
+#[ derive( Debug, Clone, Copy, PartialEq, Eq ) ]
+//
+pub enum Algorithm
+{
+	/// LZ4, with [lz4_flex](https://docs.rs/lz4_flex). Very fast, with a moderate compression ratio.
+	//
+	#[ cfg( feature = "lz4" ) ]
+	#[ cfg_attr( nightly, doc(cfg( feature = "lz4" )) ) ]
+	//
+	Lz4,
+
+	/// Zstandard, with [zstd](https://docs.rs/zstd). Better compression than LZ4, at a higher cost.
+	/// The level goes from 1 to 22. 0 means the default of zstd, which is currently 3.
+	//
+	#[ cfg( feature = "zstd" ) ]
+	#[ cfg_attr( nightly, doc(cfg( feature = "zstd" )) ) ]
+	//
+	Zstd
+	{
+		/// The compression level.
+		//
+		level: i32
+	},
+}
+
+
+impl Algorithm
+{
+	#[ allow( unreachable_code, unused_variables ) ]
+	//
+	fn flag( &self ) -> u8
+	{
+		match *self
+		{
+			#[ cfg( feature = "lz4"  ) ] Algorithm::Lz4     => FLAG_LZ4  ,
+			#[ cfg( feature = "zstd" ) ] Algorithm::Zstd{..} => FLAG_ZSTD ,
+		}
+	}
+
+
+	#[ allow( unreachable_code, unused_variables ) ]
+	//
+	fn compress( &self, payload: &[u8] ) -> Option< Vec<u8> >
+	{
+		match *self
+		{
+			#[ cfg( feature = "lz4"  ) ] Algorithm::Lz4           => Some( lz4_flex::block::compress( payload ) ),
+			#[ cfg( feature = "zstd" ) ] Algorithm::Zstd{ level } => zstd::bulk::compress( payload, level ).ok(),
+		}
+	}
+}
+
+
+
+/// A set of compression algorithms. Peers advertise the algorithms they can decode in the [`Handshake`](crate::Handshake)
+/// and only compress with one the remote advertised. See [`Features::compression`](crate::Features::compression).
+//
+#[ derive( Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize ) ]
+//
+pub struct Algorithms
+{
+	/// [`Algorithm::Lz4`].
+	//
+	#[ serde( default ) ] pub lz4: bool,
+
+	/// [`Algorithm::Zstd`], at any level.
+	//
+	#[ serde( default ) ] pub zstd: bool,
+}
+
+
+impl Algorithms
+{
+	/// The algorithms enabled in this build of the crate.
+	//
+	pub fn supported() -> Self
+	{
+		Self
+		{
+			lz4 : cfg!( feature = "lz4"  ),
+			zstd: cfg!( feature = "zstd" ),
+		}
+	}
+
+
+	/// Whether the set is empty.
+	//
+	pub fn is_empty( &self ) -> bool
+	{
+		!self.lz4 && !self.zstd
+	}
+
+
+	/// The algorithms in both sets.
+	//
+	pub fn intersect( &self, other: &Algorithms ) -> Algorithms
+	{
+		Algorithms
+		{
+			lz4 : self.lz4  && other.lz4  ,
+			zstd: self.zstd && other.zstd ,
+		}
+	}
+
+
+	/// Whether `algorithm` is in the set.
+	//
+	#[ allow( unreachable_code, unused_variables ) ]
+	//
+	pub fn contains( &self, algorithm: Algorithm ) -> bool
+	{
+		match algorithm
+		{
+			#[ cfg( feature = "lz4"  ) ] Algorithm::Lz4     => self.lz4  ,
+			#[ cfg( feature = "zstd" ) ] Algorithm::Zstd{..} => self.zstd ,
+		}
+	}
+
+
+	/// Choose the algorithm to compress with. That's `preferred` if it's in the set, otherwise another one from the
+	/// set with its default settings. Returns `None` if the set has nothing this build supports.
+	//
+	pub fn pick( &self, preferred: Algorithm ) -> Option<Algorithm>
+	{
+		if self.contains( preferred ) { return Some( preferred ) }
+
+		#[ cfg( feature = "lz4"  ) ] if self.lz4  { return Some( Algorithm::Lz4 ) }
+		#[ cfg( feature = "zstd" ) ] if self.zstd { return Some( Algorithm::Zstd{ level: 0 } ) }
+
+		None
+	}
+}
+
+
+
+/// How to compress outgoing frames. See [`Peer::set_compression`](crate::Peer::set_compression).
+//
+#[ derive( Debug, Clone, Copy, PartialEq, Eq ) ]
+//
+pub struct Compression
+{
+	/// The compression algorithm.
+	//
+	pub algorithm: Algorithm,
+
+	/// Payloads smaller than this many bytes are sent uncompressed. Compressing small messages usually
+	/// costs more than it saves.
+	//
+	pub threshold: usize,
+}
+
+
+impl Compression
+{
+	/// Compress payloads of at least `threshold` bytes with `algorithm`.
+	//
+	pub fn new( algorithm: Algorithm, threshold: usize ) -> Self
+	{
+		Self { algorithm, threshold }
+	}
+}
+
+
+
+/// Split the length field into the length of the frame and the compression flag.
+//
+pub(crate) fn split_len( field: u64 ) -> (u64, u8)
+{
+	( field & MASK_LEN, (field >> SHIFT_FLAG) as u8 )
+}
+
+
+/// Compress the payload of `frame`. Returns `None` if the payload is smaller than the threshold or
+/// if compression doesn't make the frame smaller, in which case it should be sent as is.
+//
+pub(crate) fn compress( frame: &[u8], compression: &Compression ) -> Option< Vec<u8> >
+{
+	let payload = &frame[ IDX_MSG.. ];
+
+	if payload.len() < compression.threshold
+	{
+		return None;
+	}
+
+	let packed = compression.algorithm.compress( payload )?;
+
+	if LEN_ORIG + packed.len() >= payload.len()
+	{
+		return None;
+	}
+
+	let len   = LEN_HEADER + LEN_ORIG + packed.len();
+	let field = len as u64 | u64::from( compression.algorithm.flag() ) << SHIFT_FLAG;
+
+	let mut out = Vec::with_capacity( len );
+
+	out.extend_from_slice( &field.to_le_bytes()                  );
+	out.extend_from_slice( &frame[ IDX_SID..IDX_MSG ]            );
+	out.extend_from_slice( &(payload.len() as u64).to_le_bytes() );
+	out.extend_from_slice( &packed                               );
+
+	Some( out )
+}
+
+
+/// Decompress `frame` if it has a compression flag. Otherwise it is returned unchanged.
+///
+/// `max_size` applies to the decompressed frame. It is checked before decompressing.
+//
+pub(crate) fn decompress( frame: Vec<u8>, max_size: usize ) -> Result< Vec<u8>, WireErr >
+{
+	let mut field = [0u8; LEN_LEN];
+	field.copy_from_slice( &frame[ IDX_LEN..IDX_LEN+LEN_LEN ] );
+
+	let (_, flag) = split_len( u64::from_le_bytes( field ) );
+
+	if flag == 0
+	{
+		return Ok( frame );
+	}
+
+	if frame.len() < LEN_HEADER + LEN_ORIG
+	{
+		return Err( WireErr::Deserialize{ context: "Compressed frame: not enough bytes for the original size.".to_string() } );
+	}
+
+	let mut orig = [0u8; LEN_ORIG];
+	orig.copy_from_slice( &frame[ IDX_MSG..IDX_MSG+LEN_ORIG ] );
+
+	// If it doesn't fit in usize, it's definitely bigger than max_size.
+	//
+	let orig = usize::try_from( u64::from_le_bytes( orig ) ).unwrap_or( usize::MAX );
+	let size = orig.saturating_add( LEN_HEADER );
+
+	if size > max_size
+	{
+		return Err( WireErr::MessageSizeExceeded
+		{
+			context: "Decompressed frame".to_string(),
+			size                                     ,
+			max_size                                 ,
+		});
+	}
+
+	let payload = unpack( flag, &frame[ IDX_MSG+LEN_ORIG.. ], orig )?;
+
+	if payload.len() != orig
+	{
+		return Err( WireErr::Deserialize{ context: "Compressed frame: decompressed payload doesn't match the original size.".to_string() } );
+	}
+
+	let mut out = Vec::with_capacity( size );
+
+	out.extend_from_slice( &(size as u64).to_le_bytes() );
+	out.extend_from_slice( &frame[ IDX_SID..IDX_MSG ]   );
+	out.extend_from_slice( &payload                     );
+
+	Ok( out )
+}
+
+
+// Decompress with the algorithm identified by `flag`, producing at most `orig` bytes.
+//
+#[ allow( unused_variables ) ]
+//
+fn unpack( flag: u8, packed: &[u8], orig: usize ) -> Result< Vec<u8>, WireErr >
+{
+	let context = |e: &dyn fmt::Display| WireErr::Deserialize{ context: format!( "Compressed frame: {e}" ) };
+
+	match flag
+	{
+		#[ cfg( feature = "lz4" ) ]
+		//
+		FLAG_LZ4 => lz4_flex::block::decompress( packed, orig ).map_err( |e| context( &e ) ),
+
+		#[ cfg( feature = "zstd" ) ]
+		//
+		FLAG_ZSTD => zstd::bulk::decompress( packed, orig ).map_err( |e| context( &e ) ),
+
+		#[ cfg( not( feature = "lz4" ) ) ]
+		//
+		FLAG_LZ4 => Err( context( &"compressed with LZ4, but the lz4 feature is not enabled." ) ),
+
+		#[ cfg( not( feature = "zstd" ) ) ]
+		//
+		FLAG_ZSTD => Err( context( &"compressed with zstd, but the zstd feature is not enabled." ) ),
+
+		x => Err( context( &format!( "unknown compression flag: {x}." ) ) ),
+	}
+}
+
+
+
+#[ cfg(all( test, any( feature = "lz4", feature = "zstd" ) )) ]
+//
+mod tests
+{
+	// Tests:
+	//
+	// - compressed frames decompress to the original
+	// - small or incompressible payloads are not compressed
+	// - the original size is checked against max_size before decompressing
+	//
+	use
+	{
+		super :: { *, assert_eq                          } ,
+		crate :: { CborWF, WireFormat, ServiceID, ConnID } ,
+		std   :: { io::Write                             } ,
+	};
+
+
+	fn algorithm() -> Algorithm
+	{
+		#[ cfg( feature = "lz4" ) ] { Algorithm::Lz4 }
+		#[ cfg( not( feature = "lz4" ) ) ] { Algorithm::Zstd{ level: 0 } }
+	}
+
+
+	fn frame( payload: &[u8] ) -> CborWF
+	{
+		let mut wf = CborWF::with_capacity( payload.len() );
+
+		wf.set_sid( ServiceID::from_seed( &[ 1, 2, 3 ] ) );
+		wf.set_cid( ConnID::random() );
+		wf.write_all( payload ).expect( "write payload" );
+
+		wf
+	}
+
+
+	#[test]
+	//
+	fn roundtrip()
+	{
+		let wf     = frame( &[ 7; 1000 ] );
+		let packed = compress( wf.as_buf(), &Compression::new( algorithm(), 0 ) ).expect( "compress" );
+
+		assert!( packed.len() < wf.as_buf().len() );
+		assert_eq!( algorithm().flag(), split_len( u64::from_le_bytes( packed[ ..LEN_LEN ].try_into().unwrap() ) ).1 );
+		assert_eq!( &wf.as_buf()[ IDX_SID..IDX_MSG ], &packed[ IDX_SID..IDX_MSG ] );
+
+		let unpacked = decompress( packed, 1024 + LEN_HEADER ).expect( "decompress" );
+
+		assert_eq!( wf.as_buf(), &unpacked[..] );
+	}
+
+
+	#[test]
+	//
+	fn skip()
+	{
+		let compression = Compression::new( algorithm(), 100 );
+
+		assert!( compress( frame( &[ 7; 99 ] ).as_buf(), &compression ).is_none() );
+
+		// Not compressible.
+		//
+		let mut noise = vec![ 0u8; 1000 ];
+		rand::thread_rng().fill( &mut noise[..] );
+
+		assert!( compress( frame( &noise ).as_buf(), &compression ).is_none() );
+
+		// Uncompressed frames are returned unchanged.
+		//
+		let wf = frame( &noise );
+		assert_eq!( wf.as_buf(), &decompress( wf.as_buf().to_vec(), 2000 ).expect( "decompress" )[..] );
+	}
+
+
+	#[test]
+	//
+	fn bomb()
+	{
+		let packed = compress( frame( &vec![ 0; 1_000_000 ] ).as_buf(), &Compression::new( algorithm(), 0 ) ).expect( "compress" );
+
+		assert!( packed.len() < 10_000 );
+
+		match decompress( packed, 10_000 )
+		{
+			Err( WireErr::MessageSizeExceeded{ size, max_size, .. } ) =>
+			{
+				assert_eq!( 1_000_000 + LEN_HEADER, size     );
+				assert_eq!( 10_000                , max_size );
+			}
+
+			_ => unreachable!( "Should be WireErr::MessageSizeExceeded" ),
+		}
+	}
+}
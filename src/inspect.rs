@@ -0,0 +1,110 @@
+//! Turn frames into JSON for humans to read. This is what the `thespis_inspect` binary uses, but it's also useful
+//! for logging frames from your own code.
+//!
+//! Every frame becomes an object like:
+//!
+//! ```text
+//! { "kind": "IncomingCall", "sid": "<32 hex digits>", "service": "remotes::Show", "cid": 1, "len": 33, "payload": null }
+//! ```
+//!
+//! `service` is only known for services that were registered, see [`ServiceID::import_names`]. The payload is decoded as
+//! CBOR. When that fails, because the service uses another codec, it's `null` and the raw bytes are given in `hex`.
+//! Frames of a capture also have `direction` and `elapsed_us`.
+//
+use crate::{ import::*, WireFormat, WireType, WireErr, CborWF, CborCodec, Codec, ConnectionError, ServiceID, Decoder, Record };
+
+
+/// Describe one frame.
+//
+pub fn inspect_frame( wf: &impl WireFormat ) -> serde_json::Value
+{
+	let sid  = wf.sid();
+	let kind = wf.kind();
+
+	let mut obj = serde_json::Map::new();
+
+	obj.insert( "kind"   .to_string(), format!( "{kind:?}" ).into()                  );
+	obj.insert( "sid"    .to_string(), format!( "{:032x}", u128::from( sid ) ).into() );
+	obj.insert( "service".to_string(), ServiceID::service_name( sid ).into()           );
+	obj.insert( "cid"    .to_string(), u64::from( wf.cid() ).into()                    );
+	obj.insert( "len"    .to_string(), wf.len().into()                                 );
+	obj.insert( "payload".to_string(), serde_json::Value::Null                          );
+
+	let msg = wf.msg();
+
+	if msg.is_empty()
+	{
+		return obj.into();
+	}
+
+	let payload = match kind
+	{
+		WireType::ConnectionError => CborCodec::decode::<ConnectionError>( msg ).ok().and_then( |e| serde_json::to_value( e ).ok() ),
+		_                         => CborCodec::decode::<serde_cbor::Value>( msg ).ok().and_then( |v| serde_json::to_value( v ).ok() ),
+	};
+
+	match payload
+	{
+		Some( payload ) => { obj.insert( "payload".to_string(), payload           ); }
+		None            => { obj.insert( "hex"    .to_string(), hex( msg ).into() ); }
+	}
+
+	obj.into()
+}
+
+
+
+/// Describe one frame of a [Capture](crate::Capture), with its direction and the time at which it was recorded.
+//
+pub fn inspect_record( record: &Record ) -> Result<serde_json::Value, WireErr>
+{
+	let mut value = inspect_frame( &record.to_wf::<CborWF>()? );
+
+	if let serde_json::Value::Object( obj ) = &mut value
+	{
+		obj.insert( "direction" .to_string(), format!( "{:?}", record.direction ).into() );
+		obj.insert( "elapsed_us".to_string(), u64::try_from( record.elapsed.as_micros() ).unwrap_or( u64::MAX ).into() );
+	}
+
+	Ok( value )
+}
+
+
+
+/// Describe the frames in a byte stream as written by the encoder of [CborWF]. Compressed frames and checksums
+/// are handled like the decoder would. Decoding stops at the first error, which is the last item.
+//
+pub fn inspect_stream( bytes: Vec<u8>, max_size: usize ) -> Vec< Result<serde_json::Value, WireErr> >
+{
+	let mut decoder = Decoder::new( futures::io::Cursor::new( bytes ), max_size );
+	let mut frames  = Vec::new();
+
+	futures::executor::block_on( async
+	{
+		while let Some( frame ) = decoder.next().await
+		{
+			let failed = frame.is_err();
+
+			frames.push( frame.map( |wf| inspect_frame( &wf ) ) );
+
+			// The decoder can't be polled again after an error.
+			//
+			if failed { break }
+		}
+	});
+
+	frames
+}
+
+
+
+fn hex( bytes: &[u8] ) -> String
+{
+	use fmt::Write;
+
+	bytes.iter().fold( String::with_capacity( bytes.len() * 2 ), |mut s, b|
+	{
+		let _ = write!( s, "{b:02x}" );
+		s
+	})
+}
@@ -23,26 +23,70 @@
 )]
 
 
+    mod bytes_wf          ;
+    mod capture           ;
+    mod checksum          ;
     mod cbor_wf           ;
+    mod codec             ;
+    mod compression       ;
+    mod channel           ;
+    mod loopback          ;
+    mod message_transport ;
 pub mod peer              ;
     mod relay_map         ;
     mod pub_sub           ;
     mod service_handler   ;
     mod service_map       ;
     mod service_map_macro ;
+    mod varint_wf         ;
 pub mod wire_format       ;
 
+#[ cfg( feature = "tls" ) ]
+#[ cfg_attr( nightly, doc(cfg( feature = "tls" )) ) ]
+//
+pub mod tls;
+
+#[ cfg( feature = "inspect" ) ]
+#[ cfg_attr( nightly, doc(cfg( feature = "inspect" )) ) ]
+//
+mod inspect;
+
+#[ cfg( feature = "wf_test" ) ]
+#[ cfg_attr( nightly, doc(cfg( feature = "wf_test" )) ) ]
+//
+mod mock_remote;
+
 pub use
 {
+	bytes_wf          :: * ,
+	capture           :: * ,
 	cbor_wf           :: * ,
+	codec             :: * ,
+	compression       :: * ,
+	channel           :: * ,
+	loopback          :: * ,
+	message_transport :: * ,
 	peer              :: * ,
 	pub_sub           :: * ,
 	relay_map         :: * ,
 	service_handler   :: * ,
 	service_map       :: * ,
+	varint_wf         :: * ,
 	wire_format       :: * ,
 };
 
+#[ cfg( feature = "tls" ) ]
+//
+pub use tls::{ Tls, PeerIdentity };
+
+#[ cfg( feature = "wf_test" ) ]
+//
+pub use mock_remote::MockRemote;
+
+#[ cfg( feature = "inspect" ) ]
+//
+pub use inspect::{ inspect_frame, inspect_record, inspect_stream };
+
 
 // needed for macro
 //
@@ -81,24 +125,23 @@ mod import
 		parking_lot     :: { Mutex                                               } ,
 		pharos          :: { Pharos, Observe, Observable, ObserveConfig, PharErr } ,
 		rand            :: { Rng                                                 } ,
-		serde           :: { Serialize, Deserialize                              } ,
+		serde           :: { Serialize, Deserialize, de::DeserializeOwned        } ,
 		thespis         :: { *                                                   } ,
 		thespis_impl    :: { Addr, WeakAddr, ThesErr, Mailbox, DynError          } ,
-		twox_hash       :: { XxHash64                                            } ,
+		twox_hash       :: { xxh3                                                } ,
 
 		std ::
 		{
-			collections  :: { HashMap                } ,
+			collections  :: { HashMap, VecDeque       } ,
 			convert      :: { TryFrom, TryInto       } ,
 			fmt                                        ,
 			io                                         ,
 			future       :: { Future                 } ,
-			hash         :: { Hasher                 } ,
 			marker       :: { PhantomData            } ,
 			pin          :: { Pin                    } ,
 			sync         :: { Arc                    } ,
-			sync::atomic :: { AtomicU64, Ordering::* } ,
-			task         :: { Poll, Context          } ,
+			sync::atomic :: { AtomicBool, AtomicU64, AtomicUsize, Ordering::* } ,
+			task         :: { Poll, Context, Waker   } ,
 			time         :: { Duration               } ,
 		},
 
@@ -106,17 +149,23 @@ mod import
 		futures ::
 		{
 			channel :: { oneshot, mpsc::{ self, UnboundedSender as futUnboundSender } } ,
-			future  :: { FutureExt                                                    } ,
+			future  :: { FutureExt, Either, AbortHandle, Abortable                    } ,
 			prelude :: { Stream, Sink                                                 } ,
 			sink    :: { SinkExt                                                      } ,
 			stream  :: { StreamExt, FuturesUnordered                                  } ,
 			AsyncRead, AsyncReadExt,
 			AsyncWrite,
-			pin_mut,
+			pin_mut, ready,
 		},
 	};
 
 
+	// std::time::Instant panics on wasm32-unknown-unknown.
+	//
+	#[ cfg(not( target_arch = "wasm32" )) ] pub(crate) use std::time::Instant;
+	#[ cfg(     target_arch = "wasm32"  ) ] pub(crate) use web_time::Instant;
+
+
 	#[ cfg(test) ]
 	//
 	pub(crate) use
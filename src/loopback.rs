@@ -0,0 +1,125 @@
+//! In process connections. Frames are handed to the other end over a channel, without being written to and read
+//! from a byte stream. The messages are still serialized by the service maps, so peers on a loopback behave exactly
+//! like peers on a network connection: routing, timeouts and errors are the same.
+//
+use crate::{ import::*, WireFormat, WireErr };
+
+
+// How many frames can be waiting in the channel before the sink applies back pressure.
+//
+const BOUND: usize = 16;
+
+
+
+/// Create an in process connection. Returns a Stream/Sink pair for each end, to pass to
+/// [`Peer::from_framed`](crate::Peer::from_framed). Frames bigger than `max_size` are refused by the sink, with
+/// [`WireErr::MessageSizeExceeded`], just like an encoder would.
+///
+/// ```ignore
+/// let ((in_a, out_a), (in_b, out_b)) = loopback::<CborWF>( 1024 );
+///
+/// let (peer_a, mb_a, addr_a) = Peer::from_framed( "a", in_a, out_a, AsyncStd, None, None ).expect( "create peer" );
+/// let (peer_b, mb_b, addr_b) = Peer::from_framed( "b", in_b, out_b, AsyncStd, None, None ).expect( "create peer" );
+/// ```
+//
+pub fn loopback<Wf: WireFormat>( max_size: usize )
+
+	-> ( (LoopbackStream<Wf>, LoopbackSink<Wf>), (LoopbackStream<Wf>, LoopbackSink<Wf>) )
+
+{
+	let (tx_a, rx_b) = mpsc::channel( BOUND );
+	let (tx_b, rx_a) = mpsc::channel( BOUND );
+
+	let a = ( LoopbackStream{ rx: rx_a }, LoopbackSink{ tx: tx_a, max_size } );
+	let b = ( LoopbackStream{ rx: rx_b }, LoopbackSink{ tx: tx_b, max_size } );
+
+	(a, b)
+}
+
+
+
+/// The outgoing side of a [loopback] connection.
+//
+#[ derive(Debug) ]
+//
+pub struct LoopbackSink<Wf>
+{
+	tx      : mpsc::Sender<Wf> ,
+	max_size: usize            ,
+}
+
+
+impl<Wf: WireFormat> Sink<Wf> for LoopbackSink<Wf>
+{
+	type Error = WireErr;
+
+
+	fn poll_ready( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll< Result<(), Self::Error> >
+	{
+		self.tx.poll_ready( cx ).map_err( |_| aborted() )
+	}
+
+
+	fn start_send( mut self: Pin<&mut Self>, msg: Wf ) -> Result<(), Self::Error>
+	{
+		let len = msg.len() as usize;
+
+		if len > self.max_size
+		{
+			return Err( WireErr::MessageSizeExceeded
+			{
+				context : "LoopbackSink start_send".to_string(),
+				size    : len,
+				max_size: self.max_size,
+			});
+		}
+
+		self.tx.start_send( msg ).map_err( |_| aborted() )
+	}
+
+
+	fn poll_flush( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll<Result<(), Self::Error>>
+	{
+		Pin::new( &mut self.tx ).poll_flush( cx ).map_err( |_| aborted() )
+	}
+
+
+	/// The stream of the other end will end.
+	//
+	fn poll_close( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll<Result<(), Self::Error>>
+	{
+		Pin::new( &mut self.tx ).poll_close( cx ).map_err( |_| aborted() )
+	}
+}
+
+
+
+/// The incoming side of a [loopback] connection.
+//
+#[ derive(Debug) ]
+//
+pub struct LoopbackStream<Wf>
+{
+	rx: mpsc::Receiver<Wf>,
+}
+
+
+impl<Wf: WireFormat> Stream for LoopbackStream<Wf>
+{
+	type Item = Result<Wf, WireErr>;
+
+
+	fn poll_next( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll< Option<Self::Item> >
+	{
+		Pin::new( &mut self.rx ).poll_next( cx ).map( |msg| msg.map( Ok ) )
+	}
+}
+
+
+
+// The other end was dropped, like a network connection that was closed.
+//
+fn aborted() -> WireErr
+{
+	WireErr::from( io::Error::from( io::ErrorKind::ConnectionAborted ) )
+}
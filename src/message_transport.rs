@@ -0,0 +1,200 @@
+//! Framing for message oriented transports, like WebSockets. The transport already delimits messages, so
+//! every message is exactly one frame and there is no length field:
+//!
+//! ```text
+//! 16 bytes sid | 8 bytes connID | serialized message |
+//! u128 LE      | u64 LE         | variable           |
+//! ```
+//!
+//! This works for any [WireFormat], since it only uses the accessors of the trait. The max sizes apply to
+//! [`WireFormat::len`] of the messages, just like with a byte stream. Features that live in the encoder of the
+//! wire format, like compression and checksums, are not used. See [`WireFormat::create_peer_messages`].
+//
+use crate::{ import::*, WireFormat, WireErr };
+
+
+const LEN_SID   : usize = 16; // u128
+const LEN_CID   : usize = 8;  // u64
+const LEN_HEADER: usize = LEN_SID + LEN_CID;
+
+
+
+/// Turns a [Sink] of byte messages into a [Sink] of wire format messages.
+//
+#[ derive(Debug) ]
+//
+pub struct MessageEncoder<T, Wf>
+{
+	transport: T                   ,
+	max_size : usize               ,
+	_phantom : PhantomData<fn(Wf)> ,
+}
+
+
+impl<T, Wf> MessageEncoder<T, Wf>
+{
+	/// Create a new encoder. Messages bigger than `max_size` are refused.
+	//
+	pub fn new( transport: T, max_size: usize ) -> Self
+	{
+		Self { transport, max_size, _phantom: PhantomData }
+	}
+}
+
+
+impl<T, Wf> Sink<Wf> for MessageEncoder<T, Wf>
+
+	where T       : Sink< Vec<u8> > + Unpin                   ,
+	      T::Error: std::error::Error + Send + Sync + 'static ,
+	      Wf      : WireFormat                                ,
+
+{
+	type Error = WireErr;
+
+
+	fn poll_ready( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll< Result<(), Self::Error> >
+	{
+		Pin::new( &mut self.transport ).poll_ready( cx ).map_err( transport_err )
+	}
+
+
+	fn start_send( mut self: Pin<&mut Self>, msg: Wf ) -> Result<(), Self::Error>
+	{
+		let len = msg.len() as usize;
+
+		if len > self.max_size
+		{
+			return Err( WireErr::MessageSizeExceeded
+			{
+				context : "MessageEncoder start_send".to_string(),
+				size    : len,
+				max_size: self.max_size,
+			});
+		}
+
+		let mut out = Vec::with_capacity( LEN_HEADER + msg.msg().len() );
+
+		out.extend_from_slice( &u128::from( msg.sid() ).to_le_bytes() );
+		out.extend_from_slice( &u64 ::from( msg.cid() ).to_le_bytes() );
+		out.extend_from_slice( msg.msg()                              );
+
+		Pin::new( &mut self.transport ).start_send( out ).map_err( transport_err )
+	}
+
+
+	fn poll_flush( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll<Result<(), Self::Error>>
+	{
+		Pin::new( &mut self.transport ).poll_flush( cx ).map_err( transport_err )
+	}
+
+
+	fn poll_close( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll<Result<(), Self::Error>>
+	{
+		Pin::new( &mut self.transport ).poll_close( cx ).map_err( transport_err )
+	}
+}
+
+
+
+/// Turns a [Stream] of byte messages into a [Stream] of wire format messages.
+//
+#[ derive(Debug) ]
+//
+pub struct MessageDecoder<T, Wf>
+{
+	transport: T                       ,
+	max_size : usize                   ,
+	_phantom : PhantomData<fn() -> Wf> ,
+}
+
+
+impl<T, Wf> MessageDecoder<T, Wf>
+{
+	/// Create a new decoder. Messages bigger than `max_size` are refused.
+	//
+	pub fn new( transport: T, max_size: usize ) -> Self
+	{
+		Self { transport, max_size, _phantom: PhantomData }
+	}
+}
+
+
+impl<T, Wf> MessageDecoder<T, Wf>
+
+	where Wf: WireFormat
+
+{
+	fn decode( &self, data: Vec<u8> ) -> Result<Wf, WireErr>
+	{
+		if data.len() < LEN_HEADER
+		{
+			return Err( WireErr::Deserialize{ context: "MessageDecoder: not enough bytes even for the header.".to_string() } );
+		}
+
+		let payload = &data[ LEN_HEADER.. ];
+
+		// Don't bother copying the payload if it's too big by itself.
+		//
+		let too_big = |size| WireErr::MessageSizeExceeded
+		{
+			context : "MessageDecoder".to_string(),
+			size                                  ,
+			max_size: self.max_size               ,
+		};
+
+		if payload.len() > self.max_size
+		{
+			return Err( too_big( payload.len() ) );
+		}
+
+		let mut sid = [0u8; LEN_SID];
+		let mut cid = [0u8; LEN_CID];
+
+		sid.copy_from_slice( &data[ ..LEN_SID          ] );
+		cid.copy_from_slice( &data[ LEN_SID..LEN_HEADER ] );
+
+		let mut wf = Wf::with_capacity( payload.len() );
+
+		wf.set_sid( u128::from_le_bytes( sid ).into() );
+		wf.set_cid( u64 ::from_le_bytes( cid ).into() );
+		wf.write_all( payload )?;
+
+		let len = wf.len() as usize;
+
+		if len > self.max_size
+		{
+			return Err( too_big( len ) );
+		}
+
+		Ok( wf )
+	}
+}
+
+
+impl<T, Wf> Stream for MessageDecoder<T, Wf>
+
+	where T : Stream< Item = Vec<u8> > + Unpin ,
+	      Wf: WireFormat                       ,
+
+{
+	type Item = Result<Wf, WireErr>;
+
+
+	fn poll_next( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll< Option<Self::Item> >
+	{
+		match ready!( Pin::new( &mut self.transport ).poll_next( cx ) )
+		{
+			Some( data ) => Poll::Ready( Some( self.decode( data ) ) ),
+			None         => Poll::Ready( None ),
+		}
+	}
+}
+
+
+
+// Errors of the transport are reported as io errors, since that's what they are.
+//
+fn transport_err( err: impl std::error::Error + Send + Sync + 'static ) -> WireErr
+{
+	WireErr::from( io::Error::other( err ) )
+}
@@ -0,0 +1,210 @@
+//! A scripted remote to test a [Peer](crate::Peer) at the network boundary, without a second peer.
+//
+use crate::{ import::*, *, loopback::loopback };
+
+
+
+/// Holds the other end of a connection to a real [Peer]. A test scripts the frames to send, including ones a peer
+/// would never send, like calls to unknown services, malformed payloads or responses with a ConnID nobody is waiting
+/// for, and then looks at what comes back.
+///
+/// Messages are serialized with the codec `C`, which should be the one of the service map of the peer.
+///
+/// ```ignore
+/// let (mut mock, (incoming, outgoing)) = MockRemote::<CborWF>::pair( 1024 );
+///
+/// let (mut peer, peer_mb, _) = Peer::from_framed( "server", incoming, outgoing, AsyncStd, None, None )?;
+/// peer.register_services( Arc::new( services ) );
+/// AsyncStd.spawn( peer_mb.start(peer) )?;
+///
+/// let cid = mock.call( <Show as remotes::Service>::sid(), &Show ).await?;
+/// assert_eq!( 0, mock.recv_response::<u64>( cid ).await );
+/// ```
+//
+pub struct MockRemote<Wf = CborWF, C = CborCodec>
+{
+	incoming: Pin<Box< dyn Stream< Item = Result<Wf, WireErr> > + Send >> ,
+	outgoing: Pin<Box< dyn Sink< Wf, Error = WireErr >          + Send >> ,
+	next_cid: u64                                                         ,
+	_codec  : PhantomData<C>                                              ,
+}
+
+
+impl<Wf, C> MockRemote<Wf, C>
+
+	where Wf: WireFormat + Send + 'static ,
+	      C : Codec                       ,
+
+{
+	/// Create a mock remote over any connection. `incoming` is what the peer sends, `outgoing` goes to the peer.
+	//
+	pub fn new( incoming: impl BoundsIn<Wf>, outgoing: impl BoundsOut<Wf> ) -> Self
+	{
+		Self
+		{
+			incoming: Box::pin( incoming ),
+			outgoing: Box::pin( outgoing ),
+			next_cid: 1                   ,
+			_codec  : PhantomData         ,
+		}
+	}
+
+
+	/// Create a mock remote on a [loopback] connection. Returns the stream and sink to pass to
+	/// [`Peer::from_framed`](crate::Peer::from_framed).
+	//
+	pub fn pair( max_size: usize ) -> ( Self, (LoopbackStream<Wf>, LoopbackSink<Wf>) )
+	{
+		let ((incoming, outgoing), peer) = loopback( max_size );
+
+		( Self::new( incoming, outgoing ), peer )
+	}
+
+
+	/// Craft a frame. Nothing is checked, so this can create anything.
+	//
+	pub fn frame( sid: ServiceID, cid: ConnID, payload: &[u8] ) -> Wf
+	{
+		let mut wf = Wf::with_capacity( payload.len() );
+
+		wf.set_sid( sid );
+		wf.set_cid( cid );
+
+		// Writing to a wire format doesn't fail unless it's out of memory.
+		//
+		wf.write_all( payload ).expect( "write payload to wire format" );
+
+		wf
+	}
+
+
+	/// Craft a frame for the service `sid`, with `msg` serialized with the codec. Service maps define a
+	/// `Service` trait to get the sid of a message type: `<Show as remotes::Service>::sid()`.
+	//
+	pub fn message( sid: ServiceID, msg: &impl Serialize, cid: ConnID ) -> Result<Wf, CodecErr>
+	{
+		let mut wf = Self::frame( sid, cid, &[] );
+
+		C::encode( &mut wf, msg )?;
+
+		Ok( wf )
+	}
+
+
+	/// Send a frame to the peer as is.
+	//
+	pub async fn send_raw( &mut self, wf: Wf ) -> Result<(), WireErr>
+	{
+		self.outgoing.send( wf ).await
+	}
+
+
+	/// Send `msg` to the service `sid` of the peer. The peer doesn't answer sends, unless something goes wrong.
+	///
+	/// # Panics
+	///
+	/// When `msg` can't be serialized. Use [`MockRemote::message`] to handle that.
+	//
+	pub async fn send( &mut self, sid: ServiceID, msg: &impl Serialize ) -> Result<(), WireErr>
+	{
+		let wf = Self::message( sid, msg, ConnID::null() ).expect( "MockRemote: serialize message" );
+
+		self.send_raw( wf ).await
+	}
+
+
+	/// Call the service `sid` of the peer. Returns the ConnID of the call. Get the response with
+	/// [`MockRemote::recv_response`].
+	///
+	/// # Panics
+	///
+	/// When `msg` can't be serialized. Use [`MockRemote::message`] to handle that.
+	//
+	pub async fn call( &mut self, sid: ServiceID, msg: &impl Serialize ) -> Result<ConnID, WireErr>
+	{
+		let cid = ConnID::from( self.next_cid );
+		self.next_cid += 1;
+
+		let wf = Self::message( sid, msg, cid ).expect( "MockRemote: serialize message" );
+
+		self.send_raw( wf ).await?;
+
+		Ok( cid )
+	}
+
+
+	/// The next frame the peer sends. `None` when the peer closed the connection.
+	//
+	pub async fn recv( &mut self ) -> Option< Result<Wf, WireErr> >
+	{
+		self.incoming.next().await
+	}
+
+
+	/// The response to the call with ConnID `cid`.
+	///
+	/// # Panics
+	///
+	/// When the next frame isn't a response to that call or it can't be deserialized to `R`.
+	//
+	pub async fn recv_response<R: DeserializeOwned>( &mut self, cid: ConnID ) -> R
+	{
+		let wf = self.recv_frame().await;
+
+		assert!( wf.kind() == WireType::CallResponse, "MockRemote: expected a response, got: {:?}", wf.kind()      );
+		assert!( wf.cid () == cid                   , "MockRemote: response for {} instead of {cid}", wf.cid() );
+
+		C::decode( wf.msg() ).unwrap_or_else( |e| panic!( "MockRemote: deserialize response: {e}" ) )
+	}
+
+
+	/// The error the peer sends back.
+	///
+	/// # Panics
+	///
+	/// When the next frame isn't a [ConnectionError].
+	//
+	pub async fn recv_error( &mut self ) -> ConnectionError
+	{
+		let wf = self.recv_frame().await;
+
+		assert!( wf.kind() == WireType::ConnectionError, "MockRemote: expected a ConnectionError, got: {:?}", wf.kind() );
+
+		// Peers always send errors with CBOR.
+		//
+		CborCodec::decode( wf.msg() ).unwrap_or_else( |e| panic!( "MockRemote: deserialize ConnectionError: {e}" ) )
+	}
+
+
+	/// Close the connection. The peer will see [`PeerEvent::ClosedByRemote`].
+	//
+	pub async fn close( mut self ) -> Result<(), WireErr>
+	{
+		self.outgoing.close().await
+	}
+
+
+	async fn recv_frame( &mut self ) -> Wf
+	{
+		match self.recv().await
+		{
+			Some( Ok(wf) ) => wf,
+			Some( Err(e) ) => panic!( "MockRemote: error on the connection: {e}" ),
+			None           => panic!( "MockRemote: the peer closed the connection" ),
+		}
+	}
+}
+
+
+impl<Wf, C> fmt::Debug for MockRemote<Wf, C>
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		f.debug_struct( "MockRemote" )
+
+			.field( "next_cid", &self.next_cid )
+
+		.finish()
+	}
+}
+
@@ -1,13 +1,24 @@
 //! The peer module holds everything that deals with managing a remote connection over which
 //! actor messages can be sent and received.
 //
-use crate :: { import::*, * };
+use crate   :: { import::*, * };
+use futures :: { stream::{ select_with_strategy, PollNext } };
 
 
+    mod add_services      ;
     mod call              ;
+    mod call_channel      ;
+    mod cancel            ;
     mod call_response     ;
+    mod call_stream       ;
     mod close_connection  ;
     mod connection_error  ;
+    mod deadline          ;
+    mod flush             ;
+    mod fragment          ;
+    mod discovery         ;
+    mod handshake         ;
+    mod heartbeat         ;
     mod in_call           ;
     mod in_call_response  ;
     mod in_conn_err       ;
@@ -15,21 +26,48 @@ use crate :: { import::*, * };
     mod listen_incoming   ;
     mod peer_err          ;
     mod peer_event        ;
+    mod reconnect         ;
+    mod remove_services   ;
 pub mod request_error     ;
     mod response          ;
+    mod stream_response   ;
     mod timeout           ;
 
+pub use add_services      :: { AddServices         } ;
 pub use call              :: { Call                } ;
+pub use call_channel      :: { CallChannel, CHANNEL_CREDIT } ;
 pub use call_response     :: { CallResponse        } ;
+    use cancel            :: { CancelCall          } ;
+pub use call_stream       :: { CallStream, ResponseStream, STREAM_CREDIT } ;
+    use call_stream       :: { ResponseItem        } ;
 pub use close_connection  :: { CloseConnection     } ;
 pub use connection_error  :: { ConnectionError     } ;
+pub use discovery         :: { RemoteServices      } ;
+pub use flush             :: { FlushPolicy         } ;
+pub use fragment          :: { Fragmentation       } ;
+pub use handshake         :: { Handshake, Features, Negotiated, PROTOCOL_VERSION } ;
+    use handshake         :: { Inbound             } ;
+pub use heartbeat         :: { Heartbeat           } ;
 pub use peer_err          :: { PeerErr, PeerErrCtx } ;
 pub use peer_event        :: { PeerEvent           } ;
+pub use reconnect         :: { Reconnect, ReplayPolicy, Connector } ;
+pub use remove_services   :: { RemoveServices      } ;
     use request_error     :: { RequestError        } ;
 pub use response          :: { Response            } ;
+    use stream_response   :: { StreamResponse      } ;
     use timeout           :: { Timeout             } ;
 
 
+// The bound of each of the two channels of the mailbox created by `Peer::from_framed`.
+//
+const MAILBOX_BOUND: usize = 5;
+
+// The credit of streams of frames we receive, by ConnID, shared with the task listening to the incoming stream.
+// It stops reading when a stream runs out of credit. Each frame holds a permit until it's consumed.
+//
+pub(crate) type Credit = Arc< Mutex<HashMap<ConnID, Arc<Semaphore>>> >;
+
+
 // Reduce trait bound boilerplate, since we have to repeat them all over
 //
 /// Trait bounds for the stream of incoming messages
@@ -72,7 +110,7 @@ impl<T, Wf> BoundsOut<Wf> for T
 /// Runtime modification is provided. You can tell the ServiceMap to start delivering to another
 /// actor/connection, and you can tell the peer to start/stop exposing a certain service. Once
 /// the mailbox for the peer has been started, you can only communicate to it by means of messages,
-/// so the messages `AddServices` and `RemoveServices` can be used to convey runtime instructions.
+/// so the messages [`AddServices`] and [`RemoveServices`] can be used to convey runtime instructions.
 ///
 /// In principle you setup the peer with at least one ServiceMap before starting it, that way it
 /// is fully operational before it receives the first incoming message. `service_map!` let's you
@@ -90,6 +128,10 @@ impl<T, Wf> BoundsOut<Wf> for T
 /// You can check the documentation of both [`RelayMap`] and [`service_map!`] for more information
 /// on their usage.
 ///
+/// With [`Peer::set_discovery`] the peer will tell the remote which services it exposes. The remote peer
+/// will then fail calls to other services locally rather than waiting for the error from the network.
+/// It can be queried with the [`RemoteServices`] message.
+///
 /// You can supply several service maps with different services to Peer. They will only advertise
 /// services for which you have actually set handlers. When you later want to add services with
 /// `AddServices`, you can pass in the same service map if you want, as long as you have added
@@ -140,8 +182,6 @@ impl<T, Wf> BoundsOut<Wf> for T
 /// it to be dropped, create a new connection and a new peer.
 ///
 //
-#[ derive( Actor ) ]
-//
 pub struct Peer<Wf: 'static + WireFormat = CborWF>
 {
 	/// The sink
@@ -157,6 +197,11 @@ pub struct Peer<Wf: 'static + WireFormat = CborWF>
 	//
 	addr: Option< Addr<Self> >,
 
+	// Our address on the high priority channel of the mailbox, for messages to ourselves that shouldn't wait
+	// behind incoming messages.
+	//
+	addr_out: WeakAddr<Self>,
+
 	// The ID of this actor. Since the addr isn't always there (eg. after we start closing the connection),
 	// it's a royal PITA not to have these directly available. Keeping them after the addr is gone also means
 	// better error messages.
@@ -216,6 +261,127 @@ pub struct Peer<Wf: 'static + WireFormat = CborWF>
 	// outstanding packets before closing down.
 	//
 	grace_period: Option<Duration>,
+
+	// How to get a new connection when the current one is lost. None means we don't reconnect.
+	//
+	reconnect: Option< Reconnect<Wf> >,
+
+	// Outgoing messages issued while we are disconnected. Calls come with their deadline, which is told to
+	// the remote when they go out.
+	//
+	send_buffer: VecDeque<( Wf, Option<Instant> )>,
+
+	// Copies of outgoing calls still waiting for a response, with their deadline, so they can be replayed
+	// after reconnecting. Only used when reconnecting with ReplayPolicy::Replay.
+	//
+	in_flight: HashMap<ConnID, ( Wf, Option<Instant> )>,
+
+	// Whether to advertise our services to the remote.
+	//
+	discovery: bool,
+
+	// The services the remote advertised, with their names. None if the remote didn't advertise.
+	//
+	remote_services: Option< HashMap<ServiceID, Option<String>> >,
+
+	// The handshake we send to the remote, if enabled.
+	//
+	handshake: Option<Handshake>,
+
+	// The outcome of the handshake with the remote.
+	//
+	negotiated: Option<Negotiated>,
+
+	// What was negotiated about incoming frames, shared with the task listening to the incoming stream.
+	//
+	inbound: Arc<Inbound>,
+
+	// Calls made while we wait for the handshake of the remote, with their deadline. They go out once we know
+	// what the remote accepts.
+	//
+	held_calls: VecDeque<( Wf, Option<Instant> )>,
+
+	// The error the remote answered our handshake with. Calls fail with it instead of waiting for a handshake
+	// that won't come.
+	//
+	refused: Option<ConnectionError>,
+
+	// How to compress outgoing messages, if the remote supports it.
+	//
+	compression: Option<Compression>,
+
+	// Whether to add a checksum to outgoing frames, if the remote supports it.
+	//
+	checksum: bool,
+
+	// Split outgoing messages that are too big.
+	//
+	fragmentation: Option<Fragmentation>,
+
+	// Fragments waiting to go out, with the sid and cid of the message they belong to. There is one queue per
+	// fragmented message and we send from them in turn. Messages that must not overtake the fragments wait in
+	// the same queue.
+	//
+	fragments: VecDeque< VecDeque<( ServiceID, ConnID, Wf )> >,
+
+	// The max size of reassembled messages, shared with the task listening to the incoming stream.
+	// Zero when fragmentation is disabled.
+	//
+	max_message: Arc<AtomicUsize>,
+
+	// When to flush the outgoing sink.
+	//
+	flush_policy: FlushPolicy,
+
+	// The number of bytes we put in the sink since the last flush.
+	//
+	unflushed: usize,
+
+	// Whether a task will send us a Flush message.
+	//
+	flush_scheduled: bool,
+
+	// The certificates the remote presented in the TLS handshake.
+	//
+	#[ cfg( feature = "tls" ) ]
+	//
+	identity: Option<PeerIdentity>,
+
+	// Ping the remote to detect dead connections.
+	//
+	heartbeat: Option<Heartbeat>,
+
+	// Deadlines the remote sent for incoming calls that haven't come in yet. Entries whose deadline has passed
+	// are dropped when a new one comes in, as the call might never come. The number of entries is bounded.
+	//
+	deadlines: VecDeque<( ConnID, Instant )>,
+
+	// Incoming calls that are being processed, so the remote can cancel them.
+	//
+	processing: HashMap<ConnID, AbortHandle>,
+
+	// Outgoing calls to streaming services that are waiting for more responses.
+	//
+	streams: HashMap< ConnID, mpsc::UnboundedSender< ResponseItem<Wf> > >,
+
+	// The credit of the streams we are receiving, see [`STREAM_CREDIT`].
+	//
+	stream_credit: Credit,
+
+	// Channels the remote announced, but for which the call hasn't come in yet. A task tells us when the
+	// timeout passes, the id makes sure it doesn't forget a later channel with the same cid.
+	//
+	opening   : HashMap<ConnID, u64>,
+	opening_id: u64,
+
+	// Channels the remote opened with us, to deliver the items the remote sends. Each item holds a permit
+	// from the credit of the channel until the handler takes it.
+	//
+	channels: HashMap< ConnID, mpsc::UnboundedSender<(Wf, Option<OwnedSemaphorePermit>)> >,
+
+	// The credit of each channel the remote opened with us, see [`CHANNEL_CREDIT`].
+	//
+	channel_credit: Credit,
 }
 
 
@@ -255,6 +421,23 @@ impl<Wf: WireFormat> Peer<Wf>
 
 
 
+	// Connection id for a new outgoing call.
+	//
+	fn next_cid( &self ) -> ConnID
+	{
+		let cid = ConnID::from( self.conn_id_counter.fetch_add( 1, Relaxed ) );
+
+		// We wrapped round.
+		// It must not be 0 otherwise the remote will consider it a send, and it's reserved.
+		//
+		match cid.is_null()
+		{
+			true  => ConnID::from( self.conn_id_counter.fetch_add( 1, Relaxed ) ),
+			false => cid,
+		}
+	}
+
+
 	// generate an error context for convenience.
 	//
 	fn ctx
@@ -312,6 +495,8 @@ impl<Wf: WireFormat> Peer<Wf>
 	/// Set the timeout for outgoing calls. This defaults to 60 seconds if not set by this method.
 	/// Having a timeout allows your code to detect if a remote is not reactive and prevents a memory
 	/// leak in Peer where information regarding the request would be kept indefinitely otherwise.
+	///
+	/// Individual calls can override this with [`Call::timeout`].
 	//
 	pub fn set_timeout( &mut self, delay: Duration )
 	{
@@ -320,6 +505,27 @@ impl<Wf: WireFormat> Peer<Wf>
 
 
 
+	/// The verified identity of the remote if the connection was established with [`Tls::create_peer`]
+	/// and the remote presented certificates.
+	//
+	#[ cfg( feature = "tls" ) ]
+	#[ cfg_attr( nightly, doc(cfg( feature = "tls" )) ) ]
+	//
+	pub fn identity( &self ) -> Option<&PeerIdentity>
+	{
+		self.identity.as_ref()
+	}
+
+
+	#[ cfg( feature = "tls" ) ]
+	//
+	pub(crate) fn set_identity( &mut self, identity: Option<PeerIdentity> )
+	{
+		self.identity = identity;
+	}
+
+
+
 	/// Create a new peer to represent a connection to some remote.
 	///
 	/// Ideally you don't have to call this method directly. Wire formats need to have a convenience function
@@ -353,6 +559,8 @@ impl<Wf: WireFormat> Peer<Wf>
 	/// outstanding packets before closing down. This also applies when you send a `CloseConnection` message to
 	/// this peer locally.
 	//
+	#[ allow( clippy::result_large_err ) ]
+	//
 	pub fn new
 	(
 		addr_in     : Addr<Self>                                                              ,
@@ -374,6 +582,7 @@ impl<Wf: WireFormat> Peer<Wf>
 		let (nursery, nursery_stream) = Nursery::new( exec.clone() );
 
 
+		let weak_out       = addr_out.weak();
 		let nursery_handle = exec.spawn_handle( Self::listen_request_results( nursery_stream, addr_out ) )
 
 			.map_err( |_| -> PeerErr
@@ -390,7 +599,14 @@ impl<Wf: WireFormat> Peer<Wf>
 		;
 
 
-		nursery.nurse( Self::listen_incoming( incoming, addr_in.clone(), bp.clone() ) )
+		let max_message    = Arc::new( AtomicUsize::new(0)          );
+		let inbound        = Arc::new( Inbound::default()          );
+		let stream_credit  = Credit::default();
+		let channel_credit = Credit::default();
+
+		let listen = Self::listen_incoming( incoming, addr_in.clone(), bp.clone(), max_message.clone(), inbound.clone(), stream_credit.clone(), channel_credit.clone() );
+
+		nursery.nurse( listen )
 
 			.map_err( |_| -> PeerErr
 			{
@@ -419,8 +635,40 @@ impl<Wf: WireFormat> Peer<Wf>
 			closed         : false                      ,
 			nursery_stream : Some( nursery_handle )     ,
 			addr           : Some( addr_in )            ,
+			addr_out       : weak_out                   ,
+			reconnect      : None                       ,
+			send_buffer    : VecDeque::new()            ,
+			in_flight      : HashMap::new()             ,
+			discovery      : false                      ,
+			remote_services: None                       ,
+			handshake      : None                       ,
+			negotiated     : None                       ,
+			held_calls     : VecDeque::new()            ,
+			refused        : None                       ,
+			compression    : None                       ,
+			checksum       : false                      ,
+			fragmentation  : None                       ,
+			fragments      : VecDeque::new()            ,
+			flush_policy   : FlushPolicy::Immediate     ,
+			unflushed      : 0                          ,
+			flush_scheduled: false                      ,
+			heartbeat      : None                       ,
+			deadlines      : VecDeque::new()            ,
+			processing     : HashMap::new()             ,
+			streams        : HashMap::new()             ,
+			opening        : HashMap::new()             ,
+			opening_id     : 0                          ,
+			channels       : HashMap::new()             ,
 			nursery                                     ,
 			grace_period                                ,
+			max_message                                 ,
+			inbound                                     ,
+			stream_credit                               ,
+			channel_credit                              ,
+
+			#[ cfg( feature = "tls" ) ]
+			//
+			identity: None,
 
 			// must not start at 0. Zero has a special meaning.
 			//
@@ -430,6 +678,52 @@ impl<Wf: WireFormat> Peer<Wf>
 
 
 
+	/// Create a peer with a mailbox from an already framed connection. This is what the wire formats in this
+	/// crate use to implement [`WireFormat::create_peer`], you can use it for your own wire format.
+	///
+	/// The mailbox has two channels, a high priority one for outgoing messages and a low priority one for incoming.
+	/// Both are bounded, but delivering outbound work is always prioritized over taking more inbound work. This
+	/// keeps the system from congesting.
+	///
+	/// Returns a WeakAddr to the high priority channel, as the Peer lives as long as the connection lives.
+	/// See [`Peer::new`] for the other parameters.
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	pub fn from_framed
+	(
+		name        : impl AsRef<str>          ,
+		incoming    : impl BoundsIn<Wf>        ,
+		outgoing    : impl BoundsOut<Wf>       ,
+		exec        : impl PeerExec<Wf>        ,
+		bp          : Option< Arc<Semaphore> > ,
+		grace_period: Option< Duration >       ,
+	)
+
+		-> Result< (Self, Mailbox<Self>, WeakAddr<Self>), PeerErr >
+
+	{
+		let ( low_tx,  low_rx) = mpsc::channel( MAILBOX_BOUND );
+		let (high_tx, high_rx) = mpsc::channel( MAILBOX_BOUND );
+
+		let strategy = |_: &mut ()| PollNext::Left;
+		let rx = Box::new( select_with_strategy( high_rx, low_rx, strategy ) );
+
+		let low_tx  = low_tx .sink_map_err( |e| -> DynError { Box::new(e) } );
+		let high_tx = high_tx.sink_map_err( |e| -> DynError { Box::new(e) } );
+
+		let mb       = Mailbox::new( name, rx );
+		let addr_in  = mb.addr( Box::new( low_tx  ) );
+		let addr_out = mb.addr( Box::new( high_tx ) );
+		let weak_out = addr_out.weak();
+
+		let peer = Self::new( addr_in, addr_out, incoming, outgoing, Arc::new(exec), bp, grace_period )?;
+
+		Ok( (peer, mb, weak_out) )
+	}
+
+
+
 	/// The task that will listen to results returned by the spawned tasks that process requests
 	/// as well as some other tasks that need to be confined to the lifetime of the Peer. These
 	/// include tasks spawned for timeouts, the task listening to incoming requests, ...
@@ -462,6 +756,8 @@ impl<Wf: WireFormat> Peer<Wf>
 					Response::Nothing         => Ok(())               ,
 					Response::WireFormat  (x) => addr.send( x ).await ,
 					Response::CallResponse(x) => addr.send( x ).await ,
+
+					Response::Stream{ cid, stream } => addr.send( StreamResponse{ cid, stream } ).await,
 				}
 
 				Err(err) => addr.send( RequestError::from( err ) ).await
@@ -485,6 +781,8 @@ impl<Wf: WireFormat> Peer<Wf>
 	///
 	/// Each service map and each service should be registered only once, including relayed services. Trying to
 	/// register them twice will panic in debug mode.
+	///
+	/// Once the mailbox of the peer is started, use the [`AddServices`] and [`RemoveServices`] messages instead.
 	//
 	pub fn register_services( &mut self, sm: Arc< dyn ServiceMap<Wf>> )
 	{
@@ -509,20 +807,44 @@ impl<Wf: WireFormat> Peer<Wf>
 	{
 		trace!( "{}: sending OUT WireFormat", self.identify() );
 
+		if self.reconnecting()
+		{
+			return self.buffer_msg( msg );
+		}
+
+		match self.fragment( msg )?
+		{
+			Some( msg ) => self.write_msg( msg ).await,
+			None        => Ok(()),
+		}
+	}
+
+
+	// Write a message to the connection.
+	//
+	async fn write_msg( &mut self, mut msg: Wf ) -> Result<(), PeerErr>
+	{
+		self.check_size( &msg )?;
+		self.compress( &mut msg );
+		self.seal    ( &mut msg );
+
 		match &mut self.outgoing
 		{
 			Some( out ) =>
 			{
 				let sid = msg.sid();
 				let cid = msg.cid();
+				let len = msg.len() as usize;
 
-				out.send( msg ).await
+				out.feed( msg ).await
 
 					.map_err( |source|
 					{
 						let ctx = self.ctx( sid, cid, "Sending out WireFormat" );
 						PeerErr::WireFormat{ ctx, source }
-					})
+					})?;
+
+				self.queued( len ).await
 			}
 
 			None =>
@@ -567,11 +889,15 @@ impl<Wf: WireFormat> Peer<Wf>
 		//
 		let msg = Self::prep_error( cid, err );
 
+		self.processing.remove( &cid );
+
 
 		// We are already trying to report an error. If we can't send, just give up.
 		//
 		let _ = out.send( msg ).await;
 
+		self.unflushed = 0;
+
 		if close
 		{
 			let close_conn = CloseConnection{ remote: false, reason: format!( "{:?}", err ) };
@@ -591,10 +917,10 @@ impl<Wf: WireFormat> Peer<Wf>
 	{
 		// It's bigger in CBOR because it has String data.
 		//
-		let mut msg = Wf::with_capacity( std::mem::size_of::<ConnectionError>() * 2 );
+		let mut msg = Wf::with_capacity( size_of::<ConnectionError>() * 2 );
 		msg.set_sid( ServiceID::null() );
 		msg.set_cid( cid               );
-		serde_cbor::to_writer( &mut msg, err ).expect( "serialize ConnectionError" );
+		CborCodec::encode( &mut msg, err ).expect( "serialize ConnectionError" );
 
 		msg
 	}
@@ -602,6 +928,19 @@ impl<Wf: WireFormat> Peer<Wf>
 
 
 
+impl<Wf: WireFormat + Send + 'static> Actor for Peer<Wf>
+{
+	fn started( &mut self ) -> Return<'_, ()> { async move
+	{
+		self.send_handshake().await;
+		self.advertise     ().await;
+		self.start_heartbeat();
+
+	}.boxed() }
+}
+
+
+
 // Put an outgoing multiservice message on the wire.
 //
 impl<Wf: WireFormat> Handler<Wf> for Peer<Wf>
@@ -610,6 +949,13 @@ impl<Wf: WireFormat> Handler<Wf> for Peer<Wf>
 	{
 		trace!( "{}: sending OUT WireFormat", self.identify() );
 
+		// The remote can no longer cancel a call we have answered.
+		//
+		if matches!( msg.kind(), WireType::CallResponse | WireType::ConnectionError )
+		{
+			self.processing.remove( &msg.cid() );
+		}
+
 		self.send_msg( msg ).await
 	}
 }
@@ -0,0 +1,49 @@
+use crate::{ import::*, * };
+
+
+/// Control message for [Peer]. Start exposing all the services of a service map at runtime, after the
+/// mailbox of the peer has been started. Eg. a client unlocks extra services after logging in.
+///
+/// Unlike [`Peer::register_services`], a service which is already exposed will be taken over by the
+/// new service map. Observers will receive [`PeerEvent::ServicesAdded`] with the list of service ids.
+//
+#[ derive( Debug ) ]
+//
+pub struct AddServices<Wf: WireFormat = CborWF>
+{
+	/// The service map that will handle incoming messages for the services it advertises.
+	//
+	pub sm: Arc< dyn ServiceMap<Wf> >,
+}
+
+
+impl<Wf: WireFormat> Message for AddServices<Wf>
+{
+	type Return = ();
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Handler<AddServices<Wf>> for Peer<Wf>
+{
+	#[async_fn] fn handle( &mut self, msg: AddServices<Wf> )
+	{
+		// Once closed we have cleared our services. Don't let them create a reference cycle again.
+		//
+		if self.closed { return }
+
+		let mut added = Vec::new();
+
+		for sid in msg.sm.services()
+		{
+			trace!( "{}: Add Service: {:?}", self.identify(), &sid );
+
+			self.services.insert( *sid, msg.sm.clone() );
+			added.push( *sid );
+		}
+
+		self.pharos.send( PeerEvent::ServicesAdded( added ) ).await.expect( "pharos not closed" );
+
+		self.advertise().await;
+	}
+}
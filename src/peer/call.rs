@@ -11,8 +11,9 @@ use crate::{ import::*, * };
 //
 pub struct Call<Wf>
 {
-	 wf   : Wf              ,
-	_ghost: PhantomData<Wf> ,
+	 wf     : Wf               ,
+	 timeout: Option<Duration> ,
+	_ghost  : PhantomData<Wf>  ,
 }
 
 impl<Wf: WireFormat> Message for Call<Wf>
@@ -29,7 +30,19 @@ impl<Wf: WireFormat> Call<Wf>
 	//
 	pub fn new( wf: Wf ) -> Self
 	{
-		Self{ wf, _ghost: PhantomData }
+		Self{ wf, timeout: None, _ghost: PhantomData }
+	}
+
+	/// Use another timeout for this call than the one set with [`Peer::set_timeout`]. The remaining time
+	/// is sent to the remote, so it can stop processing the call when we are no longer waiting for the
+	/// response. Relays pass it on to the next peer.
+	///
+	/// The remote must be a version of thespis_remote that understands the deadline frame.
+	//
+	pub fn timeout( mut self, timeout: Duration ) -> Self
+	{
+		self.timeout = Some( timeout );
+		self
 	}
 
 	/// Get the service id.
@@ -70,29 +83,59 @@ impl<Wf: WireFormat + Send + 'static> Handler<Call<Wf>> for Peer<Wf>
 		};
 
 
-		let mut cid = ConnID::from( self.conn_id_counter.fetch_add(1, Relaxed) );
-		let     sid = call.wf.sid();
-
-		// We wrapped round.
-		// It must not be 0 otherwise the remote will consider it a send, and it's reserved.
+		// The remote told us which services it provides, don't bother sending it if it's not one of them.
 		//
-		if cid.is_null()
+		if let Some(remote) = &self.remote_services
 		{
-			cid = ConnID::from( self.conn_id_counter.fetch_add( 1, Relaxed ) );
+			if !remote.contains_key( &call.wf.sid() )
+			{
+				let ctx = self.ctx( call.wf.sid(), None, "Handler<Call> for Peer: remote didn't advertise this service" );
+
+				return Err( PeerErr::UnknownService{ ctx } );
+			}
 		}
 
+
+		let cid = self.next_cid();
+		let sid = call.wf.sid();
+
 		call.wf.set_cid( cid );
 
-		self.send_msg( call.wf ).await?;
+		// Keep a copy in case we need to replay it after reconnecting. When disconnected, the
+		// copy is made once the buffer is flushed.
+		//
+		let replay = self.reconnect.as_ref().map( |r| r.replay() ).unwrap_or_default() && !self.reconnecting();
 
-		// If the above succeeded, store the other end of the channel
+		// Keep the deadline, so a replayed or buffered call can tell the remote how much time is left.
 		//
-		let (sender, receiver) = oneshot::channel::< Result<Wf, ConnectionError> >() ;
+		let deadline = call.timeout.and_then( |t| Instant::now().checked_add( t ) );
+
+		if replay
+		{
+			self.in_flight.insert( cid, ( call.wf.clone(), deadline ) );
+		}
+
+		if self.reconnecting()
+		{
+			self.buffer_call( call.wf, deadline )?;
+		}
+
+		else if let Err(e) = self.send_call( cid, call.wf, deadline ).await
+		{
+			self.in_flight.remove( &cid );
+			return Err(e);
+		}
+
+		// If the above succeeded, store the other end of the channel. The task below forwards the
+		// response to the caller, so it can notice when the caller is no longer interested.
+		//
+		let (    sender, receiver) = oneshot::channel::< Result<Wf, ConnectionError> >() ;
+		let (mut caller, response) = oneshot::channel::< Result<Wf, ConnectionError> >() ;
 
 
 		// send a timeout message to ourselves.
 		//
-		let delay = self.timeout;
+		let delay = call.timeout.unwrap_or( self.timeout );
 
 		// If self.closed is false, there should always be an address.
 		//
@@ -100,13 +143,53 @@ impl<Wf: WireFormat + Send + 'static> Handler<Call<Wf>> for Peer<Wf>
 
 		let task = async move
 		{
-			Delay::new( delay ).await;
+			let mut receiver = receiver;
+			let     timeout  = Delay::new( delay );
+
+			pin_mut!( timeout );
+
+			// Wait for whatever comes first: the response, the timeout or the caller dropping the receiver.
+			//
+			let canceled = match futures::future::select( &mut receiver, futures::future::select( caller.cancellation(), timeout ) ).await
+			{
+				Either::Left(( resp, _ )) =>
+				{
+					// If the connection closed, dropping caller wakes it up.
+					//
+					if let Ok( resp ) = resp
+					{
+						let _ = caller.send( resp );
+					}
+
+					return Ok(Response::Nothing);
+				}
+
+				Either::Right(( Either::Left (_), _ )) => true ,
+				Either::Right(( Either::Right(_), _ )) => false,
+			};
+
+			if canceled
+			{
+				if self_addr.send( super::CancelCall{ cid, sid } ).await.is_err()
+				{
+					error!( "{}: Failed to send cancel to self.", &identity );
+				}
+
+				return Ok(Response::Nothing);
+			}
 
 			if self_addr.send( super::Timeout{ cid, sid } ).await.is_err()
 			{
 				error!( "{}: Failed to send timeout to self.", &identity );
 			}
 
+			// The timeout handler answers with ConnectionError::Timeout.
+			//
+			if let Ok( resp ) = receiver.await
+			{
+				let _ = caller.send( resp );
+			}
+
 			Ok(Response::Nothing)
 		};
 
@@ -122,6 +205,46 @@ impl<Wf: WireFormat + Send + 'static> Handler<Call<Wf>> for Peer<Wf>
 
 		self.responses.insert( cid, sender );
 
-		Ok( receiver )
+		Ok( response )
+	}
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Peer<Wf>
+{
+	/// Send an outgoing call, preceded by a frame that tells the remote how much time is left until the deadline.
+	/// While we wait for the handshake of the remote, the call is held back, up to the limit of the reconnect
+	/// buffer. If the remote refused our handshake, the call fails right away.
+	//
+	pub(crate) async fn send_call( &mut self, cid: ConnID, wf: Wf, deadline: Option<Instant> ) -> Result<(), PeerErr>
+	{
+		if let Some( err ) = &self.refused
+		{
+			let ctx = self.ctx( wf.sid(), cid, "Remote refused our handshake" );
+
+			return Err( PeerErr::Remote{ ctx, err: err.clone() } );
+		}
+
+		if self.awaiting_handshake()
+		{
+			if self.held_calls.len() >= self.buffer_limit()
+			{
+				let ctx = self.ctx( wf.sid(), cid, "Too many calls waiting for the handshake of the remote" );
+
+				return Err( PeerErr::ConnectionClosed{ ctx } );
+			}
+
+			self.held_calls.push_back(( wf, deadline ));
+
+			return Ok(())
+		}
+
+		if let Some( deadline ) = deadline
+		{
+			self.send_deadline( cid, deadline.saturating_duration_since( Instant::now() ) ).await?;
+		}
+
+		self.send_msg( wf ).await
 	}
 }
@@ -0,0 +1,291 @@
+use crate::{ import::*, *, peer::RequestError };
+
+
+/// Type representing an outgoing call that opens a bidirectional channel with a remote service.
+/// You get a [ChannelSink] to send items and a [ResponseStream] with the items the remote sends back.
+/// Both are multiplexed on the connection of the peer and identified by the ConnID of the call.
+///
+/// On the remote, the channel takes a slot of the backpressure semaphore until the handler's stream
+/// ends. When the handler has [`CHANNEL_CREDIT`] items it hasn't taken yet, the remote stops reading from
+/// the connection until it does. Dropping the [ResponseStream] cancels the channel. Channels can not be
+/// relayed.
+///
+/// Normally you don't use this directly, but use `RemoteAddr::open_channel` from the `service_map!`
+/// macro.
+//
+#[ derive( Debug ) ]
+//
+pub struct CallChannel<Wf>
+{
+	wf: Wf,
+}
+
+impl<Wf: WireFormat> Message for CallChannel<Wf>
+{
+	type Return = Result< (ChannelSink<Wf>, ResponseStream<Wf>), PeerErr >;
+}
+
+impl<Wf: WireFormat> CallChannel<Wf>
+{
+	/// Create a new CallChannel to send an outgoing message over the peer.
+	//
+	pub fn new( wf: Wf ) -> Self
+	{
+		Self{ wf }
+	}
+
+	/// Get the service id.
+	//
+	pub fn service( &self ) -> ServiceID
+	{
+		self.wf.sid()
+	}
+}
+
+
+
+/// How many items the remote buffers for the handler of a channel before it stops reading from the
+/// connection.
+//
+pub const CHANNEL_CREDIT: usize = 32;
+
+
+
+/// Sent to the peer when the caller closes or drops it's sink.
+//
+#[ derive( Debug ) ]
+//
+pub(crate) struct CloseChannel
+{
+	pub(crate) cid: ConnID,
+}
+
+impl Message for CloseChannel
+{
+	type Return = ();
+}
+
+
+
+/// Handler for outgoing calls that open a channel.
+//
+impl<Wf: WireFormat + Send + 'static> Handler<CallChannel<Wf>> for Peer<Wf>
+{
+	#[async_fn] fn handle( &mut self, call: CallChannel<Wf> ) -> <CallChannel<Wf> as Message>::Return
+	{
+		trace!( "{}: polled Handler<CallChannel>", self.identify() );
+
+		let (cid, stream) = self.send_stream_call( call.wf, true ).await?;
+
+		// Tell the remote when the sink get's closed or dropped. Don't keep ourselves alive.
+		//
+		let (close, closed) = oneshot::channel::<()>();
+
+		let weak = match &self.addr
+		{
+			Some( addr ) => addr.weak(),
+
+			None =>
+			{
+				let ctx = self.ctx( None, cid, "Open channel" );
+
+				return Err( PeerErr::ConnectionClosed{ ctx } );
+			}
+		};
+
+		let mut self_addr = weak.clone();
+
+		let task = async move
+		{
+			let _ = closed.await;
+			let _ = self_addr.send( CloseChannel{ cid } ).await;
+
+			Ok( Response::Nothing )
+		};
+
+		self.nursery.nurse( task ).map_err( |_|
+		{
+			let ctx = self.ctx( None, cid, "close task for outgoing CallChannel" );
+
+			PeerErr::Spawn{ ctx }
+
+		})?;
+
+		Ok(( ChannelSink::new( weak, cid, close ), stream ))
+	}
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Handler<CloseChannel> for Peer<Wf>
+{
+	#[async_fn] fn handle( &mut self, msg: CloseChannel ) -> <CloseChannel as Message>::Return
+	{
+		// The channel is already over.
+		//
+		if self.closed || !self.streams.contains_key( &msg.cid ) { return }
+
+		trace!( "{}: closing outgoing channel, cid: {}", self.identify(), msg.cid );
+
+		let mut wf = Wf::with_capacity( 0 );
+		wf.set_sid( ServiceID::channel_close() );
+		wf.set_cid( msg.cid                    );
+
+		if let Err(e) = self.send_msg( wf ).await
+		{
+			self.pharos.send( PeerEvent::Error(e) ).await.expect( "pharos not closed" );
+		}
+	}
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Peer<Wf>
+{
+	/// Process the frames the remote sends on channels it opened with us.
+	//
+	pub(crate) async fn channel_frame( &mut self, frame: Wf, permit: Option<OwnedSemaphorePermit> )
+	{
+		let sid = frame.sid();
+		let cid = frame.cid();
+
+		if sid == ServiceID::channel_open()
+		{
+			trace!( "{}: remote is opening a channel, cid: {}", self.identify(), cid );
+
+			self.opening_id = self.opening_id.wrapping_add( 1 );
+			self.opening.insert( cid, self.opening_id );
+
+			self.expire_opening( cid, self.opening_id );
+		}
+
+		else if sid == ServiceID::channel_close()
+		{
+			trace!( "{}: remote closed channel, cid: {}", self.identify(), cid );
+
+			// Ends the stream of incoming items.
+			//
+			self.forget_channel( cid );
+		}
+
+		else if let Some( tx ) = self.channels.get( &cid )
+		{
+			// The handler might not be interested anymore, in which case we just drop it.
+			//
+			let _ = tx.unbounded_send(( frame, permit ));
+		}
+
+		else
+		{
+			warn!( "{}: Received item for unknown channel, cid: {}. Dropping it.", self.identify(), cid );
+		}
+	}
+
+
+	/// Open a channel the remote asked for.
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	pub(crate) fn open_channel
+	(
+		&mut self                     ,
+		sm : &Arc<dyn ServiceMap<Wf>> ,
+		msg: Wf                       ,
+		ctx: PeerErrCtx               ,
+	)
+		-> Result< Pin<Box< dyn Future< Output=Result<Response<Wf>, PeerErr> > + Send >>, RequestError >
+	{
+		let cid = msg.cid();
+
+		let (tx, rx) = mpsc::unbounded();
+
+		self.channels.insert( cid, tx );
+
+		// The credit for an item is given back when the handler takes it.
+		//
+		let rx = rx.map( |(item, _permit)| item );
+
+		sm.open_channel( msg, rx.boxed(), ctx ).map_err( |e|
+		{
+			self.forget_channel( cid );
+
+			RequestError::from( e )
+		})
+	}
+
+
+	/// The call follows right after the channel was announced, so when it hasn't come in within the timeout, it never
+	/// will. Spawn a task that tells us when the timeout passes.
+	//
+	fn expire_opening( &mut self, cid: ConnID, id: u64 )
+	{
+		// Don't keep ourselves alive.
+		//
+		let mut self_addr = match &self.addr
+		{
+			Some(a) => a.weak(),
+			None    => return,
+		};
+
+		let timeout = self.timeout;
+
+		let task = async move
+		{
+			Delay::new( timeout ).await;
+
+			let _ = self_addr.send( OpeningTimeout{ cid, id } ).await;
+
+			Ok( Response::Nothing )
+		};
+
+		if self.nursery.nurse( task ).is_err()
+		{
+			error!( "{}: Failed to spawn the task to expire channel {}.", self.identify(), cid );
+		}
+	}
+
+
+	/// Forget all about a channel the remote opened with us. Ends the stream of incoming items.
+	//
+	pub(crate) fn forget_channel( &mut self, cid: ConnID )
+	{
+		self.opening .remove( &cid );
+		self.channels.remove( &cid );
+
+		self.channel_credit.lock().remove( &cid );
+	}
+}
+
+
+
+/// Sent to the peer when the call to open a channel should have come in.
+//
+#[ derive( Debug, Clone, Copy ) ]
+//
+pub(crate) struct OpeningTimeout
+{
+	cid: ConnID,
+
+	// Tells apart channels that were announced with the same cid.
+	//
+	id: u64,
+}
+
+impl Message for OpeningTimeout
+{
+	type Return = ();
+}
+
+
+impl<Wf: WireFormat + Send + 'static> Handler<OpeningTimeout> for Peer<Wf>
+{
+	#[async_fn] fn handle( &mut self, msg: OpeningTimeout )
+	{
+		if self.opening.get( &msg.cid ) == Some( &msg.id )
+		{
+			warn!( "{}: The call to open channel {} never came in. Forgetting it.", self.identify(), msg.cid );
+
+			self.forget_channel( msg.cid );
+		}
+	}
+}
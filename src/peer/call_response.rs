@@ -11,7 +11,9 @@ use crate::{ import::*, * };
 //
 pub struct CallResponse<Wf>
 {
-	msg: Wf,
+	// None when the remote canceled the call. We still need to release the backpressure.
+	//
+	msg: Option<Wf>,
 }
 
 
@@ -30,7 +32,15 @@ impl<Wf> CallResponse<Wf>
 	//
 	pub fn new( msg: Wf ) -> Self
 	{
-		Self{ msg }
+		Self{ msg: Some( msg ) }
+	}
+
+
+	/// The remote canceled the call, there is nothing to send.
+	//
+	pub(crate) fn canceled() -> Self
+	{
+		Self{ msg: None }
 	}
 }
 
@@ -43,9 +53,19 @@ impl<Wf: WireFormat + Send + 'static> Handler<CallResponse<Wf>> for Peer<Wf>
 {
 	#[async_fn] fn handle( &mut self, wrap: CallResponse<Wf> ) -> <CallResponse<Wf> as Message>::Return
 	{
-		trace!( "{}: sending OUT CallResponse", self.identify() );
+		let res = match wrap.msg
+		{
+			Some( msg ) =>
+			{
+				trace!( "{}: sending OUT CallResponse", self.identify() );
+
+				self.processing.remove( &msg.cid() );
+
+				self.send_msg( msg ).await
+			}
 
-		let res = self.send_msg( wrap.msg ).await;
+			None => Ok(()),
+		};
 
 		if self.backpressure.is_some()
 		{
@@ -0,0 +1,280 @@
+use crate::{ import::*, * };
+
+
+/// Type representing an outgoing call to a streaming service. The remote answers with any number of
+/// responses, followed by an end of stream frame.
+///
+/// Normally you don't use this directly, but use `RemoteAddr::call_stream` from the `service_map!`
+/// macro. Streaming calls can not be relayed.
+//
+#[ derive( Debug ) ]
+//
+pub struct CallStream<Wf>
+{
+	wf: Wf,
+}
+
+impl<Wf: WireFormat> Message for CallStream<Wf>
+{
+	/// The responses come in over time, so we return a stream.
+	//
+	type Return = Result< ResponseStream<Wf>, PeerErr >;
+}
+
+impl<Wf: WireFormat> CallStream<Wf>
+{
+	/// Create a new CallStream to send an outgoing message over the peer.
+	//
+	pub fn new( wf: Wf ) -> Self
+	{
+		Self{ wf }
+	}
+
+	/// Get the service id.
+	//
+	pub fn service( &self ) -> ServiceID
+	{
+		self.wf.sid()
+	}
+}
+
+
+
+/// How many responses of a streaming call or a channel the peer buffers before it stops reading from the
+/// connection until you take them from the [ResponseStream].
+//
+pub const STREAM_CREDIT: usize = 32;
+
+
+// A response, or `None` for the end of the stream, with the credit it holds until it's consumed.
+//
+pub(crate) type ResponseItem<Wf> = ( Option<Result<Wf, ConnectionError>>, Option<OwnedSemaphorePermit> );
+
+
+
+/// The responses to a [CallStream]. Items look like what the channel returned for a [Call] gives you:
+/// `Err(Canceled)` when the connection closes before the end of the stream and `Ok(Err(ConnectionError))`
+/// when the remote fails. Either way it's the last item.
+///
+/// For calls to streaming services, when you wait longer than the timeout of the peer for the next item, the
+/// last item is `Ok(Err(ConnectionError::Timeout))` and the call is canceled. See [`Peer::set_timeout`].
+/// Channels can be idle for as long as they like.
+///
+/// Dropping it tells the remote to stop producing items.
+//
+#[ derive( Debug ) ]
+//
+pub struct ResponseStream<Wf>
+{
+	rx     : mpsc::UnboundedReceiver< ResponseItem<Wf> >,
+	ended  : bool                                       ,
+	sid    : ServiceID                                  ,
+	timeout: Option<Duration>                           ,
+
+	// Runs while the caller is waiting for the next item.
+	//
+	waiting: Option<Delay>,
+
+	// When dropped, the peer will cancel the call.
+	//
+	cancel: Option< oneshot::Sender<()> >,
+}
+
+
+impl<Wf> Stream for ResponseStream<Wf>
+{
+	type Item = Result< Result<Wf, ConnectionError>, oneshot::Canceled >;
+
+	fn poll_next( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll< Option<Self::Item> >
+	{
+		if self.ended { return Poll::Ready( None ) }
+
+		// The credit of the item is given back once it's taken.
+		//
+		let item = self.rx.poll_next_unpin( cx ).map( |item| item.map( |(item, _permit)| item ) );
+
+		if item.is_ready()
+		{
+			self.waiting = None;
+		}
+
+		match item
+		{
+			Poll::Pending =>
+			{
+				let Some( timeout ) = self.timeout else { return Poll::Pending };
+
+				let waiting = self.waiting.get_or_insert_with( || Delay::new( timeout ) );
+
+				futures::ready!( waiting.poll_unpin( cx ) );
+
+				// Dropping the sender cancels the call.
+				//
+				self.ended  = true;
+				self.cancel = None;
+
+				Poll::Ready( Some( Ok( Err( ConnectionError::Timeout{ sid: self.sid } ) ) ) )
+			}
+
+			// The peer only sends errors as last item.
+			//
+			Poll::Ready( Some( Some( resp ) ) ) =>
+			{
+				self.ended = resp.is_err();
+
+				Poll::Ready( Some( Ok( resp ) ) )
+			}
+
+			// End of stream frame.
+			//
+			Poll::Ready( Some( None ) ) =>
+			{
+				self.ended = true;
+
+				Poll::Ready( None )
+			}
+
+			// The peer dropped the sender without the end of stream frame.
+			//
+			Poll::Ready( None ) =>
+			{
+				self.ended = true;
+
+				Poll::Ready( Some( Err( oneshot::Canceled ) ) )
+			}
+		}
+	}
+}
+
+
+
+/// Handler for outgoing calls to streaming services.
+//
+impl<Wf: WireFormat + Send + 'static> Handler<CallStream<Wf>> for Peer<Wf>
+{
+	#[async_fn] fn handle( &mut self, call: CallStream<Wf> ) -> <CallStream<Wf> as Message>::Return
+	{
+		trace!( "{}: polled Handler<CallStream>", self.identify() );
+
+		self.send_stream_call( call.wf, false ).await.map( |(_, stream)| stream )
+	}
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Peer<Wf>
+{
+	/// Send a call that will be answered with a stream. If `channel` is true, the remote is told first that
+	/// the call opens a bidirectional channel.
+	//
+	pub(crate) async fn send_stream_call( &mut self, mut wf: Wf, channel: bool ) -> Result< (ConnID, ResponseStream<Wf>), PeerErr >
+	{
+		// If self.closed is false, there should always be an address.
+		//
+		let mut self_addr = match &self.addr
+		{
+			Some( addr ) if !self.closed => addr.weak(),
+
+			_ =>
+			{
+				let ctx = self.ctx( None, None, "Send streaming call" );
+
+				return Err( PeerErr::ConnectionClosed{ ctx } );
+			}
+		};
+
+
+		let sid = wf.sid();
+
+		if let Some(remote) = &self.remote_services
+		{
+			if !remote.contains_key( &sid )
+			{
+				let ctx = self.ctx( sid, None, "Send streaming call: remote didn't advertise this service" );
+
+				return Err( PeerErr::UnknownService{ ctx } );
+			}
+		}
+
+
+		let cid = self.next_cid();
+
+		// Before the call goes out, so the task reading the connection has it for the first response.
+		//
+		self.stream_credit.lock().insert( cid, Arc::new( Semaphore::new( STREAM_CREDIT ) ) );
+
+		if channel
+		{
+			let mut open = Wf::with_capacity( 0 );
+			open.set_sid( ServiceID::channel_open() );
+			open.set_cid( cid                       );
+
+			if let Err(e) = self.send_msg( open ).await
+			{
+				self.stream_credit.lock().remove( &cid );
+				return Err(e);
+			}
+		}
+
+		wf.set_cid( cid );
+
+		if let Err(e) = self.send_msg( wf ).await
+		{
+			self.stream_credit.lock().remove( &cid );
+			return Err(e);
+		}
+
+
+		// Cancel the call when the caller drops the stream. Don't keep ourselves alive.
+		//
+		let (tx    , rx      ) = mpsc::unbounded();
+		let (cancel, canceled) = oneshot::channel::<()>();
+
+		let task = async move
+		{
+			let _ = canceled.await;
+			let _ = self_addr.send( super::CancelCall{ cid, sid } ).await;
+
+			Ok( Response::Nothing )
+		};
+
+		self.nursery.nurse( task ).map_err( |_|
+		{
+			self.stream_credit.lock().remove( &cid );
+
+			let ctx = self.ctx( sid, None, "cancel task for streaming call" );
+
+			PeerErr::Spawn{ ctx }
+
+		})?;
+
+
+		self.streams.insert( cid, tx );
+
+		// Channels have no timeout.
+		//
+		let timeout = ( !channel ).then_some( self.timeout );
+
+		let stream = ResponseStream
+		{
+			rx                    ,
+			sid                   ,
+			timeout               ,
+			ended  : false        ,
+			waiting: None         ,
+			cancel : Some(cancel) ,
+		};
+
+		Ok(( cid, stream ))
+	}
+
+
+	/// Stop receiving the responses to a streaming call.
+	//
+	pub(crate) fn forget_stream( &mut self, cid: ConnID ) -> Option< mpsc::UnboundedSender< ResponseItem<Wf> > >
+	{
+		self.stream_credit.lock().remove( &cid );
+
+		self.streams.remove( &cid )
+	}
+}
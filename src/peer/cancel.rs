@@ -0,0 +1,133 @@
+use crate::{ import::*, *, peer::RequestError };
+
+
+/// Sent to the peer by the task watching an outgoing call when the caller dropped the receiver for
+/// the response. The peer forgets about the call and tells the remote to stop processing it.
+//
+#[ derive( Debug ) ]
+//
+pub(crate) struct CancelCall
+{
+	pub(crate) cid: ConnID,
+	pub(crate) sid: ServiceID,
+}
+
+impl Message for CancelCall
+{
+	type Return = ();
+}
+
+
+
+/// Handler for outgoing calls the caller is no longer interested in.
+//
+impl<Wf: WireFormat + Send + 'static> Handler<CancelCall> for Peer<Wf>
+{
+	#[async_fn] fn handle( &mut self, msg: CancelCall ) -> <CancelCall as Message>::Return
+	{
+		// Already answered or timed out.
+		//
+		let waiting = self.responses.remove( &msg.cid ).is_some() || self.forget_stream( msg.cid ).is_some();
+
+		if !waiting { return }
+
+		trace!( "{}: canceling outgoing call, sid: {}, cid: {}", self.identify(), msg.sid, msg.cid );
+
+		self.in_flight.remove( &msg.cid );
+
+		if self.closed { return }
+
+		// If the call is still waiting in the buffer for a new connection, the remote never heard of it.
+		//
+		if self.reconnecting()
+		{
+			self.send_buffer.retain( |(wf, _)| !( matches!( wf.kind(), WireType::IncomingCall ) && wf.cid() == msg.cid ) );
+
+			return;
+		}
+
+		// Same if it's waiting for the handshake of the remote.
+		//
+		let held = self.held_calls.len();
+		self.held_calls.retain( |(wf, _)| wf.cid() != msg.cid );
+
+		if self.held_calls.len() != held { return }
+
+		let mut wf = Wf::with_capacity( size_of::<ConnID>() * 2 );
+		wf.set_sid( ServiceID::cancel() );
+		wf.set_cid( ConnID::null()      );
+
+		CborCodec::encode( &mut wf, &msg.cid ).expect( "serialize ConnID" );
+
+		if let Err(e) = self.send_msg( wf ).await
+		{
+			self.pharos.send( PeerEvent::Error(e) ).await.expect( "pharos not closed" );
+		}
+	}
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Peer<Wf>
+{
+	/// Abort processing an incoming call because the remote is no longer waiting for the response.
+	/// If the call is being relayed, dropping the relayed call will cancel it on the next hop.
+	//
+	pub(crate) async fn canceled( &mut self, frame: Wf )
+	{
+		let cid: ConnID = match CborCodec::decode( frame.msg() )
+		{
+			Ok(x) => x,
+
+			Err(_) =>
+			{
+				let ctx = self.ctx( ServiceID::cancel(), None, "Deserialize cancel frame" );
+
+				return self.handle( RequestError::from( PeerErr::Deserialize{ ctx } ) ).await;
+			}
+		};
+
+		// The call came with a deadline or opens a channel, but hasn't come in yet.
+		//
+		self.take_deadline( cid );
+		self.forget_channel( cid );
+
+		if let Some( handle ) = self.processing.remove( &cid )
+		{
+			trace!( "{}: remote canceled incoming call, cid: {}", self.identify(), cid );
+
+			handle.abort();
+		}
+	}
+
+
+	/// Make an incoming call abortable by the remote. When aborted, the future resolves to a response that
+	/// doesn't send anything, but still releases the backpressure.
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	pub(crate) fn abortable
+	(
+		&mut self                                                                  ,
+		fut: Pin<Box< dyn Future< Output=Result<Response<Wf>, PeerErr> > + Send >> ,
+		cid: ConnID                                                                ,
+		bp : bool                                                                  ,
+	)
+		-> impl Future< Output=Result<Response<Wf>, PeerErr> > + Send
+	{
+		let (handle, registration) = AbortHandle::new_pair();
+
+		self.processing.insert( cid, handle );
+
+		Abortable::new( fut, registration ).map( move |result| match result
+		{
+			Ok( resp ) => resp,
+
+			Err(_) => match bp
+			{
+				true  => Ok( Response::CallResponse( CallResponse::canceled() ) ),
+				false => Ok( Response::Nothing                                  ),
+			}
+		})
+	}
+}
@@ -39,6 +39,13 @@ impl<Wf: WireFormat> Handler<CloseConnection> for Peer<Wf>
 	{
 		trace!( "{}: CloseConnection, by remote: {}, reason: {}", self.identify(), msg.remote, &msg.reason );
 
+		// We lost the connection, but we might be able to get it back.
+		//
+		if msg.remote && !self.closed && self.reconnect.is_some()
+		{
+			return self.disconnect( msg.reason ).await;
+		}
+
 		self.closed = true;
 
 		// Since we don't close it, it shouldn't be closed.
@@ -95,7 +102,24 @@ impl<Wf: WireFormat> Handler<CloseConnection> for Peer<Wf>
 		// want to send stuff over the network, so if we keep them alive, they will keep us
 		// alive. This breaks that cycle.
 		//
-		self.services .clear();
-		self.responses.clear();
+		self.services   .clear();
+		self.responses  .clear();
+		self.in_flight  .clear();
+		self.send_buffer.clear();
+		self.held_calls .clear();
+		self.deadlines  .clear();
+		self.streams    .clear();
+		self.stream_credit .lock().clear();
+		self.opening    .clear();
+		self.channels   .clear();
+		self.channel_credit.lock().clear();
+		self.fragments  .clear();
+
+		// Abort the processing of incoming calls. We can't send the responses anymore.
+		//
+		for (_, handle) in self.processing.drain()
+		{
+			handle.abort();
+		}
 	}
 }
@@ -41,6 +41,10 @@ pub enum ConnectionError
 	/// We don't provide this service.
 	//
 	PubSubNoCall{ sid: Option<ServiceID>, cid: Option<ConnID> },
+
+	/// The handshake failed because we speak another protocol version. The connection will be closed.
+	//
+	IncompatibleVersion{ version: u16 },
 }
 
 
@@ -77,6 +81,10 @@ impl fmt::Display for ConnectionError
 			ConnectionError::PubSubNoCall{ sid, .. } =>
 
 				write!( f, "Remote broadcasts this message type using thespis_remote::PubSub which does not support the `call` operation. Only `send` is supported (sid: {:?}).", sid ),
+
+			ConnectionError::IncompatibleVersion{ version } =>
+
+				write!( f, "Remote speaks version {} of the protocol, which is incompatible with ours.", version ),
 		}
 	}
 }
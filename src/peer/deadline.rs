@@ -0,0 +1,142 @@
+use crate::{ import::*, *, peer::RequestError };
+
+
+// How many deadlines we keep for calls that haven't come in yet. The call follows right after its deadline, so only a
+// remote that sends deadlines without calls fills this up. When full, the oldest deadline is forgotten.
+//
+const MAX_DEADLINES: usize = 64;
+
+
+/// The frame that tells the remote how much time it has to answer a call. It is sent right before the
+/// call itself when the call has it's own timeout, also when the call is replayed after reconnecting.
+/// See [`Call::timeout`].
+//
+#[ derive( Debug, Serialize, Deserialize ) ]
+//
+struct DeadlineFrame
+{
+	/// The connection id of the call.
+	//
+	cid: ConnID,
+
+	/// The remaining time in milliseconds.
+	//
+	millis: u64,
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Peer<Wf>
+{
+	/// Tell the remote the deadline for an outgoing call.
+	//
+	pub(crate) async fn send_deadline( &mut self, cid: ConnID, timeout: Duration ) -> Result<(), PeerErr>
+	{
+		let frame = DeadlineFrame{ cid, millis: timeout.as_millis().try_into().unwrap_or( u64::MAX ) };
+
+		let mut wf = Wf::with_capacity( size_of::<DeadlineFrame>() * 2 );
+		wf.set_sid( ServiceID::deadline() );
+		wf.set_cid( ConnID::null()        );
+
+		CborCodec::encode( &mut wf, &frame ).map_err( |_|
+		{
+			let ctx = self.ctx( ServiceID::deadline(), cid, "Serialize deadline frame" );
+
+			PeerErr::Serialize{ ctx }
+
+		})?;
+
+		self.send_msg( wf ).await
+	}
+
+
+	/// Store the deadline for an incoming call. The call itself will follow.
+	//
+	pub(crate) async fn deadline( &mut self, frame: Wf )
+	{
+		let deadline: DeadlineFrame = match CborCodec::decode( frame.msg() )
+		{
+			Ok(x) => x,
+
+			Err(_) =>
+			{
+				let ctx = self.ctx( ServiceID::deadline(), None, "Deserialize deadline frame" );
+
+				return self.handle( RequestError::from( PeerErr::Deserialize{ ctx } ) ).await;
+			}
+		};
+
+		trace!( "{}: deadline for cid {}: {}ms", self.identify(), deadline.cid, deadline.millis );
+
+		let now = Instant::now();
+
+		// A deadline too far away to represent is no deadline.
+		//
+		let Some( instant ) = now.checked_add( Duration::from_millis( deadline.millis ) ) else { return };
+
+		// The call should follow right after its deadline. If it didn't come in time, it probably never will,
+		// eg. when it was refused for being too big.
+		//
+		self.deadlines.retain( |(_, d)| *d > now );
+
+		if self.deadlines.len() == MAX_DEADLINES
+		{
+			self.deadlines.pop_front();
+		}
+
+		self.deadlines.push_back(( deadline.cid, instant ));
+	}
+
+
+	/// Remove the deadline of an incoming call if the remote sent one.
+	//
+	pub(crate) fn take_deadline( &mut self, cid: ConnID ) -> Option<Instant>
+	{
+		let idx = self.deadlines.iter().position( |(c, _)| *c == cid )?;
+
+		self.deadlines.remove( idx ).map( |(_, d)| d )
+	}
+
+
+	/// Stop processing an incoming call when nobody is waiting for the response anymore. The caller gets
+	/// [`ConnectionError::Timeout`] if it's still listening.
+	//
+	pub(crate) async fn with_deadline
+	(
+		fut     : Pin<Box< dyn Future< Output=Result<Response<Wf>, PeerErr> > + Send >> ,
+		deadline: Instant                                                               ,
+		sid     : ServiceID                                                             ,
+		cid     : ConnID                                                                ,
+		bp      : bool                                                                  ,
+	)
+		-> Result<Response<Wf>, PeerErr>
+	{
+		let remaining = deadline.saturating_duration_since( Instant::now() );
+
+		// The future hasn't been polled yet, so dropping it means the service never get's called.
+		//
+		if !remaining.is_zero()
+		{
+			let delay = Delay::new( remaining );
+
+			pin_mut!( delay );
+
+			if let Either::Left(( resp, _ )) = futures::future::select( fut, delay ).await
+			{
+				return resp;
+			}
+		}
+
+		trace!( "Deadline passed for incoming call, sid: {}, cid: {}", sid, cid );
+
+		let err = Self::prep_error( cid, &ConnectionError::Timeout{ sid } );
+
+		// Make sure backpressure get's released.
+		//
+		match bp
+		{
+			true  => Ok( Response::CallResponse( CallResponse::new( err ) ) ),
+			false => Ok( Response::WireFormat  ( err                      ) ),
+		}
+	}
+}
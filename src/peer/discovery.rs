@@ -0,0 +1,120 @@
+use crate::{ import::*, *, peer::RequestError };
+
+
+/// One entry of the discovery frame.
+//
+#[ derive( Debug, Serialize, Deserialize ) ]
+//
+struct Advert
+{
+	sid : ServiceID      ,
+	name: Option<String> ,
+}
+
+
+
+/// Control message for [Peer]. Query the services the remote advertised with the discovery handshake.
+/// See [`Peer::set_discovery`].
+///
+/// Returns `None` if the remote hasn't advertised anything (yet). Otherwise you get the service ids with
+/// the name the remote registered for them, if any.
+//
+#[ derive( Debug, Clone, Copy ) ]
+//
+pub struct RemoteServices;
+
+impl Message for RemoteServices
+{
+	type Return = Option< HashMap<ServiceID, Option<String>> >;
+}
+
+
+
+impl<Wf: WireFormat> Handler<RemoteServices> for Peer<Wf>
+{
+	#[async_fn] fn handle( &mut self, _msg: RemoteServices ) -> <RemoteServices as Message>::Return
+	{
+		self.remote_services.clone()
+	}
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Peer<Wf>
+{
+	/// Advertise our services to the remote. When enabled, a discovery frame with the service ids (and their names
+	/// from [`ServiceID::service_name`]) of all registered service maps is sent when the mailbox starts. It is sent
+	/// again when services change with [`AddServices`] and [`RemoveServices`] and after reconnecting.
+	///
+	/// The remote peer will fail outgoing calls to services that we don't advertise locally with
+	/// [`PeerErr::UnknownService`] instead of sending them over the network. Observers of the remote peer
+	/// will get [`PeerEvent::RemoteServices`].
+	///
+	/// The remote must be a version of thespis_remote that understands the discovery frame. Older versions
+	/// will respond with [`ConnectionError::UnknownService`]. Defaults to false.
+	//
+	pub fn set_discovery( &mut self, advertise: bool )
+	{
+		self.discovery = advertise;
+	}
+
+
+	/// Send the discovery frame if enabled.
+	//
+	pub(crate) async fn advertise( &mut self )
+	{
+		if !self.discovery || self.closed { return }
+
+		let adverts: Vec<Advert> = self.services.keys().map( |sid| Advert
+		{
+			sid : *sid,
+			name: ServiceID::service_name( *sid ).map( String::from ),
+
+		}).collect();
+
+		trace!( "{}: advertising {} services.", self.identify(), adverts.len() );
+
+		let mut wf = Wf::with_capacity( adverts.len() * 2 * size_of::<Advert>() );
+		wf.set_sid( ServiceID::discovery() );
+		wf.set_cid( ConnID::null()         );
+
+		if CborCodec::encode( &mut wf, &adverts ).is_err()
+		{
+			let ctx = self.ctx( ServiceID::discovery(), None, "Serialize discovery frame" );
+			self.pharos.send( PeerEvent::Error( PeerErr::Serialize{ ctx } ) ).await.expect( "pharos not closed" );
+
+			return;
+		}
+
+		if let Err(e) = self.send_msg( wf ).await
+		{
+			self.pharos.send( PeerEvent::Error(e) ).await.expect( "pharos not closed" );
+		}
+	}
+
+
+	/// Process a discovery frame from the remote.
+	//
+	pub(crate) async fn discovered( &mut self, frame: Wf )
+	{
+		let adverts: Vec<Advert> = match CborCodec::decode( frame.msg() )
+		{
+			Ok(x) => x,
+
+			Err(_) =>
+			{
+				let ctx = self.ctx( ServiceID::discovery(), None, "Deserialize discovery frame" );
+
+				return self.handle( RequestError::from( PeerErr::Deserialize{ ctx } ) ).await;
+			}
+		};
+
+		trace!( "{}: remote advertised {} services.", self.identify(), adverts.len() );
+
+		let sids = adverts.iter().map( |a| a.sid ).collect();
+
+		self.remote_services = Some( adverts.into_iter().map( |a| (a.sid, a.name) ).collect() );
+
+		self.pharos.send( PeerEvent::RemoteServices( sids ) ).await.expect( "pharos not closed" );
+	}
+}
@@ -0,0 +1,148 @@
+use crate::{ import::*, * };
+
+
+/// When the peer flushes the connection. Until a flush, outgoing messages are queued in the sink, so a batch of
+/// messages can go out in one write. This avoids a syscall and a tiny TCP segment per message when sending many
+/// small messages at a high rate. The [Encoder] of [CborWF] writes the batch with vectored I/O.
+///
+/// Messages that are queued when the connection is lost are lost as well, just like messages that were still in
+/// the buffers of the OS. With reconnection, calls waiting for a response are replayed according to the
+/// [ReplayPolicy].
+//
+#[ derive( Debug, Clone, Copy, PartialEq, Eq, Default ) ]
+//
+pub enum FlushPolicy
+{
+	/// Flush after every message. This is the default.
+	//
+	#[ default ]
+	//
+	Immediate,
+
+	/// Flush as soon as this many bytes are queued. Whatever is left is flushed once the peer has processed the
+	/// outgoing messages that were already in its mailbox, so a lone message is never held back. Incoming messages
+	/// don't delay this flush.
+	//
+	Bytes( usize ),
+
+	/// Flush this long after the first message that was queued since the last flush.
+	//
+	Delay( Duration ),
+}
+
+
+
+/// Sent to the peer when it's time to flush the connection.
+//
+#[ derive( Debug, Clone, Copy ) ]
+//
+pub(crate) struct Flush;
+
+impl Message for Flush
+{
+	type Return = ();
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Peer<Wf>
+{
+	/// Set when the connection is flushed. Defaults to [`FlushPolicy::Immediate`].
+	//
+	pub fn set_flush_policy( &mut self, policy: FlushPolicy )
+	{
+		self.flush_policy = policy;
+	}
+
+
+	/// A message of `len` bytes was queued in the sink. Flush now or schedule a flush according to the policy.
+	//
+	pub(crate) async fn queued( &mut self, len: usize ) -> Result<(), PeerErr>
+	{
+		self.unflushed += len;
+
+		match self.flush_policy
+		{
+			FlushPolicy::Immediate                            => self.flush().await,
+			FlushPolicy::Bytes( max ) if self.unflushed >= max => self.flush().await,
+			FlushPolicy::Bytes( _   )                         => self.schedule_flush( None ),
+			FlushPolicy::Delay( delay )                       => self.schedule_flush( Some(delay) ),
+		}
+	}
+
+
+	/// Flush the connection.
+	//
+	pub(crate) async fn flush( &mut self ) -> Result<(), PeerErr>
+	{
+		if self.unflushed == 0 { return Ok(()) }
+
+		let Some( out ) = &mut self.outgoing else { return Ok(()) };
+
+		let result = out.flush().await;
+
+		self.unflushed = 0;
+
+		result.map_err( |source|
+		{
+			let ctx = self.ctx( None, None, "Flushing the connection" );
+			PeerErr::WireFormat{ ctx, source }
+		})
+	}
+
+
+	/// Flush later, unless a flush is scheduled already. The flush goes through the high priority channel of our
+	/// mailbox, like outgoing messages. Without a delay, it comes right after the outgoing messages that are
+	/// already waiting, and incoming messages can't hold it back.
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	fn schedule_flush( &mut self, delay: Option<Duration> ) -> Result<(), PeerErr>
+	{
+		// No more flushing once we are closing the connection.
+		//
+		if self.flush_scheduled || self.addr.is_none() { return Ok(()) }
+
+		// A weak address, so we don't keep ourselves alive.
+		//
+		let mut self_addr = self.addr_out.clone();
+
+		let task = async move
+		{
+			if let Some( delay ) = delay
+			{
+				Delay::new( delay ).await;
+			}
+
+			let _ = self_addr.send( Flush ).await;
+
+			Ok( Response::Nothing )
+		};
+
+		self.nursery.nurse( task ).map_err( |_|
+		{
+			let ctx = self.ctx( None, None, "Task to flush the connection" );
+
+			PeerErr::Spawn{ ctx }
+		})?;
+
+		self.flush_scheduled = true;
+
+		Ok(())
+	}
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Handler<Flush> for Peer<Wf>
+{
+	#[async_fn] fn handle( &mut self, _msg: Flush )
+	{
+		self.flush_scheduled = false;
+
+		if let Err(e) = self.flush().await
+		{
+			self.pharos.send( PeerEvent::Error(e) ).await.expect( "pharos not closed" );
+		}
+	}
+}
@@ -0,0 +1,419 @@
+use crate::{ import::*, * };
+
+
+/// Configuration for fragmentation. Outgoing messages bigger than `fragment_size` are split into numbered fragments
+/// which the remote reassembles before processing the message. Fragments of a big message are sent one at a time,
+/// so other messages can go out in between. When several messages are being fragmented, we send a fragment of each
+/// in turn. One huge upload won't block small calls or other uploads on the same connection.
+///
+/// Messages that belong to the same call, stream or channel keep their order. So do sends to the same service.
+///
+/// Both sides need to enable fragmentation. The remote should accept frames of `fragment_size` and it should have
+/// the same `max_message`. When the handshake is enabled, the max sizes of the handshake apply to the fragments.
+//
+#[ derive( Debug, Clone, Copy, PartialEq, Eq ) ]
+//
+pub struct Fragmentation
+{
+	fragment_size: usize,
+	max_message  : usize,
+}
+
+
+impl Fragmentation
+{
+	/// `fragment_size` is the maximum size of a fragment, header included. It should not be bigger than the max
+	/// size of outgoing frames. `max_message` is the maximum size of a reassembled message. It applies both to
+	/// outgoing messages and to messages we reassemble.
+	//
+	pub fn new( fragment_size: usize, max_message: usize ) -> Self
+	{
+		Self { fragment_size, max_message }
+	}
+}
+
+
+
+/// Sent to the peer for each fragment that is ready to go out.
+//
+#[ derive( Debug, Clone, Copy ) ]
+//
+pub(crate) struct NextFragment;
+
+impl Message for NextFragment
+{
+	type Return = ();
+}
+
+
+
+// The length of the index and the count at the start of the payload of a fragment.
+//
+const LEN_INDEX: usize = 4; // u32
+const LEN_COUNT: usize = 4; // u32
+
+// The reassembled data starts with the sid and the cid of the original message.
+//
+const LEN_SID: usize = 16; // u128
+const LEN_IDS: usize = 24; // u128 + u64
+
+// How many failed messages the reassembler remembers to ignore the rest of their fragments. When a remote fails more
+// messages at once, the fragments of the ones we forget are reported as out of order.
+//
+const MAX_FAILED: usize = 64;
+
+
+
+impl<Wf: WireFormat + Send + 'static> Peer<Wf>
+{
+	/// Split outgoing messages bigger than the fragment size and reassemble incoming fragments. See [Fragmentation].
+	//
+	pub fn set_fragmentation( &mut self, fragmentation: Fragmentation )
+	{
+		self.fragmentation = Some( fragmentation );
+		self.max_message.store( fragmentation.max_message, Relaxed );
+	}
+
+
+	/// Split messages that are too big. Returns the message if it should be sent now. Otherwise it is queued
+	/// behind the fragments that are waiting to go out.
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	pub(crate) fn fragment( &mut self, msg: Wf ) -> Result< Option<Wf>, PeerErr >
+	{
+		let Some( frag ) = self.fragmentation else { return Ok( Some(msg) ) };
+
+		let sid  = msg.sid();
+		let cid  = msg.cid();
+		let size = msg.len() as usize;
+
+		let was_empty = self.fragments.is_empty();
+
+		// Don't overtake the fragments of a message that comes before this one.
+		//
+		let wait = self.fragments.iter().position( |queue| queue.iter().any( |(s, c, _)| match cid.is_null()
+		{
+			true  => c.is_null() && *s == sid,
+			false => *c == cid,
+		}));
+
+		if size <= frag.fragment_size
+		{
+			let Some( queue ) = wait else { return Ok( Some(msg) ) };
+
+			self.fragments[ queue ].push_back(( sid, cid, msg ));
+			return Ok( None );
+		}
+
+		if size > frag.max_message
+		{
+			let ctx    = self.ctx( sid, cid, "Fragmenting outgoing message" );
+			let source = WireErr::MessageSizeExceeded{ context: "Max message size for fragmentation".to_string(), size, max_size: frag.max_message };
+
+			return Err( PeerErr::WireFormat{ ctx, source } );
+		}
+
+		// The data we split is the sid, the cid and the payload of the message.
+		//
+		let mut data = Vec::with_capacity( LEN_IDS + msg.msg().len() );
+
+		data.extend_from_slice( &u128::from( sid ).to_le_bytes() );
+		data.extend_from_slice( &u64::from( cid ).to_le_bytes() );
+		data.extend_from_slice( msg.msg()                        );
+
+		// Some wire formats have a header that grows with the length of the payload, so measure it for a fragment
+		// as big as they can get.
+		//
+		let stream = ConnID::from( self.conn_id_counter.fetch_add( 1, Relaxed ) );
+		let header = Wf::header_len( ServiceID::fragment(), stream, frag.fragment_size ) + LEN_INDEX + LEN_COUNT;
+		let chunk  = frag.fragment_size.saturating_sub( header ).max( 1 );
+		let count  = data.len().div_ceil( chunk );
+
+		let Ok( count ) = u32::try_from( count ) else
+		{
+			let ctx    = self.ctx( sid, cid, "Fragmenting outgoing message" );
+			let source = WireErr::MessageSizeExceeded{ context: "Too many fragments".to_string(), size, max_size: frag.max_message };
+
+			return Err( PeerErr::WireFormat{ ctx, source } );
+		};
+
+		trace!( "{}: splitting message of {} bytes in {} fragments, sid: {}, cid: {}", self.identify(), size, count, sid, cid );
+
+		let mut fragments = VecDeque::with_capacity( count as usize );
+
+		for (index, bytes) in ( 0..count ).zip( data.chunks( chunk ) )
+		{
+			let mut wf = Wf::with_capacity( LEN_INDEX + LEN_COUNT + bytes.len() );
+
+			wf.set_sid( ServiceID::fragment() );
+			wf.set_cid( stream                );
+
+			let written = wf.write_all( &index.to_le_bytes() )
+				.and_then( |_| wf.write_all( &count.to_le_bytes() ) )
+				.and_then( |_| wf.write_all( bytes                ) )
+			;
+
+			if written.is_err()
+			{
+				let ctx = self.ctx( sid, cid, "Writing fragment" );
+				return Err( PeerErr::Serialize{ ctx } );
+			}
+
+			fragments.push_back(( sid, cid, wf ));
+		}
+
+		match wait
+		{
+			Some( queue ) => self.fragments[ queue ].append( &mut fragments ),
+			None          => self.fragments.push_back( fragments ),
+		}
+
+		if was_empty
+		{
+			self.next_fragment()?;
+		}
+
+		Ok( None )
+	}
+
+
+	/// Schedule sending the next fragment. It goes through our mailbox, so other messages can go out in between.
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	fn next_fragment( &mut self ) -> Result<(), PeerErr>
+	{
+		// Don't keep ourselves alive.
+		//
+		let mut self_addr = match &self.addr
+		{
+			Some(a) => a.weak(),
+			None    => return Ok(()),
+		};
+
+		let task = async move
+		{
+			let _ = self_addr.send( NextFragment ).await;
+
+			Ok( Response::Nothing )
+		};
+
+		self.nursery.nurse( task ).map_err( |_|
+		{
+			let ctx = self.ctx( None, None, "Task to send the next fragment" );
+
+			PeerErr::Spawn{ ctx }
+		})
+	}
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Handler<NextFragment> for Peer<Wf>
+{
+	#[async_fn] fn handle( &mut self, _msg: NextFragment )
+	{
+		let Some( mut queue   ) = self.fragments.pop_front() else { return };
+		let Some(( _, _, wf )) = queue.pop_front()          else { return };
+
+		// Round robin over the messages being fragmented.
+		//
+		if !queue.is_empty()
+		{
+			self.fragments.push_back( queue );
+		}
+
+		if let Err(e) = self.write_msg( wf ).await
+		{
+			self.pharos.send( PeerEvent::Error(e) ).await.expect( "pharos not closed" );
+		}
+
+		if !self.fragments.is_empty()
+		{
+			if let Err(e) = self.next_fragment()
+			{
+				self.pharos.send( PeerEvent::Error(e) ).await.expect( "pharos not closed" );
+			}
+		}
+	}
+}
+
+
+
+/// Reassembles incoming fragments before the frames are delivered to the peer.
+//
+#[ derive( Debug ) ]
+//
+pub(crate) struct Reassembler
+{
+	// Shared with the peer, so fragmentation can be enabled after the connection was framed. Zero means disabled.
+	//
+	max_message: Arc<AtomicUsize>,
+
+	// Messages that are not complete yet, by the cid of the fragments.
+	//
+	partial: HashMap<ConnID, Partial>,
+
+	// The total size of the partial messages.
+	//
+	buffered: usize,
+
+	// Messages we failed to reassemble. We ignore the rest of their fragments. Holds at most `MAX_FAILED`, the
+	// oldest are forgotten first.
+	//
+	failed: VecDeque<ConnID>,
+}
+
+
+#[ derive( Debug ) ]
+//
+struct Partial
+{
+	data : Vec<u8>,
+	next : u32    ,
+	count: u32    ,
+}
+
+
+impl Reassembler
+{
+	pub(crate) fn new( max_message: Arc<AtomicUsize> ) -> Self
+	{
+		Self
+		{
+			max_message                ,
+			partial    : HashMap::new(),
+			buffered   : 0             ,
+			failed     : VecDeque::new(),
+		}
+	}
+
+
+	/// Process a frame coming in from the network. Returns `None` when we are waiting for more fragments.
+	/// Other frames are returned unchanged.
+	//
+	pub(crate) fn push<Wf: WireFormat>( &mut self, frame: Wf ) -> Result< Option<Wf>, WireErr >
+	{
+		let max_message = self.max_message.load( Relaxed );
+
+		if max_message == 0 || frame.sid() != ServiceID::fragment()
+		{
+			return Ok( Some(frame) );
+		}
+
+		let stream = frame.cid();
+
+		match self.reassemble( frame, max_message )
+		{
+			Err( e ) =>
+			{
+				self.drop_partial( stream );
+
+				if self.failed.len() == MAX_FAILED
+				{
+					self.failed.pop_front();
+				}
+
+				self.failed.push_back( stream );
+
+				Err( e )
+			}
+
+			ok => ok,
+		}
+	}
+
+
+	fn reassemble<Wf: WireFormat>( &mut self, frame: Wf, max_message: usize ) -> Result< Option<Wf>, WireErr >
+	{
+		let stream = frame.cid();
+		let msg    = frame.msg();
+
+		let fail = |context: &str| WireErr::Deserialize{ context: format!( "Reassembling fragments: {context}" ) };
+
+		if msg.len() < LEN_INDEX + LEN_COUNT
+		{
+			return Err( fail( "fragment too short for the index and count." ) );
+		}
+
+		let index = u32::from_le_bytes( msg[ ..LEN_INDEX                     ].try_into().expect( "4 bytes" ) );
+		let count = u32::from_le_bytes( msg[ LEN_INDEX..LEN_INDEX+LEN_COUNT ].try_into().expect( "4 bytes" ) );
+		let bytes = &msg[ LEN_INDEX+LEN_COUNT.. ];
+
+		if let Some( failed ) = self.failed.iter().position( |s| *s == stream )
+		{
+			if index >= count.saturating_sub( 1 ) { self.failed.remove( failed ); }
+
+			return Ok( None );
+		}
+
+		if index == 0
+		{
+			if count == 0 || self.partial.contains_key( &stream )
+			{
+				return Err( fail( "invalid first fragment." ) );
+			}
+
+			self.partial.insert( stream, Partial{ data: Vec::new(), next: 0, count } );
+		}
+
+		match self.partial.get( &stream )
+		{
+			Some( p ) if p.next == index && p.count == count => {}
+
+			_ => return Err( fail( "fragment out of order." ) ),
+		}
+
+		if self.buffered + bytes.len() > max_message
+		{
+			let size = self.partial[ &stream ].data.len() + bytes.len();
+
+			return Err( WireErr::MessageSizeExceeded
+			{
+				context : "Reassembling fragments".to_string(),
+				max_size: max_message                          ,
+				size                                           ,
+			});
+		}
+
+		let partial = self.partial.get_mut( &stream ).expect( "checked above" );
+
+		partial.data.extend_from_slice( bytes );
+		partial.next  += 1;
+		self.buffered += bytes.len();
+
+		if partial.next < partial.count
+		{
+			return Ok( None );
+		}
+
+		let data = self.drop_partial( stream ).expect( "checked above" ).data;
+
+		if data.len() < LEN_IDS
+		{
+			return Err( fail( "message too short for the sid and cid." ) );
+		}
+
+		let sid = u128::from_le_bytes( data[ ..LEN_SID        ].try_into().expect( "16 bytes" ) );
+		let cid = u64 ::from_le_bytes( data[ LEN_SID..LEN_IDS ].try_into().expect( "8 bytes"  ) );
+
+		let mut wf = Wf::with_capacity( data.len() - LEN_IDS );
+
+		wf.set_sid( sid.into() );
+		wf.set_cid( cid.into() );
+		wf.write_all( &data[ LEN_IDS.. ] )?;
+
+		Ok( Some(wf) )
+	}
+
+
+	fn drop_partial( &mut self, stream: ConnID ) -> Option<Partial>
+	{
+		let partial = self.partial.remove( &stream )?;
+
+		self.buffered -= partial.data.len();
+
+		Some( partial )
+	}
+}
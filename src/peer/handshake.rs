@@ -0,0 +1,453 @@
+use crate::{ import::*, *, peer::RequestError };
+
+
+/// The version of the thespis_remote protocol implemented by this crate. It is exchanged in the [Handshake]
+/// and peers with a different version will refuse to talk to each other.
+//
+pub const PROTOCOL_VERSION: u16 = 2;
+
+
+
+/// Optional protocol features a peer can support. The handshake only enables a feature if both sides support it.
+//
+#[ derive( Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize ) ]
+//
+pub struct Features
+{
+	/// The compression algorithms we can decode. The remote only compresses with one of these. See
+	/// [`Peer::set_compression`].
+	//
+	#[ serde( default ) ] pub compression: Algorithms,
+
+	/// Interleaving of frames from several logical streams over the connection.
+	//
+	#[ serde( default ) ] pub multiplexing: bool,
+
+	/// Heartbeat frames to detect dead connections.
+	//
+	#[ serde( default ) ] pub heartbeat: bool,
+
+	/// Integrity checksums on frames. See [`Peer::set_checksum`].
+	//
+	#[ serde( default ) ] pub checksum: bool,
+}
+
+
+impl Features
+{
+	/// The features supported by both sides.
+	//
+	pub fn intersect( &self, other: &Features ) -> Features
+	{
+		Features
+		{
+			compression : self.compression.intersect( &other.compression ),
+			multiplexing: self.multiplexing && other.multiplexing ,
+			heartbeat   : self.heartbeat    && other.heartbeat    ,
+			checksum    : self.checksum     && other.checksum     ,
+		}
+	}
+}
+
+
+
+/// The first frame a peer sends when the handshake is enabled with [`Peer::set_handshake`]. It tells the remote
+/// which protocol version we speak, what message sizes we accept and which optional features we support.
+//
+#[ derive( Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize ) ]
+//
+pub struct Handshake
+{
+	/// The protocol version. Set to [PROTOCOL_VERSION] by [`Handshake::new`].
+	//
+	pub version: u16,
+
+	/// The maximum size of incoming messages. Should be the same as what was passed to the codec.
+	//
+	pub max_size_read: usize,
+
+	/// The maximum size of outgoing messages. Should be the same as what was passed to the codec.
+	//
+	pub max_size_write: usize,
+
+	/// The optional features we support.
+	//
+	#[ serde( default ) ] pub features: Features,
+}
+
+
+impl Handshake
+{
+	/// Create a handshake for the current protocol version, without optional features.
+	//
+	pub fn new( max_size_read: usize, max_size_write: usize ) -> Self
+	{
+		Self
+		{
+			version : PROTOCOL_VERSION   ,
+			features: Features::default(),
+			max_size_read                ,
+			max_size_write               ,
+		}
+	}
+
+
+	/// Set the optional features we support.
+	//
+	pub fn features( mut self, features: Features ) -> Self
+	{
+		self.features = features;
+		self
+	}
+
+
+	/// Combine with the handshake of the remote. Returns `None` if the versions are incompatible.
+	//
+	pub fn negotiate( &self, remote: &Handshake ) -> Option<Negotiated>
+	{
+		if self.version != remote.version { return None }
+
+		Some( Negotiated
+		{
+			version     : self.version                                     ,
+			max_size_in : self.max_size_read .min( remote.max_size_write ) ,
+			max_size_out: self.max_size_write.min( remote.max_size_read  ) ,
+			features    : self.features.intersect( &remote.features )      ,
+		})
+	}
+}
+
+
+
+/// The outcome of a successful handshake. Observers receive it in [`PeerEvent::Negotiated`].
+//
+#[ derive( Debug, Clone, Copy, PartialEq, Eq ) ]
+//
+pub struct Negotiated
+{
+	/// The protocol version spoken on this connection.
+	//
+	pub version: u16,
+
+	/// The maximum size of messages the remote will send us.
+	//
+	pub max_size_in: usize,
+
+	/// The maximum size of messages we can send to the remote. Larger outgoing messages will be refused
+	/// with [`WireErr::MessageSizeExceeded`] before they are sent.
+	//
+	pub max_size_out: usize,
+
+	/// The optional features supported by both sides.
+	//
+	pub features: Features,
+}
+
+
+
+/// What the remote agreed to in the handshake about the frames it sends us. Shared with the task listening to the
+/// incoming stream.
+//
+#[ derive( Debug ) ]
+//
+pub(crate) struct Inbound
+{
+	/// The negotiated max size of incoming frames. usize::MAX until the handshake of the remote comes in.
+	//
+	pub(crate) max_size: AtomicUsize,
+
+	/// Whether checksums were negotiated, so the remote seals its frames once it has our handshake.
+	//
+	pub(crate) checksum: AtomicBool,
+}
+
+
+impl Inbound
+{
+	/// Forget what was negotiated, eg. when the connection is lost.
+	//
+	pub(crate) fn reset( &self )
+	{
+		self.max_size.store( usize::MAX, Relaxed );
+		self.checksum.store( false     , Relaxed );
+	}
+}
+
+
+impl Default for Inbound
+{
+	fn default() -> Self
+	{
+		Self
+		{
+			max_size: AtomicUsize::new( usize::MAX ),
+			checksum: AtomicBool ::new( false      ),
+		}
+	}
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Peer<Wf>
+{
+	/// Enable the handshake. The handshake frame is the first thing we send on the connection, including
+	/// after reconnecting. When the handshake of the remote comes in, the versions are compared. On a match,
+	/// observers get [`PeerEvent::Negotiated`]. On a mismatch, they get [`PeerEvent::IncompatibleVersion`],
+	/// the remote gets [`ConnectionError::IncompatibleVersion`] and the connection is closed.
+	///
+	/// Calls are held back until the handshake of the remote comes in, so the negotiated max sizes and features
+	/// apply to them. Other messages, streaming calls and channels are not held back. At most as many calls are held
+	/// back as the [`Reconnect::buffer`] allows, 64 if reconnecting is not enabled. Further calls are refused with
+	/// [`PeerErr::ConnectionClosed`].
+	///
+	/// Both sides need to enable the handshake. A remote that doesn't understand it responds with
+	/// [`ConnectionError::UnknownService`]. The calls that were held back then fail with that error, as do further
+	/// calls until we reconnect.
+	///
+	/// Incoming messages bigger than the negotiated [`Negotiated::max_size_in`] are refused with
+	/// [`WireErr::MessageSizeExceeded`] and the connection is closed.
+	//
+	pub fn set_handshake( &mut self, mut handshake: Handshake )
+	{
+		// Don't advertise compression we can't decode.
+		//
+		let compression = &mut handshake.features.compression;
+		*compression    = compression.intersect( &Wf::compression() );
+
+		self.handshake = Some( handshake );
+	}
+
+
+	/// Compress outgoing messages. This only takes effect when the handshake is enabled on both sides and they
+	/// advertise a common algorithm in [`Features::compression`]. If the remote doesn't advertise the algorithm
+	/// of `compression`, another one we have in common is used. Until the handshake of the remote comes in, messages
+	/// are sent uncompressed.
+	///
+	/// Compression is done by the encoder of the wire format. [`CborWF`] supports it. [`BytesWF`] and [`VarintWF`]
+	/// don't, see [`WireFormat::compression`]. With those, the handshake never advertises compression, so this has no
+	/// effect. The max sizes apply to the uncompressed messages.
+	//
+	pub fn set_compression( &mut self, compression: Compression )
+	{
+		self.compression = Some( compression );
+	}
+
+
+	/// Add an integrity checksum to outgoing frames. This only takes effect when the handshake is enabled on both
+	/// sides and both advertise [`Features::checksum`]. Until the handshake of the remote comes in, frames are sent
+	/// without checksum.
+	///
+	/// The checksum is added by the encoder of the wire format and verified by the decoder of the remote. [`CborWF`]
+	/// supports it, other wire formats can ignore it. When a frame doesn't match its checksum, the remote reports
+	/// [`WireErr::Checksum`] and closes the connection, as nothing that comes after can be trusted.
+	///
+	/// Frames the remote sent before it had our handshake have no checksum. Once the first frame with a checksum
+	/// comes in, frames without one are refused the same way, so flipping the flag can't turn verification off.
+	//
+	pub fn set_checksum( &mut self, checksum: bool )
+	{
+		self.checksum = checksum;
+	}
+
+
+	/// Send our handshake frame if enabled.
+	//
+	pub(crate) async fn send_handshake( &mut self )
+	{
+		let handshake = match &self.handshake
+		{
+			Some(h) if !self.closed => *h,
+			_                       => return,
+		};
+
+		let mut wf = Wf::with_capacity( size_of::<Handshake>() * 2 );
+		wf.set_sid( ServiceID::handshake() );
+		wf.set_cid( ConnID::null()         );
+
+		if CborCodec::encode( &mut wf, &handshake ).is_err()
+		{
+			let ctx = self.ctx( ServiceID::handshake(), None, "Serialize handshake frame" );
+			self.pharos.send( PeerEvent::Error( PeerErr::Serialize{ ctx } ) ).await.expect( "pharos not closed" );
+
+			return;
+		}
+
+		if let Err(e) = self.send_msg( wf ).await
+		{
+			self.pharos.send( PeerEvent::Error(e) ).await.expect( "pharos not closed" );
+		}
+	}
+
+
+	/// Process the handshake frame of the remote.
+	//
+	pub(crate) async fn handshaken( &mut self, frame: Wf )
+	{
+		let remote: Handshake = match CborCodec::decode( frame.msg() )
+		{
+			Ok(x) => x,
+
+			Err(_) =>
+			{
+				let ctx = self.ctx( ServiceID::handshake(), None, "Deserialize handshake frame" );
+
+				return self.handle( RequestError::from( PeerErr::Deserialize{ ctx } ) ).await;
+			}
+		};
+
+		// If we didn't enable it, we consider the remote compatible with everything we do.
+		//
+		let local = self.handshake.unwrap_or_else( || Handshake
+		{
+			version       : remote.version      ,
+			max_size_read : usize::MAX          ,
+			max_size_write: usize::MAX          ,
+			features      : Features::default() ,
+		});
+
+		match local.negotiate( &remote )
+		{
+			Some( negotiated ) =>
+			{
+				debug!( "{}: handshake successful: {:?}", self.identify(), &negotiated );
+
+				self.negotiated = Some( negotiated );
+				self.inbound.max_size.store( negotiated.max_size_in      , Relaxed );
+				self.inbound.checksum.store( negotiated.features.checksum, Relaxed );
+
+				self.pharos.send( PeerEvent::Negotiated( negotiated ) ).await.expect( "pharos not closed" );
+
+				self.release_calls().await;
+			}
+
+			None =>
+			{
+				warn!( "{}: remote speaks protocol version {}, we speak {}. Closing connection.", self.identify(), remote.version, local.version );
+
+				let event = PeerEvent::IncompatibleVersion{ local: local.version, remote: remote.version };
+				self.pharos.send( event ).await.expect( "pharos not closed" );
+
+				let err = ConnectionError::IncompatibleVersion{ version: local.version };
+				self.send_err( ConnID::null(), &err, true ).await;
+			}
+		}
+	}
+
+
+	/// Whether calls have to wait for the handshake of the remote.
+	//
+	pub(crate) fn awaiting_handshake( &self ) -> bool
+	{
+		self.handshake.is_some() && self.negotiated.is_none()
+	}
+
+
+	/// Send the calls that waited for the handshake of the remote. Those that timed out or were canceled in the
+	/// mean time are no longer there.
+	//
+	async fn release_calls( &mut self )
+	{
+		while let Some(( wf, deadline )) = self.held_calls.pop_front()
+		{
+			let cid = wf.cid();
+
+			if let Err(e) = self.send_call( cid, wf, deadline ).await
+			{
+				// Dropping the sender wakes up the caller.
+				//
+				self.in_flight.remove( &cid );
+				self.responses.remove( &cid );
+
+				self.pharos.send( PeerEvent::Error(e) ).await.expect( "pharos not closed" );
+			}
+		}
+	}
+
+
+	/// Whether the remote answered our handshake frame with this error.
+	//
+	pub(crate) fn refuses_handshake( err: &ConnectionError ) -> bool
+	{
+		match err
+		{
+			ConnectionError::UnknownService{ sid, .. } => *sid == Some( ServiceID::handshake() ),
+			ConnectionError::IncompatibleVersion{..}   => true,
+			_                                          => false,
+		}
+	}
+
+
+	/// The remote refused our handshake. Fail the calls that waited for it, as well as further calls until we
+	/// reconnect.
+	//
+	pub(crate) fn refuse_calls( &mut self, err: &ConnectionError )
+	{
+		warn!( "{}: remote refused our handshake: {}", self.identify(), err );
+
+		self.refused = Some( err.clone() );
+
+		for ( wf, _ ) in self.held_calls.drain(..)
+		{
+			let cid = wf.cid();
+
+			self.in_flight.remove( &cid );
+
+			if let Some( channel ) = self.responses.remove( &cid )
+			{
+				let _ = channel.send( Err( err.clone() ) );
+			}
+		}
+	}
+
+
+	/// Refuse outgoing messages that are bigger than what the remote accepts.
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	pub(crate) fn check_size( &self, msg: &Wf ) -> Result<(), PeerErr>
+	{
+		let max_size = match &self.negotiated
+		{
+			Some(n) => n.max_size_out,
+			None    => return Ok(()),
+		};
+
+		let size = msg.len() as usize;
+
+		if size > max_size
+		{
+			let ctx    = self.ctx( msg.sid(), msg.cid(), "Sending out WireFormat" );
+			let source = WireErr::MessageSizeExceeded{ context: "Negotiated max size of the remote".to_string(), size, max_size };
+
+			return Err( PeerErr::WireFormat{ ctx, source } );
+		}
+
+		Ok(())
+	}
+
+
+	/// Mark outgoing messages for compression if the remote supports it.
+	//
+	pub(crate) fn compress( &self, msg: &mut Wf )
+	{
+		if let ( Some(n), Some(c) ) = ( &self.negotiated, &self.compression )
+		{
+			if let Some( algorithm ) = n.features.compression.pick( c.algorithm )
+			{
+				msg.set_compression( Some( Compression{ algorithm, ..*c } ) );
+			}
+		}
+	}
+
+
+	/// Mark outgoing messages for a checksum if the remote supports it.
+	//
+	pub(crate) fn seal( &self, msg: &mut Wf )
+	{
+		let negotiated = self.negotiated.is_some_and( |n| n.features.checksum );
+
+		// Frames that came in with a checksum, like relayed ones, are marked already.
+		//
+		msg.set_checksum( negotiated && self.checksum );
+	}
+}
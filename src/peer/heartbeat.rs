@@ -0,0 +1,259 @@
+use crate::{ import::*, *, peer::RequestError };
+
+
+/// Configuration for heartbeats. The peer sends a ping frame every interval and the remote answers with a pong.
+/// This allows detecting dead connections, even when no calls are outstanding.
+///
+/// When `missed` intervals pass without a pong, observers get [`PeerEvent::HeartbeatTimeout`] and the connection
+/// is closed as if the remote had closed it. If reconnection is configured, the peer will try to reconnect.
+/// Every pong updates the round trip latency, which observers get in [`PeerEvent::Latency`].
+///
+/// The pings and pongs are handled by [Peer] itself, they never reach a service map.
+//
+#[ derive( Debug, Clone, Copy ) ]
+//
+pub struct Heartbeat
+{
+	interval: Duration,
+	missed  : usize   ,
+
+	// Runtime state. The sequence number of the last ping, when it was sent if we are waiting for the pong,
+	// and how many intervals passed without a pong.
+	//
+	seq         : u64                   ,
+	outstanding : Option<(u64, Instant)>,
+	missed_count: usize                 ,
+}
+
+
+impl Heartbeat
+{
+	/// Send a ping every `interval`. By default the connection is considered dead after 3 missed intervals.
+	//
+	pub fn new( interval: Duration ) -> Self
+	{
+		Self
+		{
+			interval           ,
+			missed      : 3    ,
+			seq         : 0    ,
+			outstanding : None ,
+			missed_count: 0    ,
+		}
+	}
+
+
+	/// After how many intervals without a pong the connection is considered dead. At least 1, as a ping has to go
+	/// out before a pong can be missed. 0 counts as 1.
+	//
+	pub fn missed( mut self, missed: usize ) -> Self
+	{
+		self.missed = missed.max( 1 );
+		self
+	}
+
+
+	/// Forget about outstanding pings, eg. after reconnecting.
+	//
+	pub(crate) fn reset( &mut self )
+	{
+		self.outstanding  = None;
+		self.missed_count = 0;
+	}
+}
+
+
+
+/// Sent to the peer every interval by the heartbeat task. Returns false when the task should stop.
+//
+#[ derive( Debug, Clone, Copy ) ]
+//
+pub(crate) struct HeartbeatTick;
+
+impl Message for HeartbeatTick
+{
+	type Return = bool;
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Peer<Wf>
+{
+	/// Send a ping to the remote at a regular interval to detect dead connections. See [Heartbeat].
+	///
+	/// The remote must be a version of thespis_remote that answers pings. When the handshake is enabled with
+	/// [`Peer::set_handshake`], pings are only sent if both sides announce [`Features::heartbeat`].
+	//
+	pub fn set_heartbeat( &mut self, heartbeat: Heartbeat )
+	{
+		self.heartbeat = Some( heartbeat );
+	}
+
+
+	/// Spawn the task that ticks every interval.
+	//
+	pub(crate) fn start_heartbeat( &mut self )
+	{
+		let interval = match &self.heartbeat
+		{
+			Some(h) => h.interval,
+			None    => return,
+		};
+
+		// Don't keep ourselves alive.
+		//
+		let mut self_addr = match &self.addr
+		{
+			Some(a) => a.weak(),
+			None    => return,
+		};
+
+		let task = async move
+		{
+			loop
+			{
+				Delay::new( interval ).await;
+
+				match self_addr.call( HeartbeatTick ).await
+				{
+					Ok(true) => continue,
+					_        => break   ,
+				}
+			}
+
+			Ok( Response::Nothing )
+		};
+
+		if self.nursery.nurse( task ).is_err()
+		{
+			error!( "{}: Failed to spawn heartbeat task.", self.identify() );
+		}
+	}
+
+
+	/// Answer a ping from the remote.
+	//
+	pub(crate) async fn ping( &mut self, frame: Wf )
+	{
+		trace!( "{}: received ping.", self.identify() );
+
+		let mut pong = Wf::with_capacity( frame.msg().len() );
+		pong.set_sid( ServiceID::pong() );
+		pong.set_cid( ConnID::null()    );
+
+		// Echo the sequence number. Writing to the in memory buffer of the wire format doesn't fail.
+		//
+		let _ = io::Write::write_all( &mut pong, frame.msg() );
+
+		if let Err(e) = self.send_msg( pong ).await
+		{
+			self.pharos.send( PeerEvent::Error(e) ).await.expect( "pharos not closed" );
+		}
+	}
+
+
+	/// Process a pong from the remote.
+	//
+	pub(crate) async fn pong( &mut self, frame: Wf )
+	{
+		let seq: u64 = match CborCodec::decode( frame.msg() )
+		{
+			Ok(x) => x,
+
+			Err(_) =>
+			{
+				let ctx = self.ctx( ServiceID::pong(), None, "Deserialize pong frame" );
+
+				return self.handle( RequestError::from( PeerErr::Deserialize{ ctx } ) ).await;
+			}
+		};
+
+		let hb = match &mut self.heartbeat
+		{
+			Some(h) => h,
+			None    => return,
+		};
+
+		// Any pong means the remote is alive, but only the last one tells us the latency.
+		//
+		hb.missed_count = 0;
+
+		let rtt = match hb.outstanding
+		{
+			Some(( s, sent )) if s == seq => sent.elapsed(),
+			_                             => return,
+		};
+
+		hb.outstanding = None;
+
+		trace!( "{}: received pong, round trip: {:?}", self.identify(), rtt );
+
+		self.pharos.send( PeerEvent::Latency( rtt ) ).await.expect( "pharos not closed" );
+	}
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Handler<HeartbeatTick> for Peer<Wf>
+{
+	#[async_fn] fn handle( &mut self, _msg: HeartbeatTick ) -> bool
+	{
+		if self.closed         { return false }
+		if self.reconnecting() { return true  }
+
+		// The remote told us it doesn't do heartbeats.
+		//
+		if let Some(n) = &self.negotiated
+		{
+			if !n.features.heartbeat { return true }
+		}
+
+		let hb = match &mut self.heartbeat
+		{
+			Some(h) => h,
+			None    => return false,
+		};
+
+		if hb.outstanding.is_some()
+		{
+			hb.missed_count += 1;
+		}
+
+		if hb.missed_count >= hb.missed
+		{
+			let missed = hb.missed_count;
+
+			warn!( "{}: no pong from remote for {} intervals, closing connection.", self.identify(), missed );
+
+			self.pharos.send( PeerEvent::HeartbeatTimeout ).await.expect( "pharos not closed" );
+
+			let close = CloseConnection{ remote: true, reason: "Heartbeat timeout.".to_string() };
+
+			Handler::<CloseConnection>::handle( self, close ).await;
+
+			// We might be reconnecting.
+			//
+			return !self.closed;
+		}
+
+		hb.seq        += 1;
+		hb.outstanding = Some(( hb.seq, Instant::now() ));
+
+		let seq = hb.seq;
+
+		let mut ping = Wf::with_capacity( size_of::<u64>() * 2 );
+		ping.set_sid( ServiceID::ping() );
+		ping.set_cid( ConnID::null()    );
+
+		CborCodec::encode( &mut ping, &seq ).expect( "serialize u64" );
+
+		trace!( "{}: sending ping {}.", self.identify(), seq );
+
+		if let Err(e) = self.send_msg( ping ).await
+		{
+			self.pharos.send( PeerEvent::Error(e) ).await.expect( "pharos not closed" );
+		}
+
+		true
+	}
+}
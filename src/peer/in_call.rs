@@ -18,13 +18,11 @@ pub struct IncomingCall<Wf>
 
 impl<Wf: WireFormat> Message for IncomingCall<Wf>
 {
-	///
-	//
 	type Return = ();
 }
 
 
-///
+/// Handler for incoming calls. Looks up the service map and spawns the future that processes the call.
 //
 impl<Wf: WireFormat + Send + 'static> Handler<IncomingCall<Wf>> for Peer<Wf>
 {
@@ -36,6 +34,10 @@ impl<Wf: WireFormat + Send + 'static> Handler<IncomingCall<Wf>> for Peer<Wf>
 
 		let ctx = self.ctx( msg.sid, msg.cid, "Peer: Handle incoming call" );
 
+		// If the caller told us how long it's willing to wait, the deadline came in before the call.
+		//
+		let deadline = self.take_deadline( msg.cid );
+
 
 		// Find our handler.
 		//
@@ -54,7 +56,9 @@ impl<Wf: WireFormat + Send + 'static> Handler<IncomingCall<Wf>> for Peer<Wf>
 		};
 
 
-		if sm.apply_backpressure() {
+		let bp = sm.apply_backpressure();
+
+		if bp {
 		if let Some( p ) = msg.permit
 		{
 			self.permits.push(p);
@@ -63,15 +67,54 @@ impl<Wf: WireFormat + Send + 'static> Handler<IncomingCall<Wf>> for Peer<Wf>
 		else { drop( msg.permit.take() ); }
 
 
-		// Get future from service map.
+		// The call opens a channel.
 		//
-		let fut = match sm.call_service( msg.frame, ctx.clone() )
+		if self.opening.remove( &msg.cid ).is_some()
 		{
-			Ok (f) => f,
-			Err(e) => return self.handle( RequestError::from(e) ).await,
+			let sm = sm.clone();
+
+			let fut = match self.open_channel( &sm, msg.frame, ctx.clone() )
+			{
+				Ok (f) => f,
+				Err(e) => return self.handle( e ).await,
+			};
+
+			let fut = self.abortable( fut, msg.cid, bp );
+
+			if self.nursery.nurse( fut ).is_err()
+			{
+				let err = PeerErr::Spawn{ ctx };
+
+				self.handle( RequestError::from( err ) ).await;
+			}
+
+			return;
+		}
+
+
+		// Get future from service map. If the caller sent a deadline, stop processing once it passes.
+		//
+		let fut = match deadline
+		{
+			None => match sm.call_service( msg.frame, ctx.clone() )
+			{
+				Ok (f) => f,
+				Err(e) => return self.handle( RequestError::from(e) ).await,
+			},
+
+			Some( deadline ) => match sm.call_service_deadline( msg.frame, ctx.clone(), deadline.saturating_duration_since( Instant::now() ) )
+			{
+				Ok (f) => Self::with_deadline( f, deadline, msg.sid, msg.cid, bp ).boxed(),
+				Err(e) => return self.handle( RequestError::from(e) ).await,
+			},
 		};
 
 
+		// The remote can cancel the call while we process it.
+		//
+		let fut = self.abortable( fut, msg.cid, bp );
+
+
 		// Call handling actor,
 		//
 		if self.nursery.nurse( fut ).is_err()
@@ -1,26 +1,28 @@
 use crate::{ import::*, * };
 
 
-///
+/// A response from the remote to one of our outgoing calls.
 //
 #[ derive( Debug ) ]
 //
 pub struct IncomingCallResponse<Wf>
 {
-	pub(crate) frame: Wf,
-	pub(crate) cid  : ConnID,
+	pub(crate) frame : Wf,
+	pub(crate) cid   : ConnID,
+
+	// Credit of the stream when this is an item of a streaming response.
+	//
+	pub(crate) permit: Option<OwnedSemaphorePermit>,
 }
 
 
 impl<Wf: WireFormat> Message for IncomingCallResponse<Wf>
 {
-	///
-	//
 	type Return = ();
 }
 
 
-///
+/// Handler for responses to our outgoing calls. Delivers the response to the waiting caller.
 //
 impl<Wf: WireFormat + Send + 'static> Handler<IncomingCallResponse<Wf>> for Peer<Wf>
 {
@@ -28,6 +30,8 @@ impl<Wf: WireFormat + Send + 'static> Handler<IncomingCallResponse<Wf>> for Peer
 	{
 		// it's a succesful response to a (relayed) call
 		//
+		self.in_flight.remove( &msg.cid );
+
 		if let Some( channel ) = self.responses.remove( &msg.cid )
 		{
 			// It's a response
@@ -42,6 +46,16 @@ impl<Wf: WireFormat + Send + 'static> Handler<IncomingCallResponse<Wf>> for Peer
 			}
 		}
 
+		// One of the responses to a call to a streaming service. If the caller dropped the stream, the
+		// call get's canceled, so we can ignore errors.
+		//
+		else if let Some( channel ) = self.streams.get( &msg.cid )
+		{
+			trace!( "{}: Incoming stream item", self.identify() );
+
+			let _ = channel.unbounded_send(( Some( Ok(msg.frame) ), msg.permit ));
+		}
+
 		// There is a CID, so it's a response, but it's not in our self.responses, so it has timed out.
 		// We are no longer waiting for this response, so we can only drop it.
 		//
@@ -16,13 +16,11 @@ pub struct IncomingConnErr<Wf>
 
 impl<Wf: WireFormat> Message for IncomingConnErr<Wf>
 {
-	///
-	//
 	type Return = ();
 }
 
 
-///
+/// Handler for connection errors sent to us by the remote.
 //
 impl<Wf: WireFormat + Send + 'static> Handler<IncomingConnErr<Wf>> for Peer<Wf>
 {
@@ -32,10 +30,19 @@ impl<Wf: WireFormat + Send + 'static> Handler<IncomingConnErr<Wf>> for Peer<Wf>
 
 		// We can correctly interprete the error
 		//
-		if let Ok( err ) = serde_cbor::from_slice::<ConnectionError>( serialized )
+		if let Ok( err ) = CborCodec::decode::<ConnectionError>( serialized )
 		{
+			// The remote doesn't understand our handshake, so the calls waiting for it would only time out.
+			//
+			if self.awaiting_handshake() && Self::refuses_handshake( &err )
+			{
+				self.refuse_calls( &err );
+			}
+
 			// We need to report the connection error to the caller
 			//
+			self.in_flight.remove( &msg.cid );
+
 			if let Some( channel ) = self.responses.remove( &msg.cid )
 			{
 				// If this returns an error, it means the receiver was dropped, so if they no longer
@@ -48,6 +55,15 @@ impl<Wf: WireFormat + Send + 'static> Handler<IncomingConnErr<Wf>> for Peer<Wf>
 				return
 			}
 
+			// The remote failed while streaming, this ends the stream.
+			//
+			if let Some( channel ) = self.forget_stream( msg.cid )
+			{
+				let _ = channel.unbounded_send(( Some( Err( err ) ), None ));
+
+				return
+			}
+
 			// Notify observers
 			//
 			let shine = PeerEvent::RemoteError( err );
@@ -9,20 +9,22 @@ use crate::{ import::*, *, peer::RequestError };
 //
 pub struct IncomingSend<Wf>
 {
-	pub(crate) frame: Wf,
-	pub(crate) sid  : ServiceID,
+	pub(crate) frame : Wf,
+	pub(crate) sid   : ServiceID,
+
+	// Credit of the channel when this is an item of a channel the remote opened with us.
+	//
+	pub(crate) permit: Option<OwnedSemaphorePermit>,
 }
 
 
 impl<Wf: WireFormat> Message for IncomingSend<Wf>
 {
-	///
-	//
 	type Return = ();
 }
 
 
-///
+/// Handler for incoming sends. Looks up the service map and spawns the future that processes the send.
 //
 impl<Wf: WireFormat + Send + 'static> Handler<IncomingSend<Wf>> for Peer<Wf>
 {
@@ -32,6 +34,46 @@ impl<Wf: WireFormat + Send + 'static> Handler<IncomingSend<Wf>> for Peer<Wf>
 
 		trace!( "{}: Incoming Send, sid: {}", &identity, &msg.sid );
 
+		if msg.sid == ServiceID::handshake()
+		{
+			return self.handshaken( msg.frame ).await;
+		}
+
+		if msg.sid == ServiceID::discovery()
+		{
+			return self.discovered( msg.frame ).await;
+		}
+
+		if msg.sid == ServiceID::deadline()
+		{
+			return self.deadline( msg.frame ).await;
+		}
+
+		if msg.sid == ServiceID::cancel()
+		{
+			return self.canceled( msg.frame ).await;
+		}
+
+		if msg.sid.is_channel()
+		{
+			return self.channel_frame( msg.frame, msg.permit ).await;
+		}
+
+		if msg.sid == ServiceID::stream_end()
+		{
+			return self.stream_ended( msg.frame ).await;
+		}
+
+		if msg.sid == ServiceID::ping()
+		{
+			return self.ping( msg.frame ).await;
+		}
+
+		if msg.sid == ServiceID::pong()
+		{
+			return self.pong( msg.frame ).await;
+		}
+
 		let ctx = self.ctx( msg.sid, None, "Peer: Handle incoming send" );
 
 		let sm = match self.services.get( &msg.sid )
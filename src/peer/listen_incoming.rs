@@ -7,6 +7,8 @@ use super::
 	in_call_response ::IncomingCallResponse ,
 	in_conn_err      ::IncomingConnErr      ,
 	in_send          ::IncomingSend         ,
+	fragment         ::Reassembler          ,
+	handshake        ::Inbound              ,
 	*                                       ,
 };
 
@@ -18,18 +20,41 @@ impl<Wf: WireFormat> Peer<Wf>
 	//
 	pub(crate) async fn listen_incoming
 	(
-		mut incoming: impl BoundsIn<Wf>         ,
-		mut addr    : Addr<Peer<Wf>>            ,
-		    bp      : Option< Arc<Semaphore> >  ,
+		mut incoming      : impl BoundsIn<Wf>                         ,
+		mut addr          : Addr<Peer<Wf>>                            ,
+		    bp            : Option< Arc<Semaphore> >                  ,
+		    max_message   : Arc<AtomicUsize>                          ,
+		    inbound       : Arc<Inbound>                              ,
+		    stream_credit : Credit                                    ,
+		    channel_credit: Credit                                    ,
 	)
 		-> Result<Response<Wf>, PeerErr>
 
 	{
+		let mut reassembler = Reassembler::new( max_message );
+
+		// Whether the remote has started sealing its frames.
+		//
+		let mut sealed = false;
+
+		// A message read while waiting for a permit that still needs processing. Some(None) is the end
+		// of the stream.
+		//
+		let mut held: Option< Option<Result<Wf, WireErr>> > = None;
+
 		// Stream over Result<Wf, WireErr>
 		// From the codec.
 		//
-		while let Some(msg) = incoming.next().await
+		loop
 		{
+			let msg = match held.take()
+			{
+				Some( msg ) => msg,
+				None        => incoming.next().await,
+			};
+
+			let Some( msg ) = msg else { break };
+
 			trace!( "{}: incoming message.", &addr );
 
 			// Handle errors first.
@@ -45,18 +70,98 @@ impl<Wf: WireFormat> Peer<Wf>
 					// - WireErr::IO...
 					//
 					let ctx = Self::err_ctx( &addr.weak(), None, None, "Deserialize Incoming message or IO error.".to_string() );
+					let io  = matches!( error, WireErr::Io{..} );
 					let err = PeerErr::WireFormat{ source: error, ctx };
 
 					Self::send_to_self( &mut addr, RequestError::from( err ) ).await?;
 
+					// The transport is broken, so the connection is lost. This allows the peer to reconnect.
+					//
+					if io
+					{
+						let close = CloseConnection{ remote: true, reason: "Io error on the connection.".to_string() };
+
+						Self::send_to_self( &mut addr, close ).await?;
+					}
+
+					return Ok(Response::Nothing)
+				}
+			};
+
+
+			// The remote agreed to this in the handshake, so it's not to be trusted anymore. The frame is complete,
+			// so we could keep reading, but the decoder would refuse it had it been configured with this max size.
+			//
+			let size     = frame.len() as usize;
+			let max_size = inbound.max_size.load( Relaxed );
+
+			if size > max_size
+			{
+				let ctx    = Self::err_ctx( &addr.weak(), frame.sid(), None, "Incoming message bigger than negotiated.".to_string() );
+				let source = WireErr::MessageSizeExceeded{ context: "Negotiated max size of incoming messages".to_string(), size, max_size };
+
+				Self::send_to_self( &mut addr, RequestError::from( PeerErr::WireFormat{ source, ctx } ) ).await?;
+
+				let close = CloseConnection{ remote: false, reason: "The remote sent a message bigger than negotiated.".to_string() };
+
+				Self::send_to_self( &mut addr, close ).await?;
+
+				return Ok(Response::Nothing)
+			}
+
+
+			// Once the remote has our handshake, it seals every frame, so its first sealed frame arms the check. A frame
+			// without the flag after that has been tampered with, probably to turn verification off.
+			//
+			if inbound.checksum.load( Relaxed )
+			{
+				sealed = sealed || frame.has_checksum();
+
+				if sealed && !frame.has_checksum()
+				{
+					let ctx    = Self::err_ctx( &addr.weak(), frame.sid(), None, "Incoming frame without checksum.".to_string() );
+					let source = WireErr::Checksum{ expected: None, actual: None };
+
+					// Closes the connection.
+					//
+					Self::send_to_self( &mut addr, RequestError::from( PeerErr::WireFormat{ source, ctx } ) ).await?;
+
 					return Ok(Response::Nothing)
 				}
+			}
+
+
+			// Fragments are only delivered once the message is complete. When reassembling fails, the
+			// stream is still coherent, so we keep reading. Otherwise the remote might block on sending
+			// us the rest of the fragments while we try to send it the error.
+			//
+			let frame = match reassembler.push( frame )
+			{
+				Ok( Some(frame) ) => frame,
+				Ok( None        ) => continue,
+
+				Err( error ) =>
+				{
+					let ctx = Self::err_ctx( &addr.weak(), None, None, "Reassembling fragments.".to_string() );
+					let err = PeerErr::WireFormat{ source: error, ctx };
+
+					Self::send_to_self( &mut addr, RequestError::from( err ) ).await?;
+
+					continue;
+				}
 			};
 
 
-			let sid  = frame.sid();
-			let cid  = frame.cid();
-			let kind = frame.kind();
+			let sid = frame.sid();
+			let cid = frame.cid();
+
+			// Frames of bidirectional channels have the cid of the channel, but they aren't calls.
+			//
+			let kind = match sid.is_channel()
+			{
+				true  => WireType::IncomingSend,
+				false => frame.kind(),
+			};
 
 
 			match kind
@@ -68,7 +173,32 @@ impl<Wf: WireFormat> Peer<Wf>
 
 				WireType::IncomingSend =>
 				{
-					Self::send_to_self( &mut addr, IncomingSend{ frame, sid } ).await?;
+					// Items of channels take credit from the channel, so we stop reading when the handler doesn't
+					// keep up with them.
+					//
+					if sid == ServiceID::channel_open()
+					{
+						channel_credit.lock().insert( cid, Arc::new( Semaphore::new( CHANNEL_CREDIT ) ) );
+					}
+
+					let credit = match sid == ServiceID::channel_item()
+					{
+						true  => channel_credit.lock().get( &cid ).cloned(),
+						false => None,
+					};
+
+					let permit = match credit
+					{
+						None => None,
+
+						Some(c) => match Self::wait_permit( &mut incoming, &mut addr, c, Some( cid ), &mut held ).await?
+						{
+							Some(p) => Some(p),
+							None    => continue,
+						}
+					};
+
+					Self::send_to_self( &mut addr, IncomingSend{ frame, sid, permit } ).await?;
 				}
 
 				WireType::IncomingCall =>
@@ -81,24 +211,22 @@ impl<Wf: WireFormat> Peer<Wf>
 						{
 							trace!( "check for backpressure" );
 
-							let p = match Arc::clone(b).acquire_owned().await
+							match Self::wait_permit( &mut incoming, &mut addr, Arc::clone(b), Some( cid ), &mut held ).await?
 							{
-								Ok(p) => p,
-
-								Err(_e) =>
+								Some(p) =>
 								{
-									error!( "{}: The semaphore for backpressure was closed externally.", Peer::identify_addr( &addr ) );
-
-									let ctx = Self::err_ctx( &addr.weak(), None, None, "Peer::listen_incoming: backpressure semaphore is closed.".to_string() );
+									trace!( "backpressure allows progress now." );
 
-									let err = PeerErr::BackpressureClosed{ ctx };
-									return Err(err);
+									Some(p)
 								}
-							};
 
-							trace!( "backpressure allows progress now." );
+								None =>
+								{
+									trace!( "{}: call canceled while waiting for backpressure, cid: {}", Peer::identify_addr( &addr ), cid );
 
-							Some(p)
+									continue;
+								}
+							}
 						}
 					};
 
@@ -108,7 +236,18 @@ impl<Wf: WireFormat> Peer<Wf>
 
 				WireType::CallResponse =>
 				{
-					Self::send_to_self( &mut addr, IncomingCallResponse{ frame, cid } ).await?;
+					// Items of streaming responses take credit from the stream, so we stop reading when the caller
+					// doesn't keep up with them.
+					//
+					let credit = stream_credit.lock().get( &cid ).cloned();
+
+					let permit = match credit
+					{
+						None    => None,
+						Some(c) => Self::wait_permit( &mut incoming, &mut addr, c, None, &mut held ).await?,
+					};
+
+					Self::send_to_self( &mut addr, IncomingCallResponse{ frame, cid, permit } ).await?;
 				}
 			}
 		}
@@ -130,6 +269,65 @@ impl<Wf: WireFormat> Peer<Wf>
 	}
 
 
+	/// Wait for a permit of `semaphore` to deliver a frame. While waiting, keep reading cancel frames. The remote
+	/// might be canceling a call that holds a permit, so they can't wait behind this frame. Anything else is put
+	/// in `held` and stops reading until we get a permit.
+	///
+	/// Returns `None` when the remote canceled `cid`, the call the frame belongs to.
+	//
+	async fn wait_permit
+	(
+		incoming : &mut impl BoundsIn<Wf>                     ,
+		addr     : &mut Addr<Peer<Wf>>                        ,
+		semaphore: Arc<Semaphore>                             ,
+		cid      : Option<ConnID>                             ,
+		held     : &mut Option< Option<Result<Wf, WireErr>> > ,
+	)
+		-> Result< Option<OwnedSemaphorePermit>, PeerErr >
+
+	{
+		let mut acquire = Box::pin( semaphore.acquire_owned() );
+
+		let acquired = loop
+		{
+			if held.is_some() { break acquire.await }
+
+			match futures::future::select( &mut acquire, incoming.next() ).await
+			{
+				Either::Left (( p, _ )) => break p,
+
+				Either::Right(( Some( Ok(cancel) ), _ )) if cancel.sid() == ServiceID::cancel() =>
+				{
+					let canceled: Option<ConnID> = CborCodec::decode( cancel.msg() ).ok();
+
+					Self::send_to_self( addr, IncomingSend{ frame: cancel, sid: ServiceID::cancel(), permit: None } ).await?;
+
+					// The remote no longer waits for the frame we are holding.
+					//
+					if cid.is_some() && canceled == cid { return Ok( None ) }
+				}
+
+				Either::Right(( msg, _ )) => *held = Some( msg ),
+			}
+		};
+
+		match acquired
+		{
+			Ok(p) => Ok( Some(p) ),
+
+			Err(_e) =>
+			{
+				error!( "{}: The semaphore for backpressure was closed externally.", Peer::identify_addr( addr ) );
+
+				let ctx = Self::err_ctx( &addr.weak(), None, None, "Peer::listen_incoming: backpressure semaphore is closed.".to_string() );
+
+				Err( PeerErr::BackpressureClosed{ ctx } )
+			}
+		}
+	}
+
+
+
 	async fn send_to_self<T>
 	(
 		addr: &mut Addr<Peer<Wf>>,
@@ -149,6 +149,16 @@ pub enum PeerErr
 		ctx   : PeerErrCtx ,
 	},
 
+	/// The TLS handshake failed. The context has the reason.
+	//
+	Tls
+	{
+		/// The contex in which the error happened.
+		//
+		ctx: PeerErrCtx,
+	},
+
+
 	/// The semaphore for the backpressure has been closed externally.
 	//
 	BackpressureClosed
@@ -226,6 +236,10 @@ impl fmt::Display for PeerErr
 
 				write!( f, "PubSub does not support `Address::call` operation, only `Sink::send`.{}", ctx ),
 
+			PeerErr::Tls{ ctx } =>
+
+				write!( f, "The TLS handshake failed.{}", ctx ),
+
 			PeerErr::BackpressureClosed{ ctx } =>
 
 				write!( f, "The semaphore for backpressure was closed externally.{}", ctx ),
@@ -262,6 +276,14 @@ impl PeerErr
 					WireErr::Io{..} =>
 
 						format!( "An error happened on the underlying transport.{}", &ctx ),
+
+					WireErr::ServiceIDCollision{..} =>
+
+						format!( "The remote has services with colliding ServiceIDs.{}", &ctx ),
+
+					WireErr::Checksum{..} =>
+
+						format!( "A frame you sent was corrupted on the way.{}", &ctx ),
 				}
 
 			}
@@ -294,6 +316,7 @@ impl PeerErr
 			PeerErr::UnknownService     { ctx, .. } => ctx,
 			PeerErr::WireFormat         { ctx, .. } => ctx,
 			PeerErr::PubSubNoCall       { ctx, .. } => ctx,
+			PeerErr::Tls                { ctx, .. } => ctx,
 			PeerErr::BackpressureClosed { ctx, .. } => ctx,
 		}
 	}
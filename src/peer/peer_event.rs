@@ -1,4 +1,5 @@
-use crate::{ PeerErr, ConnectionError };
+use crate::{ PeerErr, ConnectionError, ServiceID, Negotiated };
+use std::time::Duration;
 
 
 /// Events that can happen during the lifecycle of the peer. Use the [`observe`] method to subscribe to events.
@@ -21,6 +22,16 @@ pub enum PeerEvent
 	//
 	ClosedByRemote,
 
+	/// The connection was lost and the peer is trying to reconnect. Only emitted when
+	/// reconnection is configured with [`Peer::set_reconnect`](crate::Peer::set_reconnect).
+	/// The peer can still be used, outgoing messages will be buffered.
+	//
+	Disconnected,
+
+	/// The peer has a new connection after [`PeerEvent::Disconnected`].
+	//
+	Reconnected,
+
 	/// A remote endpoint to which we relayed messages is no longer reachable.
 	//
 	RelayDisappeared(usize),
@@ -36,5 +47,44 @@ pub enum PeerEvent
 	/// our messages.
 	//
 	RemoteError( ConnectionError ),
+
+	/// Services were added at runtime with [`AddServices`](crate::AddServices).
+	//
+	ServicesAdded( Vec<ServiceID> ),
+
+	/// Services were removed at runtime with [`RemoveServices`](crate::RemoveServices).
+	//
+	ServicesRemoved( Vec<ServiceID> ),
+
+	/// The remote advertised the services it provides. See [`Peer::set_discovery`](crate::Peer::set_discovery).
+	/// This is sent again every time the remote advertises a change.
+	//
+	RemoteServices( Vec<ServiceID> ),
+
+	/// The handshake with the remote succeeded. See [`Peer::set_handshake`](crate::Peer::set_handshake).
+	//
+	Negotiated( Negotiated ),
+
+	/// The remote didn't answer heartbeats. The connection will be closed.
+	/// See [`Peer::set_heartbeat`](crate::Peer::set_heartbeat).
+	//
+	HeartbeatTimeout,
+
+	/// The round trip time of the last heartbeat.
+	//
+	Latency( Duration ),
+
+	/// The remote speaks another protocol version. The connection will be closed.
+	//
+	IncompatibleVersion
+	{
+		/// Our protocol version.
+		//
+		local: u16,
+
+		/// The protocol version of the remote.
+		//
+		remote: u16,
+	},
 }
 
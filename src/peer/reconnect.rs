@@ -0,0 +1,489 @@
+use crate::{ import::*, * };
+
+
+/// A boxed closure that establishes a new connection and frames it.
+//
+pub type Connector<Wf> = Box
+<
+	dyn FnMut() -> Pin<Box< dyn Future< Output = Result<(Box<dyn BoundsIn<Wf>>, Box<dyn BoundsOut<Wf>>), WireErr> > + Send >>
+	+ Send
+>;
+
+
+/// The default for [`Reconnect::buffer`]. Also bounds the calls held back while we wait for the handshake of
+/// the remote when reconnecting is not enabled.
+//
+pub(crate) const BUFFER: usize = 64;
+
+
+/// What to do with outgoing calls that have not received a response yet when the connection is lost.
+//
+#[ derive( Debug, Clone, Copy, PartialEq, Eq ) ]
+//
+pub enum ReplayPolicy
+{
+	/// Send the calls again over the new connection. The caller will get the response from the new connection,
+	/// or a timeout if it doesn't come in time. Note that the remote might process the request twice if it
+	/// had already received it before the connection was lost.
+	///
+	/// The calls are buffered like everything that is sent while disconnected. When there are more than
+	/// [`Reconnect::buffer`] allows, the most recent ones fail with [`PeerErr::ConnectionClosed`].
+	//
+	Replay,
+
+	/// Fail the calls immediately when the connection is lost. The caller will get [`PeerErr::ConnectionClosed`].
+	//
+	Fail,
+}
+
+
+
+/// Configuration to make a [Peer] reconnect automatically when the connection is lost.
+///
+/// When the remote closes the connection (or the underlying transport errors), the peer will not shut down but
+/// use the connector to establish a new connection. All addresses to the peer, including `RemoteAddr`, keep working
+/// across reconnects. Sends issued while disconnected are buffered up to a limit and sent out once connected again.
+/// Outstanding calls are replayed or failed according to the [ReplayPolicy].
+///
+/// Observers will receive [`PeerEvent::Disconnected`] and [`PeerEvent::Reconnected`]. If all attempts fail,
+/// the peer closes like it would without reconnection and [`PeerEvent::ClosedByRemote`] is emitted.
+///
+/// Closing the connection locally with [CloseConnection] will not trigger reconnection.
+///
+/// ```ignore
+/// let reconnect = Reconnect::new( || async { TcpStream::connect( "127.0.0.1:8998" ).await }, 1024, 1024 )
+///
+///    .delay   ( Duration::from_secs(1) )
+///    .attempts( Some(10)               )
+///    .policy  ( ReplayPolicy::Replay   )
+/// ;
+///
+/// peer.set_reconnect( reconnect );
+/// ```
+//
+pub struct Reconnect<Wf>
+{
+	connector: Connector<Wf>        ,
+	buffer   : usize                ,
+	policy   : ReplayPolicy         ,
+	delay    : Duration             ,
+	attempts : Option<usize>        ,
+}
+
+
+impl<Wf: WireFormat> Reconnect<Wf>
+{
+	/// Create a new reconnect configuration. `connect` will be called to establish a new connection each time
+	/// we need to reconnect. The connection will be framed with [WireFormat::frame] and the max sizes given here.
+	///
+	/// Defaults: a buffer of 64 sends, [`ReplayPolicy::Replay`], a delay of 1 second between attempts and
+	/// unlimited attempts. The first attempt is made immediately.
+	//
+	pub fn new<F, Fut, S>( mut connect: F, max_size_read: usize, max_size_write: usize ) -> Self
+
+		where F  : FnMut() -> Fut + Send + 'static                         ,
+		      Fut: Future< Output = io::Result<S> > + Send + 'static         ,
+		      S  : AsyncRead + AsyncWrite + Unpin + Send + 'static         ,
+	{
+		let connector: Connector<Wf> = Box::new( move ||
+		{
+			let connecting = connect();
+
+			async move
+			{
+				let socket         = connecting.await?;
+				let (stream, sink) = Wf::frame( socket, max_size_read, max_size_write );
+
+				let stream: Box<dyn BoundsIn <Wf>> = Box::new( stream );
+				let sink  : Box<dyn BoundsOut<Wf>> = Box::new( sink   );
+
+				Ok( (stream, sink) )
+
+			}.boxed()
+		});
+
+		Self
+		{
+			connector                       ,
+			buffer  : BUFFER                ,
+			policy  : ReplayPolicy::Replay  ,
+			delay   : Duration::from_secs(1),
+			attempts: None                  ,
+		}
+	}
+
+
+	/// The maximum number of outgoing messages (sends, calls and responses) to buffer while disconnected.
+	/// When the buffer is full, further messages are refused with [`PeerErr::ConnectionClosed`].
+	//
+	pub fn buffer( mut self, buffer: usize ) -> Self
+	{
+		self.buffer = buffer;
+		self
+	}
+
+
+	/// What to do with outstanding calls when the connection is lost.
+	//
+	pub fn policy( mut self, policy: ReplayPolicy ) -> Self
+	{
+		self.policy = policy;
+		self
+	}
+
+
+	/// How long to wait between failed attempts.
+	//
+	pub fn delay( mut self, delay: Duration ) -> Self
+	{
+		self.delay = delay;
+		self
+	}
+
+
+	/// The maximum number of consecutive attempts to make before giving up. `None` means try forever.
+	//
+	pub fn attempts( mut self, attempts: Option<usize> ) -> Self
+	{
+		self.attempts = attempts;
+		self
+	}
+
+
+	/// The replay policy for outstanding calls.
+	//
+	pub(crate) fn replay( &self ) -> bool
+	{
+		self.policy == ReplayPolicy::Replay
+	}
+}
+
+
+impl<Wf> fmt::Debug for Reconnect<Wf>
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		f.debug_struct( "Reconnect" )
+
+			.field( "buffer"  , &self.buffer   )
+			.field( "policy"  , &self.policy   )
+			.field( "delay"   , &self.delay    )
+			.field( "attempts", &self.attempts )
+
+		.finish()
+	}
+}
+
+
+
+/// The outcome of an attempt to reconnect, sent by the task running the connector to the peer.
+//
+pub(crate) struct Reconnected<Wf>
+{
+	result : Result<(Box<dyn BoundsIn<Wf>>, Box<dyn BoundsOut<Wf>>), WireErr>,
+	attempt: usize,
+}
+
+
+impl<Wf: WireFormat> Message for Reconnected<Wf>
+{
+	type Return = ();
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Peer<Wf>
+{
+	/// Configure this peer to reconnect automatically when the connection is lost. See [Reconnect].
+	//
+	pub fn set_reconnect( &mut self, reconnect: Reconnect<Wf> )
+	{
+		self.reconnect = Some( reconnect );
+	}
+
+
+	/// Whether we lost the connection but are trying to get it back.
+	//
+	pub(crate) fn reconnecting( &self ) -> bool
+	{
+		!self.closed && self.outgoing.is_none() && self.reconnect.is_some()
+	}
+
+
+	/// The maximum number of outgoing messages to hold back, while disconnected or waiting for the handshake.
+	//
+	pub(crate) fn buffer_limit( &self ) -> usize
+	{
+		self.reconnect.as_ref().map( |r| r.buffer ).unwrap_or( BUFFER )
+	}
+
+
+	/// Buffer an outgoing message while disconnected.
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	pub(crate) fn buffer_msg( &mut self, msg: Wf ) -> Result<(), PeerErr>
+	{
+		self.buffer_call( msg, None )
+	}
+
+
+	/// Buffer an outgoing call while disconnected. The deadline is told to the remote when it goes out.
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	pub(crate) fn buffer_call( &mut self, msg: Wf, deadline: Option<Instant> ) -> Result<(), PeerErr>
+	{
+		if self.send_buffer.len() >= self.buffer_limit()
+		{
+			let ctx = self.ctx( msg.sid(), msg.cid(), "Buffer for reconnection is full" );
+
+			return Err( PeerErr::ConnectionClosed{ ctx } );
+		}
+
+		trace!( "{}: disconnected, buffering outgoing message", self.identify() );
+
+		self.send_buffer.push_back(( msg, deadline ));
+
+		Ok(())
+	}
+
+
+	/// The connection was lost. Start reconnecting.
+	//
+	pub(crate) async fn disconnect( &mut self, reason: String )
+	{
+		warn!( "{}: connection lost, reconnecting. Reason: {}", self.identify(), &reason );
+
+		if let Some(mut out) = self.outgoing.take()
+		{
+			// It's already broken, nothing to do about errors here.
+			//
+			let _ = out.close().await;
+		}
+
+		self.unflushed = 0;
+
+		self.pharos.send( PeerEvent::Disconnected ).await.expect( "pharos not closed" );
+
+		// The buffer is empty while we are connected.
+		//
+		debug_assert!( self.send_buffer.is_empty() );
+
+		match self.reconnect.as_ref().map( |r| r.replay() ).unwrap_or_default()
+		{
+			// Queue the outstanding calls before anything that get's sent while disconnected, in the order they
+			// were made. Those that don't fit in the buffer fail, like further messages when it's full.
+			//
+			true =>
+			{
+				let mut calls: Vec<_> = self.in_flight.drain().collect();
+				calls.sort_unstable_by_key( |(cid, _)| u64::from( *cid ) );
+
+				let max = self.buffer_limit();
+
+				for ( cid, call ) in calls
+				{
+					if self.send_buffer.len() < max
+					{
+						self.send_buffer.push_back( call );
+					}
+
+					// Dropping the sender will wake up the caller with an error.
+					//
+					else
+					{
+						warn!( "{}: buffer for reconnection is full, failing outstanding call: {}", self.identify(), cid );
+
+						self.responses.remove( &cid );
+					}
+				}
+			}
+
+			// Dropping the senders will wake up the callers with an error.
+			//
+			false => self.responses.clear(),
+		}
+
+		self.in_flight.clear();
+
+		// Streams can't be replayed, the callers get an error. Channels the remote opened are gone.
+		// Messages that are half way through being fragmented can't be resumed.
+		//
+		self.streams  .clear();
+		self.stream_credit.lock().clear();
+		self.opening  .clear();
+		self.channels .clear();
+		self.channel_credit.lock().clear();
+		self.fragments.clear();
+
+		// We might end up talking to a different process.
+		//
+		self.remote_services = None;
+		self.negotiated      = None;
+		self.refused         = None;
+
+		self.inbound.reset();
+
+		// They are in in_flight if they should be replayed.
+		//
+		self.held_calls.clear();
+
+		if let Some(hb) = &mut self.heartbeat { hb.reset() }
+
+		self.try_reconnect( 1 );
+	}
+
+
+	/// Spawn a task that makes one attempt to reconnect.
+	//
+	fn try_reconnect( &mut self, attempt: usize )
+	{
+		let reconnect = match &mut self.reconnect
+		{
+			Some(r) => r,
+			None    => return,
+		};
+
+		let delay      = if attempt > 1 { Some( reconnect.delay ) } else { None };
+		let connecting = (reconnect.connector)();
+
+		// If self.closed is false, there should always be an address.
+		//
+		let mut self_addr = match &self.addr
+		{
+			Some(a) => a.clone(),
+			None    => return,
+		};
+
+		let identity = self.identify();
+
+		let task = async move
+		{
+			if let Some(d) = delay { Delay::new( d ).await; }
+
+			let result = connecting.await;
+
+			if self_addr.send( Reconnected{ result, attempt } ).await.is_err()
+			{
+				error!( "{}: Failed to send Reconnected to self.", &identity );
+			}
+
+			Ok( Response::Nothing )
+		};
+
+		if self.nursery.nurse( task ).is_err()
+		{
+			error!( "{}: Failed to spawn task to reconnect.", self.identify() );
+		}
+	}
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Handler<Reconnected<Wf>> for Peer<Wf>
+{
+	#[async_fn] fn handle( &mut self, msg: Reconnected<Wf> )
+	{
+		// The connection was closed locally in the mean time.
+		//
+		if self.closed { return }
+
+		let (stream, sink) = match msg.result
+		{
+			Ok(x) => x,
+
+			Err( source ) =>
+			{
+				let ctx = self.ctx( None, None, format!( "Reconnect, attempt: {}", msg.attempt ) );
+				self.pharos.send( PeerEvent::Error( PeerErr::WireFormat{ ctx, source } ) ).await.expect( "pharos not closed" );
+
+				let exhausted = self.reconnect.as_ref()
+
+					.and_then( |r| r.attempts )
+					.map( |max| msg.attempt >= max )
+					.unwrap_or_default()
+				;
+
+				if exhausted
+				{
+					// Give up, close like we would without reconnection.
+					//
+					self.reconnect = None;
+					self.send_buffer.clear();
+
+					let close = CloseConnection{ remote: true, reason: "Failed to reconnect.".to_string() };
+
+					return Handler::<CloseConnection>::handle( self, close ).await;
+				}
+
+				return self.try_reconnect( msg.attempt + 1 );
+			}
+		};
+
+
+		// If self.closed is false, there should always be an address.
+		//
+		let addr = match &self.addr
+		{
+			Some(a) => a.clone(),
+			None    => return,
+		};
+
+		let listen = Self::listen_incoming
+		(
+			stream                     ,
+			addr                       ,
+			self.backpressure  .clone(),
+			self.max_message   .clone(),
+			self.inbound       .clone(),
+			self.stream_credit .clone(),
+			self.channel_credit.clone(),
+		);
+
+		if self.nursery.nurse( listen ).is_err()
+		{
+			let ctx = self.ctx( None, None, "Incoming stream for peer after reconnect" );
+			self.pharos.send( PeerEvent::Error( PeerErr::Spawn{ ctx } ) ).await.expect( "pharos not closed" );
+
+			return;
+		}
+
+		self.outgoing = Some( sink );
+
+		debug!( "{}: reconnected after {} attempt(s)", self.identify(), msg.attempt );
+
+		self.pharos.send( PeerEvent::Reconnected ).await.expect( "pharos not closed" );
+
+		self.send_handshake().await;
+		self.advertise     ().await;
+
+
+		// Flush everything that was buffered.
+		//
+		while let Some(( wf, deadline )) = self.send_buffer.pop_front()
+		{
+			let cid = wf.cid();
+
+			let call = matches!( wf.kind(), WireType::IncomingCall );
+
+			// A call we are still waiting for should be replayed again if we loose the connection.
+			//
+			if call && self.responses.contains_key( &cid )
+			{
+				self.in_flight.insert( cid, ( wf.clone(), deadline ) );
+			}
+
+			let result = match call
+			{
+				true  => self.send_call( cid, wf, deadline ).await,
+				false => self.send_msg ( wf               ).await,
+			};
+
+			if let Err(e) = result
+			{
+				self.pharos.send( PeerEvent::Error(e) ).await.expect( "pharos not closed" );
+			}
+		}
+	}
+}
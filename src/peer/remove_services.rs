@@ -0,0 +1,60 @@
+use crate::{ import::*, * };
+
+
+/// Control message for [Peer]. Stop exposing all the services of a service map at runtime.
+///
+/// Only services that are currently handled by this very service map instance will be removed. If another
+/// service map has taken over a service in the mean time with [`AddServices`], that service remains exposed.
+/// Remotes calling a removed service will receive [`ConnectionError::UnknownService`]. Observers will receive
+/// [`PeerEvent::ServicesRemoved`] with the list of service ids that were actually removed.
+//
+#[ derive( Debug ) ]
+//
+pub struct RemoveServices<Wf: WireFormat = CborWF>
+{
+	/// The service map that should no longer handle incoming messages. This must be the same instance
+	/// (a clone of the same `Arc`) that was passed to [`Peer::register_services`] or [`AddServices`].
+	//
+	pub sm: Arc< dyn ServiceMap<Wf> >,
+}
+
+
+impl<Wf: WireFormat> Message for RemoveServices<Wf>
+{
+	type Return = ();
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Handler<RemoveServices<Wf>> for Peer<Wf>
+{
+	#[async_fn] fn handle( &mut self, msg: RemoveServices<Wf> )
+	{
+		if self.closed { return }
+
+		let mut removed = Vec::new();
+
+		for sid in msg.sm.services()
+		{
+			// Compare the data pointer only, vtables aren't guaranteed to be unique.
+			//
+			let owned = self.services.get( sid )
+
+				.map( |sm| std::ptr::addr_eq( Arc::as_ptr( sm ), Arc::as_ptr( &msg.sm ) ) )
+				.unwrap_or( false )
+			;
+
+			if owned
+			{
+				trace!( "{}: Remove Service: {:?}", self.identify(), &sid );
+
+				self.services.remove( sid );
+				removed.push( *sid );
+			}
+		}
+
+		self.pharos.send( PeerEvent::ServicesRemoved( removed ) ).await.expect( "pharos not closed" );
+
+		self.advertise().await;
+	}
+}
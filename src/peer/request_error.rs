@@ -64,6 +64,17 @@ impl<Wf: WireFormat> Handler<RequestError> for Peer<Wf>
 		//
 		self.pharos.send( PeerEvent::Error( msg.error.clone() ) ).await.expect( "pharos not closed" );
 
+		// A corrupted frame means nothing that comes after can be trusted. Tell the remote and close the
+		// connection, even though we don't know what the frame was.
+		//
+		if let PeerErr::WireFormat{ source: WireErr::Checksum{..}, .. } = &msg.error
+		{
+			let err = ConnectionError::DeserializeWireFormat{ context: msg.error.remote_err() };
+
+			return self.send_err( ConnID::null(), &err, true ).await;
+		}
+
+
 		// If it was a send, don't send errors to the remote. Only call buys into feedback.
 		//
 		let cid = match msg.error.ctx().cid
@@ -1,10 +1,8 @@
-use crate::{ CallResponse, CborWF };
+use crate::{ import::*, CallResponse, CborWF, ConnID, PeerErr };
 
-/// A type to unify the two types of responses that can be returned by spawned tasks that
+/// A type to unify the types of responses that can be returned by spawned tasks that
 /// process a request.
 //
-#[ derive(Debug) ]
-//
 pub enum Response<Wf = CborWF>
 {
 	/// Eg. a relay, we are not using back pressure for this in the peer.
@@ -15,7 +13,39 @@ pub enum Response<Wf = CborWF>
 	//
 	CallResponse(CallResponse<Wf>),
 
+	/// Response to a call to a streaming service. Every item is sent to the remote as a response to
+	/// the call and the end of the stream is marked with a frame of it's own. This uses back pressure in
+	/// the peer, the slot is released when the stream ends.
+	//
+	Stream
+	{
+		/// The connection id of the call.
+		//
+		cid: ConnID,
+
+		/// The serialized items.
+		//
+		stream: Pin<Box< dyn Stream< Item=Result<Wf, PeerErr> > + Send >>,
+	},
+
 	/// Nothing, eg. task handles a send. listen_incoming also returns this.
 	//
 	Nothing,
 }
+
+
+impl<Wf: fmt::Debug> fmt::Debug for Response<Wf>
+{
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
+	{
+		match self
+		{
+			Self::WireFormat  (wf) => f.debug_tuple( "WireFormat"   ).field( wf ).finish(),
+			Self::CallResponse(r ) => f.debug_tuple( "CallResponse" ).field( r  ).finish(),
+
+			Self::Stream{ cid, .. } => f.debug_struct( "Stream" ).field( "cid", cid ).finish_non_exhaustive(),
+
+			Self::Nothing => write!( f, "Nothing" ),
+		}
+	}
+}
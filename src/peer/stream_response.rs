@@ -0,0 +1,205 @@
+use crate::{ import::*, *, peer::RequestError };
+
+
+/// A streaming service started answering an incoming call. The peer forwards the items to the remote
+/// until the stream ends or the remote cancels the call.
+//
+pub(crate) struct StreamResponse<Wf>
+{
+	pub(crate) cid   : ConnID                                                       ,
+	pub(crate) stream: Pin<Box< dyn Stream< Item=Result<Wf, PeerErr> > + Send >> ,
+}
+
+impl<Wf: WireFormat> Message for StreamResponse<Wf>
+{
+	type Return = ();
+}
+
+
+
+/// One item of a streaming response, ready to be sent out.
+//
+#[ derive( Debug ) ]
+//
+pub(crate) struct StreamItem<Wf>
+{
+	pub(crate) frame: Wf,
+}
+
+impl<Wf: WireFormat> Message for StreamItem<Wf>
+{
+	type Return = ();
+}
+
+
+
+/// A streaming response is over. `complete` is false when it was canceled or failed, in which case we
+/// don't send the end of stream frame.
+//
+#[ derive( Debug ) ]
+//
+pub(crate) struct StreamEnd
+{
+	pub(crate) cid     : ConnID,
+	pub(crate) complete: bool  ,
+}
+
+impl Message for StreamEnd
+{
+	type Return = ();
+}
+
+
+
+/// The end of stream frame.
+//
+#[ derive( Debug, Serialize, Deserialize ) ]
+//
+struct EndFrame
+{
+	cid: ConnID,
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Handler<StreamResponse<Wf>> for Peer<Wf>
+{
+	#[async_fn] fn handle( &mut self, msg: StreamResponse<Wf> ) -> <StreamResponse<Wf> as Message>::Return
+	{
+		if self.closed { return }
+
+		let cid        = msg.cid;
+		let mut stream = msg.stream;
+
+		trace!( "{}: streaming response, cid: {}", self.identify(), cid );
+
+		// The remote can cancel the stream.
+		//
+		let (handle, registration) = AbortHandle::new_pair();
+
+		self.processing.insert( cid, handle );
+
+		// If self.closed is false, there should always be an address.
+		//
+		let mut self_addr  = self.addr.as_ref().unwrap().clone();
+		let mut items_addr = self_addr.clone();
+
+		let forward = async move
+		{
+			while let Some( item ) = stream.next().await
+			{
+				match item
+				{
+					Ok( frame ) =>
+					{
+						if items_addr.send( StreamItem{ frame } ).await.is_err() { return false }
+					}
+
+					Err( e ) =>
+					{
+						let _ = items_addr.send( RequestError::from(e) ).await;
+
+						return false;
+					}
+				}
+			}
+
+			true
+		};
+
+		let task = async move
+		{
+			let complete = Abortable::new( forward, registration ).await.unwrap_or( false );
+
+			let _ = self_addr.send( StreamEnd{ cid, complete } ).await;
+
+			Ok( Response::Nothing )
+		};
+
+		if self.nursery.nurse( task ).is_err()
+		{
+			let ctx = self.ctx( None, cid, "Spawn task for streaming response" );
+
+			self.handle( RequestError::from( PeerErr::Spawn{ ctx } ) ).await;
+		}
+	}
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Handler<StreamItem<Wf>> for Peer<Wf>
+{
+	#[async_fn] fn handle( &mut self, msg: StreamItem<Wf> ) -> <StreamItem<Wf> as Message>::Return
+	{
+		trace!( "{}: sending OUT stream item", self.identify() );
+
+		if let Err(e) = self.send_msg( msg.frame ).await
+		{
+			self.pharos.send( PeerEvent::Error(e) ).await.expect( "pharos not closed" );
+		}
+	}
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Handler<StreamEnd> for Peer<Wf>
+{
+	#[async_fn] fn handle( &mut self, msg: StreamEnd ) -> <StreamEnd as Message>::Return
+	{
+		trace!( "{}: end of streaming response, cid: {}, complete: {}", self.identify(), msg.cid, msg.complete );
+
+		self.processing.remove( &msg.cid );
+		self.forget_channel( msg.cid );
+
+		if self.backpressure.is_some()
+		{
+			trace!( "Liberate slot for backpressure." );
+
+			drop( self.permits.pop() );
+		}
+
+		if !msg.complete || self.closed { return }
+
+		let mut wf = Wf::with_capacity( size_of::<EndFrame>() * 2 );
+		wf.set_sid( ServiceID::stream_end() );
+		wf.set_cid( ConnID::null()          );
+
+		CborCodec::encode( &mut wf, &EndFrame{ cid: msg.cid } ).expect( "serialize ConnID" );
+
+		if let Err(e) = self.send_msg( wf ).await
+		{
+			self.pharos.send( PeerEvent::Error(e) ).await.expect( "pharos not closed" );
+		}
+	}
+}
+
+
+
+impl<Wf: WireFormat + Send + 'static> Peer<Wf>
+{
+	/// The remote has sent all items of a streaming response.
+	//
+	pub(crate) async fn stream_ended( &mut self, frame: Wf )
+	{
+		let end: EndFrame = match CborCodec::decode( frame.msg() )
+		{
+			Ok(x) => x,
+
+			Err(_) =>
+			{
+				let ctx = self.ctx( ServiceID::stream_end(), None, "Deserialize end of stream frame" );
+
+				return self.handle( RequestError::from( PeerErr::Deserialize{ ctx } ) ).await;
+			}
+		};
+
+		trace!( "{}: end of incoming stream, cid: {}", self.identify(), end.cid );
+
+		if let Some( tx ) = self.forget_stream( end.cid )
+		{
+			// The caller might have dropped the stream already.
+			//
+			let _ = tx.unbounded_send(( None, None ));
+		}
+	}
+}
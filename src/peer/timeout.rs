@@ -30,6 +30,9 @@ impl<Wf: WireFormat + Send> Handler<Timeout> for Peer<Wf>
 
 		async move
 		{
+			self.in_flight.remove( &msg.cid );
+			self.held_calls.retain( |(wf, _)| wf.cid() != msg.cid );
+
 			if let Some( tx ) = self.responses.remove( &msg.cid )
 			{
 				// If this fails, the receiver is already gone, so ignore the result.
@@ -74,6 +74,8 @@ impl<Wf: WireFormat> PubSub<Wf>
 	/// list as needed. When this `PubSub` get's dropped or you drop the sender,
 	/// that task will be canceled and dropped.
 	//
+	#[ allow( clippy::result_large_err ) ]
+	//
 	pub fn rt_subscribe( &mut self, exec: &impl SpawnHandle<()> )
 
 		-> Result< futUnboundSender< Subscriber<Wf> >, PeerErr >
@@ -108,6 +110,8 @@ impl<Wf: WireFormat> PubSub<Wf>
 	/// list as needed. When this `PubSub` get's dropped or you drop the sender,
 	/// that task will be canceled and dropped.
 	//
+	#[ allow( clippy::result_large_err ) ]
+	//
 	pub fn rt_unsubscribe( &mut self, exec: &impl SpawnHandle<()> )
 
 		-> Result< futUnboundSender< usize >, PeerErr >
@@ -98,8 +98,27 @@ impl<Wf: WireFormat> ServiceMap<Wf> for RelayMap<Wf>
 
 		match &*self.handler.lock()
 		{
-			ServiceHandler::Address( a ) => Ok( make_call( a.clone_box(), frame, ctx ).boxed() ),
-			ServiceHandler::Closure( c ) => Ok( make_call( c(&sid)      , frame, ctx ).boxed() ),
+			ServiceHandler::Address( a ) => Ok( make_call( a.clone_box(), frame, ctx, None ).boxed() ),
+			ServiceHandler::Closure( c ) => Ok( make_call( c(&sid)      , frame, ctx, None ).boxed() ),
+		}
+	}
+
+
+	/// Relay a call for which the caller specified a deadline. The remaining time is passed on
+	/// to the relayed peer.
+	//
+	fn call_service_deadline( &self, frame: Wf, ctx: PeerErrCtx, remaining: Duration )
+
+		-> Result< Pin<Box< dyn Future< Output=Result<Response<Wf>, PeerErr> > + Send >>, PeerErr >
+	{
+		trace!( "RelayMap: Incoming Call with deadline for relayed actor." );
+
+		let sid = frame.sid();
+
+		match &*self.handler.lock()
+		{
+			ServiceHandler::Address( a ) => Ok( make_call( a.clone_box(), frame, ctx, Some( remaining ) ).boxed() ),
+			ServiceHandler::Closure( c ) => Ok( make_call( c(&sid)      , frame, ctx, Some( remaining ) ).boxed() ),
 		}
 	}
 
@@ -119,7 +138,7 @@ impl<Wf: WireFormat> ServiceMap<Wf> for RelayMap<Wf>
 
 #[ allow(clippy::needless_return) ]
 //
-async fn make_call<T, Wf: WireFormat + Send + 'static>( mut relay: Box<T>, frame: Wf, ctx: PeerErrCtx )
+async fn make_call<T, Wf: WireFormat + Send + 'static>( mut relay: Box<T>, frame: Wf, ctx: PeerErrCtx, remaining: Option<Duration> )
 
 	-> Result<Response<Wf>, PeerErr >
 
@@ -134,6 +153,14 @@ async fn make_call<T, Wf: WireFormat + Send + 'static>( mut relay: Box<T>, frame
 	let relay_gone = PeerErr::RelayGone{ ctx, relay_id, relay_name };
 	let new_call   = Call::new( frame );
 
+	// Pass on how much time the original caller has left.
+	//
+	let new_call = match remaining
+	{
+		Some( t ) => new_call.timeout( t ),
+		None      => new_call,
+	};
+
 	// Peer for relay still online.
 	// FIXME: use map_err when rustc supports it... currently relay_gone would have to be cloned.
 	//
@@ -153,6 +180,15 @@ async fn make_call<T, Wf: WireFormat + Send + 'static>( mut relay: Box<T>, frame
 	{
 		Ok(x) => x,
 
+		// The relayed remote didn't advertise this service, let the caller know.
+		//
+		Err( PeerErr::UnknownService{ ctx: unknown } ) =>
+		{
+			let err = ConnectionError::UnknownService{ sid: unknown.sid, cid: cid.into() };
+
+			return Ok( Response::WireFormat( Peer::prep_error( cid, &err ) ) );
+		}
+
 		// Sending out call to relayed failed. This normally only happens if the connection
 		// was closed, or a network transport malfunctioned.
 		//
@@ -222,7 +258,7 @@ impl<Wf> fmt::Debug for RelayMap<Wf>
 
 		for sid in &self.services
 		{
-			writeln!( f, "\tsid: 0x{:02x}", sid )?;
+			writeln!( f, "\tsid: {:#034x}", sid )?;
 		}
 
 		write!( f, "}}" )
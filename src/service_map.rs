@@ -13,6 +13,8 @@ pub trait ServiceMap<Wf = CborWF>: fmt::Debug + Send + Sync
 {
 	/// Send a message to a handler. This should take care of deserialization.
 	//
+	#[ allow( clippy::result_large_err ) ]
+	//
 	fn send_service( &self, msg: Wf, ctx: PeerErrCtx )
 
 		-> Result< Pin<Box< dyn Future< Output=Result<Response<Wf>, PeerErr> > + Send >>, PeerErr >
@@ -23,12 +25,43 @@ pub trait ServiceMap<Wf = CborWF>: fmt::Debug + Send + Sync
 	/// This should take care of deserialization. The return address is the address of the peer
 	/// to which the serialized answer shall be send.
 	//
+	#[ allow( clippy::result_large_err ) ]
+	//
 	fn call_service( &self, msg: Wf, ctx: PeerErrCtx )
 
 		-> Result< Pin<Box< dyn Future< Output=Result<Response<Wf>, PeerErr> > + Send >>, PeerErr >
 	;
 
 
+	/// Call a Service for which the remote caller specified a deadline. `remaining` is the time left until the
+	/// deadline. Peer will stop polling the returned future once it passes. Implementations that forward the call
+	/// to another process, like [`RelayMap`](crate::RelayMap), should pass on the remaining time. The default
+	/// implementation just calls [`ServiceMap::call_service`].
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	fn call_service_deadline( &self, msg: Wf, ctx: PeerErrCtx, _remaining: Duration )
+
+		-> Result< Pin<Box< dyn Future< Output=Result<Response<Wf>, PeerErr> > + Send >>, PeerErr >
+	{
+		self.call_service( msg, ctx )
+	}
+
+
+	/// Open a bidirectional channel. `incoming` has the frames the remote sends on the channel. The returned
+	/// future should resolve to a [`Response::Stream`] with the frames to send back. The default implementation
+	/// returns [`PeerErr::UnknownService`].
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	fn open_channel( &self, _msg: Wf, _incoming: Pin<Box< dyn Stream<Item=Wf> + Send >>, ctx: PeerErrCtx )
+
+		-> Result< Pin<Box< dyn Future< Output=Result<Response<Wf>, PeerErr> > + Send >>, PeerErr >
+	{
+		Err( PeerErr::UnknownService{ ctx } )
+	}
+
+
 	/// Get a list of all services provided by this service map.
 	//
 	// TODO: Find a way to avoid the heap allocation.
@@ -25,10 +25,29 @@
 /// (
 ///    namespace: myns;
 ///
+///    wire_format: CborWF;
+///
+///    // Optional. How the messages are serialized, CborCodec if you leave it out.
+///    //
+///    codec: CborCodec;
+///
 ///    services:
 ///
 ///       ServiceA,
-///       ServiceB,
+///       ServiceB;
+///
+///    // Optional. The handlers of these return a Stream.
+///    //
+///    streams:
+///
+///       StreamC;
+///
+///    // Optional. Bidirectional channels, with the type of the items the caller sends. The handlers
+///    // receive a Channel<ChannelD, In> and return a Stream.
+///    //
+///    channels:
+///
+///       ChannelD => In,
 /// );
 ///
 /// mod myns
@@ -39,6 +58,14 @@
 ///    impl Service for ServiceA { fn sid() -> ServiceID }
 ///    impl Service for ServiceB {...}
 ///
+///    // Item is the item type of the stream returned by the handler.
+///    //
+///    impl StreamService for StreamC { type Item; fn sid() -> ServiceID }
+///
+///    // In is the type of the items the caller sends, Out the item type of the stream returned by the handler.
+///    //
+///    impl ChannelService for ChannelD { type In; type Out; fn sid() -> ServiceID }
+///
 ///    // implements Clone, Debug and ServiceMap.
 ///    //
 ///    pub struct Services {}
@@ -47,7 +74,11 @@
 ///
 ///    impl Services
 ///    {
+///       pub fn new() -> Self
+///       pub fn try_new() -> Result<Self, WireErr>
 ///       pub fn register_handler<S>( &mut self, handler: BoxAddress<S, ThesErr> )
+///       pub fn register_stream_handler<S>( &mut self, handler: BoxAddress<S, ThesErr> )
+///       pub fn register_channel_handler<M>( &mut self, handler: BoxAddress<Channel<M, M::In>, ThesErr> )
 ///    }
 ///
 ///    // Service map is defined in the thespis crate. This exposes the register_handler method
@@ -72,6 +103,11 @@
 macro_rules! service_map
 {
 
+// Internal: the codec to use when none is given.
+//
+(@codec                ) => { $crate::CborCodec };
+(@codec $codec: path   ) => { $codec            };
+
 (
 	/// namespace unique to this servicemap. It allows you to use your services with
 	/// several service_maps and it also gets used in the unique ID generation of
@@ -87,9 +123,23 @@ macro_rules! service_map
 	//
 	wire_format: $wf: path;
 
+	/// Optional. Which [Codec] to use to serialize messages. Defaults to [CborCodec].
+	//
+	$( codec: $codec: path; )?
+
 	/// Comma separated list of Services you want to include. They must be in scope.
 	//
-	services: $($services: path),+ $(,)? $(;)?
+	services: $($services: path),* $(,)? ;
+
+	/// Comma separated list of streaming services you want to include. They must be in scope.
+	/// Their handlers return a [Stream](futures::Stream) of items, see `StreamService`.
+	//
+	streams: $($streams: path),* $(,)? ;
+
+	/// Comma separated list of bidirectional channels you want to include, each followed by the type of
+	/// the items the caller sends. They must be in scope. See `ChannelService`.
+	//
+	channels: $( $channels: path => $chan_in: ty ),* $(,)? $(;)?
 ) =>
 
 {
@@ -105,17 +155,17 @@ use
 	// we should not have a leading comma before the next item, but if the comma is after the closing
 	// parenthesis, it will not output a trailing comma, which will be needed to separate from the next item.
 	//
-	super :: { $( $services, )+                                                                               } ,
+	super :: { $( $services, )* $( $streams, )* $( $channels, )*                                              } ,
 	$crate:: { *, peer::request_error::RequestError                                                           } ,
-	std   :: { pin::Pin, collections::HashMap, fmt, any::Any, sync::{ Arc, Once }, ops::Deref, future::Future } ,
+	std   :: { pin::Pin, collections::HashMap, fmt, any::Any, sync::Arc, ops::Deref, future::Future           } ,
 
 	$crate::external_deps::
 	{
 		once_cell       :: { sync::Lazy                                          } ,
-		futures         :: { future::FutureExt, task::{ Context, Poll }, SinkExt } ,
+		futures         :: { future::{ FutureExt, ready }, task::{ Context, Poll }, SinkExt } ,
+		futures         :: { stream::{ Stream, StreamExt }                       } ,
 		thespis         :: { *                                                   } ,
 		thespis_impl    :: { WeakAddr, ThesErr, ThesRes                          } ,
-		serde_cbor      :: { self, from_slice as des                             } ,
 		serde           :: { Serialize, Deserialize, de::DeserializeOwned        } ,
 		tracing         :: { error                                               } ,
 		parking_lot     :: { Mutex                                               } ,
@@ -125,6 +175,20 @@ use
 
 
 
+/// The codec used for the messages of this service map.
+//
+type MapCodec = $crate::service_map!( @codec $( $codec )? );
+
+
+// Deserialize a message with the codec of this service map.
+//
+fn des<T: DeserializeOwned>( bytes: &[u8] ) -> Result<T, CodecErr>
+{
+	<MapCodec as Codec>::decode( bytes )
+}
+
+
+
 /// A [Message] that can be received from remote code. Mainly defines that this [Message] type has
 /// a unique id which allows distinguishing it from other services. It is namespaced, so that different
 /// components/processes can expose services to the network which will accept the same [Message] type,
@@ -173,7 +237,77 @@ $(
 		}
 	}
 
-)+
+)*
+
+
+
+/// A [Message] that is answered with a stream of items rather than a single response. The handler
+/// returns a [Stream] and each item is serialized and sent to the caller as it becomes available.
+/// Use `RemoteAddr::call_stream` to call these services.
+///
+/// Streaming services can't be relayed.
+//
+pub trait StreamService
+
+	where  Self                    : Message + Serialize + DeserializeOwned,
+	      <Self as Message>::Return: Stream< Item=Self::Item > + Send,
+{
+	/// The items in the stream returned by the handler.
+	//
+	type Item: Serialize + DeserializeOwned + Send;
+
+	/// The unique service id. See [Service::sid].
+	//
+	fn sid() -> ServiceID where Self: Sized;
+}
+
+
+
+$(
+
+	impl StreamService for $streams
+	{
+		type Item = <<$streams as Message>::Return as Stream>::Item;
+
+		/// A service ID that is unique for this type, based on a hash of the namespace and type name.
+		//
+		fn sid() -> ServiceID
+		{
+			static INSTANCE : Lazy< ServiceID > = Lazy::new( ||
+
+				ServiceID::from_seed( stringify!( $ns::$streams ).as_bytes() )
+			);
+
+			*INSTANCE
+		}
+	}
+
+)*
+
+
+/// A [Message] that opens a bidirectional channel. The handler receives a [Channel] with the message and
+/// the items the caller sends, and returns a [Stream] of items that are sent back to the caller.
+/// Use `RemoteAddr::open_channel` to open these channels.
+///
+/// Channels can't be relayed.
+//
+pub trait ChannelService
+
+	where  Self                    : Message + Serialize + DeserializeOwned,
+	      <Self as Message>::Return: Stream< Item=Self::Out > + Send,
+{
+	/// The items the caller sends on the channel.
+	//
+	type In: Serialize + DeserializeOwned + Send + 'static;
+
+	/// The items in the stream returned by the handler.
+	//
+	type Out: Serialize + DeserializeOwned + Send;
+
+	/// The unique service id. See [Service::sid].
+	//
+	fn sid() -> ServiceID where Self: Sized;
+}
 
 
 /// The actual service map.
@@ -206,48 +340,32 @@ impl fmt::Debug for Services
 
 		$(
 			width = std::cmp::max( width, stringify!( $services ).len() );
-		)+
-
-		write!( f, "{}::Services\n{{\n", stringify!( $ns ) )?;
+		)*
 
+		$(
+			width = std::cmp::max( width, stringify!( $streams ).len() );
+		)*
 
 		$(
-			let sid = <$services as Service>::sid();
+			width = std::cmp::max( width, stringify!( $channels ).len() );
+		)*
 
-			write!
-			(
-				f,
+		write!( f, "{}::Services\n{{\n", stringify!( $ns ) )?;
 
-				"\t{:width$} - sid: 0x{:02x} - handler: ",
+		$(
+			self.fmt_handler::<$services>( f, stringify!( $services ), <$services as Service>::sid(), width )?;
+		)*
 
-				stringify!( $services ),
-				sid,
+		$(
+			self.fmt_handler::<$streams>( f, stringify!( $streams ), <$streams as StreamService>::sid(), width )?;
+		)*
 
-				width = width
+		$(
+			self.fmt_handler::< Channel<$channels, <$channels as ChannelService>::In> >
+			(
+				f, stringify!( $channels ), <$channels as ChannelService>::sid(), width
 			)?;
-
-			if let Some(h) = self.handlers.get( &sid )
-			{
-				let h = h.lock();
-
-				// This expect shouldn't ever fail. We manually make the receiver in this file.
-				//
-				let handler: &BoxAddress<$services, ThesErr> = h.downcast_ref().expect( "downcast receiver in Debug for Services" );
-
-				match handler.name().is_empty()
-				{
-					true  => write!( f, "id({})", &handler.id() )?,
-					false => write!( f, "id({}), name({})", &handler.id(), &handler.name() )?,
-				};
-			}
-
-			else
-			{
-				write!( f, "none" )?;
-			}
-
-			write!( f, "\n" )?;
-		)+
+		)*
 
 		write!( f, "}}" )
 	}
@@ -272,14 +390,23 @@ impl Clone for Services
 				$(
 					_ if *k == <$services as Service>::sid() =>
 					{
-						// This should never fail, we make this type in this file.
-						//
-						let v = v.lock();
-						let h: &BoxAddress<$services, ThesErr> = v.downcast_ref().expect( "downcast receiver in Clone" );
+						handlers.insert( *k, Self::clone_handler::<$services>( v ) );
+					},
+				)*
 
-						handlers.insert( *k, Mutex::new( Box::new(h.clone_box()) ) );
+				$(
+					_ if *k == <$streams as StreamService>::sid() =>
+					{
+						handlers.insert( *k, Self::clone_handler::<$streams>( v ) );
 					},
-				)+
+				)*
+
+				$(
+					_ if *k == <$channels as ChannelService>::sid() =>
+					{
+						handlers.insert( *k, Self::clone_handler::< Channel<$channels, <$channels as ChannelService>::In> >( v ) );
+					},
+				)*
 
 
 				// every sid in our handlers map should also be a valid service in this service map,
@@ -298,23 +425,38 @@ impl Clone for Services
 
 impl Services
 {
-	/// Create a new service map
+	/// Create a new service map.
+	///
+	/// # Panics
+	///
+	/// When two services hash to the same [ServiceID]. See [`Services::try_new`].
 	//
 	pub fn new() -> Self
 	{
-		$(
-			paste::expr!
-			{
-				static [< __ONCE__ $services >]: Once = Once::new();
+		Self::try_new().unwrap_or_else( |e| panic!( "{}", e ) )
+	}
 
-				[< __ONCE__ $services >].call_once( ||
-				{
-					ServiceID::register_service( $services::sid(), concat!( stringify!($ns) , "::", stringify!($services) ) );
-				});
-			}
-		)+
 
-		Self{ handlers: HashMap::new() }
+	/// Create a new service map. The first time, this registers the names of all services, which fails with
+	/// [`WireErr::ServiceIDCollision`] if a different name was registered already for one of their ServiceIDs.
+	/// Later service maps get the same outcome without registering again.
+	//
+	pub fn try_new() -> Result<Self, WireErr>
+	{
+		// The registry is global, so there's no need to lock it for every service map.
+		//
+		static REGISTERED: Lazy< Result<(), WireErr> > = Lazy::new( || -> Result<(), WireErr>
+		{
+			$( ServiceID::register_service( <$services as Service      >::sid(), concat!( stringify!($ns) , "::", stringify!($services) ) )?; )*
+			$( ServiceID::register_service( <$streams  as StreamService >::sid(), concat!( stringify!($ns) , "::", stringify!($streams ) ) )?; )*
+			$( ServiceID::register_service( <$channels as ChannelService>::sid(), concat!( stringify!($ns) , "::", stringify!($channels) ) )?; )*
+
+			Ok(())
+		});
+
+		REGISTERED.clone()?;
+
+		Ok( Self{ handlers: HashMap::new() } )
 	}
 
 
@@ -330,6 +472,73 @@ impl Services
 	}
 
 
+	/// Register a handler for a given streaming service type
+	/// Calling this method twice for the same type will override the first handler.
+	//
+	pub fn register_stream_handler<S>( &mut self, handler: BoxAddress<S, ThesErr> )
+
+		where  S                    : StreamService,
+		      <S as Message>::Return: Stream< Item=<S as StreamService>::Item > + Send,
+	{
+		self.handlers.insert( <S as StreamService>::sid(), Mutex::new(Box::new( handler )) );
+	}
+
+
+	/// Register a handler for a given bidirectional channel type
+	/// Calling this method twice for the same type will override the first handler.
+	//
+	pub fn register_channel_handler<M>( &mut self, handler: BoxAddress< Channel<M, <M as ChannelService>::In>, ThesErr > )
+
+		where  M                    : ChannelService,
+		      <M as Message>::Return: Stream< Item=<M as ChannelService>::Out > + Send,
+	{
+		self.handlers.insert( <M as ChannelService>::sid(), Mutex::new(Box::new( handler )) );
+	}
+
+
+	// Helper for Debug. Writes one line for the service M.
+	//
+	fn fmt_handler<M: Message>( &self, f: &mut fmt::Formatter<'_>, name: &str, sid: ServiceID, width: usize ) -> fmt::Result
+	{
+		write!( f, "\t{:width$} - sid: {:#034x} - handler: ", name, sid, width = width )?;
+
+		if let Some(h) = self.handlers.get( &sid )
+		{
+			let h = h.lock();
+
+			// This expect shouldn't ever fail. We manually make the receiver in this file.
+			//
+			let handler: &BoxAddress<M, ThesErr> = h.downcast_ref().expect( "downcast receiver in Debug for Services" );
+
+			match handler.name().is_empty()
+			{
+				true  => write!( f, "id({})", &handler.id() )?,
+				false => write!( f, "id({}), name({})", &handler.id(), &handler.name() )?,
+			};
+		}
+
+		else
+		{
+			write!( f, "none" )?;
+		}
+
+		write!( f, "\n" )
+	}
+
+
+	// Helper for Clone. Clones the handler for the service M.
+	//
+	fn clone_handler<M: Message>( v: &Mutex<Box<dyn Any + Send>> ) -> Mutex<Box<dyn Any + Send>>
+	{
+		// This should never fail, we make this type in this file.
+		//
+		let v = v.lock();
+		let h: &BoxAddress<M, ThesErr> = v.downcast_ref().expect( "downcast receiver in Clone" );
+
+		Mutex::new( Box::new( h.clone_box() ) )
+	}
+
+
 	// Helper function for call_service below.
 	// The receiver passed in here keeps a mutex locked. This method should never be async, nor await anything.
 	//
@@ -396,7 +605,7 @@ impl Services
 
 			// serialize the response
 			//
-			serde_cbor::to_writer( &mut wf, &response ).map_err( |_|
+			<MapCodec as Codec>::encode( &mut wf, &response ).map_err( |_|
 			{
 				ctx.context.as_mut().map( |c| c.push_str( " - Response to remote call" ) );
 
@@ -409,6 +618,163 @@ impl Services
 
 		}.boxed() )
 	}
+
+
+	// Helper function for call_service below. Like call_service_gen, but for streaming services.
+	// The receiver passed in here keeps a mutex locked. This method should never be async, nor await anything.
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	fn call_stream_gen<S>
+	(
+		    msg      :  $wf                   ,
+		    receiver : &Box< dyn Any + Send > ,
+		mut ctx      :  PeerErrCtx            ,
+
+	) -> Result< Pin<Box< dyn Future< Output=Result<Response<$wf>, PeerErr> > + Send >>, PeerErr >
+
+		where  S                    : StreamService + Send,
+		      <S as Message>::Return: Stream< Item=<S as StreamService>::Item > + Send,
+
+	{
+		// Deserialize the message.
+		//
+		let message: S = match des( &msg.msg() )
+		{
+			Ok (x) => x,
+			Err(_) => return Err( PeerErr::Deserialize{ ctx } )
+		};
+
+
+		// Downcast the receiver, should never fail as we make it in this file.
+		//
+		let backup: &BoxAddress<S, ThesErr> = receiver.downcast_ref()
+
+			.expect( "downcast receiver in call_stream_gen" );
+
+
+		let mut rec = backup.clone_box() ;
+		let     cid = msg.cid()      ;
+
+		Ok( async move
+		{
+			// Call the service to get the stream.
+			//
+			let stream = match rec.call( message ).await
+			{
+				Ok(x) => x,
+
+				Err(_) =>
+				{
+					ctx.context.as_mut().map( |c| c.push_str( " - Process call for local Actor" ) );
+
+					return Err( PeerErr::HandlerDead{ ctx } );
+				}
+			};
+
+			Ok( Self::stream_response( stream, cid, ctx ) )
+
+		}.boxed() )
+	}
+
+
+	// Helper function for open_channel below.
+	// The receiver passed in here keeps a mutex locked. This method should never be async, nor await anything.
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	fn open_channel_gen<M>
+	(
+		    msg      :  $wf                                        ,
+		    incoming :  Pin<Box< dyn Stream<Item=$wf> + Send >>    ,
+		    receiver : &Box< dyn Any + Send >                      ,
+		mut ctx      :  PeerErrCtx                                 ,
+
+	) -> Result< Pin<Box< dyn Future< Output=Result<Response<$wf>, PeerErr> > + Send >>, PeerErr >
+
+		where  M                    : ChannelService + Send,
+		      <M as Message>::Return: Stream< Item=<M as ChannelService>::Out > + Send,
+
+	{
+		// Deserialize the message.
+		//
+		let message: M = match des( &msg.msg() )
+		{
+			Ok (x) => x,
+			Err(_) => return Err( PeerErr::Deserialize{ ctx } )
+		};
+
+
+		// Downcast the receiver, should never fail as we make it in this file.
+		//
+		let backup: &BoxAddress< Channel<M, <M as ChannelService>::In>, ThesErr > = receiver.downcast_ref()
+
+			.expect( "downcast receiver in open_channel_gen" );
+
+
+		let mut rec = backup.clone_box() ;
+		let     cid = msg.cid()      ;
+
+		// The handler can't do anything with items that don't deserialize, so we drop them.
+		//
+		let incoming = incoming.filter_map( move |wf| ready( match des::< <M as ChannelService>::In >( &wf.msg() )
+		{
+			Ok (item) => Some( item ),
+
+			Err(_) =>
+			{
+				error!( "Failed to deserialize item on bidirectional channel, cid: {}. Dropping it.", cid );
+
+				None
+			}
+		}));
+
+		Ok( async move
+		{
+			// Call the service to get the stream.
+			//
+			let stream = match rec.call( Channel{ msg: message, incoming: incoming.boxed() } ).await
+			{
+				Ok(x) => x,
+
+				Err(_) =>
+				{
+					ctx.context.as_mut().map( |c| c.push_str( " - Open channel with local Actor" ) );
+
+					return Err( PeerErr::HandlerDead{ ctx } );
+				}
+			};
+
+			Ok( Self::stream_response( stream, cid, ctx ) )
+
+		}.boxed() )
+	}
+
+
+	// Helper for call_stream_gen and open_channel_gen. Every item of the stream returned by the handler
+	// becomes a response to the call, so the sid must be full, like in call_service_gen.
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	fn stream_response<T>( stream: impl Stream<Item=T> + Send + 'static, cid: ConnID, mut ctx: PeerErrCtx ) -> Response<$wf>
+
+		where T: Serialize,
+	{
+		ctx.context.as_mut().map( |c| c.push_str( " - Item of streaming response to remote call" ) );
+
+		let items = stream.map( move |item|
+		{
+			let mut wf = <$wf>::with_capacity( ::std::mem::size_of::<T>() * 2 );
+			wf.set_sid( ServiceID::full() );
+			wf.set_cid( cid               );
+
+			<MapCodec as Codec>::encode( &mut wf, &item ).map_err( |_| PeerErr::Serialize{ ctx: ctx.clone() } )?;
+
+			Ok( wf )
+		});
+
+		Response::Stream{ cid, stream: items.boxed() }
+	}
 }
 
 
@@ -483,7 +849,7 @@ impl ServiceMap<$wf> for Services
 
 					}.boxed() )
 				},
-			)+
+			)*
 
 			_ =>
 			{
@@ -533,7 +899,14 @@ impl ServiceMap<$wf> for Services
 				{
 					Self::call_service_gen::<$services>( msg, &*receiver, ctx )
 				}
-			)+
+			)*
+
+			$(
+				_ if sid == <$streams as StreamService>::sid() =>
+				{
+					Self::call_stream_gen::<$streams>( msg, &*receiver, ctx )
+				}
+			)*
 
 
 			_ => return Err( PeerErr::UnknownService{ ctx } )
@@ -541,6 +914,52 @@ impl ServiceMap<$wf> for Services
 	}
 
 
+	/// Will match the type of the service id to deserialize the message and open a channel with the handling
+	/// actor. Like call_service, it returns a future that is spawned by Peer.
+	///
+	/// This can return the following errors:
+	/// - PeerErr::UnknownService
+	/// - PeerErr::Deserialize
+	//
+	fn open_channel
+	(
+		&self                                            ,
+		msg      : $wf                                   ,
+		incoming : Pin<Box< dyn Stream<Item=$wf> + Send >> ,
+		ctx      : PeerErrCtx                            ,
+
+	) -> Result< Pin<Box< dyn Future< Output=Result<Response<$wf>, PeerErr> > + Send >>, PeerErr >
+	{
+		let sid = msg.sid();
+		let ctx = ctx.context( "Services::open_channel".to_string() );
+
+		match sid
+		{
+			$(
+				_ if sid == <$channels as ChannelService>::sid() =>
+				{
+					let receiver = self.handlers.get( &sid )
+
+						.ok_or_else( || PeerErr::NoHandler{ ctx: ctx.clone() } )?
+						.lock()
+					;
+
+					Self::open_channel_gen::<$channels>( msg, incoming, &*receiver, ctx )
+				}
+			)*
+
+			_ =>
+			{
+				// Without channels in this service map, these would be unused.
+				//
+				drop(( msg, incoming ));
+
+				Err( PeerErr::UnknownService{ ctx } )
+			}
+		}
+	}
+
+
 	fn apply_backpressure( &self ) -> bool
 	{
 		true
@@ -599,7 +1018,7 @@ impl RemoteAddr
 
 		// serialize the response
 		//
-		serde_cbor::to_writer( &mut wf, &msg ).map_err( |_|
+		<MapCodec as Codec>::encode( &mut wf, &msg ).map_err( |_|
 		{
 			let mut ctx = PeerErrCtx::default();
 			ctx.context = "Outgoing request".to_string().into();
@@ -631,7 +1050,7 @@ impl RemoteAddr
 
 		// serialize the response
 		//
-		serde_cbor::to_writer( &mut wf, &msg ).map_err( |_|
+		<MapCodec as Codec>::encode( &mut wf, &msg ).map_err( |_|
 		{
 			let mut ctx = PeerErrCtx::default();
 			ctx.context = "Outgoing request".to_string().into();
@@ -643,29 +1062,191 @@ impl RemoteAddr
 
 		Ok( Call::new( wf ) )
 	}
-}
 
 
+	/// Call a remote actor with a different timeout than the one configured on the peer. The remaining time is
+	/// sent along with the call, so the remote (and any relays in between) stop processing it when we no longer
+	/// wait for the response. Returns [`PeerErr::Timeout`] when the timeout expires.
+	//
+	pub async fn call_with_timeout<S>( &mut self, msg: S, timeout: ::std::time::Duration ) -> Result< <S as Message>::Return, PeerErr >
 
-impl<S> Address<S> for RemoteAddr
+		where  S                    : Service + Send,
+		      <S as Message>::Return: Serialize + DeserializeOwned + Send,
 
-	where  S                    : Service + Send,
-	      <S as Message>::Return: Serialize + DeserializeOwned + Send,
+	{
+		let call = Self::build_call( msg )?.timeout( timeout );
 
-{
-	/// Call a remote actor.
-	///
-	/// ### potential errors
+		self.send_call::<S>( call ).await
+	}
+
+
+	/// Call a remote streaming service. Items are deserialized as they come in. The stream ends after
+	/// the last item, or after the first error. Dropping the stream cancels the call on the remote.
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	pub async fn call_stream<S>( &mut self, msg: S )
+
+		-> Result< Pin<Box< dyn Stream< Item=Result<<S as StreamService>::Item, PeerErr> > + Send >>, PeerErr >
+
+		where  S                    : StreamService + Send,
+		      <S as Message>::Return: Stream< Item=<S as StreamService>::Item > + Send,
+		      <S as StreamService>::Item: 'static,
+
+	{
+		let sid = <S as StreamService>::sid();
+		let wf  = Self::build_request( &msg, sid )?;
+		let ctx = Peer::err_ctx( &self.peer, sid, None, "Call remote streaming service".to_string() );
+
+		let responses = self.peer.call( CallStream::new( wf ) ).await
+
+			// The peer panicked.
+			//
+			.map_err( |_| PeerErr::PeerGone{ ctx: ctx.clone() } )?
+
+			// The actual sending out over the network can fail. The peer can also refuse the call
+			// if the remote didn't advertise the service.
+			//
+			.map_err( |e| match e
+			{
+				PeerErr::UnknownService{..} => PeerErr::UnknownService  { ctx: ctx.clone() },
+				_                           => PeerErr::ConnectionClosed{ ctx: ctx.clone() },
+			})?;
+
+
+		Ok( Self::typed_stream( responses, ctx ) )
+	}
+
+
+	/// Open a bidirectional channel with a remote service. Items sent on the sink are delivered to the
+	/// handler as they come in. Closing or dropping the sink ends the incoming items on the remote.
 	///
-	/// 1. serialization of the outgoing message
-	/// 2.
+	/// The stream has the items the handler sends back, it ends like the one from `call_stream`.
+	/// Dropping the stream cancels the channel on the remote.
 	//
-	fn call( &mut self, msg: S ) -> Return<Result< <S as Message>::Return, PeerErr >> { async move
+	#[ allow( clippy::result_large_err, clippy::type_complexity ) ]
+	//
+	pub async fn open_channel<M>( &mut self, msg: M ) -> Result
+	<
+		(
+			Pin<Box< dyn Sink< <M as ChannelService>::In, Error=PeerErr > + Send >>                ,
+			Pin<Box< dyn Stream< Item=Result<<M as ChannelService>::Out, PeerErr> > + Send >> ,
+		),
+		PeerErr
+	>
+
+		where  M                        : ChannelService + Send,
+		      <M as Message>::Return    : Stream< Item=<M as ChannelService>::Out > + Send,
+		      <M as ChannelService>::Out: 'static,
+
 	{
-		// Serialization can fail
+		let sid = <M as ChannelService>::sid();
+		let wf  = Self::build_request( &msg, sid )?;
+		let ctx = Peer::err_ctx( &self.peer, sid, None, "Open channel with remote service".to_string() );
+
+		let (sink, responses) = self.peer.call( CallChannel::new( wf ) ).await
+
+			// The peer panicked.
+			//
+			.map_err( |_| PeerErr::PeerGone{ ctx: ctx.clone() } )?
+
+			// The actual sending out over the network can fail. The peer can also refuse the call
+			// if the remote didn't advertise the service.
+			//
+			.map_err( |e| match e
+			{
+				PeerErr::UnknownService{..} => PeerErr::UnknownService  { ctx: ctx.clone() },
+				_                           => PeerErr::ConnectionClosed{ ctx: ctx.clone() },
+			})?;
+
+
+		// The ChannelSink sets the sid and cid.
 		//
-		let call = Self::build_call( msg )?;
+		let sink = sink.with( move |item: <M as ChannelService>::In| ready( Self::build_request( &item, sid ) ) );
 
+		Ok(( Box::pin( sink ), Self::typed_stream( responses, ctx ) ))
+	}
+
+
+	/// Serialize a message or item for a streaming call or a channel.
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	fn build_request<T: Serialize>( msg: &T, sid: ServiceID ) -> Result< $wf, PeerErr >
+	{
+		let mut wf = <$wf>::with_capacity( ::std::mem::size_of::<T>() * 2 );
+		wf.set_sid( sid );
+
+		<MapCodec as Codec>::encode( &mut wf, msg ).map_err( |_|
+		{
+			let mut ctx = PeerErrCtx::default();
+			ctx.context = "Outgoing request".to_string().into();
+			ctx.sid     = sid.into();
+
+			PeerErr::Serialize{ ctx }
+
+		})?;
+
+		Ok( wf )
+	}
+
+
+	/// Deserialize the responses of a streaming call or a channel.
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	fn typed_stream<T>( responses: ResponseStream<$wf>, ctx: PeerErrCtx )
+
+		-> Pin<Box< dyn Stream< Item=Result<T, PeerErr> > + Send >>
+
+		where T: DeserializeOwned + Send + 'static,
+	{
+		responses.map( move |item| match item
+		{
+			Ok( Ok( resp ) ) => des( &resp.msg() ).map_err( |_|
+			{
+				let ctx = ctx.clone().context( "Item of stream from remote actor".to_string() ).cid( resp.cid() );
+
+				PeerErr::Deserialize{ ctx }
+			}),
+
+			// We waited too long for the next item.
+			//
+			Ok( Err( ConnectionError::Timeout{..} ) ) =>
+			{
+				let ctx = ctx.clone().context( "Time out waiting for the next item of stream from remote call".to_string() );
+
+				Err( PeerErr::Timeout{ ctx } )
+			}
+
+			// The remote returned an error.
+			//
+			Ok( Err( err ) ) =>
+			{
+				let ctx = ctx.clone().context( "Remote could not process our message".to_string() );
+
+				Err( PeerErr::Remote{ err, ctx } )
+			}
+
+			Err(_) =>
+			{
+				let ctx = ctx.clone().context( "Peer stopped before the end of the stream from remote call".to_string() );
+
+				Err( PeerErr::ConnectionClosed{ ctx } )
+			}
+
+		}).boxed()
+	}
+
+
+	/// Send the call to the peer and wait for the response.
+	//
+	async fn send_call<S>( &mut self, call: Call<$wf> ) -> Result< <S as Message>::Return, PeerErr >
+
+		where  S                    : Service + Send,
+		      <S as Message>::Return: Serialize + DeserializeOwned + Send,
+
+	{
 		// Can fail if the peer is down already.
 		//
 		let rx = self.peer.call( call ).await
@@ -680,13 +1261,19 @@ impl<S> Address<S> for RemoteAddr
 
 			})?
 
-			// The actual sending out over the network can fail.
+			// The actual sending out over the network can fail. The peer can also refuse the call
+			// if the remote didn't advertise the service or refused our handshake.
 			//
-			.map_err( |_|
+			.map_err( |e|
 			{
 				let ctx = Peer::err_ctx( &self.peer, <S as Service>::sid(), None, "Call remote service".to_string() );
 
-				PeerErr::ConnectionClosed{ ctx }
+				match e
+				{
+					PeerErr::UnknownService{..} => PeerErr::UnknownService  { ctx      },
+					PeerErr::Remote{ err, .. }  => PeerErr::Remote          { ctx, err },
+					_                           => PeerErr::ConnectionClosed{ ctx      },
+				}
 
 			})?;
 
@@ -777,6 +1364,31 @@ impl<S> Address<S> for RemoteAddr
 				}
 			},
 		}
+	}
+}
+
+
+
+impl<S> Address<S> for RemoteAddr
+
+	where  S                    : Service + Send,
+	      <S as Message>::Return: Serialize + DeserializeOwned + Send,
+
+{
+	/// Call a remote actor.
+	///
+	/// ### potential errors
+	///
+	/// 1. serialization of the outgoing message
+	/// 2.
+	//
+	fn call( &mut self, msg: S ) -> Return<Result< <S as Message>::Return, PeerErr >> { async move
+	{
+		// Serialization can fail
+		//
+		let call = Self::build_call( msg )?;
+
+		self.send_call::<S>( call ).await
 
 	}.boxed() }
 
@@ -866,4 +1478,77 @@ impl Identify for RemoteAddr
 	}
 }
 
-}}} // End of macro
+}
+
+
+// The types of the incoming items are only in scope here, so ChannelService is implemented outside of the module.
+//
+$(
+
+	impl $ns::ChannelService for $channels
+	{
+		type In  = $chan_in;
+		type Out = <<$channels as $crate::external_deps::thespis::Message>::Return as $crate::external_deps::futures::Stream>::Item;
+
+		/// A service ID that is unique for this type, based on a hash of the namespace and type name.
+		//
+		fn sid() -> $crate::ServiceID
+		{
+			static INSTANCE : $crate::external_deps::once_cell::sync::Lazy< $crate::ServiceID > =
+
+				$crate::external_deps::once_cell::sync::Lazy::new( ||
+
+					$crate::ServiceID::from_seed( stringify!( $ns::$channels ).as_bytes() )
+				)
+			;
+
+			*INSTANCE
+		}
+	}
+
+)*
+
+};
+
+
+(
+	namespace  : $ns: ident;
+	wire_format: $wf: path;
+	$( codec   : $codec: path; )?
+	services   : $($services: path),+ $(,)? ;
+	streams    : $($streams : path),* $(,)? $(;)?
+) =>
+
+{
+	$crate::service_map!
+	(
+		namespace  : $ns             ;
+		wire_format: $wf             ;
+		$( codec   : $codec ;       )?
+		services   : $( $services ),+ ;
+		streams    : $( $streams  ),* ;
+		channels   :                 ;
+	);
+};
+
+
+(
+	namespace  : $ns: ident;
+	wire_format: $wf: path;
+	$( codec   : $codec: path; )?
+	services   : $($services: path),+ $(,)? $(;)?
+) =>
+
+{
+	$crate::service_map!
+	(
+		namespace  : $ns             ;
+		wire_format: $wf             ;
+		$( codec   : $codec ;       )?
+		services   : $( $services ),+ ;
+		streams    :                 ;
+		channels   :                 ;
+	);
+};
+
+} // End of macro
@@ -0,0 +1,173 @@
+//! Encrypted and authenticated connections with [rustls](https://docs.rs/rustls). Requires the `tls` feature.
+//!
+//! [Tls] wraps the socket in a TLS stream before it is framed, so the wire format and everything above it
+//! doesn't change. The certificates the remote presented, which rustls has verified, are available on the
+//! peer with [`Peer::identity`].
+//!
+//! For mutual authentication, the server config needs a client certificate verifier, like
+//! [`WebPkiClientVerifier`](rustls::server::WebPkiClientVerifier), and the client config needs a certificate
+//! to present, see [`ConfigBuilder::with_client_auth_cert`](rustls::ConfigBuilder::with_client_auth_cert).
+//!
+//! ```ignore
+//! let tls = Tls::Client{ config: Arc::new( client_config ), server_name: "server.example".try_into()? };
+//!
+//! let (peer, peer_mb, peer_addr) = tls.create_peer::<CborWF>( "server", tcp, 1024, 1024, exec, None, None ).await?;
+//!
+//! let server_cert = peer.identity().expect( "servers always present a certificate" ).end_entity();
+//! ```
+//
+use
+{
+	crate          :: { import::*, Peer, PeerErr, PeerErrCtx, PeerExec, WireFormat          } ,
+	futures_rustls :: { TlsAcceptor, TlsConnector, TlsStream                                 } ,
+	rustls         :: { ClientConfig, ServerConfig, pki_types::{ CertificateDer, ServerName } } ,
+};
+
+pub use futures_rustls::rustls;
+
+
+
+/// Which side of the TLS handshake to take and how. Note that this is unrelated to which side opened the
+/// connection or which side makes calls. Both peers can call each other over the same connection.
+//
+#[ derive( Debug, Clone ) ]
+//
+pub enum Tls
+{
+	/// Connect to a TLS server and verify its certificate for `server_name`.
+	//
+	Client
+	{
+		/// The rustls client configuration.
+		//
+		config: Arc<ClientConfig>,
+
+		/// The name the certificate of the server must be valid for.
+		//
+		server_name: ServerName<'static>,
+	},
+
+	/// Accept a TLS client.
+	//
+	Server
+	{
+		/// The rustls server configuration. If it has a client certificate verifier, clients must present
+		/// a valid certificate.
+		//
+		config: Arc<ServerConfig>,
+	},
+}
+
+
+impl Tls
+{
+	/// Run the TLS handshake over `socket`. Returns the encrypted stream and the identity of the remote, if it
+	/// presented certificates.
+	///
+	/// This returns an io error, so it can be used in the connect closure of [`Reconnect`](crate::Reconnect).
+	//
+	pub async fn wrap<S>( &self, socket: S ) -> io::Result<( TlsStream<S>, Option<PeerIdentity> )>
+
+		where S: AsyncRead + AsyncWrite + Unpin
+	{
+		let stream: TlsStream<S> = match self
+		{
+			Tls::Client{ config, server_name } =>
+			{
+				TlsConnector::from( config.clone() ).connect( server_name.clone(), socket ).await?.into()
+			}
+
+			Tls::Server{ config } =>
+			{
+				TlsAcceptor::from( config.clone() ).accept( socket ).await?.into()
+			}
+		};
+
+		let certificates = match &stream
+		{
+			TlsStream::Client( s ) => s.get_ref().1.peer_certificates(),
+			TlsStream::Server( s ) => s.get_ref().1.peer_certificates(),
+		};
+
+		let identity = certificates
+
+			.filter( |c| !c.is_empty() )
+			.map( |c| PeerIdentity{ certificates: c.iter().map( |c| c.clone().into_owned() ).collect() } )
+		;
+
+		Ok(( stream, identity ))
+	}
+
+
+	/// Run the TLS handshake over `socket` and create a peer on the encrypted stream. See
+	/// [`WireFormat::create_peer`] for the other parameters.
+	///
+	/// The identity of the remote is set on the peer, see [`Peer::identity`].
+	//
+	#[ allow( clippy::too_many_arguments ) ]
+	//
+	pub async fn create_peer<Wf: WireFormat>
+	(
+		&self                                                                ,
+		name          : impl AsRef<str>                                      ,
+		socket        : impl AsyncRead + AsyncWrite + Unpin + Send + 'static ,
+		max_size_read : usize                                                ,
+		max_size_write: usize                                                ,
+		exec          : impl PeerExec<Wf>                                    ,
+		bp            : Option< Arc<Semaphore> >                             ,
+		grace_period  : Option< Duration       >                             ,
+	)
+
+		-> Result< (Peer<Wf>, Mailbox<Peer<Wf>>, WeakAddr<Peer<Wf>>), PeerErr >
+
+	{
+		let (stream, identity) = self.wrap( socket ).await.map_err( |e|
+		{
+			let ctx = PeerErrCtx::default()
+
+				.peer_name( Arc::from( name.as_ref() ) )
+				.context  ( format!( "TLS handshake: {e}" ) )
+			;
+
+			PeerErr::Tls{ ctx }
+		})?;
+
+		let (mut peer, peer_mb, peer_addr) = Wf::create_peer( name, stream, max_size_read, max_size_write, exec, bp, grace_period )?;
+
+		peer.set_identity( identity );
+
+		Ok(( peer, peer_mb, peer_addr ))
+	}
+}
+
+
+
+/// The certificates the remote presented during the TLS handshake. They have been verified by rustls
+/// according to the configuration of [Tls].
+//
+#[ derive( Debug, Clone, PartialEq, Eq ) ]
+//
+pub struct PeerIdentity
+{
+	certificates: Vec< CertificateDer<'static> >,
+}
+
+
+impl PeerIdentity
+{
+	/// The certificate of the remote itself.
+	//
+	pub fn end_entity( &self ) -> &CertificateDer<'static>
+	{
+		&self.certificates[0]
+	}
+
+
+	/// The whole chain, starting with the end entity certificate.
+	//
+	pub fn certificates( &self ) -> &[ CertificateDer<'static> ]
+	{
+		&self.certificates
+	}
+}
+
@@ -0,0 +1,549 @@
+use
+{
+	crate :: { import::*, Peer, PeerErr, PeerExec, BoundsIn, BoundsOut, wire_format::* } ,
+};
+
+
+mod encoder;
+mod decoder;
+
+pub use encoder::*;
+pub use decoder::*;
+
+
+// The cid field is present. Without it, the cid is null.
+//
+const FLAG_CID: u8 = 0b001;
+
+// The sid is null. The sid field is omitted.
+//
+const FLAG_SID_NULL: u8 = 0b010;
+
+// The sid is full. The sid field is omitted.
+//
+const FLAG_SID_FULL: u8 = 0b100;
+
+const LEN_FLAGS: usize = 1;
+const LEN_SID  : usize = 16;
+
+// A u64 takes at most 10 bytes as a LEB128 varint.
+//
+pub(crate) const MAX_VARINT: usize = 10;
+
+
+
+/// A wire format with a compact header. The length and the cid are LEB128 varints, followed by a flags byte
+/// that says which fields are present:
+///
+/// ```text
+/// varint length | u8 flags | [ 16 byte sid ] | [ varint cid ] | payload
+/// ```
+///
+/// The length counts the bytes after the length field itself. The sid is left out when it's null or full, which
+/// is the case for connection errors and responses, and the cid is left out when it's null, which is the case
+/// for sends. So a send takes 18 bytes of header on top of the varint length, a response 1 byte plus the cid.
+/// [`WireFormat::len`] still returns the size of the entire frame, the length field included.
+///
+/// This is not compatible on the wire with [`CborWF`](crate::CborWF). Compression is not supported,
+/// [`WireFormat::set_compression`] is ignored and the handshake advertises no compression algorithm.
+//
+#[ derive( Debug, Clone, PartialEq, Eq ) ]
+//
+pub struct VarintWF
+{
+	sid : ServiceID ,
+	cid : ConnID    ,
+	data: Vec<u8>   ,
+}
+
+
+
+impl Message for VarintWF
+{
+	type Return = Result<(), PeerErr>;
+}
+
+
+impl VarintWF
+{
+	/// The flags byte for the header fields of this message.
+	//
+	fn flags( &self ) -> u8
+	{
+		let sid = match self.sid
+		{
+			x if x.is_null() => FLAG_SID_NULL ,
+			x if x.is_full() => FLAG_SID_FULL ,
+			_                => 0             ,
+		};
+
+		let cid = if self.cid.is_null() { 0 } else { FLAG_CID };
+
+		sid | cid
+	}
+
+
+	/// The number of bytes after the length field.
+	//
+	fn body_len( &self ) -> u64
+	{
+		let sid = if self.flags() & ( FLAG_SID_NULL | FLAG_SID_FULL ) == 0 { LEN_SID } else { 0 };
+		let cid = if self.cid.is_null() { 0 } else { varint_len( u64::from( self.cid ) ) };
+
+		( LEN_FLAGS + sid + cid + self.data.len() ) as u64
+	}
+
+
+	/// Serialize the entire frame, length field included, onto `out`.
+	//
+	pub(crate) fn encode( &self, out: &mut Vec<u8> )
+	{
+		let flags = self.flags();
+
+		out.reserve( self.len() as usize );
+
+		write_varint( self.body_len(), out );
+		out.push( flags );
+
+		if flags & ( FLAG_SID_NULL | FLAG_SID_FULL ) == 0
+		{
+			out.extend_from_slice( &u128::from( self.sid ).to_le_bytes() );
+		}
+
+		if flags & FLAG_CID != 0
+		{
+			write_varint( u64::from( self.cid ), out );
+		}
+
+		out.extend_from_slice( &self.data );
+	}
+
+
+	/// Deserialize a frame without its length field.
+	//
+	pub(crate) fn decode( body: &[u8] ) -> Result<Self, WireErr>
+	{
+		let err = |context: &str| WireErr::Deserialize{ context: format!( "VarintWF: {context}" ) };
+
+		let (&flags, mut rest) = body.split_first().ok_or_else( || err( "missing flags byte." ) )?;
+
+		if flags & !( FLAG_CID | FLAG_SID_NULL | FLAG_SID_FULL ) != 0
+		{
+			return Err( err( &format!( "unknown flags: {flags:#010b}." ) ) );
+		}
+
+		let sid = match flags & ( FLAG_SID_NULL | FLAG_SID_FULL )
+		{
+			FLAG_SID_NULL => ServiceID::null(),
+			FLAG_SID_FULL => ServiceID::full(),
+
+			0 =>
+			{
+				if rest.len() < LEN_SID
+				{
+					return Err( err( "not enough bytes for the sid." ) );
+				}
+
+				let mut field = [0u8; LEN_SID];
+				field.copy_from_slice( &rest[ ..LEN_SID ] );
+				rest = &rest[ LEN_SID.. ];
+
+				let sid = ServiceID::from( u128::from_le_bytes( field ) );
+
+				// Otherwise the same message could be encoded in two ways.
+				//
+				if sid.is_null() || sid.is_full()
+				{
+					return Err( err( "null or full sid must be encoded in the flags." ) );
+				}
+
+				sid
+			}
+
+			_ => return Err( err( "sid can't be both null and full." ) ),
+		};
+
+		let cid = if flags & FLAG_CID != 0
+		{
+			let (cid, used) = read_varint( rest )?.ok_or_else( || err( "not enough bytes for the cid." ) )?;
+			rest = &rest[ used.. ];
+
+			if cid == 0
+			{
+				return Err( err( "null cid must be encoded in the flags." ) );
+			}
+
+			ConnID::from( cid )
+		}
+
+		else { ConnID::null() };
+
+		Ok( Self{ sid, cid, data: rest.to_vec() } )
+	}
+}
+
+
+
+impl WireFormat for VarintWF
+{
+	/// Create a Peer directly from an asynchronous stream. See [`CborWF::create_peer`](crate::CborWF::create_peer).
+	//
+	fn create_peer
+	(
+		name          : impl AsRef<str>                                      ,
+		socket        : impl AsyncRead + AsyncWrite + Unpin + Send + 'static ,
+		max_size_read : usize                                                ,
+		max_size_write: usize                                                ,
+		exec          : impl PeerExec<VarintWF>                              ,
+		bp            : Option< Arc<Semaphore> >                             ,
+		grace_period  : Option< Duration       >                             ,
+	)
+
+		-> Result< (Peer<VarintWF>, Mailbox<Peer<VarintWF>>, WeakAddr<Peer<VarintWF>>), PeerErr >
+
+	{
+		let (stream, sink) = Self::frame( socket, max_size_read, max_size_write );
+
+		Peer::from_framed( name, stream, sink, exec, bp, grace_period )
+	}
+
+
+	/// Frame the connection with [VarintDecoder] and [VarintEncoder].
+	//
+	fn frame
+	(
+		socket        : impl AsyncRead + AsyncWrite + Unpin + Send + 'static ,
+		max_size_read : usize                                                ,
+		max_size_write: usize                                                ,
+	)
+
+		-> ( impl BoundsIn<Self>, impl BoundsOut<Self> )
+
+	{
+		let (reader, writer) = socket.split();
+
+		let stream = VarintDecoder::new( reader, max_size_read  );
+		let sink   = VarintEncoder::new( writer, max_size_write );
+
+		(stream, sink)
+	}
+
+
+	fn sid( &self ) -> ServiceID
+	{
+		self.sid
+	}
+
+
+	fn set_sid( &mut self, sid: ServiceID ) -> &mut Self
+	{
+		self.sid = sid;
+		self
+	}
+
+
+	fn cid( &self ) -> ConnID
+	{
+		self.cid
+	}
+
+
+	fn set_cid( &mut self, cid: ConnID ) -> &mut Self
+	{
+		self.cid = cid;
+		self
+	}
+
+
+	fn msg( &self ) -> &[u8]
+	{
+		&self.data
+	}
+
+
+	/// The size of the entire frame, length field included.
+	//
+	fn len( &self ) -> u64
+	{
+		let body = self.body_len();
+
+		varint_len( body ) as u64 + body
+	}
+
+
+	/// The length field is a varint, so it grows with the payload.
+	//
+	fn header_len( sid: ServiceID, cid: ConnID, payload_len: usize ) -> usize
+	{
+		let header = Self{ sid, cid, data: Vec::new() }.body_len() as usize;
+
+		varint_len( ( header + payload_len ) as u64 ) + header
+	}
+
+
+	fn with_capacity( size: usize ) -> Self
+	{
+		Self{ sid: ServiceID::null(), cid: ConnID::null(), data: Vec::with_capacity( size ) }
+	}
+
+
+	fn kind( &self ) -> WireType
+	{
+		let flags = self.flags();
+
+		match flags
+		{
+			x if x & FLAG_SID_NULL != 0 => WireType::ConnectionError ,
+			x if x & FLAG_SID_FULL != 0 => WireType::CallResponse    ,
+			x if x & FLAG_CID      != 0 => WireType::IncomingCall    ,
+			_                           => WireType::IncomingSend    ,
+		}
+	}
+}
+
+
+
+impl Default for VarintWF
+{
+	/// An empty message with null sid and cid.
+	//
+	fn default() -> Self
+	{
+		Self::with_capacity( 0 )
+	}
+}
+
+
+
+impl io::Write for VarintWF
+{
+	fn write( &mut self, buf: &[u8] ) -> io::Result<usize>
+	{
+		self.data.extend_from_slice( buf );
+
+		Ok( buf.len() )
+	}
+
+	fn flush( &mut self ) -> io::Result<()>
+	{
+		Ok(())
+	}
+}
+
+
+
+/// The number of bytes `value` takes as a LEB128 varint.
+//
+pub(crate) fn varint_len( value: u64 ) -> usize
+{
+	( 64 - value.leading_zeros() as usize ).div_ceil( 7 ).max( 1 )
+}
+
+
+/// Append `value` as a LEB128 varint.
+//
+pub(crate) fn write_varint( mut value: u64, out: &mut Vec<u8> )
+{
+	while value >= 0x80
+	{
+		out.push( value as u8 | 0x80 );
+		value >>= 7;
+	}
+
+	out.push( value as u8 );
+}
+
+
+/// Read a LEB128 varint from the start of `buf`. Returns the value and the number of bytes it took, or `None`
+/// if `buf` ends before the varint does. Varints that are longer than 10 bytes or overflow a u64 are an error.
+//
+pub(crate) fn read_varint( buf: &[u8] ) -> Result< Option<(u64, usize)>, WireErr >
+{
+	let mut value = 0u64;
+
+	for (i, &byte) in buf.iter().enumerate()
+	{
+		let bits = u64::from( byte & 0x7f );
+
+		if i == MAX_VARINT - 1 && byte > 1
+		{
+			return Err( WireErr::Deserialize{ context: "VarintWF: varint overflows a u64.".to_string() } );
+		}
+
+		value |= bits << ( 7 * i );
+
+		if byte & 0x80 == 0
+		{
+			return Ok( Some(( value, i+1 )) );
+		}
+	}
+
+	Ok( None )
+}
+
+
+
+#[ cfg(test) ]
+//
+mod tests
+{
+	// Tests:
+	//
+	// - varints round trip and reject overflow
+	// - header fields round trip and kind() for every combination of sid and cid
+	// - the header is compact
+	// - header_len matches the frame for payloads around the varint boundaries
+	// - unknown flags are rejected
+	// - TestSuite for VarintEncoder/VarintDecoder
+	//
+	use
+	{
+		super           :: { *, assert_eq                      } ,
+		crate           :: { TestSuite, MockConnection         } ,
+		futures::io     :: { WriteHalf, ReadHalf, AsyncReadExt } ,
+		async_executors :: { AsyncStd                          } ,
+		std::io         :: { Write                             } ,
+	};
+
+
+	#[test]
+	//
+	fn varint()
+	{
+		for value in [ 0, 1, 127, 128, 300, 16_383, 16_384, u32::MAX as u64, u64::MAX ]
+		{
+			let mut buf = Vec::new();
+			write_varint( value, &mut buf );
+
+			assert_eq!( varint_len( value ), buf.len() );
+			assert_eq!( Some(( value, buf.len() )), read_varint( &buf ).expect( "valid varint" ) );
+
+			// Incomplete.
+			//
+			assert_eq!( None, read_varint( &buf[ ..buf.len()-1 ] ).expect( "valid prefix" ) );
+		}
+
+		assert!( read_varint( &[ 0xff; 10 ] ).is_err() );
+		assert!( read_varint( &[ 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02 ] ).is_err() );
+	}
+
+
+	#[test]
+	//
+	fn header_fields()
+	{
+		let sid = ServiceID::from_seed( &[ 1, 2, 3 ] );
+		let cid = ConnID::random();
+
+		let cases =
+		[
+			( ServiceID::null(), ConnID::null(), WireType::ConnectionError ),
+			( ServiceID::full(), cid           , WireType::CallResponse    ),
+			( sid              , ConnID::null(), WireType::IncomingSend    ),
+			( sid              , cid           , WireType::IncomingCall    ),
+		];
+
+		for (sid, cid, kind) in cases
+		{
+			let mut wf = VarintWF::default();
+
+			wf.set_sid( sid );
+			wf.set_cid( cid );
+			wf.write_all( b"payload" ).expect( "write payload" );
+
+			let mut buf = Vec::new();
+			wf.encode( &mut buf );
+
+			assert_eq!( wf.len() as usize, buf.len() );
+
+			let (body, used) = read_varint( &buf ).expect( "valid varint" ).expect( "complete varint" );
+
+			assert_eq!( buf.len() - used, body as usize );
+
+			let decoded = VarintWF::decode( &buf[ used.. ] ).expect( "decode" );
+
+			assert_eq!( wf          , decoded        );
+			assert_eq!( sid         , decoded.sid()  );
+			assert_eq!( cid         , decoded.cid()  );
+			assert_eq!( &b"payload"[..], decoded.msg() );
+			assert_eq!( kind        , decoded.kind() );
+		}
+	}
+
+
+	#[test]
+	//
+	fn compact()
+	{
+		let mut wf = VarintWF::default();
+
+		// length and flags.
+		//
+		assert_eq!( 2, wf.len() );
+
+		wf.set_sid( ServiceID::full() );
+		wf.set_cid( ConnID::from( 5 ) );
+
+		assert_eq!( 3, wf.len() );
+
+		wf.set_sid( ServiceID::from_seed( &[ 1, 2, 3 ] ) );
+
+		assert_eq!( 19, wf.len() );
+	}
+
+
+	#[test]
+	//
+	fn header_len()
+	{
+		let sid = ServiceID::from_seed( &[ 1, 2, 3 ] );
+		let cid = ConnID::from( u64::MAX );
+
+		for size in [ 0, 1, 100, 108, 109, 127, 128, 16_000, 16_400 ]
+		{
+			let mut wf = VarintWF::default();
+
+			wf.set_sid( sid );
+			wf.set_cid( cid );
+			wf.write_all( &vec![ 0; size ] ).expect( "write payload" );
+
+			assert_eq!( wf.len() as usize - size, VarintWF::header_len( sid, cid, size ) );
+		}
+	}
+
+
+	#[test]
+	//
+	fn unknown_flags()
+	{
+		assert!( VarintWF::decode( &[ 0b1000 ] ).is_err() );
+		assert!( VarintWF::decode( &[ FLAG_SID_NULL | FLAG_SID_FULL ] ).is_err() );
+		assert!( VarintWF::decode( &[] ).is_err() );
+	}
+
+
+
+	type Reader = ReadHalf <Box<dyn MockConnection>>;
+	type Writer = WriteHalf<Box<dyn MockConnection>>;
+
+
+	fn frame( socket: Box<dyn MockConnection>, max_size: usize ) -> (VarintEncoder<Writer>, VarintDecoder<Reader>)
+	{
+		let (reader, writer) = socket.split();
+
+		let stream = VarintDecoder::new( reader, max_size );
+		let sink   = VarintEncoder::new( writer, max_size );
+
+		(sink, stream)
+	}
+
+
+	#[async_std::test]
+	//
+	async fn varint_decoder_encoder()
+	{
+		let test_suite = TestSuite::new( frame );
+
+		test_suite.run( AsyncStd ).await;
+	}
+}
@@ -0,0 +1,161 @@
+use crate::{ import::*, VarintWF, WireErr, varint_wf::{ read_varint } };
+
+
+// How many bytes we try to read at once, unless the frame we are reading is bigger.
+//
+const READ_SIZE: usize = 8 * 1024;
+
+
+
+/// Decodes a stream of bytes into a stream of [VarintWF] messages.
+///
+/// Bytes are read into one buffer that is reused for all frames. Frames that were read are consumed from the
+/// front of it.
+//
+#[ derive(Debug) ]
+//
+pub struct VarintDecoder<T>
+{
+	byte_stream: T       ,
+	buffer     : Vec<u8> ,
+	closed     : bool    ,
+	max_size   : usize   ,
+}
+
+
+impl<T> VarintDecoder<T>
+{
+	/// Create a new decoder.
+	//
+	pub fn new( byte_stream: T, max_size: usize ) -> Self
+	{
+		Self
+		{
+			byte_stream                                  ,
+			max_size                                     ,
+			buffer     : Vec::with_capacity( READ_SIZE ) ,
+			closed     : false                           ,
+		}
+	}
+
+
+	/// The length of the frame at the start of the buffer, length field included, and the length of the length
+	/// field, if we have read the length field.
+	//
+	fn frame_len( &self ) -> Result< Option<(usize, usize)>, WireErr >
+	{
+		let Some( (body, used) ) = read_varint( &self.buffer )? else { return Ok( None ) };
+
+		// If it doesn't fit in usize, it's definitely bigger than max_size.
+		//
+		let len = usize::try_from( body ).unwrap_or( usize::MAX ).saturating_add( used );
+
+		Ok( Some(( len, used )) )
+	}
+}
+
+
+
+impl<T> Stream for VarintDecoder<T>
+
+	where T: AsyncRead + Unpin
+{
+	type Item = Result<VarintWF, WireErr>;
+
+
+	fn poll_next( mut self: Pin<&mut Self>, cx: &mut Context<'_> ) -> Poll< Option<Self::Item> >
+	{
+		if self.closed
+		{
+			return Poll::Ready( None );
+		}
+
+		loop
+		{
+			let len = match self.frame_len()
+			{
+				Ok ( len ) => len,
+
+				Err( err ) =>
+				{
+					self.closed = true;
+
+					return Poll::Ready( Some(Err( err )) );
+				}
+			};
+
+			if let Some( (len, used) ) = len
+			{
+				if len > self.max_size
+				{
+					self.closed = true;
+
+					let err = WireErr::MessageSizeExceeded
+					{
+						size    : len                         ,
+						max_size: self.max_size               ,
+						context : "VarintDecoder".to_string() ,
+					};
+
+					return Poll::Ready( Some(Err( err )) );
+				}
+
+				// We have an entire frame.
+				//
+				if self.buffer.len() >= len
+				{
+					let wf = VarintWF::decode( &self.buffer[ used..len ] );
+
+					self.buffer.drain( ..len );
+
+					if wf.is_err() { self.closed = true; }
+
+					return Poll::Ready( Some( wf ) );
+				}
+			}
+
+
+			// Read more. Make sure there is room for at least the rest of the current frame.
+			//
+			let filled = self.buffer.len();
+			let wanted = len.map_or( 1, |(len, _)| len ).saturating_sub( filled ).max( READ_SIZE );
+
+			self.buffer.resize( filled + wanted, 0 );
+
+			let this = &mut *self;
+
+			match Pin::new( &mut this.byte_stream ).poll_read( cx, &mut this.buffer[ filled.. ] )
+			{
+				Poll::Pending =>
+				{
+					self.buffer.truncate( filled );
+
+					return Poll::Pending;
+				}
+
+				// The connection was closed. If it was in the middle of a frame, the frame is lost.
+				//
+				Poll::Ready( Ok(0) ) =>
+				{
+					self.buffer.truncate( filled );
+					self.closed = true;
+
+					return Poll::Ready( None );
+				}
+
+				Poll::Ready( Ok(read) ) =>
+				{
+					self.buffer.truncate( filled + read );
+				}
+
+				Poll::Ready( Err(e) ) =>
+				{
+					self.buffer.truncate( filled );
+					self.closed = true;
+
+					return Poll::Ready( Some(Err( WireErr::from(e) )) );
+				}
+			}
+		}
+	}
+}
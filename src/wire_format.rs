@@ -1,4 +1,4 @@
-use crate::{ import::*, PeerErr, Peer, PeerExec } ;
+use crate::{ import::*, PeerErr, Peer, PeerExec, BoundsIn, BoundsOut, Compression, Algorithms, MessageEncoder, MessageDecoder } ;
 
 mod unique_id  ;
 mod conn_id    ;
@@ -39,6 +39,8 @@ pub trait WireFormat: Message< Return = Result<(), PeerErr> > + Default + Clone
 	/// attempts to write a message that is too big, your codec should return [WireErr::MessageSizeExceeded].
 	/// The same in case your reader detects a message on the network that is too big.
 	//
+	#[ allow( clippy::result_large_err ) ]
+	//
 	fn create_peer
 	(
 		name          : impl AsRef<str>                                      ,
@@ -56,6 +58,80 @@ pub trait WireFormat: Message< Return = Result<(), PeerErr> > + Default + Clone
 	;
 
 
+	/// Frame a connection that implements [AsyncRead]/[AsyncWrite] into a [Stream]/[Sink] over your message type.
+	/// This is used by [Reconnect](crate::Reconnect) to frame new connections after the previous one was lost.
+	/// The same rules about respecting the max sizes as for `create_peer` apply.
+	//
+	fn frame
+	(
+		socket        : impl AsyncRead + AsyncWrite + Unpin + Send + 'static ,
+		max_size_read : usize                                                ,
+		max_size_write: usize                                                ,
+	)
+
+	-> ( impl BoundsIn<Self>, impl BoundsOut<Self> )
+
+		where Self: Sized
+	;
+
+
+	/// Create a Peer from a message oriented transport, like a WebSocket. Every message of the transport is one
+	/// frame and there is no length field, see [MessageEncoder] and [MessageDecoder]. The other parameters are the
+	/// same as for `create_peer` and the max sizes still apply.
+	///
+	/// Since the transport isn't a byte stream, [Reconnect](crate::Reconnect) can't frame new connections for these
+	/// peers.
+	//
+	#[ allow( clippy::result_large_err ) ]
+	//
+	fn create_peer_messages<T>
+	(
+		name          : impl AsRef<str>          ,
+		transport     : T                        ,
+		max_size_read : usize                    ,
+		max_size_write: usize                    ,
+		exec          : impl PeerExec<Self>      ,
+		bp            : Option< Arc<Semaphore> > ,
+		grace_period  : Option< Duration >       ,
+	)
+
+	-> Result< (Peer<Self>, Mailbox<Peer<Self>>, WeakAddr<Peer<Self>>), PeerErr >
+
+		where Self                         : Sized + Send + 'static                                           ,
+		      T                            : Sink< Vec<u8> > + Stream< Item = Vec<u8> > + Unpin + Send + 'static ,
+		      <T as Sink<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static                          ,
+	{
+		let (stream, sink) = Self::frame_messages( transport, max_size_read, max_size_write );
+
+		Peer::from_framed( name, stream, sink, exec, bp, grace_period )
+	}
+
+
+	/// Frame a message oriented transport into a [Stream]/[Sink] over your message type. See
+	/// [`WireFormat::create_peer_messages`].
+	//
+	fn frame_messages<T>
+	(
+		transport     : T     ,
+		max_size_read : usize ,
+		max_size_write: usize ,
+	)
+
+	-> ( impl BoundsIn<Self>, impl BoundsOut<Self> )
+
+		where Self                         : Sized + Send + 'static                                           ,
+		      T                            : Sink< Vec<u8> > + Stream< Item = Vec<u8> > + Unpin + Send + 'static ,
+		      <T as Sink<Vec<u8>>>::Error: std::error::Error + Send + Sync + 'static                          ,
+	{
+		let (sink, stream) = transport.split();
+
+		let stream = MessageDecoder::new( stream, max_size_read  );
+		let sink   = MessageEncoder::new( sink  , max_size_write );
+
+		(stream, sink)
+	}
+
+
 
 	/// The service id of this message. When coming in over the wire, this identifies
 	/// which service you are calling. A ServiceID should be unique for a given service.
@@ -87,6 +163,63 @@ pub trait WireFormat: Message< Return = Result<(), PeerErr> > + Default + Clone
 	//
 	fn len( &self ) -> u64;
 
+	/// The number of bytes a frame with this sid and cid adds to a payload of `payload_len` bytes. Used to size
+	/// fragments without building a frame of the full size.
+	///
+	/// The default implementation measures an empty frame, which is correct as long as the size of the header doesn't
+	/// depend on the length of the payload. Wire formats with a variable length field should override it.
+	//
+	fn header_len( sid: ServiceID, cid: ConnID, _payload_len: usize ) -> usize
+	{
+		let mut wf = Self::with_capacity( 0 );
+
+		wf.set_sid( sid );
+		wf.set_cid( cid );
+
+		wf.len() as usize
+	}
+
+	/// The compression algorithms the decoder of this wire format can decompress. The peer never advertises
+	/// anything else in the [`Handshake`](crate::Handshake), so the remote won't send frames we can't decode.
+	///
+	/// Compression is optional, so the default implementation returns an empty set.
+	//
+	fn compression() -> Algorithms
+	{
+		Algorithms::default()
+	}
+
+	/// Ask the encoder to compress the payload of this message when it goes out over the network. The peer
+	/// sets this when compression was negotiated with the remote, see [`Peer::set_compression`].
+	///
+	/// Compression is optional, so the default implementation ignores it.
+	//
+	fn set_compression( &mut self, _compression: Option<Compression> ) -> &mut Self
+	{
+		self
+	}
+
+	/// Ask the encoder to add an integrity checksum to this message when it goes out over the network. The peer
+	/// sets this when checksums were negotiated with the remote, see [`Peer::set_checksum`].
+	///
+	/// Checksums are optional, so the default implementation ignores it.
+	//
+	fn set_checksum( &mut self, _checksum: bool ) -> &mut Self
+	{
+		self
+	}
+
+	/// Whether this message has a checksum. For incoming messages, whether the decoder verified one, for outgoing
+	/// messages whether the encoder will add one. Once the remote seals its frames, the peer refuses incoming frames
+	/// without checksum.
+	///
+	/// Checksums are optional, so the default implementation returns false.
+	//
+	fn has_checksum( &self ) -> bool
+	{
+		false
+	}
+
 	/// Make sure there is enough room for the serialized payload to avoid frequent re-allocation.
 	//
 	fn with_capacity( size: usize ) -> Self;
@@ -1,21 +1,14 @@
-use
-{
-	crate :: { import::*                },
-	super :: { unique_id::UniqueID      },
-};
+use crate::{ import::* };
 
-/// A unique identifier for a service that is exposed to other processes. This will allow
-/// identifying the type to which the payload needs to be deserialized and the actor to which
-/// this message is to be delivered.
+/// An identifier for a call, used to match the response to the request.
 ///
-/// Ideally we want to use 128 bits here to have globally unique identifiers with little chance
-/// of collision, but we use xxhash which for the moment only supports 64 bit.
+/// It only needs to be unique among the calls that are in flight on a connection, so 64 bits will do.
 //
 #[ derive( Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize ) ]
 //
 pub struct ConnID
 {
-	inner: UniqueID,
+	inner: u64,
 }
 
 
@@ -25,7 +18,8 @@ impl ConnID
 	//
 	pub fn random() -> Self
 	{
-		Self { inner: UniqueID::random() }
+		let mut rng = rand::thread_rng();
+		Self { inner: rng.gen::<u64>() }
 	}
 
 
@@ -34,7 +28,7 @@ impl ConnID
 	//
 	pub fn null() -> Self
 	{
-		Self{ inner: UniqueID::null() }
+		Self{ inner: 0 }
 	}
 
 
@@ -42,7 +36,7 @@ impl ConnID
 	//
 	pub fn is_null( &self ) -> bool
 	{
-		self.inner.is_null()
+		self.inner == 0
 	}
 }
 
@@ -55,7 +49,7 @@ impl From< ConnID > for u64
 {
 	fn from( cid: ConnID ) -> u64
 	{
-		cid.inner.into()
+		cid.inner
 	}
 }
 
@@ -66,7 +60,7 @@ impl From< u64 > for ConnID
 {
 	fn from( id: u64 ) -> Self
 	{
-		Self { inner: UniqueID::from( id ) }
+		Self { inner: id }
 	}
 }
 
@@ -85,7 +79,10 @@ impl fmt::Debug for ConnID
 {
 	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
 	{
-		self.inner.fmt( f )
+		// Padding so that it's always showing the leading zero if the first byte is single digit.
+		// The 0x prefix is counted, hence 18 instead of 16.
+		//
+		write!( f, "{:#018x}", self.inner )
 	}
 }
 
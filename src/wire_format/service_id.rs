@@ -1,26 +1,47 @@
 use
 {
-	crate :: { import::*           } ,
+	crate :: { import::*, WireErr  } ,
 	super :: { unique_id::UniqueID } ,
 };
 
 
+// The names of the services. The reserved values are registered from the start, so services that collide
+// with them are detected as well.
+//
 static SERVICES: SyncLazy<Mutex< HashMap<ServiceID, &'static str> >> = SyncLazy::new( ||
-
-	Mutex::new( HashMap::new() )
-);
+{
+	let reserved =
+	[
+		( ServiceID::null         (), "thespis_remote::null"          ),
+		( ServiceID::full         (), "thespis_remote::full"          ),
+		( ServiceID::discovery    (), "thespis_remote::discovery"     ),
+		( ServiceID::handshake    (), "thespis_remote::handshake"     ),
+		( ServiceID::ping         (), "thespis_remote::ping"          ),
+		( ServiceID::pong         (), "thespis_remote::pong"          ),
+		( ServiceID::deadline     (), "thespis_remote::deadline"      ),
+		( ServiceID::cancel       (), "thespis_remote::cancel"        ),
+		( ServiceID::stream_end   (), "thespis_remote::stream_end"    ),
+		( ServiceID::channel_open (), "thespis_remote::channel_open"  ),
+		( ServiceID::channel_item (), "thespis_remote::channel_item"  ),
+		( ServiceID::channel_close(), "thespis_remote::channel_close" ),
+		( ServiceID::fragment     (), "thespis_remote::fragment"      ),
+	];
+
+	Mutex::new( reserved.into_iter().collect() )
+});
 
 /// A unique identifier for a service that is exposed to other processes. This will allow
 /// identifying the type to which the payload needs to be deserialized and the actor to which
 /// this message is to be delivered.
 ///
-/// Ideally we want to use 128 bits here to have globally unique identifiers with little chance
-/// of collision, but we use xxhash which for the moment only supports 64 bit, so we hash the
-/// namespace and typename separately both to 64 bits.
+/// It's 128 bits, the namespace and typename are hashed with xxh3. Since namespaces are shared
+/// between many services, [`ServiceID::register_service`] reports two different names that hash to the
+/// same ServiceID. The `service_map!` macro registers all its services when creating the service map.
 ///
-/// 2 values are reserved, all zero's and all one's are used as special values by Peer to
-/// detect error conditions. If ever your namespace + typename would hash to one of these,
-/// please change them.
+/// Some values are reserved. All zero's and all one's are used as special values by Peer to
+/// detect error conditions. The values right below all one's are used for control frames
+/// like [`ServiceID::handshake`] and [`ServiceID::discovery`]. If ever your namespace + typename would hash to one of these,
+/// registering it fails, please change them.
 //
 #[ derive( Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize ) ]
 //
@@ -78,14 +99,121 @@ impl ServiceID
 	}
 
 
+	/// The ServiceID of the frame in which a peer advertises the services it provides.
+	/// See [`Peer::set_discovery`](crate::Peer::set_discovery).
+	//
+	pub fn discovery() -> Self
+	{
+		Self{ inner: UniqueID::from( u128::MAX - 1 ) }
+	}
+
+
+	/// The ServiceID of the frame with which a peer starts the handshake.
+	/// See [`Peer::set_handshake`](crate::Peer::set_handshake).
+	//
+	pub fn handshake() -> Self
+	{
+		Self{ inner: UniqueID::from( u128::MAX - 2 ) }
+	}
+
+
+	/// The ServiceID of heartbeat pings. See [`Peer::set_heartbeat`](crate::Peer::set_heartbeat).
+	//
+	pub fn ping() -> Self
+	{
+		Self{ inner: UniqueID::from( u128::MAX - 3 ) }
+	}
+
+
+	/// The ServiceID of the answer to heartbeat pings.
+	//
+	pub fn pong() -> Self
+	{
+		Self{ inner: UniqueID::from( u128::MAX - 4 ) }
+	}
+
+
+	/// The ServiceID of the frame which tells the remote the deadline of the call that follows.
+	/// See [`Call::timeout`](crate::Call::timeout).
+	//
+	pub fn deadline() -> Self
+	{
+		Self{ inner: UniqueID::from( u128::MAX - 5 ) }
+	}
+
+
+	/// The ServiceID of the frame which tells the remote we are no longer interested in the response to a call.
+	//
+	pub fn cancel() -> Self
+	{
+		Self{ inner: UniqueID::from( u128::MAX - 6 ) }
+	}
+
+
+	/// The ServiceID of the frame which marks the end of the response to a call to a streaming service.
+	//
+	pub fn stream_end() -> Self
+	{
+		Self{ inner: UniqueID::from( u128::MAX - 7 ) }
+	}
+
+
+	/// The ServiceID of the frame which tells the remote that the call that follows opens a bidirectional channel.
+	/// Channel frames carry the ConnID of the channel.
+	//
+	pub fn channel_open() -> Self
+	{
+		Self{ inner: UniqueID::from( u128::MAX - 8 ) }
+	}
+
+
+	/// The ServiceID of frames that carry an item the caller sends on a bidirectional channel.
+	//
+	pub fn channel_item() -> Self
+	{
+		Self{ inner: UniqueID::from( u128::MAX - 9 ) }
+	}
+
+
+	/// The ServiceID of the frame which tells the remote the caller won't send any more items on a bidirectional channel.
+	//
+	pub fn channel_close() -> Self
+	{
+		Self{ inner: UniqueID::from( u128::MAX - 10 ) }
+	}
+
+
+	/// The ServiceID of the fragments of a message that was too big to send in one frame.
+	/// See [`Peer::set_fragmentation`](crate::Peer::set_fragmentation).
+	//
+	pub fn fragment() -> Self
+	{
+		Self{ inner: UniqueID::from( u128::MAX - 11 ) }
+	}
+
+
+	/// Whether this is one of the frames of a bidirectional channel. They have a ConnID, but they aren't calls.
+	//
+	pub(crate) fn is_channel( &self ) -> bool
+	{
+		*self == Self::channel_open() || *self == Self::channel_item() || *self == Self::channel_close()
+	}
+
+
 	/// Register the typename a ServiceID refers to so it can be used later for log output.
 	/// the `service_map!` macro does this automatically for you.
+	///
+	/// Registering the same name twice is fine. If another name is registered for this ServiceID already,
+	/// the two names collide and [`WireErr::ServiceIDCollision`] is returned. The reserved values are
+	/// registered with names in the `thespis_remote` namespace.
 	//
-	pub fn register_service( sid: ServiceID, name: &'static str )
+	pub fn register_service( sid: ServiceID, name: &'static str ) -> Result<(), WireErr>
 	{
-		let mut s = SERVICES.lock();
+		let registered = *SERVICES.lock().entry( sid ).or_insert( name );
 
-		s.entry( sid ).or_insert( name );
+		if registered == name { return Ok(()) }
+
+		Err( WireErr::ServiceIDCollision{ sid, name, registered } )
 	}
 
 
@@ -97,27 +225,69 @@ impl ServiceID
 
 		s.get( &sid ).copied()
 	}
+
+
+	/// Write all registered names as a table, one `<sid as 32 hex digits> <name>` per line, sorted by name.
+	/// Tools that don't link your services, like the `thespis_inspect` binary, can load it with
+	/// [`ServiceID::import_names`] to show names instead of numbers.
+	//
+	pub fn export_names( mut out: impl io::Write ) -> io::Result<()>
+	{
+		let mut names: Vec<_> = SERVICES.lock().iter().map( |(sid, name)| (*sid, *name) ).collect();
+
+		names.sort_by_key( |(_, name)| *name );
+
+		for (sid, name) in names
+		{
+			writeln!( out, "{:032x} {}", u128::from( sid ), name )?;
+		}
+
+		Ok(())
+	}
+
+
+	/// Register the names of a table written by [`ServiceID::export_names`]. Names that aren't registered yet
+	/// are leaked, so only use this for tables of bounded size. Collisions are reported like with
+	/// [`ServiceID::register_service`].
+	//
+	pub fn import_names( input: impl io::BufRead ) -> Result<(), WireErr>
+	{
+		for line in input.lines()
+		{
+			let line = line?;
+			let line = line.trim();
+
+			if line.is_empty() { continue }
+
+			let invalid = || WireErr::Deserialize{ context: format!( "ServiceID name table: invalid line: {line}" ) };
+
+			let (sid, name) = line.split_once( ' ' ).ok_or_else( invalid )?;
+			let sid         = ServiceID::from( u128::from_str_radix( sid, 16 ).map_err( |_| invalid() )? );
+
+			if Self::service_name( sid ) == Some( name ) { continue }
+
+			Self::register_service( sid, Box::leak( name.to_string().into_boxed_str() ) )?;
+		}
+
+		Ok(())
+	}
 }
 
 
-/// Internally is also represented as Bytes, so you just get a copy.
-//
-impl From< ServiceID > for u64
+impl From< ServiceID > for u128
 {
-	fn from( sid: ServiceID ) -> u64
+	fn from( sid: ServiceID ) -> u128
 	{
 		sid.inner.into()
 	}
 }
 
 
-/// The object will just keep the bytes as internal representation, no copies will be made
-//
-impl From< u64 > for ServiceID
+impl From< u128 > for ServiceID
 {
-	fn from( bytes: u64 ) -> Self
+	fn from( id: u128 ) -> Self
 	{
-		Self { inner: UniqueID::from( bytes ) }
+		Self { inner: UniqueID::from( id ) }
 	}
 }
 
@@ -156,3 +326,88 @@ impl fmt::LowerHex for ServiceID
 		fmt::LowerHex::fmt( &self.inner, f )
 	}
 }
+
+
+
+#[cfg(test)]
+//
+mod tests
+{
+	// What's tested:
+	// 1. Registering the same name twice is fine.
+	// 2. Registering another name for the same sid is a collision.
+	// 3. The reserved values are registered.
+	// 4. Names can be exported to a table and imported again.
+	//
+	use super::{ *, assert_eq };
+
+
+	#[test]
+	//
+	fn register_twice()
+	{
+		let sid = ServiceID::from( 0x0f0e0d0c0b0a0908_0706050403020100 );
+
+		assert_eq!( Ok(()), ServiceID::register_service( sid, "tests::Twice" ) );
+		assert_eq!( Ok(()), ServiceID::register_service( sid, "tests::Twice" ) );
+
+		assert_eq!( Some( "tests::Twice" ), ServiceID::service_name( sid ) );
+	}
+
+
+	#[test]
+	//
+	fn collision()
+	{
+		let sid = ServiceID::from( 0x0001020304050607_08090a0b0c0d0e0f );
+
+		assert_eq!( Ok(()), ServiceID::register_service( sid, "tests::First" ) );
+
+		assert_eq!
+		(
+			Err( WireErr::ServiceIDCollision{ sid, name: "tests::Second", registered: "tests::First" } ),
+			ServiceID::register_service( sid, "tests::Second" ),
+		);
+
+		assert_eq!( Some( "tests::First" ), ServiceID::service_name( sid ) );
+	}
+
+
+	#[test]
+	//
+	fn reserved()
+	{
+		let err = ServiceID::register_service( ServiceID::handshake(), "tests::Handshake" );
+
+		assert_eq!
+		(
+			Err( WireErr::ServiceIDCollision{ sid: ServiceID::handshake(), name: "tests::Handshake", registered: "thespis_remote::handshake" } ),
+			err,
+		);
+	}
+
+
+	#[test]
+	//
+	fn export_import()
+	{
+		let sid = ServiceID::from( 0x0a0b0c0d0e0f0001_0203040506070809 );
+
+		assert_eq!( Ok(()), ServiceID::register_service( sid, "tests::Exported" ) );
+
+		let mut table = Vec::new();
+		ServiceID::export_names( &mut table ).expect( "export names" );
+
+		let table = String::from_utf8( table ).expect( "utf8" );
+
+		assert!( table.contains( "0a0b0c0d0e0f00010203040506070809 tests::Exported\n" ) );
+		assert!( table.contains( "thespis_remote::handshake\n" ) );
+
+		let other = ServiceID::from( 0x1111_2222 );
+
+		assert_eq!( Ok(()), ServiceID::import_names( format!( "{table}{:032x} tests::Imported\n", 0x1111_2222 ).as_bytes() ) );
+		assert_eq!( Some( "tests::Imported" ), ServiceID::service_name( other ) );
+
+		assert!( matches!( ServiceID::import_names( &b"nonsense"[..] ), Err( WireErr::Deserialize{..} ) ) );
+	}
+}
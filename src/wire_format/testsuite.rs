@@ -1,26 +1,51 @@
 //! Tests for the wire format:
 //!
 //! - Encoder
-//!   -
+//!   ✔ refuse messages bigger than the max size.
 //!
 //! - Decoder
 //!   ✔ send everything at once
 //!   ✔ send data in small chunks, half the length, then the rest of the message in several parts.
 //!   ✔ randomly intersperse Pending...needs this functionality in futures_ringbuf.
-//!   - send incorrect data (eg. length) and verify the errors.
-//!   - close connection halfway through and verify errors.
-//!   - try to exceed the allowed length.
-//!   - fuzz test
+//!   ✔ send incorrect data (eg. length) and verify the errors.
+//!   ✔ close connection halfway through and verify errors.
+//!   ✔ try to exceed the allowed length.
+//!   ✔ fuzz test
+//!
+//! - Both
+//!   ✔ random frames survive a round trip.
+//!
+//! The random tests print their seed before they start, so a failure can be reproduced by setting the
+//! environment variable `THESPIS_SEED` to that seed.
 //!
 use
 {
 	pretty_assertions :: { assert_eq                                 } ,
 	crate             :: { import::*, *                              } ,
 	futures           :: { join, task::{ LocalSpawn, LocalSpawnExt } } ,
+	futures           :: { AsyncWriteExt, AsyncReadExt               } ,
 	futures_ringbuf   :: { Endpoint, Dictator, Sketchy               } ,
+	rand              :: { rngs::StdRng, SeedableRng                 } ,
 };
 
 
+// The max size used by the adversarial tests.
+//
+const MAX_SIZE: usize = 64;
+
+// How many random cases the fuzz and round trip tests try.
+//
+const RANDOM_CASES: usize = 500;
+
+// Decoding stops after this many frames, so a decoder that invents frames can't make a test run forever.
+//
+const MAX_FRAMES: usize = 64;
+
+// The environment variable that fixes the seed of the random tests.
+//
+const SEED_VAR: &str = "THESPIS_SEED";
+
+
 
 /// Represents something that behaves like a TCP connection.
 //
@@ -77,6 +102,11 @@ impl<Wf, Si, St> TestSuite<Wf, Si, St>
 		self.send_all().await;
 		self.send_chunked( exec ).await;
 		self.read_pending().await;
+		self.incorrect_length().await;
+		self.closed_halfway().await;
+		self.exceed_max_size().await;
+		self.fuzz().await;
+		self.roundtrip().await;
 	}
 
 
@@ -204,4 +234,243 @@ impl<Wf, Si, St> TestSuite<Wf, Si, St>
 			assert_eq!( received.msg(), msg      );
 		}
 	}
+
+
+	/// Length fields that don't match the data. The first byte is set to every possible value and the rest is zeroed,
+	/// which covers lengths that are smaller than the header for little endian and varint length fields. The decoder must
+	/// not panic, must end once the connection is closed and must not return frames bigger than the max size.
+	//
+	pub async fn incorrect_length( &self )
+	{
+		for first in 0..=u8::MAX
+		{
+			let mut data = vec![ 0u8; MAX_SIZE ];
+			data[0] = first;
+
+			for frame in self.decode( &data, MAX_SIZE ).await.into_iter().flatten()
+			{
+				assert!( frame.len() as usize <= MAX_SIZE, "incorrect_length: frame bigger than max size, first byte: {first}" );
+			}
+		}
+	}
+
+
+	/// The connection is closed before an entire frame was sent. Since the frame is incomplete, the decoder must not
+	/// return it. It can return an error or just end.
+	//
+	pub async fn closed_halfway( &self )
+	{
+		let data = self.encode( Self::message(), MAX_SIZE ).await;
+
+		for cut in 0..data.len()
+		{
+			let frames = self.decode( &data[ ..cut ], MAX_SIZE ).await;
+
+			assert!( frames.iter().all( Result::is_err ), "closed_halfway: got a frame from {cut} out of {} bytes", data.len() );
+		}
+	}
+
+
+	/// Both the encoder and the decoder must refuse frames bigger than the max size with [WireErr::MessageSizeExceeded].
+	//
+	pub async fn exceed_max_size( &self )
+	{
+		let mut wf = Wf::with_capacity( MAX_SIZE );
+		wf.set_sid( ServiceID::from_seed( &[1, 2, 3 ] ) );
+		wf.set_cid( ConnID::random() );
+		wf.write_all( &[ 7; MAX_SIZE ] ).expect( "be able to write serialized message" );
+
+		// The encoder.
+		//
+		let (trans_a, _trans_b) = Endpoint::pair( 4 * MAX_SIZE, 4 * MAX_SIZE );
+		let (mut sink_a, _    ) = (self.factory)( Box::new(trans_a), MAX_SIZE );
+
+		match sink_a.send( wf.clone() ).await
+		{
+			Err( WireErr::MessageSizeExceeded{ max_size, .. } ) => assert_eq!( MAX_SIZE, max_size ),
+			other => panic!( "exceed_max_size: encoder should refuse the frame, got: {:?}", other.map( |_| "Ok" ) ),
+		}
+
+		// The decoder.
+		//
+		let data   = self.encode( wf, 4 * MAX_SIZE ).await;
+		let frames = self.decode( &data, MAX_SIZE ).await;
+
+		match frames.first()
+		{
+			Some( Err( WireErr::MessageSizeExceeded{ max_size, .. } ) ) => assert_eq!( MAX_SIZE, *max_size ),
+			other => panic!( "exceed_max_size: decoder should refuse the frame, got: {:?}", other.map( |f| f.as_ref().map( |_| "a frame" ) ) ),
+		}
+	}
+
+
+	/// Random data and valid frames with random bytes changed. The decoder must not panic, must end once the
+	/// connection is closed and must not return frames bigger than the max size.
+	//
+	pub async fn fuzz( &self )
+	{
+		let seed    = Self::seed( "fuzz" );
+		let mut rng = StdRng::seed_from_u64( seed );
+		let valid   = self.encode( Self::message(), MAX_SIZE ).await;
+
+		for _ in 0..RANDOM_CASES
+		{
+			let data: Vec<u8> = if rng.gen()
+			{
+				(0..rng.gen_range( 0..4 * MAX_SIZE )).map( |_| rng.gen() ).collect()
+			}
+
+			else
+			{
+				let mut data = valid.clone();
+
+				for _ in 0..rng.gen_range( 1..4 )
+				{
+					let idx = rng.gen_range( 0..data.len() );
+					data[ idx ] = rng.gen();
+				}
+
+				data
+			};
+
+			for frame in self.decode( &data, MAX_SIZE ).await.into_iter().flatten()
+			{
+				assert!( frame.len() as usize <= MAX_SIZE, "fuzz: frame bigger than max size, seed: {seed}" );
+			}
+		}
+	}
+
+
+	/// Frames with a random sid, cid and payload that fit in the max size are received exactly like they were sent.
+	//
+	pub async fn roundtrip( &self )
+	{
+		let seed    = Self::seed( "roundtrip" );
+		let mut rng = StdRng::seed_from_u64( seed );
+
+		for _ in 0..RANDOM_CASES
+		{
+			let sid = match rng.gen_range( 0..4 )
+			{
+				0 => ServiceID::null(),
+				1 => ServiceID::full(),
+				_ => ServiceID::from( rng.gen::<u128>() ),
+			};
+
+			let cid = match rng.gen_range( 0..4 )
+			{
+				0 => ConnID::null(),
+				1 => ConnID::from( u64::MAX ),
+				_ => ConnID::from( rng.gen::<u64>() ),
+			};
+
+			let msg: Vec<u8> = (0..rng.gen_range( 0..MAX_SIZE )).map( |_| rng.gen() ).collect();
+
+			let mut wf = Wf::with_capacity( msg.len() );
+			wf.set_sid( sid );
+			wf.set_cid( cid );
+			wf.write_all( &msg ).expect( "be able to write serialized message" );
+
+			if wf.len() as usize > MAX_SIZE { continue }
+
+			let (trans_a, trans_b) = Endpoint::pair( 4 * MAX_SIZE, 4 * MAX_SIZE );
+			let (mut sink_a, _  )  = (self.factory)( Box::new(trans_a), MAX_SIZE );
+			let (_, mut stream_b)  = (self.factory)( Box::new(trans_b), MAX_SIZE );
+
+			let (send, received) = join!
+			(
+				sink_a.send( wf.clone() ),
+				stream_b.next(),
+			);
+
+			send.unwrap_or_else( |e| panic!( "roundtrip: send on sink: {e}, seed: {seed}" ) );
+
+			let received = received
+
+				.unwrap_or_else( ||  panic!( "roundtrip: stream ended, seed: {seed}" ) )
+				.unwrap_or_else( |e| panic!( "roundtrip: receive: {e}, seed: {seed}" ) )
+			;
+
+			assert_eq!( received.len(), wf.len(), "seed: {seed}" );
+			assert_eq!( received.sid(), sid     , "seed: {seed}" );
+			assert_eq!( received.cid(), cid     , "seed: {seed}" );
+			assert_eq!( received.msg(), &msg[..], "seed: {seed}" );
+		}
+	}
+
+
+	// The seed for a random test. Taken from the environment if set, so failures can be reproduced.
+	// It's printed before the test starts, as a panic in the codec won't mention it.
+	//
+	fn seed( test: &str ) -> u64
+	{
+		let seed = match std::env::var( SEED_VAR )
+		{
+			Ok(s)  => s.parse().unwrap_or_else( |_| panic!( "{SEED_VAR} should be a u64, got: {s}" ) ),
+			Err(_) => Dictator::new_seed(),
+		};
+
+		println!( "{test}: seed: {seed}, set {SEED_VAR}={seed} to reproduce." );
+
+		seed
+	}
+
+
+	// A small valid message.
+	//
+	fn message() -> Wf
+	{
+		let mut wf = Wf::default();
+		wf.set_sid( ServiceID::from_seed( &[1, 2, 3 ] ) );
+		wf.set_cid( ConnID::random() );
+		wf.write_all( "message".as_bytes() ).expect( "be able to write serialized message" );
+
+		wf
+	}
+
+
+	// The bytes the encoder puts on the wire for a message.
+	//
+	async fn encode( &self, wf: Wf, max_size: usize ) -> Vec<u8>
+	{
+		let (trans_a, mut trans_b) = Endpoint::pair( 4 * max_size, 4 * max_size );
+		let (mut sink_a, _)        = (self.factory)( Box::new(trans_a), max_size );
+
+		sink_a.send( wf ).await.expect( "send on sink" );
+
+		// Encoders don't have to close the transport when they are closed, but dropping them closes the endpoint.
+		//
+		drop( sink_a );
+
+		let mut data = Vec::new();
+		trans_b.read_to_end( &mut data ).await.expect( "read encoded frame" );
+
+		data
+	}
+
+
+	// Feed data to a decoder and close the connection. Returns what the decoder returns, up to and including
+	// the first error.
+	//
+	async fn decode( &self, data: &[u8], max_size: usize ) -> Vec< Result<Wf, WireErr> >
+	{
+		let (mut trans_a, trans_b) = Endpoint::pair( data.len().max( 1 ), 64 );
+		let (_, mut stream_b)      = (self.factory)( Box::new(trans_b), max_size );
+
+		trans_a.write_all( data ).await.expect( "write to transport" );
+		trans_a.close().await.expect( "close transport" );
+
+		let mut frames = Vec::new();
+
+		while let Some( frame ) = stream_b.next().await
+		{
+			let failed = frame.is_err();
+
+			frames.push( frame );
+
+			if failed || frames.len() == MAX_FRAMES { break }
+		}
+
+		frames
+	}
 }
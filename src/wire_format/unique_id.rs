@@ -5,41 +5,27 @@ use crate::{ import::* };
 /// identifying the type to which the payload needs to be deserialized and the actor to which
 /// this message is to be delivered.
 ///
-/// Ideally we want to use 128 bits here to have globally unique identifiers with little chance
-/// of collision, but we use xxhash which for the moment only supports 64 bit.
+/// It's 128 bits, so globally unique identifiers have little chance of collision.
+/// u128 still isn't well supported everywhere (WASM), so use 2 u64.
 //
 #[ derive( Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize ) ]
 //
 pub(crate) struct UniqueID
 {
-	id: u64,
+	hi: u64,
+	lo: u64,
 }
 
 
 impl UniqueID
 {
-	/// Generate a random ID
-	//
-	pub(crate) fn random() -> Self
-	{
-		let mut rng = rand::thread_rng();
-		Self { id: rng.gen::<u64>() }
-	}
-
-
-	/// Seed the UniqueID. The data will be hashed with xxhash. The UniqueID is 128 bits and currently
-	/// xxhash only supports hashing to 64bit output. We take 2 seeds, one for each 64bit hash.
-	/// Parameter high will be the most significant bytes in the output in little endian (msb is highest offset).
+	/// Seed the UniqueID. The data will be hashed with xxh3 to 128 bits.
 	///
 	/// An identical input here should always give an identical UniqueID.
 	//
 	pub(crate) fn from_seed( data: &[u8] ) -> Self
 	{
-		let mut h = XxHash64::default();
-
-		h.write( data );
-
-		Self { id: h.finish() }
+		Self::from( xxh3::hash128( data ) )
 	}
 
 
@@ -48,17 +34,16 @@ impl UniqueID
 	//
 	pub(crate) fn null() -> Self
 	{
-		Self { id: 0 }
+		Self { hi: 0, lo: 0 }
 	}
 
 
 	/// And full UniqueID. Reserved for use by thespis_remote, would usually be all
 	/// one bytes.
-	/// u128 still isn't well supported everywhere (WASM), so use 2 u64;
 	//
 	pub(crate) fn full() -> Self
 	{
-		Self { id: u64::MAX }
+		Self { hi: u64::MAX, lo: u64::MAX }
 	}
 
 
@@ -66,7 +51,7 @@ impl UniqueID
 	//
 	pub(crate) fn is_null( &self ) -> bool
 	{
-		self.id == 0
+		*self == Self::null()
 	}
 
 
@@ -74,30 +59,26 @@ impl UniqueID
 	//
 	pub(crate) fn is_full( &self ) -> bool
 	{
-		self.id == u64::MAX
+		*self == Self::full()
 	}
 }
 
 
 
-/// Internally is also represented as Bytes, so you just get a copy.
-//
-impl From< UniqueID > for u64
+impl From< UniqueID > for u128
 {
-	fn from( uid: UniqueID ) -> u64
+	fn from( uid: UniqueID ) -> u128
 	{
-		uid.id
+		u128::from( uid.hi ) << 64 | u128::from( uid.lo )
 	}
 }
 
 
-/// The object will just keep the bytes as internal representation, no copies will be made
-//
-impl From< u64 > for UniqueID
+impl From< u128 > for UniqueID
 {
-	fn from( id: u64 ) -> Self
+	fn from( id: u128 ) -> Self
 	{
-		Self { id }
+		Self { hi: ( id >> 64 ) as u64, lo: id as u64 }
 	}
 }
 
@@ -118,9 +99,9 @@ impl fmt::Debug for UniqueID
 	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
 	{
 		// Padding so that it's always showing the leading zero if the first byte is single digit.
-		// The 0x prefix is counted, hence 18 instead of 16.
+		// The 0x prefix is counted, hence 34 instead of 32.
 		//
-		write!( f, "{:#018x}", self )?;
+		write!( f, "{:#034x}", self )?;
 		Ok(())
 	}
 }
@@ -130,7 +111,7 @@ impl fmt::LowerHex for UniqueID
 {
 	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result
 	{
-		self.id.fmt( f )
+		u128::from( *self ).fmt( f )
 	}
 }
 
@@ -143,6 +124,7 @@ mod tests
 	// 1. Identical input data should give identical hash.
 	// 2. Creating full/null values create values that get detected by is_null, is_full predicates.
 	// 3. debug output.
+	// 4. conversion to and from u128.
 	//
 	use super::{ *, assert_eq };
 
@@ -182,10 +164,19 @@ mod tests
 	//
 	fn debug()
 	{
-		let sid = UniqueID{ id: 0x0f0e0d0c0b0a0908 };
+		let sid = UniqueID::from( 0x0f0e0d0c0b0a0908_0706050403020100 );
 
-		assert_eq!( "0x0f0e0d0c0b0a0908", &format!( "{:?}", sid ) );
+		assert_eq!( "0x0f0e0d0c0b0a09080706050403020100", &format!( "{:?}", sid ) );
 	}
-}
 
 
+	#[test]
+	//
+	fn u128()
+	{
+		let id  = 0x0f0e0d0c0b0a0908_0706050403020100;
+		let sid = UniqueID::from( id );
+
+		assert_eq!( id, u128::from( sid ) );
+	}
+}
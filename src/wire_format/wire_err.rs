@@ -1,4 +1,4 @@
-use crate::{ import::* };
+use crate::{ import::*, ServiceID };
 
 
 /// Errors that can happen in thespis_impl.
@@ -46,7 +46,42 @@ pub enum WireErr
 	{
 		/// The ErrorKind
 		//
-		kind: std::io::ErrorKind
+		kind: io::ErrorKind
+	},
+
+
+	/// Two different service names hash to the same [ServiceID]. Messages for one would be delivered to the other,
+	/// so one of them has to be renamed.
+	//
+	ServiceIDCollision
+	{
+		/// The ServiceID both names hash to.
+		//
+		sid: ServiceID,
+
+		/// The name that was being registered.
+		//
+		name: &'static str,
+
+		/// The name that was registered before.
+		//
+		registered: &'static str,
+	},
+
+
+	/// The checksum of an incoming frame doesn't match its content, or the frame has no checksum although the remote
+	/// seals its frames. The connection will be closed because the stream integrity can no longer be assumed. See
+	/// [`Peer::set_checksum`](crate::Peer::set_checksum).
+	//
+	Checksum
+	{
+		/// The checksum in the frame. `None` if the frame has no checksum.
+		//
+		expected: Option<u64>,
+
+		/// The checksum of the data we received. `None` if the frame has no checksum.
+		//
+		actual: Option<u64>,
 	},
 }
 
@@ -72,15 +107,27 @@ impl fmt::Display for WireErr
 			WireErr::Io{ kind } =>
 
 				write!( f, "Io: {:?}", kind ),
+
+			WireErr::ServiceIDCollision{ sid, name, registered } =>
+
+				write!( f, "The services {} and {} both have ServiceID {:#034x}. Please rename one of them.", name, registered, sid ),
+
+			WireErr::Checksum{ expected: Some(expected), actual: Some(actual) } =>
+
+				write!( f, "Frame corrupted: checksum in the frame is {:#018x}, the data hashes to {:#018x}. The connection will be closed.", expected, actual ),
+
+			WireErr::Checksum{..} =>
+
+				write!( f, "Frame without checksum, but the remote seals its frames. The connection will be closed." ),
 		}
 	}
 }
 
 
 
-impl From< std::io::Error > for WireErr
+impl From< io::Error > for WireErr
 {
-	fn from( inner: std::io::Error ) -> WireErr
+	fn from( inner: io::Error ) -> WireErr
 	{
 		WireErr::Io{ kind: inner.kind() }
 	}
@@ -1,6 +1,6 @@
 /// Type of message.
 //
-#[ derive(Debug, Copy, Clone) ]
+#[ derive(Debug, Copy, Clone, PartialEq, Eq) ]
 //
 pub enum WireType
 {
@@ -71,7 +71,7 @@ pub struct Parallel
 
 impl Handler< Show > for Parallel
 {
-	fn handle( &mut self, _: Show ) -> Return<i64> { Box::pin( async move
+	fn handle( &mut self, _: Show ) -> Return<'_, i64> { Box::pin( async move
 	{
 		self.sum.call( Show ).await.expect( "call sum" )
 	})}
@@ -0,0 +1,104 @@
+// Tests:
+//
+// - ✔ Peers using BytesWF can call each other.
+// - ✔ BytesWF and CborWF are compatible on the wire.
+//
+mod common;
+
+use common::{ *, import::{ *, assert_eq } };
+
+
+mod bytes
+{
+	use super::*;
+
+	service_map!
+	(
+		namespace  : remotes   ;
+		wire_format: BytesWF   ;
+		services   : Add, Show ;
+	);
+}
+
+
+mod cbor
+{
+	use super::*;
+
+	service_map!
+	(
+		namespace  : remotes   ;
+		wire_format: CborWF    ;
+		services   : Add, Show ;
+	);
+}
+
+
+
+// Start a server with a BytesWF peer that provides Add and Show.
+//
+async fn server( socket: Endpoint ) -> JoinHandle< MailboxEnd<Peer<BytesWF>> >
+{
+	let sum = Addr::builder( "sum" ).spawn( Sum(5), &AsyncStd ).expect( "spawn actor mailbox" );
+
+	let mut sm = bytes::remotes::Services::new();
+	sm.register_handler::<Add >( sum.clone_box() );
+	sm.register_handler::<Show>( sum.clone_box() );
+
+	let (mut peer, peer_mb, _) = BytesWF::create_peer( "server", socket, 1024, 1024, AsyncStd, None, None ).expect( "spawn peer" );
+
+	peer.register_services( Arc::new( sm ) );
+
+	AsyncStd.spawn_handle( peer_mb.start(peer) ).expect( "start mailbox of Peer" )
+}
+
+
+
+// Peers using BytesWF can call each other.
+//
+#[async_std::test]
+//
+async fn bytes_wf_peers()
+{
+	let (server_sock, client_sock) = Endpoint::pair( 64, 64 );
+
+	let handle = server( server_sock ).await;
+
+	let (peer, peer_mb, mut peer_addr) = BytesWF::create_peer( "client", client_sock, 1024, 1024, AsyncStd, None, None ).expect( "spawn peer" );
+	let _client = AsyncStd.spawn_handle( peer_mb.start(peer) ).expect( "start mailbox of Peer" );
+
+	let mut addr = bytes::remotes::RemoteAddr::new( peer_addr.clone() );
+
+	addr.send( Add(2) ).await.expect( "send Add" );
+
+	assert_eq!( 7, addr.call( Show ).await.expect( "call Show" ) );
+
+	peer_addr.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	handle.await;
+}
+
+
+
+// BytesWF and CborWF are compatible on the wire.
+//
+#[async_std::test]
+//
+async fn cbor_to_bytes_wf()
+{
+	let (server_sock, client_sock) = Endpoint::pair( 64, 64 );
+
+	let handle = server( server_sock ).await;
+
+	let (mut peer, _) = peer_connect( client_sock, AsyncStd, "client" ).await;
+
+	let mut addr = cbor::remotes::RemoteAddr::new( peer.clone() );
+
+	addr.send( Add(3) ).await.expect( "send Add" );
+
+	assert_eq!( 8, addr.call( Show ).await.expect( "call Show" ) );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	handle.await;
+}
@@ -0,0 +1,215 @@
+// Tests:
+//
+// - ✔ Dropping the future of a call cancels it on the remote and releases the backpressure.
+// - ✔ Relays pass the cancellation on to the provider.
+// - ✔ A cancel frame behind a call that waits for backpressure is still processed.
+// - ✔ A call canceled while it waits for backpressure is never processed.
+//
+mod common;
+
+use
+{
+	common        :: { *, import::{ *, assert_eq }                             } ,
+	std           :: { time::Duration, sync::atomic::{ AtomicUsize, Ordering } } ,
+	futures       :: { AsyncReadExt                                            } ,
+	futures_timer :: { Delay                                                   } ,
+	tokio::sync   :: { Semaphore                                               } ,
+};
+
+
+// Never answers.
+//
+#[ derive(Actor) ] struct Stuck{ calls: Arc<AtomicUsize> }
+
+impl Handler<Add> for Stuck
+{
+	fn handle( &mut self, _msg: Add ) -> Return<'_, ()> { async move
+	{
+		self.calls.fetch_add( 1, Ordering::SeqCst );
+
+		futures::future::pending::<()>().await;
+
+	}.boxed() }
+}
+
+
+
+service_map!
+(
+	namespace  : cancels ;
+	wire_format: CborWF  ;
+	services   : Add, Show ;
+);
+
+
+
+// A provider that processes one call at a time. Add never answers, Show does.
+//
+async fn provider( socket: Endpoint ) -> (Arc<AtomicUsize>, JoinHandle< MailboxEnd<Peer> >)
+{
+	let calls = Arc::new( AtomicUsize::new(0) );
+	let stuck = Addr::builder( "stuck" ).spawn( Stuck{ calls: calls.clone() }, &AsyncStd ).expect( "spawn actor mailbox" );
+	let sum   = Addr::builder( "sum"   ).spawn( Sum(5)                        , &AsyncStd ).expect( "spawn actor mailbox" );
+
+	let mut sm = cancels::Services::new();
+	sm.register_handler::<Add >( stuck.clone_box() );
+	sm.register_handler::<Show>( sum  .clone_box() );
+
+	let bp = Some( Arc::new( Semaphore::new(1) ) );
+
+	let (mut peer, peer_mb, _) = CborWF::create_peer( "provider", socket, 1024, 1024, AsyncStd, bp, None ).expect( "spawn peer" );
+
+	peer.register_services( Arc::new( sm ) );
+
+	let handle = AsyncStd.spawn_handle( peer_mb.start(peer) ).expect( "start mailbox of Peer" );
+
+	(calls, handle)
+}
+
+
+
+// Start a call to Add, drop it once the provider is processing it and verify Show still gets through.
+//
+async fn cancel( peer: WeakAddr<Peer>, calls: Arc<AtomicUsize> )
+{
+	let mut addr  = cancels::RemoteAddr::new( peer );
+	let mut addr2 = addr.clone();
+
+	let call = AsyncStd.spawn_handle( async move { addr2.call( Add(1) ).await } ).expect( "spawn call" );
+
+	while calls.load( Ordering::SeqCst ) == 0
+	{
+		Delay::new( Duration::from_millis(1) ).await;
+	}
+
+	drop( call );
+
+	// Without the cancellation this can't get a backpressure slot.
+	//
+	assert_eq!( 5, addr.call_with_timeout( Show, Duration::from_secs(5) ).await.expect( "call Show" ) );
+}
+
+
+
+// Dropping the future of a call cancels it on the remote and releases the backpressure.
+//
+#[async_std::test]
+//
+async fn cancel_call()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+
+	let (calls, handle) = provider( server ).await;
+	let (mut peer, _)   = peer_connect( client, AsyncStd, "client" ).await;
+
+	cancel( peer.clone(), calls ).await;
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	handle.await;
+}
+
+
+
+// Relays pass the cancellation on to the provider.
+//
+#[async_std::test]
+//
+async fn cancel_relayed()
+{
+	let (server, relay_out) = Endpoint::pair( 64, 64 );
+	let (relay_in, client ) = Endpoint::pair( 64, 64 );
+
+	let (calls, handle)  = provider( server ).await;
+	let (mut provider, _) = peer_connect( relay_out, AsyncStd, "relay_to_provider" ).await;
+
+	let add  = <Add  as cancels::Service>::sid();
+	let show = <Show as cancels::Service>::sid();
+
+	let rm = RelayMap::new( ServiceHandler::Address( Box::new( provider.clone() ) ), vec![ add, show ] );
+
+	let (_, _, relay_handle) = peer_listen( relay_in, Arc::new( rm ), AsyncStd, "relay" ).await;
+	let (mut peer, _)        = peer_connect( client, AsyncStd, "client" ).await;
+
+	cancel( peer.clone(), calls ).await;
+
+	peer    .send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+	provider.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	relay_handle.await;
+	handle      .await;
+}
+
+
+
+// A mock remote connected to the provider, which has called Add, so the only backpressure slot is taken.
+//
+async fn mock_stuck() -> (MockRemote, ConnID, JoinHandle< MailboxEnd<Peer> >)
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+
+	let (calls, handle) = provider( server ).await;
+	let (reader, writer) = client.split();
+
+	let mut mock = MockRemote::new( Decoder::new( reader, 1024 ), Encoder::new( writer, 1024 ) );
+
+	let add = mock.call( <Add as cancels::Service>::sid(), &Add(1) ).await.expect( "call Add" );
+
+	while calls.load( Ordering::SeqCst ) == 0
+	{
+		Delay::new( Duration::from_millis(1) ).await;
+	}
+
+	(mock, add, handle)
+}
+
+
+fn cancel_frame( cid: ConnID ) -> CborWF
+{
+	MockRemote::<CborWF>::message( ServiceID::cancel(), &cid, ConnID::null() ).expect( "serialize cancel" )
+}
+
+
+
+// A cancel frame behind a call that waits for backpressure is still processed.
+//
+#[async_std::test]
+//
+async fn cancel_behind_call()
+{
+	let (mut mock, add, handle) = mock_stuck().await;
+
+	let show = mock.call( <Show as cancels::Service>::sid(), &Show ).await.expect( "call Show" );
+
+	mock.send_raw( cancel_frame( add ) ).await.expect( "send cancel" );
+
+	assert_eq!( 5, mock.recv_response::<u64>( show ).await );
+
+	mock.close().await.expect( "close mock" );
+	handle.await;
+}
+
+
+
+// A call canceled while it waits for backpressure is never processed.
+//
+#[async_std::test]
+//
+async fn cancel_waiting_call()
+{
+	let (mut mock, add, handle) = mock_stuck().await;
+
+	let show = mock.call( <Show as cancels::Service>::sid(), &Show ).await.expect( "call Show" );
+
+	mock.send_raw( cancel_frame( show ) ).await.expect( "send cancel" );
+	mock.send_raw( cancel_frame( add  ) ).await.expect( "send cancel" );
+
+	// The first response is for this call, not for the canceled one.
+	//
+	let show2 = mock.call( <Show as cancels::Service>::sid(), &Show ).await.expect( "call Show" );
+
+	assert_eq!( 5, mock.recv_response::<u64>( show2 ).await );
+
+	mock.close().await.expect( "close mock" );
+	handle.await;
+}
@@ -0,0 +1,244 @@
+// Tests:
+//
+// - ✔ Frames in both directions get recorded.
+// - ✔ Replaying a capture into a fresh peer makes it send the same frames.
+// - ✔ Reading something that isn't a capture, or a truncated capture fails.
+// - ✔ A capture that can't be written doesn't hold up the connection.
+//
+mod common;
+
+use
+{
+	common :: { *, import::{ *, assert_eq } } ,
+	std    :: { sync::Mutex, io             } ,
+};
+
+
+// A capture in memory that can still be read after the recorder is gone.
+//
+#[ derive( Clone, Default ) ]
+//
+struct Buffer( Arc< Mutex< Vec<u8> > > );
+
+impl io::Write for Buffer
+{
+	fn write( &mut self, buf: &[u8] ) -> io::Result<usize>
+	{
+		self.0.lock().unwrap().extend_from_slice( buf );
+		Ok( buf.len() )
+	}
+
+	fn flush( &mut self ) -> io::Result<()>
+	{
+		Ok(())
+	}
+}
+
+
+// Run a server that provides Add and Show, recording its side of the connection, and have a client send
+// Add(5), Add(3) and call Show.
+//
+async fn record() -> Capture
+{
+	let buffer                         = Buffer::default();
+	let recorder                       = Recorder::new( buffer.clone() ).expect( "create recorder" );
+	let ((in_a, out_a), (in_b, out_b)) = loopback::<CborWF>( 1024 );
+
+	let (incoming, outgoing) = recorder.tap( in_a, out_a );
+
+	let (mut server, server_mb, _) = Peer::from_framed( "server", incoming, outgoing, AsyncStd, None, None ).expect( "spawn peer" );
+	server.register_services( Arc::new( add_show_sum() ) );
+	let server_handle = AsyncStd.spawn_handle( server_mb.start(server) ).expect( "start mailbox of Peer" );
+
+	let (client, client_mb, mut client_addr) = Peer::from_framed( "client", in_b, out_b, AsyncStd, None, None ).expect( "spawn peer" );
+	let client_handle = AsyncStd.spawn_handle( client_mb.start(client) ).expect( "start mailbox of Peer" );
+
+	let mut addr = remotes::RemoteAddr::new( client_addr.clone() );
+
+	addr.send( Add(5) ).await.expect( "send Add" );
+	addr.send( Add(3) ).await.expect( "send Add" );
+
+	assert_eq!( 8, addr.call( Show ).await.expect( "call Show" ) );
+
+	client_addr.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	client_handle.await;
+	server_handle.await;
+
+	recorder.flush().await;
+
+	let data = buffer.0.lock().unwrap().clone();
+
+	Capture::read( data.as_slice() ).expect( "read capture" )
+}
+
+
+
+// Frames in both directions get recorded.
+//
+#[async_std::test]
+//
+async fn record_frames()
+{
+	let capture = record().await;
+	let records = capture.records();
+
+	let directions: Vec<_> = records.iter().map( |r| r.direction ).collect();
+
+	assert_eq!( vec![ Direction::Incoming, Direction::Incoming, Direction::Incoming, Direction::Outgoing ], directions );
+
+	assert_eq!( <Add  as remotes::Service>::sid(), records[0].sid );
+	assert_eq!( <Add  as remotes::Service>::sid(), records[1].sid );
+	assert_eq!( <Show as remotes::Service>::sid(), records[2].sid );
+
+	// The response goes back to the same connection id.
+	//
+	assert_eq!( records[2].cid, records[3].cid );
+
+	assert!( records.windows(2).all( |w| w[0].elapsed <= w[1].elapsed ) );
+}
+
+
+
+// Replaying a capture into a fresh peer makes it send the same frames.
+//
+#[async_std::test]
+//
+async fn replay()
+{
+	let capture = record().await;
+
+	let (incoming, outgoing, replayed) = capture.replay::<CborWF>();
+
+	let (mut server, server_mb, _) = Peer::from_framed( "replay", incoming, outgoing, AsyncStd, None, None ).expect( "spawn peer" );
+	server.register_services( Arc::new( add_show_sum() ) );
+
+	// The peer stops when the replay stream ends.
+	//
+	server_mb.start( server ).await;
+
+	let strip = |records: Vec<Record>| -> Vec<_>
+	{
+		records.into_iter().map( |r| (r.sid, r.cid, r.msg) ).collect()
+	};
+
+	let expected = capture.records().iter().filter( |r| r.direction == Direction::Outgoing ).cloned().collect();
+
+	assert_eq!( strip( expected ), strip( replayed.sent() ) );
+}
+
+
+
+// Reading something that isn't a capture, or a truncated capture fails.
+//
+#[async_std::test]
+//
+async fn invalid_capture()
+{
+	match Capture::read( &b"not a capture"[..] )
+	{
+		Err( WireErr::Deserialize{..} ) => {}
+		e => unreachable!( "Should be WireErr::Deserialize, got: {:?}", e ),
+	}
+
+	let buffer   = Buffer::default();
+	let recorder = Recorder::new( buffer.clone() ).expect( "create recorder" );
+	let ((incoming, outgoing), _other) = loopback::<CborWF>( 1024 );
+
+	let (_, mut outgoing) = recorder.tap( incoming, outgoing );
+
+	let mut wf = CborWF::with_capacity( 5 );
+	wf.write_all( &[ 1, 2, 3, 4, 5 ] ).expect( "write to wf" );
+
+	futures::SinkExt::send( &mut outgoing, wf ).await.expect( "send frame" );
+	recorder.flush().await;
+
+	let mut data = buffer.0.lock().unwrap().clone();
+
+	assert_eq!( 1, Capture::read( data.as_slice() ).expect( "read capture" ).records().len() );
+
+	data.pop();
+
+	match Capture::read( data.as_slice() )
+	{
+		Err( WireErr::Deserialize{..} ) => {}
+		e => unreachable!( "Should be WireErr::Deserialize, got: {:?}", e ),
+	}
+}
+
+
+
+// A capture that blocks until the test lets it write.
+//
+struct Blocked
+{
+	buffer : Buffer                      ,
+	release: std::sync::mpsc::Receiver<()> ,
+}
+
+impl io::Write for Blocked
+{
+	fn write( &mut self, buf: &[u8] ) -> io::Result<usize>
+	{
+		// The header is written before the writer thread starts, don't wait for that one.
+		//
+		if !self.buffer.0.lock().unwrap().is_empty()
+		{
+			let _ = self.release.recv();
+		}
+
+		self.buffer.write( buf )
+	}
+
+	fn flush( &mut self ) -> io::Result<()>
+	{
+		Ok(())
+	}
+}
+
+
+// A capture that can't be written doesn't hold up the connection.
+//
+#[async_std::test]
+//
+async fn slow_capture()
+{
+	let buffer        = Buffer::default();
+	let (release, rx) = std::sync::mpsc::channel();
+	let recorder      = Recorder::new( Blocked{ buffer: buffer.clone(), release: rx } ).expect( "create recorder" );
+
+	let ((in_a, out_a), (in_b, out_b)) = loopback::<CborWF>( 1024 );
+	let (incoming, outgoing)           = recorder.tap( in_a, out_a );
+
+	let (mut server, server_mb, _) = Peer::from_framed( "server", incoming, outgoing, AsyncStd, None, None ).expect( "spawn peer" );
+	server.register_services( Arc::new( add_show_sum() ) );
+	let server_handle = AsyncStd.spawn_handle( server_mb.start(server) ).expect( "start mailbox of Peer" );
+
+	let (client, client_mb, mut client_addr) = Peer::from_framed( "client", in_b, out_b, AsyncStd, None, None ).expect( "spawn peer" );
+	let client_handle = AsyncStd.spawn_handle( client_mb.start(client) ).expect( "start mailbox of Peer" );
+
+	let mut addr = remotes::RemoteAddr::new( client_addr.clone() );
+
+	addr.send( Add(5) ).await.expect( "send Add" );
+	assert_eq!( 5, addr.call( Show ).await.expect( "call Show" ) );
+
+	client_addr.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	client_handle.await;
+	server_handle.await;
+
+	// Nothing but the header got written so far.
+	//
+	assert_eq!( 8, buffer.0.lock().unwrap().len() );
+
+	for _ in 0..3
+	{
+		release.send(()).expect( "release writer" );
+	}
+
+	recorder.flush().await;
+
+	let data = buffer.0.lock().unwrap().clone();
+
+	assert_eq!( 3, Capture::read( data.as_slice() ).expect( "read capture" ).records().len() );
+}
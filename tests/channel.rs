@@ -0,0 +1,247 @@
+// Tests:
+//
+// - ✔ Items sent on a channel reach the handler, the responses come back and the channel ends when
+//     the caller closes the sink.
+// - ✔ Closing the connection ends the responses with an error.
+// - ✔ The remote stops reading when the handler has CHANNEL_CREDIT items it hasn't taken.
+// - ✔ A channel whose call doesn't come in within the timeout is forgotten.
+//
+mod common;
+
+use
+{
+	common        :: { *, import::{ *, assert_eq }                                } ,
+	futures       :: { stream::{ self, BoxStream }, future::{ ready, select, Either } } ,
+	futures_timer :: { Delay                                                        } ,
+	serde         :: { Serialize, Deserialize                                       } ,
+	std           :: { sync::Mutex                                                  } ,
+	futures       :: { AsyncReadExt                                                 } ,
+};
+
+
+// Sends back the running total of the numbers the caller sends.
+//
+#[ derive( Serialize, Deserialize, Debug ) ] pub struct Total;
+
+impl Message for Total { type Return = BoxStream<'static, usize>; }
+
+
+#[ derive(Actor) ] struct Adder;
+
+impl Handler< Channel<Total, usize> > for Adder
+{
+	#[async_fn] fn handle( &mut self, msg: Channel<Total, usize> ) -> BoxStream<'static, usize>
+	{
+		msg.incoming.scan( 0, |total, x|
+		{
+			*total += x;
+
+			ready( Some( *total ) )
+
+		}).boxed()
+	}
+}
+
+
+
+// Keeps the items of the channel for the test to take.
+//
+#[ derive(Actor) ] struct Hoard
+{
+	incoming: Arc<Mutex< Option<BoxStream<'static, usize>> >>,
+}
+
+impl Handler< Channel<Total, usize> > for Hoard
+{
+	#[async_fn] fn handle( &mut self, msg: Channel<Total, usize> ) -> BoxStream<'static, usize>
+	{
+		*self.incoming.lock().expect( "lock" ) = Some( msg.incoming );
+
+		stream::pending().boxed()
+	}
+}
+
+
+
+service_map!
+(
+	namespace  : channels       ;
+	wire_format: CborWF         ;
+	services   : Add, Show      ;
+	streams    :                ;
+	channels   : Total => usize ;
+);
+
+
+
+// Connect to a server that has an Adder.
+//
+async fn connect() -> (channels::RemoteAddr, WeakAddr<Peer>, JoinHandle< MailboxEnd<Peer> >)
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+
+	let adder = Addr::builder( "adder" ).spawn( Adder, &AsyncStd ).expect( "spawn actor mailbox" );
+
+	let mut sm = channels::Services::new();
+	sm.register_channel_handler::<Total>( adder.clone_box() );
+
+	let (_, _, handle) = peer_listen( server, Arc::new( sm ), AsyncStd, "server" ).await;
+	let (peer, _)      = peer_connect( client, AsyncStd, "client" ).await;
+
+	(channels::RemoteAddr::new( peer.clone() ), peer, handle)
+}
+
+
+
+// Items sent on a channel reach the handler, the responses come back and the channel ends when
+// the caller closes the sink.
+//
+#[async_std::test]
+//
+async fn channel_items()
+{
+	let (mut addr, mut peer, handle) = connect().await;
+
+	let (mut tx, mut rx) = addr.open_channel( Total ).await.expect( "open channel" );
+
+	tx.send( 1 ).await.expect( "send on channel" );
+	assert_eq!( 1, rx.next().await.unwrap().expect( "channel item" ) );
+
+	tx.send( 2 ).await.expect( "send on channel" );
+	tx.send( 3 ).await.expect( "send on channel" );
+	tx.close().await.expect( "close channel" );
+
+	let rest: Vec<usize> = rx.map( |item| item.expect( "channel item" ) ).collect().await;
+
+	assert_eq!( vec![ 3, 6 ], rest );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	handle.await;
+}
+
+
+
+// Closing the connection ends the responses with an error.
+//
+#[async_std::test]
+//
+async fn channel_close_connection()
+{
+	let (mut addr, mut peer, handle) = connect().await;
+
+	let (mut tx, mut rx) = addr.open_channel( Total ).await.expect( "open channel" );
+
+	tx.send( 1 ).await.expect( "send on channel" );
+	assert_eq!( 1, rx.next().await.unwrap().expect( "channel item" ) );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	assert!( matches!( rx.next().await, Some( Err( PeerErr::ConnectionClosed{..} ) ) ) );
+	assert!( rx.next().await.is_none() );
+
+	handle.await;
+}
+
+
+
+// The remote stops reading when the handler has CHANNEL_CREDIT items it hasn't taken.
+//
+#[async_std::test]
+//
+async fn channel_credit()
+{
+	let (mut mock, (incoming, outgoing)) = MockRemote::<CborWF>::pair( 1024 );
+
+	let hoarded = Arc::new( Mutex::new( None ) );
+	let hoard   = Addr::builder( "hoard" ).spawn( Hoard{ incoming: hoarded.clone() }, &AsyncStd ).expect( "spawn actor mailbox" );
+	let sum     = Addr::builder( "sum"   ).spawn( Sum(0)                              , &AsyncStd ).expect( "spawn actor mailbox" );
+
+	let mut sm = channels::Services::new();
+	sm.register_channel_handler::<Total>( hoard.clone_box() );
+	sm.register_handler        ::<Show >( sum  .clone_box() );
+
+	let peer           = Peer::from_framed( "server", incoming, outgoing, AsyncStd, None, None ).expect( "spawn peer" );
+	let (_, _, handle) = start_peer( peer, |peer| peer.register_services( Arc::new( sm ) ) ).await;
+
+	let cid = ConnID::from( 1 );
+
+	mock.send_raw( MockRemote::<CborWF>::frame( ServiceID::channel_open(), cid, &[] ) ).await.expect( "open channel" );
+	mock.send_raw( MockRemote::<CborWF>::message( <Total as channels::ChannelService>::sid(), &Total, cid ).expect( "serialize" ) ).await.expect( "call Total" );
+
+	for i in 0..=CHANNEL_CREDIT
+	{
+		mock.send_raw( MockRemote::<CborWF>::message( ServiceID::channel_item(), &i, cid ).expect( "serialize" ) ).await.expect( "send item" );
+	}
+
+	let show = mock.call( <Show as channels::Service>::sid(), &Show ).await.expect( "call Show" );
+
+	// The call stays behind the item that has no credit.
+	//
+	match select( Box::pin( mock.recv() ), Delay::new( Duration::from_millis(200) ) ).await
+	{
+		Either::Left (( frame, _ )) => panic!( "the peer kept reading: {frame:?}" ),
+		Either::Right(( ()   , _ )) => {}
+	}
+
+	let mut items = hoarded.lock().expect( "lock" ).take().expect( "the handler got the channel" );
+
+	assert_eq!( Some( 0 ), items.next().await );
+	assert_eq!( 0, mock.recv_response::<i64>( show ).await );
+
+	mock.close().await.expect( "close mock" );
+	handle.await;
+}
+
+
+
+// A channel whose call doesn't come in within the timeout is forgotten. The call that comes in late is treated
+// as a normal call, which this service doesn't accept.
+//
+#[async_std::test]
+//
+async fn forget_opening()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+
+	let adder = Addr::builder( "adder" ).spawn( Adder, &AsyncStd ).expect( "spawn actor mailbox" );
+
+	let mut sm = channels::Services::new();
+	sm.register_channel_handler::<Total>( adder.clone_box() );
+
+	let (mut peer_addr, _, handle) = config_peer( server, "server", 1024, |peer|
+	{
+		peer.register_services( Arc::new( sm ) );
+		peer.set_timeout( Duration::from_millis(20) );
+
+	}).await;
+
+	let (reader, writer) = client.split();
+	let mut mock: MockRemote<CborWF> = MockRemote::new( Decoder::new( reader, 1024 ), Encoder::new( writer, 1024 ) );
+
+	// The first call of the mock will get ConnID 1.
+	//
+	mock.send_raw( MockRemote::<CborWF>::frame( ServiceID::channel_open(), ConnID::from(1), &[] ) ).await.expect( "send channel open" );
+
+	// No other frame comes in to trigger forgetting it.
+	//
+	Delay::new( Duration::from_millis(100) ).await;
+
+	let cid = mock.call( <Total as channels::ChannelService>::sid(), &Total ).await.expect( "call Total" );
+
+	assert_eq!( ConnID::from(1), cid );
+
+	// Had the channel been opened, no error would come back.
+	//
+	let err = match select( mock.recv_error().boxed(), Delay::new( Duration::from_secs(5) ) ).await
+	{
+		Either::Left(( err, _ )) => err,
+		Either::Right(_)         => panic!( "The call opened the channel that should have been forgotten." ),
+	};
+
+	assert!( !matches!( err, ConnectionError::Timeout{..} ), "unexpected error: {err:?}" );
+
+	peer_addr.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	handle.await;
+}
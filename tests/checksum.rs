@@ -0,0 +1,269 @@
+// Tests:
+//
+// - ✔ Peers that negotiated checksums can call each other.
+// - ✔ The encoder adds the checksum and the decoders verify and strip it. A corrupted frame gives WireErr::Checksum.
+// - ✔ The max size of the decoders doesn't count the checksum.
+// - ✔ A peer that receives a corrupted frame reports the error and closes the connection.
+// - ✔ Once the remote seals its frames, a frame without checksum closes the connection.
+//
+mod common;
+
+use
+{
+	common  :: { *, import::{ *, assert_eq }       } ,
+	serde   :: { Serialize, Deserialize            } ,
+	futures :: { SinkExt, AsyncReadExt, io::Cursor } ,
+};
+
+
+#[ derive( Serialize, Deserialize, Debug ) ] pub struct Echo( String );
+
+impl Message for Echo { type Return = String; }
+
+
+#[ derive(Actor) ] struct Echoer;
+
+impl Handler<Echo> for Echoer
+{
+	#[async_fn] fn handle( &mut self, msg: Echo ) -> String
+	{
+		msg.0
+	}
+}
+
+
+service_map!
+(
+	namespace  : check    ;
+	wire_format: CborWF   ;
+	services   : Echo     ;
+);
+
+
+
+// Serve Echo and add checksums to outgoing frames if the remote supports it.
+//
+fn sealed( peer: &mut Peer )
+{
+	let echo = Addr::builder( "echo" ).spawn( Echoer, &AsyncStd ).expect( "spawn actor mailbox" );
+
+	let mut sm = check::Services::new();
+	sm.register_handler::<Echo>( echo.clone_box() );
+
+	peer.register_services( Arc::new( sm ) );
+	peer.set_handshake( Handshake::new( 2048, 2048 ).features( Features{ checksum: true, ..Default::default() } ) );
+	peer.set_checksum( true );
+}
+
+
+// Decode the wire with each decoder.
+//
+async fn decode( wire: Vec<u8>, max_size: usize ) -> Vec< Result<CborWF, WireErr> >
+{
+	let mut decoder  = Decoder        ::new( Cursor::new( wire.clone() ), max_size );
+	let mut noheap   = DecoderNoHeap  ::new( Cursor::new( wire.clone() ), max_size );
+	let mut buffered = DecoderBuffered::new( Cursor::new( wire         ), max_size );
+
+	vec!
+	[
+		decoder .next().await.expect( "a frame" ),
+		noheap  .next().await.expect( "a frame" ),
+		buffered.next().await.expect( "a frame" ),
+	]
+}
+
+
+
+// Peers that negotiated checksums can call each other.
+//
+#[async_std::test]
+//
+async fn peers()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+
+	let (_       , mut server_evts, server_handle) = config_peer( server, "server", 2048, sealed ).await;
+	let (mut peer, mut client_evts, client_handle) = config_peer( client, "client", 2048, sealed ).await;
+
+	let features = Features{ checksum: true, ..Default::default() };
+	let expect   = Negotiated{ version: PROTOCOL_VERSION, max_size_in: 2048, max_size_out: 2048, features };
+
+	assert_eq!( PeerEvent::Negotiated( expect ), server_evts.next().await.unwrap() );
+	assert_eq!( PeerEvent::Negotiated( expect ), client_evts.next().await.unwrap() );
+
+	let mut addr = check::RemoteAddr::new( peer.clone() );
+
+	let text = "integrity ".repeat( 100 );
+
+	assert_eq!( text, addr.call( Echo( text.clone() ) ).await.expect( "call Echo" ) );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	client_handle.await;
+	server_handle.await;
+}
+
+
+
+// The encoder adds the checksum and the decoders verify and strip it.
+//
+#[async_std::test]
+//
+async fn encoder_decoder()
+{
+	let mut wf = frame( <Echo as check::Service>::sid(), 100 );
+	wf.set_checksum( true );
+
+	let wire = encode( wf.clone(), 2048 ).await;
+
+	assert_eq!( wf.len() as usize + 8, wire.len() );
+
+	for decoded in decode( wire.clone(), 2048 ).await
+	{
+		let decoded = decoded.expect( "decode frame" );
+
+		assert_eq!( wf.len(), decoded.len() );
+		assert_eq!( wf.sid(), decoded.sid() );
+		assert_eq!( wf.cid(), decoded.cid() );
+		assert_eq!( wf.msg(), decoded.msg() );
+	}
+
+	// Flip a bit in the payload.
+	//
+	let mut corrupt = wire;
+	corrupt[ 50 ] ^= 1;
+
+	for decoded in decode( corrupt, 2048 ).await
+	{
+		assert!( matches!( decoded, Err( WireErr::Checksum{..} ) ) );
+	}
+}
+
+
+
+// The max size of the decoders doesn't count the checksum.
+//
+#[async_std::test]
+//
+async fn max_size()
+{
+	let mut wf = frame( <Echo as check::Service>::sid(), 100 );
+	wf.set_checksum( true );
+
+	let size = wf.len() as usize;
+	let wire = encode( wf, size ).await;
+
+	for decoded in decode( wire.clone(), size ).await
+	{
+		decoded.expect( "decode frame" );
+	}
+
+	for decoded in decode( wire, size - 1 ).await
+	{
+		assert!( matches!( decoded, Err( WireErr::MessageSizeExceeded{..} ) ) );
+	}
+}
+
+
+
+// A peer that receives a corrupted frame reports the error, tells the remote and closes the connection.
+//
+#[async_std::test]
+//
+async fn corrupted_frame()
+{
+	// Big enough for the connection error, as we only read it at the end.
+	//
+	let (server, client) = Endpoint::pair( 1024, 1024 );
+
+	let (_, mut server_evts, server_handle) = config_peer( server, "server", 2048, sealed ).await;
+
+	let mut wf = frame( <Echo as check::Service>::sid(), 100 );
+	wf.set_checksum( true );
+
+	let mut wire = encode( wf, 2048 ).await;
+	wire[ 50 ] ^= 1;
+
+	let (reader, mut writer) = client.split();
+
+	writer.write_all( &wire ).await.expect( "write corrupted frame" );
+
+	match server_evts.next().await.expect( "an event" )
+	{
+		PeerEvent::Error( PeerErr::WireFormat{ source: WireErr::Checksum{..}, .. } ) => {}
+		e => unreachable!( "Should be WireErr::Checksum, got: {:?}", e ),
+	}
+
+	assert_eq!( PeerEvent::Closed, server_evts.next().await.expect( "an event" ) );
+
+	// The remote gets a connection error, after the handshake.
+	//
+	let mut decoder   = Decoder::new( reader, 2048 );
+	let     handshake = decoder.next().await.expect( "a frame" ).expect( "decode frame" );
+	let     err       = decoder.next().await.expect( "a frame" ).expect( "decode frame" );
+
+	assert_eq!( ServiceID::handshake(), handshake.sid() );
+
+	assert!( err.sid().is_null() );
+
+	match CborCodec::decode( err.msg() ).expect( "deserialize ConnectionError" )
+	{
+		ConnectionError::DeserializeWireFormat{..} => {}
+		e => unreachable!( "Should be ConnectionError::DeserializeWireFormat, got: {:?}", e ),
+	}
+
+	server_handle.await;
+}
+
+
+
+// Once the remote seals its frames, a frame without checksum closes the connection. Frames that were sent before
+// the remote had our handshake don't have one, so those are accepted.
+//
+#[async_std::test]
+//
+async fn unsealed_frame()
+{
+	let (server, client) = Endpoint::pair( 1024, 1024 );
+
+	let (_, mut server_evts, server_handle) = config_peer( server, "server", 2048, sealed ).await;
+
+	let (reader, writer) = client.split();
+	let mut decoder      = Decoder::new( reader, 2048 );
+	let mut encoder      = Encoder::new( writer, 2048 );
+
+	let call = |cid: u64, sealed: bool|
+	{
+		let mut wf = MockRemote::<CborWF>::message( <Echo as check::Service>::sid(), &Echo( "hi".to_string() ), ConnID::from(cid) ).expect( "serialize Echo" );
+		wf.set_checksum( sealed );
+		wf
+	};
+
+	let handshake = Handshake::new( 2048, 2048 ).features( Features{ checksum: true, ..Default::default() } );
+	let handshake = MockRemote::<CborWF>::message( ServiceID::handshake(), &handshake, ConnID::null() ).expect( "serialize handshake" );
+
+	assert_eq!( ServiceID::handshake(), decoder.next().await.expect( "a frame" ).expect( "decode frame" ).sid() );
+
+	encoder.send( handshake     ).await.expect( "send handshake" );
+	encoder.send( call( 1, false ) ).await.expect( "send call" );
+	encoder.send( call( 2, true  ) ).await.expect( "send call" );
+
+	assert!( matches!( server_evts.next().await.expect( "an event" ), PeerEvent::Negotiated(_) ) );
+
+	// Both calls get a response.
+	//
+	assert_eq!( ConnID::from(1), decoder.next().await.expect( "a frame" ).expect( "decode frame" ).cid() );
+	assert_eq!( ConnID::from(2), decoder.next().await.expect( "a frame" ).expect( "decode frame" ).cid() );
+
+	encoder.send( call( 3, false ) ).await.expect( "send call" );
+
+	match server_evts.next().await.expect( "an event" )
+	{
+		PeerEvent::Error( PeerErr::WireFormat{ source: WireErr::Checksum{ expected: None, .. }, .. } ) => {}
+		e => unreachable!( "Should be WireErr::Checksum, got: {:?}", e ),
+	}
+
+	assert_eq!( PeerEvent::Closed, server_evts.next().await.expect( "an event" ) );
+
+	server_handle.await;
+}
@@ -0,0 +1,106 @@
+// Tests:
+//
+// - ✔ Calls and sends work with every codec. The extra codecs need their feature enabled.
+// - ✔ Codec errors tell which codec failed.
+//
+mod common;
+
+use
+{
+	common :: { *, import::{ *, assert_eq } } ,
+	serde  :: { Serialize, Deserialize      } ,
+};
+
+
+#[ derive( Serialize, Deserialize, Debug ) ] pub struct Greet{ name: String, times: Option<u8> }
+
+impl Message for Greet { type Return = Vec<String>; }
+
+
+#[ derive(Actor) ] struct Greeter;
+
+impl Handler<Greet> for Greeter
+{
+	#[async_fn] fn handle( &mut self, msg: Greet ) -> Vec<String>
+	{
+		vec![ format!( "Hello {}", msg.name ); msg.times.unwrap_or(1).into() ]
+	}
+}
+
+
+
+// Generate a service map with the given codec and a test that sends and calls over it.
+//
+macro_rules! codec_test
+{
+	( $test: ident, $ns: ident, $codec: path $(, $feature: literal )? ) =>
+	{
+		$( #[ cfg( feature = $feature ) ] )?
+		//
+		service_map!
+		(
+			namespace  : $ns          ;
+			wire_format: CborWF       ;
+			codec      : $codec       ;
+			services   : Add, Show, Greet ;
+		);
+
+
+		$( #[ cfg( feature = $feature ) ] )?
+		//
+		#[async_std::test]
+		//
+		async fn $test()
+		{
+			let (server, client) = Endpoint::pair( 64, 64 );
+
+			let sum     = Addr::builder( "sum"     ).spawn( Sum(5)  , &AsyncStd ).expect( "spawn actor mailbox" );
+			let greeter = Addr::builder( "greeter" ).spawn( Greeter , &AsyncStd ).expect( "spawn actor mailbox" );
+
+			let mut sm = $ns::Services::new();
+			sm.register_handler::<Add  >( sum    .clone_box() );
+			sm.register_handler::<Show >( sum    .clone_box() );
+			sm.register_handler::<Greet>( greeter.clone_box() );
+
+			let (_, _, handle) = peer_listen( server, Arc::new( sm ), AsyncStd, "server" ).await;
+			let (mut peer, _)  = peer_connect( client, AsyncStd, "client" ).await;
+
+			let mut addr = $ns::RemoteAddr::new( peer.clone() );
+
+			addr.send( Add(2) ).await.expect( "send Add" );
+
+			assert_eq!( 7, addr.call( Show ).await.expect( "call Show" ) );
+
+			let greeting = addr.call( Greet{ name: "codec".to_string(), times: Some(2) } ).await.expect( "call Greet" );
+
+			assert_eq!( vec![ "Hello codec".to_string(); 2 ], greeting );
+
+			peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+			handle.await;
+		}
+	};
+}
+
+
+codec_test!( cbor   , cbor_codec   , CborCodec               );
+codec_test!( bincode, bincode_codec, BincodeCodec, "bincode" );
+codec_test!( msgpack, msgpack_codec, MsgPackCodec, "msgpack" );
+codec_test!( json   , json_codec   , JsonCodec   , "json"    );
+
+
+
+// Codec errors tell which codec failed.
+//
+#[ test ]
+//
+fn codec_mismatch()
+{
+	let mut wf = CborWF::default();
+
+	CborCodec::encode( &mut wf, &"not a number" ).expect( "serialize" );
+
+	let err = CborCodec::decode::<i64>( wf.msg() ).expect_err( "deserialize string as i64" );
+
+	assert_eq!( "CBOR", err.codec );
+}
@@ -3,6 +3,8 @@
 pub mod actors;
 
 
+#[ allow( unused_imports ) ]
+//
 pub mod import
 {
 	pub use
@@ -72,7 +74,7 @@ pub fn add_show_sum() -> remotes::Services
 pub async fn peer_listen
 (
 	socket: Endpoint                                              ,
-	sm    : Arc<impl ServiceMap + Send + Sync + 'static>          ,
+	sm    : Arc<impl ServiceMap + 'static>                        ,
 	exec  : impl Spawn + SpawnHandle<MailboxEnd<Peer>> + PeerExec ,
 	name  : &str                                                  ,
 )
@@ -98,6 +100,78 @@ pub async fn peer_listen
 
 
 
+// Create a peer on `socket` with max sizes of `max_size`. `config` registers services and enables features
+// before the mailbox starts.
+//
+pub async fn config_peer
+(
+	socket  : Endpoint                 ,
+	name    : &str                     ,
+	max_size: usize                    ,
+	config  : impl FnOnce( &mut Peer ) ,
+)
+	-> (WeakAddr<Peer>, Events<PeerEvent>, JoinHandle< MailboxEnd<Peer> >)
+{
+	let peer = CborWF::create_peer( name, socket, max_size, max_size, AsyncStd, None, None ).expect( "spawn peer" );
+
+	start_peer( peer, config ).await
+}
+
+
+
+// Configure a peer that was just created and start its mailbox.
+//
+pub async fn start_peer
+(
+	peer  : ( Peer, Mailbox<Peer>, WeakAddr<Peer> ) ,
+	config: impl FnOnce( &mut Peer )                ,
+)
+	-> (WeakAddr<Peer>, Events<PeerEvent>, JoinHandle< MailboxEnd<Peer> >)
+{
+	let (mut peer, peer_mb, peer_addr) = peer;
+
+	config( &mut peer );
+
+	let evts   = peer.observe( ObserveConfig::default() ).await.expect( "pharos not closed" );
+	let handle = AsyncStd.spawn_handle( peer_mb.start(peer) ).expect( "start mailbox of Peer" );
+
+	(peer_addr, evts, handle)
+}
+
+
+
+// A frame for the service `sid` with a payload of `size` bytes.
+//
+pub fn frame( sid: ServiceID, size: usize ) -> CborWF
+{
+	let mut wf = CborWF::with_capacity( size );
+
+	wf.set_sid( sid );
+	wf.set_cid( ConnID::random() );
+	wf.write_all( &vec![ 7; size ] ).expect( "write to wf" );
+
+	wf
+}
+
+
+
+// The bytes the encoder puts on the wire for `wf`.
+//
+pub async fn encode( wf: CborWF, max_size: usize ) -> Vec<u8>
+{
+	use futures::{ SinkExt, io::Cursor };
+
+	let mut wire    = Cursor::new( Vec::new() );
+	let mut encoder = Encoder::new( &mut wire, max_size );
+
+	encoder.send( wf ).await.expect( "encode wf" );
+
+	wire.into_inner()
+}
+
+
+
+
 pub async fn peer_connect
 (
 	socket: Endpoint                                   ,
@@ -0,0 +1,266 @@
+// Tests:
+//
+// - ✔ Peers that negotiated compression can call each other. Each algorithm needs its feature enabled.
+// - ✔ The encoder compresses the payload and the decoders restore it.
+// - ✔ The max size of the decoders applies to the decompressed size.
+// - ✔ Peers compress with an algorithm the remote advertised, even if they prefer another one.
+//
+#![ cfg(any( feature = "lz4", feature = "zstd" )) ]
+
+mod common;
+
+use
+{
+	common  :: { *, import::{ *, assert_eq } } ,
+	serde   :: { Serialize, Deserialize   } ,
+	futures :: { SinkExt, AsyncRead, AsyncReadExt, io::Cursor } ,
+};
+
+
+#[ derive( Serialize, Deserialize, Debug ) ] pub struct Echo( String );
+
+impl Message for Echo { type Return = String; }
+
+
+#[ derive(Actor) ] struct Echoer;
+
+impl Handler<Echo> for Echoer
+{
+	#[async_fn] fn handle( &mut self, msg: Echo ) -> String
+	{
+		msg.0
+	}
+}
+
+
+service_map!
+(
+	namespace  : compress ;
+	wire_format: CborWF   ;
+	services   : Echo     ;
+);
+
+
+
+// Serve Echo and compress outgoing messages if the remote supports it.
+//
+fn compressed( compression: Compression ) -> impl FnOnce( &mut Peer )
+{
+	move |peer|
+	{
+		let echo = Addr::builder( "echo" ).spawn( Echoer, &AsyncStd ).expect( "spawn actor mailbox" );
+
+		let mut sm = compress::Services::new();
+		sm.register_handler::<Echo>( echo.clone_box() );
+
+		peer.register_services( Arc::new( sm ) );
+		peer.set_handshake( Handshake::new( 2048, 2048 ).features( Features{ compression: Algorithms::supported(), ..Default::default() } ) );
+		peer.set_compression( compression );
+	}
+}
+
+
+// Read a raw frame, without decompressing it.
+//
+async fn read_frame( reader: &mut (impl AsyncRead + Unpin) ) -> Vec<u8>
+{
+	let mut frame = vec![ 0; 8 ];
+	reader.read_exact( &mut frame ).await.expect( "read length" );
+
+	// The most significant byte holds the compression flag.
+	//
+	let len = u64::from_le_bytes( frame[ 0..8 ].try_into().unwrap() ) & ( u64::MAX >> 8 );
+
+	frame.resize( len as usize, 0 );
+	reader.read_exact( &mut frame[ 8.. ] ).await.expect( "read frame" );
+
+	frame
+}
+
+
+
+macro_rules! compression_tests
+{
+	( $module: ident, $algorithm: expr, $feature: literal ) =>
+	{
+		#[ cfg( feature = $feature ) ]
+		//
+		mod $module
+		{
+			use super::{ *, assert_eq };
+
+
+			// Peers that negotiated compression can call each other.
+			//
+			#[async_std::test]
+			//
+			async fn peers()
+			{
+				let (server, client) = Endpoint::pair( 64, 64 );
+				let compression      = Compression::new( $algorithm, 100 );
+
+				let (_       , mut server_evts, server_handle) = config_peer( server, "server", 2048, compressed( compression ) ).await;
+				let (mut peer, mut client_evts, client_handle) = config_peer( client, "client", 2048, compressed( compression ) ).await;
+
+				let features = Features{ compression: Algorithms::supported(), ..Default::default() };
+				let expect   = Negotiated{ version: PROTOCOL_VERSION, max_size_in: 2048, max_size_out: 2048, features };
+
+				assert_eq!( PeerEvent::Negotiated( expect ), server_evts.next().await.unwrap() );
+				assert_eq!( PeerEvent::Negotiated( expect ), client_evts.next().await.unwrap() );
+
+				let mut addr = compress::RemoteAddr::new( peer.clone() );
+
+				// Both the call and the response are above the threshold.
+				//
+				let text = "chat and telemetry ".repeat( 100 );
+
+				assert_eq!( text, addr.call( Echo( text.clone() ) ).await.expect( "call Echo" ) );
+
+				// And below.
+				//
+				assert_eq!( "hi", addr.call( Echo( "hi".to_string() ) ).await.expect( "call Echo" ) );
+
+				peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+				client_handle.await;
+				server_handle.await;
+			}
+
+
+			// The encoder compresses the payload and the decoders restore it.
+			//
+			#[async_std::test]
+			//
+			async fn encoder_decoder()
+			{
+				let mut wf = frame( <Echo as compress::Service>::sid(), 1000 );
+				wf.set_compression( Some( Compression::new( $algorithm, 100 ) ) );
+
+				let wire = encode( wf.clone(), 2048 ).await;
+
+				assert!( wire.len() < wf.len() as usize );
+
+				let mut decoder = Decoder      ::new( Cursor::new( wire.clone() ), 2048 );
+				let mut noheap  = DecoderNoHeap::new( Cursor::new( wire         ), 2048 );
+
+				for decoded in [ decoder.next().await, noheap.next().await ]
+				{
+					let decoded = decoded.expect( "a frame" ).expect( "decode frame" );
+
+					assert_eq!( wf.len(), decoded.len() );
+					assert_eq!( wf.sid(), decoded.sid() );
+					assert_eq!( wf.cid(), decoded.cid() );
+					assert_eq!( wf.msg(), decoded.msg() );
+				}
+
+				// Below the threshold, the frame goes out unchanged.
+				//
+				let mut wf = frame( <Echo as compress::Service>::sid(), 99 );
+				wf.set_compression( Some( Compression::new( $algorithm, 100 ) ) );
+
+				let wire = encode( wf.clone(), 2048 ).await;
+
+				assert_eq!( wf.len() as usize, wire.len() );
+			}
+
+
+			// The max size of the decoders applies to the decompressed size, even though the frame on the wire
+			// is small.
+			//
+			#[async_std::test]
+			//
+			async fn compression_bomb()
+			{
+				let mut wf = frame( <Echo as compress::Service>::sid(), 1_000_000 );
+				wf.set_compression( Some( Compression::new( $algorithm, 100 ) ) );
+
+				let wire = encode( wf, 2_000_000 ).await;
+
+				assert!( wire.len() < 10_000 );
+
+				let mut decoder = Decoder      ::new( Cursor::new( wire.clone() ), 10_000 );
+				let mut noheap  = DecoderNoHeap::new( Cursor::new( wire         ), 10_000 );
+
+				for decoded in [ decoder.next().await, noheap.next().await ]
+				{
+					match decoded.expect( "a frame" )
+					{
+						Err( WireErr::MessageSizeExceeded{ size, max_size, .. } ) =>
+						{
+							assert_eq!( 1_000_032, size     );
+							assert_eq!( 10_000   , max_size );
+						}
+
+						_ => unreachable!( "Should be WireErr::MessageSizeExceeded" ),
+					}
+				}
+			}
+		}
+	};
+}
+
+
+compression_tests!( lz4 , Algorithm::Lz4             , "lz4"  );
+compression_tests!( zstd, Algorithm::Zstd{ level: 0 }, "zstd" );
+
+
+
+// Peers compress with an algorithm the remote advertised, even if they prefer another one.
+//
+#[ cfg(all( feature = "lz4", feature = "zstd" )) ]
+//
+#[async_std::test]
+//
+async fn common_algorithm()
+{
+	// The compression flag of LZ4 in the most significant byte of the length field.
+	//
+	const FLAG_LZ4: u8 = 1;
+
+	let (server, client) = Endpoint::pair( 4096, 4096 );
+	let compression      = Compression::new( Algorithm::Zstd{ level: 0 }, 100 );
+
+	let (mut peer, mut evts, handle) = config_peer( server, "server", 2048, compressed( compression ) ).await;
+	let (mut reader, writer)         = client.split();
+
+	// The handshake of the peer.
+	//
+	read_frame( &mut reader ).await;
+
+	// The remote can only decode LZ4.
+	//
+	let features  = Features{ compression: Algorithms{ lz4: true, zstd: false }, ..Default::default() };
+	let handshake = Handshake::new( 2048, 2048 ).features( features );
+	let handshake = MockRemote::<CborWF>::message( ServiceID::handshake(), &handshake, ConnID::null() ).expect( "serialize handshake" );
+
+	let mut encoder = Encoder::new( writer, 2048 );
+	encoder.send( handshake ).await.expect( "send handshake" );
+
+	match evts.next().await.unwrap()
+	{
+		PeerEvent::Negotiated( n ) => assert_eq!( features, n.features ),
+		_                          => unreachable!( "Should be PeerEvent::Negotiated" ),
+	}
+
+	let text = "chat and telemetry ".repeat( 100 );
+	let mut addr = compress::RemoteAddr::new( peer.clone() );
+
+	addr.send( Echo( text.clone() ) ).await.expect( "send Echo" );
+
+	let frame = read_frame( &mut reader ).await;
+
+	assert_eq!( FLAG_LZ4, frame[ 7 ] );
+
+	// And it decompresses.
+	//
+	let mut decoder = Decoder::new( Cursor::new( frame ), 2048 );
+	let decoded     = decoder.next().await.expect( "a frame" ).expect( "decode frame" );
+
+	let expect = MockRemote::<CborWF>::message( <Echo as compress::Service>::sid(), &Echo( text ), ConnID::null() ).expect( "serialize Echo" );
+
+	assert_eq!( expect.msg(), decoded.msg() );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	handle.await;
+}
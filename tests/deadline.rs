@@ -0,0 +1,225 @@
+// Tests:
+//
+// - ✔ A per-call timeout overrides the timeout of the peer, in both directions.
+// - ✔ The provider doesn't call the service when the deadline has passed.
+// - ✔ A deadline whose call never came is forgotten.
+// - ✔ The number of deadlines waiting for their call is bounded.
+//
+mod common;
+
+use
+{
+	common        :: { *, import::{ *, assert_eq }                             } ,
+	std           :: { time::Duration, sync::atomic::{ AtomicUsize, Ordering } } ,
+	futures       :: { AsyncReadExt                                            } ,
+	futures_timer :: { Delay                                                   } ,
+	serde         :: { Serialize                                               } ,
+};
+
+
+#[ derive(Actor) ] struct Slow{ calls: Arc<AtomicUsize> }
+
+impl Handler<Add> for Slow
+{
+	fn handle( &mut self, _msg: Add ) -> Return<'_, ()> { async move
+	{
+		self.calls.fetch_add( 1, Ordering::SeqCst );
+
+		Delay::new( Duration::from_millis(50) ).await;
+
+	}.boxed() }
+}
+
+
+
+service_map!
+(
+	namespace  : deadlines ;
+	wire_format: CborWF    ;
+	services   : Add       ;
+);
+
+
+
+// The frame that tells the provider the deadline of a call.
+//
+#[ derive( Serialize ) ] struct DeadlineFrame{ cid: ConnID, millis: u64 }
+
+
+
+// A server with a Slow handler. Returns the number of calls to the handler.
+//
+async fn provider( server: Endpoint ) -> (Arc<AtomicUsize>, JoinHandle< MailboxEnd<Peer> >)
+{
+	let calls = Arc::new( AtomicUsize::new(0) );
+	let slow  = Addr::builder( "slow" ).spawn( Slow{ calls: calls.clone() }, &AsyncStd ).expect( "spawn actor mailbox" );
+
+	let mut sm = deadlines::Services::new();
+	sm.register_handler::<Add>( slow.clone_box() );
+
+	let (_, _, handle) = peer_listen( server, Arc::new( sm ), AsyncStd, "server" ).await;
+
+	(calls, handle)
+}
+
+
+
+// Connect a client with the given default timeout to a server with a Slow handler.
+//
+async fn connect( timeout: Duration ) -> (WeakAddr<Peer>, Arc<AtomicUsize>, JoinHandle< MailboxEnd<Peer> >)
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+	let (calls, handle)  = provider( server ).await;
+
+	let (mut peer, peer_mb, peer_addr) = CborWF::create_peer( "client", client, 1024, 1024, AsyncStd, None, None ).expect( "spawn peer" );
+
+	peer.set_timeout( timeout );
+
+	AsyncStd.spawn( peer_mb.start(peer).map(|_|()) ).expect( "Start mailbox of Peer" );
+
+	(peer_addr, calls, handle)
+}
+
+
+
+// A per-call timeout overrides the timeout of the peer, in both directions.
+//
+#[async_std::test]
+//
+async fn call_with_timeout()
+{
+	let (mut peer, calls, handle) = connect( Duration::from_millis(10) ).await;
+	let mut addr                  = deadlines::RemoteAddr::new( peer.clone() );
+
+	// Longer than the peer timeout.
+	//
+	addr.call_with_timeout( Add(1), Duration::from_secs(5) ).await.expect( "call with long timeout" );
+	assert_eq!( 1, calls.load( Ordering::SeqCst ) );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+	handle.await;
+
+
+	let (mut peer, _    , handle) = connect( Duration::from_secs(5) ).await;
+	let mut addr                  = deadlines::RemoteAddr::new( peer.clone() );
+
+	// Shorter than the peer timeout.
+	//
+	let resp = addr.call_with_timeout( Add(1), Duration::from_millis(10) ).await;
+
+	assert!(matches!( resp, Err(PeerErr::Timeout{..}) ));
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+	handle.await;
+}
+
+
+
+// The provider doesn't call the service when the deadline has passed.
+//
+#[async_std::test]
+//
+async fn expired_deadline()
+{
+	let (mut peer, calls, handle) = connect( Duration::from_secs(5) ).await;
+	let mut addr                  = deadlines::RemoteAddr::new( peer.clone() );
+
+	let resp = addr.call_with_timeout( Add(1), Duration::ZERO ).await;
+
+	assert!(matches!( resp, Err(PeerErr::Timeout{..}) ));
+
+	// Give the server the time to process the call.
+	//
+	Delay::new( Duration::from_millis(50) ).await;
+
+	assert_eq!( 0, calls.load( Ordering::SeqCst ) );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	handle.await;
+}
+
+
+
+// A deadline whose call never came is forgotten once it has passed. It doesn't apply to a later call that
+// happens to have the same cid.
+//
+#[async_std::test]
+//
+async fn forget_deadline()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+	let (calls, handle)  = provider( server ).await;
+	let (reader, writer) = client.split();
+
+	let mut mock: MockRemote<CborWF> = MockRemote::new( Decoder::new( reader, 1024 ), Encoder::new( writer, 1024 ) );
+
+	let deadline = |cid, millis|
+	{
+		MockRemote::<CborWF>::message( ServiceID::deadline(), &DeadlineFrame{ cid, millis }, ConnID::null() ).expect( "serialize deadline" )
+	};
+
+	// The first call of the mock will get ConnID 1.
+	//
+	mock.send_raw( deadline( ConnID::from(1), 10 ) ).await.expect( "send deadline" );
+
+	Delay::new( Duration::from_millis(50) ).await;
+
+	// A new deadline makes the provider drop the ones that passed.
+	//
+	mock.send_raw( deadline( ConnID::from(1000), 60_000 ) ).await.expect( "send deadline" );
+
+	let cid = mock.call( <Add as deadlines::Service>::sid(), &Add(1) ).await.expect( "call Add" );
+
+	assert_eq!( ConnID::from(1), cid );
+
+	mock.recv_response::<()>( cid ).await;
+
+	assert_eq!( 1, calls.load( Ordering::SeqCst ) );
+
+	mock.close().await.expect( "close mock" );
+	handle.await;
+}
+
+
+
+// The number of deadlines waiting for their call is bounded. When a remote sends deadlines that never get a call, the
+// oldest are forgotten, even if they haven't passed yet.
+//
+#[async_std::test]
+//
+async fn bounded_deadlines()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+	let (calls, handle)  = provider( server ).await;
+	let (reader, writer) = client.split();
+
+	let mut mock: MockRemote<CborWF> = MockRemote::new( Decoder::new( reader, 1024 ), Encoder::new( writer, 1024 ) );
+
+	let deadline = |cid, millis|
+	{
+		MockRemote::<CborWF>::message( ServiceID::deadline(), &DeadlineFrame{ cid, millis }, ConnID::null() ).expect( "serialize deadline" )
+	};
+
+	// The first call of the mock will get ConnID 1.
+	//
+	mock.send_raw( deadline( ConnID::from(1), 200 ) ).await.expect( "send deadline" );
+
+	for cid in 1000..1064
+	{
+		mock.send_raw( deadline( ConnID::from(cid), 60_000 ) ).await.expect( "send deadline" );
+	}
+
+	// Had the deadline of ConnID 1 been kept, the call would now time out without calling the service.
+	//
+	Delay::new( Duration::from_millis(300) ).await;
+
+	let cid = mock.call( <Add as deadlines::Service>::sid(), &Add(1) ).await.expect( "call Add" );
+
+	mock.recv_response::<()>( cid ).await;
+
+	assert_eq!( 1, calls.load( Ordering::SeqCst ) );
+
+	mock.close().await.expect( "close mock" );
+	handle.await;
+}
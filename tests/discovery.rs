@@ -0,0 +1,111 @@
+// Tests:
+//
+// - ✔ The remote advertises it's services on start and calls to other services fail locally.
+// - ✔ The remote advertises again when services are added at runtime.
+//
+mod common;
+
+use common::*                       ;
+use common::import::{ *, assert_eq };
+
+
+// Serve `sm` and advertise it's services.
+//
+fn advertising( sm: remotes::Services ) -> impl FnOnce( &mut Peer )
+{
+	move |peer|
+	{
+		peer.register_services( Arc::new( sm ) );
+		peer.set_discovery( true );
+	}
+}
+
+
+
+// The remote advertises it's services on start and calls to other services fail locally.
+//
+#[async_std::test]
+//
+async fn advertise()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+
+	let (_, _, handle)       = config_peer( server, "server", 1024, advertising( add_show_sum() ) ).await;
+	let (mut peer, mut evts) = peer_connect( client, AsyncStd, "client" ).await;
+	let mut addr             = remotes::RemoteAddr::new( peer.clone() );
+
+	let add  = <Add  as remotes::Service>::sid();
+	let sub  = <Sub  as remotes::Service>::sid();
+	let show = <Show as remotes::Service>::sid();
+
+	match evts.next().await.unwrap()
+	{
+		PeerEvent::RemoteServices( sids ) =>
+		{
+			assert_eq!( 2, sids.len() );
+			assert!( sids.contains( &add  ) );
+			assert!( sids.contains( &show ) );
+		}
+
+		_ => unreachable!( "Should be PeerEvent::RemoteServices" ),
+	}
+
+	let remote = peer.call( RemoteServices ).await.expect( "call peer" ).expect( "remote advertised" );
+
+	assert_eq!( Some( "remotes::Add".to_string() ), remote[ &add ] );
+
+	// Sub isn't provided by the remote.
+	//
+	match addr.call( Sub(5) ).await
+	{
+		Err( PeerErr::UnknownService{ ctx } ) => assert_eq!( Some( sub ), ctx.sid ),
+		_                                     => unreachable!( "Should be PeerErr::UnknownService" ),
+	}
+
+	addr.call( Add(5) ).await.expect( "call Add" );
+	assert_eq!( 5, addr.call( Show ).await.expect( "call Show" ) );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+	handle.await;
+}
+
+
+
+// The remote advertises again when services are added at runtime.
+//
+#[async_std::test]
+//
+async fn readvertise()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+
+	let handler = Addr::builder( "handler" ).spawn( Sum(0), &AsyncStd ).expect( "spawn actor mailbox" );
+
+	let mut sm = remotes::Services::new();
+	sm.register_handler::<Add>( handler.clone_box() );
+
+	let (mut server_addr, _, handle) = config_peer( server, "server", 1024, advertising( sm ) ).await;
+	let (mut peer, mut evts)         = peer_connect( client, AsyncStd, "client" ).await;
+	let mut addr                     = remotes::RemoteAddr::new( peer.clone() );
+
+	assert_eq!( PeerEvent::RemoteServices( vec![ <Add as remotes::Service>::sid() ] ), evts.next().await.unwrap() );
+
+	assert!(matches!( addr.call( Show ).await, Err( PeerErr::UnknownService{..} ) ));
+
+	let mut sm = remotes::Services::new();
+	sm.register_handler::<Show>( handler.clone_box() );
+
+	server_addr.call( AddServices{ sm: Arc::new( sm ) } ).await.expect( "add services" );
+
+	match evts.next().await.unwrap()
+	{
+		PeerEvent::RemoteServices( sids ) => assert_eq!( 2, sids.len() ),
+		_                                 => unreachable!( "Should be PeerEvent::RemoteServices" ),
+	}
+
+	addr.call( Add(3) ).await.expect( "call Add" );
+	assert_eq!( 3, addr.call( Show ).await.expect( "call Show" ) );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+	handle.await;
+}
@@ -0,0 +1,72 @@
+// Tests:
+//
+// - ✔ With FlushPolicy::Bytes, a batch of concurrent calls goes out and a lone call isn't held back.
+// - ✔ With FlushPolicy::Delay, messages go out after the delay.
+//
+mod common;
+
+use
+{
+	common  :: { *, import::{ *, assert_eq } } ,
+	futures :: { future::join_all             } ,
+	std     :: { time::Instant                } ,
+};
+
+
+#[async_std::test]
+//
+async fn bytes()
+{
+	let (server, client) = Endpoint::pair( 8192, 8192 );
+
+	let (_, _         , server_handle)    = peer_listen( server, Arc::new( add_show_sum() ), AsyncStd, "server" ).await;
+	let (mut peer_addr, _, client_handle) = config_peer( client, "client", 1024, |peer| peer.set_flush_policy( FlushPolicy::Bytes( 512 ) ) ).await;
+
+	let addr = remotes::RemoteAddr::new( peer_addr.clone() );
+
+	let calls = ( 0..50 ).map( |_|
+	{
+		let mut addr = addr.clone();
+		async move { addr.call( Add(1) ).await }
+	});
+
+	for result in join_all( calls ).await
+	{
+		assert_eq!( Ok(()), result );
+	}
+
+	// A lone call, far below the threshold.
+	//
+	assert_eq!( Ok(50), addr.clone().call( Show ).await );
+
+	peer_addr.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	client_handle.await;
+	server_handle.await;
+}
+
+
+
+#[async_std::test]
+//
+async fn delay()
+{
+	let (server, client) = Endpoint::pair( 8192, 8192 );
+	let delay            = Duration::from_millis( 50 );
+
+	let (_, _         , server_handle)    = peer_listen( server, Arc::new( add_show_sum() ), AsyncStd, "server" ).await;
+	let (mut peer_addr, _, client_handle) = config_peer( client, "client", 1024, |peer| peer.set_flush_policy( FlushPolicy::Delay( delay ) ) ).await;
+
+	let mut addr  = remotes::RemoteAddr::new( peer_addr.clone() );
+	let     start = Instant::now();
+
+	assert_eq!( Ok(()), addr.call( Add(5) ).await );
+	assert!( start.elapsed() >= delay );
+
+	assert_eq!( Ok(5), addr.call( Show ).await );
+
+	peer_addr.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	client_handle.await;
+	server_handle.await;
+}
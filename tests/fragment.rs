@@ -0,0 +1,250 @@
+// Tests:
+//
+// - ✔ Messages bigger than the max frame size are fragmented and reassembled.
+// - ✔ Outgoing messages bigger than the max message size are refused locally.
+// - ✔ Small calls aren't blocked by a big upload.
+// - ✔ Fragments of concurrent uploads are sent in turn, so a small upload isn't blocked by a big one.
+// - ✔ Fragments of a message that failed to reassemble are ignored, even with absurd indices.
+// - ✔ The remote refuses to reassemble messages bigger than its max message size.
+//
+mod common;
+
+use
+{
+	common  :: { *, import::{ *, assert_eq }  } ,
+	futures :: { future::{ select, Either }   } ,
+	serde   :: { Serialize, Deserialize       } ,
+};
+
+
+#[ derive( Serialize, Deserialize, Debug ) ] pub struct Upload( String );
+#[ derive( Serialize, Deserialize, Debug ) ] pub struct Uploads;
+
+impl Message for Upload  { type Return = usize; }
+impl Message for Uploads { type Return = usize; }
+
+
+// Counts the uploads it received.
+//
+#[ derive(Actor) ] struct Store( usize );
+
+impl Handler<Upload> for Store
+{
+	#[async_fn] fn handle( &mut self, msg: Upload ) -> usize
+	{
+		self.0 += 1;
+		msg.0.len()
+	}
+}
+
+impl Handler<Uploads> for Store
+{
+	#[async_fn] fn handle( &mut self, _msg: Uploads ) -> usize
+	{
+		self.0
+	}
+}
+
+
+service_map!
+(
+	namespace  : store           ;
+	wire_format: CborWF          ;
+	services   : Upload, Uploads ;
+);
+
+
+
+// Serve the store and fragment messages bigger than the frames of 1024 bytes.
+//
+fn fragmented( fragmentation: Fragmentation ) -> impl FnOnce( &mut Peer )
+{
+	move |peer|
+	{
+		let store = Addr::builder( "store" ).spawn( Store(0), &AsyncStd ).expect( "spawn actor mailbox" );
+
+		let mut sm = store::Services::new();
+		sm.register_handler::<Upload >( store.clone_box() );
+		sm.register_handler::<Uploads>( store.clone_box() );
+
+		peer.register_services( Arc::new( sm ) );
+		peer.set_fragmentation( fragmentation );
+	}
+}
+
+
+
+// Messages bigger than the max frame size are fragmented and reassembled. Messages bigger than
+// the max message size are refused locally.
+//
+#[async_std::test]
+//
+async fn fragmented_call()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+	let fragmentation    = Fragmentation::new( 256, 64 * 1024 );
+
+	let (_       , _, server_handle) = config_peer( server, "server", 1024, fragmented( fragmentation ) ).await;
+	let (mut peer, _, client_handle) = config_peer( client, "client", 1024, fragmented( fragmentation ) ).await;
+
+	let mut addr = store::RemoteAddr::new( peer.clone() );
+
+	assert_eq!( 10_000, addr.call( Upload( "x".repeat( 10_000 ) ) ).await.expect( "call Upload" ) );
+	assert_eq!( 1     , addr.call( Uploads                       ).await.expect( "call Uploads" ) );
+
+	// Too big for the max message size.
+	//
+	let mut wf = CborWF::with_capacity( 70_000 );
+	wf.set_sid( <Upload as store::Service>::sid() );
+	wf.write_all( &[ 0; 70_000 ] ).expect( "write to wf" );
+
+	match peer.call( wf ).await.expect( "call peer" )
+	{
+		Err( PeerErr::WireFormat{ source: WireErr::MessageSizeExceeded{ max_size, .. }, .. } ) => assert_eq!( 64 * 1024, max_size ),
+		_ => unreachable!( "Should be PeerErr::WireFormat( WireErr::MessageSizeExceeded )" ),
+	}
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	client_handle.await;
+	server_handle.await;
+}
+
+
+
+// Small calls aren't blocked by a big upload.
+//
+#[async_std::test]
+//
+async fn interleave()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+	let fragmentation    = Fragmentation::new( 256, 64 * 1024 );
+
+	let (_       , _, server_handle) = config_peer( server, "server", 1024, fragmented( fragmentation ) ).await;
+	let (mut peer, _, client_handle) = config_peer( client, "client", 1024, fragmented( fragmentation ) ).await;
+
+	let mut upload  = store::RemoteAddr::new( peer.clone() );
+	let mut uploads = store::RemoteAddr::new( peer.clone() );
+
+	// The upload is split in about 200 fragments. The second call goes out in between.
+	//
+	let (len, count) = join
+	(
+		upload .call( Upload( "x".repeat( 50_000 ) ) ),
+		uploads.call( Uploads                         ),
+	).await;
+
+	assert_eq!( 50_000, len  .expect( "call Upload"  ) );
+	assert_eq!( 0     , count.expect( "call Uploads" ) );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	client_handle.await;
+	server_handle.await;
+}
+
+
+
+// Fragments of concurrent uploads are sent in turn, so a small upload isn't blocked by a big one.
+//
+#[async_std::test]
+//
+async fn round_robin()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+	let fragmentation    = Fragmentation::new( 256, 64 * 1024 );
+
+	let (_       , _, server_handle) = config_peer( server, "server", 1024, fragmented( fragmentation ) ).await;
+	let (mut peer, _, client_handle) = config_peer( client, "client", 1024, fragmented( fragmentation ) ).await;
+
+	let mut big   = store::RemoteAddr::new( peer.clone() );
+	let mut small = store::RemoteAddr::new( peer.clone() );
+
+	// About 200 fragments for the big one and 20 for the small one, which starts later.
+	//
+	let big   = Box::pin( big  .call( Upload( "x".repeat( 50_000 ) ) ) );
+	let small = Box::pin( small.call( Upload( "x".repeat(  5_000 ) ) ) );
+
+	match select( big, small ).await
+	{
+		Either::Right(( len, big )) =>
+		{
+			assert_eq!( 5_000 , len      .expect( "call small Upload" ) );
+			assert_eq!( 50_000, big.await.expect( "call big Upload"   ) );
+		}
+
+		Either::Left(_) => unreachable!( "The small upload should finish first." ),
+	}
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	client_handle.await;
+	server_handle.await;
+}
+
+
+
+// Fragments of a message that failed to reassemble are ignored, even with absurd indices.
+//
+#[async_std::test]
+//
+async fn failed_fragments()
+{
+	let (mut mock, (incoming, outgoing)) = MockRemote::<CborWF>::pair( 1024 );
+
+	let peer = Peer::from_framed( "server", incoming, outgoing, AsyncStd, None, None ).expect( "spawn peer" );
+
+	let (_, mut evts, handle) = start_peer( peer, fragmented( Fragmentation::new( 256, 4096 ) ) ).await;
+
+	let fragment = |index: u32, count: u32|
+	{
+		let payload = [ index.to_le_bytes(), count.to_le_bytes() ].concat();
+
+		MockRemote::<CborWF>::frame( ServiceID::fragment(), ConnID::from( 7 ), &payload )
+	};
+
+	// Doesn't start at zero, so the message fails.
+	//
+	mock.send_raw( fragment( 3, 5 ) ).await.expect( "send fragment" );
+
+	assert!( matches!( evts.next().await, Some( PeerEvent::Error( PeerErr::WireFormat{ source: WireErr::Deserialize{..}, .. } ) ) ) );
+
+	mock.send_raw( fragment( u32::MAX, 0 ) ).await.expect( "send fragment" );
+
+	let cid = mock.call( <Uploads as store::Service>::sid(), &Uploads ).await.expect( "call Uploads" );
+
+	assert_eq!( 0, mock.recv_response::<usize>( cid ).await );
+
+	mock.close().await.expect( "close mock" );
+	handle.await;
+}
+
+
+
+// The remote refuses to reassemble messages bigger than its max message size.
+//
+#[async_std::test]
+//
+async fn reassembled_size()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+
+	let (_       , mut server_evts, server_handle) = config_peer( server, "server", 1024, fragmented( Fragmentation::new( 256, 4096      ) ) ).await;
+	let (mut peer, _              , client_handle) = config_peer( client, "client", 1024, fragmented( Fragmentation::new( 256, 64 * 1024 ) ) ).await;
+
+	let mut addr = store::RemoteAddr::new( peer.clone() );
+
+	addr.send( Upload( "x".repeat( 10_000 ) ) ).await.expect( "send Upload" );
+
+	match server_evts.next().await.unwrap()
+	{
+		PeerEvent::Error( PeerErr::WireFormat{ source: WireErr::MessageSizeExceeded{ max_size, .. }, .. } ) => assert_eq!( 4096, max_size ),
+		_ => unreachable!( "Should be PeerErr::WireFormat( WireErr::MessageSizeExceeded )" ),
+	}
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	client_handle.await;
+	server_handle.await;
+}
@@ -0,0 +1,272 @@
+// Tests:
+//
+// - ✔ Compatible peers negotiate max sizes and features.
+// - ✔ Outgoing messages bigger than the negotiated size are refused locally.
+// - ✔ Incompatible versions produce PeerEvent::IncompatibleVersion and close the connection.
+// - ✔ Calls wait for the handshake of the remote.
+// - ✔ Incoming messages bigger than the negotiated size close the connection.
+// - ✔ Calls held back for the handshake are bounded.
+// - ✔ Calls fail when the remote refuses our handshake.
+//
+mod common;
+
+use common::*                       ;
+use common::import::{ *, assert_eq };
+use futures::AsyncReadExt           ;
+
+
+// Serve Add and Show with the handshake enabled.
+//
+fn negotiating( handshake: Handshake ) -> impl FnOnce( &mut Peer )
+{
+	move |peer|
+	{
+		peer.register_services( Arc::new( add_show_sum() ) );
+		peer.set_handshake( handshake );
+	}
+}
+
+
+
+// A mock remote connected to a peer with the handshake enabled. The handshake of the peer has been received.
+//
+async fn mock_peer( handshake: Handshake )
+
+	-> (MockRemote<CborWF>, WeakAddr<Peer>, Events<PeerEvent>, JoinHandle< MailboxEnd<Peer> >)
+{
+	let (server, client) = Endpoint::pair( 1024, 1024 );
+
+	let (peer, evts, handle) = config_peer( server, "peer", 1024, negotiating( handshake ) ).await;
+	let (reader, writer)     = client.split();
+
+	let mut mock = MockRemote::new( Decoder::new( reader, 1024 ), Encoder::new( writer, 1024 ) );
+
+	let frame = mock.recv().await.expect( "a frame" ).expect( "decode frame" );
+	assert_eq!( ServiceID::handshake(), frame.sid() );
+
+	(mock, peer, evts, handle)
+}
+
+
+fn handshake_frame( handshake: Handshake ) -> CborWF
+{
+	MockRemote::<CborWF>::message( ServiceID::handshake(), &handshake, ConnID::null() ).expect( "serialize handshake" )
+}
+
+
+
+// Compatible peers negotiate max sizes and features and outgoing messages bigger than the
+// negotiated size are refused locally.
+//
+#[async_std::test]
+//
+async fn negotiate()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+
+	let server_hs = Handshake::new( 100, 1024 ).features( Features{ heartbeat: true, compression: Algorithms::supported(), ..Default::default() } );
+	let client_hs = Handshake::new( 1024, 512 ).features( Features{ heartbeat: true, ..Default::default() } );
+
+	let (_       , mut server_evts, server_handle) = config_peer( server, "server", 1024, negotiating( server_hs ) ).await;
+	let (mut peer, mut client_evts, client_handle) = config_peer( client, "client", 1024, negotiating( client_hs ) ).await;
+
+	let features = Features{ heartbeat: true, ..Default::default() };
+
+	let expect_server = Negotiated{ version: PROTOCOL_VERSION, max_size_in: 100 , max_size_out: 1024, features };
+	let expect_client = Negotiated{ version: PROTOCOL_VERSION, max_size_in: 1024, max_size_out: 100 , features };
+
+	assert_eq!( PeerEvent::Negotiated( expect_server ), server_evts.next().await.unwrap() );
+	assert_eq!( PeerEvent::Negotiated( expect_client ), client_evts.next().await.unwrap() );
+
+	// Normal messages still work.
+	//
+	let mut addr = remotes::RemoteAddr::new( peer.clone() );
+
+	addr.call( Add(5) ).await.expect( "call Add" );
+	assert_eq!( 5, addr.call( Show ).await.expect( "call Show" ) );
+
+	// Too big for the server.
+	//
+	let mut wf = CborWF::with_capacity( 200 );
+	wf.set_sid( <Add as remotes::Service>::sid() );
+	wf.write_all( &[ 0; 200 ] ).expect( "write to wf" );
+
+	match peer.call( wf ).await.expect( "call peer" )
+	{
+		Err( PeerErr::WireFormat{ source: WireErr::MessageSizeExceeded{ max_size, .. }, .. } ) => assert_eq!( 100, max_size ),
+		_ => unreachable!( "Should be PeerErr::WireFormat( WireErr::MessageSizeExceeded )" ),
+	}
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	client_handle.await;
+	server_handle.await;
+}
+
+
+
+// Incompatible versions produce PeerEvent::IncompatibleVersion and close the connection.
+//
+#[async_std::test]
+//
+async fn incompatible_version()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+
+	let server_hs = Handshake{ version: PROTOCOL_VERSION + 1, ..Handshake::new( 1024, 1024 ) };
+	let client_hs = Handshake::new( 1024, 1024 );
+
+	let (_, mut server_evts, server_handle) = config_peer( server, "server", 1024, negotiating( server_hs ) ).await;
+	let (_, mut client_evts, client_handle) = config_peer( client, "client", 1024, negotiating( client_hs ) ).await;
+
+	assert_eq!
+	(
+		PeerEvent::IncompatibleVersion{ local: PROTOCOL_VERSION + 1, remote: PROTOCOL_VERSION },
+		server_evts.next().await.unwrap()
+	);
+
+	assert_eq!
+	(
+		PeerEvent::IncompatibleVersion{ local: PROTOCOL_VERSION, remote: PROTOCOL_VERSION + 1 },
+		client_evts.next().await.unwrap()
+	);
+
+	// Both peers close the connection, so the mailboxes end without us having to close them.
+	//
+	client_handle.await;
+	server_handle.await;
+}
+
+
+
+// Calls wait for the handshake of the remote, so the negotiated sizes and features apply to them.
+//
+#[async_std::test]
+//
+async fn call_waits_for_handshake()
+{
+	let (mut mock, peer, mut evts, handle) = mock_peer( Handshake::new( 1024, 1024 ) ).await;
+	let mut addr                           = remotes::RemoteAddr::new( peer.clone() );
+
+	let call = AsyncStd.spawn_handle( async move { addr.call( Show ).await } ).expect( "spawn call" );
+
+	assert!( async_std::future::timeout( Duration::from_millis(50), mock.recv() ).await.is_err() );
+
+	mock.send_raw( handshake_frame( Handshake::new( 1024, 1024 ) ) ).await.expect( "send handshake" );
+
+	assert!(matches!( evts.next().await.unwrap(), PeerEvent::Negotiated(_) ));
+
+	let frame = mock.recv().await.expect( "a frame" ).expect( "decode frame" );
+
+	assert_eq!( <Show as remotes::Service>::sid(), frame.sid() );
+
+	let response = MockRemote::<CborWF>::message( ServiceID::full(), &5i64, frame.cid() ).expect( "serialize response" );
+	mock.send_raw( response ).await.expect( "send response" );
+
+	assert_eq!( Ok(5), call.await );
+
+	mock.close().await.expect( "close mock" );
+	handle.await;
+}
+
+
+
+// Incoming messages bigger than the negotiated size close the connection.
+//
+#[async_std::test]
+//
+async fn incoming_too_big()
+{
+	let (mut mock, _peer, mut evts, handle) = mock_peer( Handshake::new( 100, 1024 ) ).await;
+
+	mock.send_raw( handshake_frame( Handshake::new( 1024, 1024 ) ) ).await.expect( "send handshake" );
+
+	match evts.next().await.unwrap()
+	{
+		PeerEvent::Negotiated( n ) => assert_eq!( 100, n.max_size_in ),
+		_ => unreachable!( "Should be PeerEvent::Negotiated" ),
+	}
+
+	let frame = MockRemote::<CborWF>::frame( <Add as remotes::Service>::sid(), ConnID::null(), &[ 0; 200 ] );
+	mock.send_raw( frame ).await.expect( "send frame" );
+
+	match evts.next().await.unwrap()
+	{
+		PeerEvent::Error( PeerErr::WireFormat{ source: WireErr::MessageSizeExceeded{ max_size, .. }, .. } ) => assert_eq!( 100, max_size ),
+		_ => unreachable!( "Should be PeerEvent::Error( PeerErr::WireFormat( WireErr::MessageSizeExceeded ) )" ),
+	}
+
+	assert_eq!( PeerEvent::Closed, evts.next().await.unwrap() );
+
+	handle.await;
+}
+
+
+
+// Calls held back for the handshake are bounded by the reconnect buffer, 64 by default.
+//
+#[async_std::test]
+//
+async fn held_calls_bounded()
+{
+	let (mock, mut peer, _evts, handle) = mock_peer( Handshake::new( 1024, 1024 ) ).await;
+	let mut held                        = Vec::new();
+
+	for _ in 0..64
+	{
+		let wf = MockRemote::<CborWF>::message( <Show as remotes::Service>::sid(), &Show, ConnID::null() ).expect( "serialize Show" );
+
+		held.push( peer.call( Call::new( wf ) ).await.expect( "call peer" ).expect( "hold call" ) );
+	}
+
+	let wf = MockRemote::<CborWF>::message( <Show as remotes::Service>::sid(), &Show, ConnID::null() ).expect( "serialize Show" );
+
+	match peer.call( Call::new( wf ) ).await.expect( "call peer" )
+	{
+		Err( PeerErr::ConnectionClosed{..} ) => {}
+		_ => unreachable!( "Should be PeerErr::ConnectionClosed" ),
+	}
+
+	mock.close().await.expect( "close mock" );
+	handle.await;
+}
+
+
+
+// A remote that doesn't understand the handshake answers with UnknownService. The calls that were held back
+// fail with it instead of timing out, as do further calls.
+//
+#[async_std::test]
+//
+async fn refused_handshake()
+{
+	let (mut mock, peer, mut evts, handle) = mock_peer( Handshake::new( 1024, 1024 ) ).await;
+	let mut addr                           = remotes::RemoteAddr::new( peer.clone() );
+	let mut addr2                          = addr.clone();
+
+	let call = AsyncStd.spawn_handle( async move { addr2.call( Show ).await } ).expect( "spawn call" );
+
+	assert!( async_std::future::timeout( Duration::from_millis(50), mock.recv() ).await.is_err() );
+
+	let refused = ConnectionError::UnknownService{ sid: Some( ServiceID::handshake() ), cid: None };
+	let frame   = MockRemote::<CborWF>::message( ServiceID::null(), &refused, ConnID::null() ).expect( "serialize error" );
+
+	mock.send_raw( frame ).await.expect( "send error" );
+
+	assert_eq!( PeerEvent::RemoteError( refused.clone() ), evts.next().await.unwrap() );
+
+	match call.await
+	{
+		Err( PeerErr::Remote{ err, .. } ) => assert_eq!( refused, err ),
+		_ => unreachable!( "Should be PeerErr::Remote" ),
+	}
+
+	match addr.call( Show ).await
+	{
+		Err( PeerErr::Remote{ err, .. } ) => assert_eq!( refused, err ),
+		_ => unreachable!( "Should be PeerErr::Remote" ),
+	}
+
+	mock.close().await.expect( "close mock" );
+	handle.await;
+}
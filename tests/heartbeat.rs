@@ -0,0 +1,85 @@
+// Tests:
+//
+// - ✔ The remote answers pings and observers get the latency.
+// - ✔ A remote that doesn't answer causes PeerEvent::HeartbeatTimeout and closes the connection.
+// - ✔ Heartbeat::missed(0) doesn't close the connection before the first ping.
+//
+mod common;
+
+use common::*                       ;
+use common::import::{ *, assert_eq };
+
+
+// The remote answers pings and observers get the latency.
+//
+#[async_std::test]
+//
+async fn latency()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+	let heartbeat        = Heartbeat::new( Duration::from_millis(10) ).missed(2);
+
+	let (_       , _       , server_handle) = peer_listen( server, Arc::new( add_show_sum() ), AsyncStd, "server" ).await;
+	let (mut peer, mut evts, client_handle) = config_peer( client, "client", 1024, |peer| peer.set_heartbeat( heartbeat ) ).await;
+
+	for _ in 0..3
+	{
+		match evts.next().await.unwrap()
+		{
+			PeerEvent::Latency( rtt ) => assert!( rtt < Duration::from_secs(5) ),
+			_                         => unreachable!( "Should be PeerEvent::Latency" ),
+		}
+	}
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	client_handle.await;
+	server_handle.await;
+}
+
+
+
+// A remote that doesn't answer causes PeerEvent::HeartbeatTimeout and closes the connection.
+//
+#[async_std::test]
+//
+async fn timeout()
+{
+	// Nobody reads on the other end, but keep it alive so the connection doesn't close.
+	//
+	let (_server, client) = Endpoint::pair( 1024, 1024 );
+	let heartbeat         = Heartbeat::new( Duration::from_millis(10) ).missed(2);
+
+	let (_, mut evts, handle) = config_peer( client, "client", 1024, |peer| peer.set_heartbeat( heartbeat ) ).await;
+
+	assert_eq!( PeerEvent::HeartbeatTimeout, evts.next().await.unwrap() );
+	assert_eq!( PeerEvent::ClosedByRemote  , evts.next().await.unwrap() );
+
+	handle.await;
+}
+
+
+
+// Heartbeat::missed(0) doesn't close the connection before the first ping. It counts as 1.
+//
+#[async_std::test]
+//
+async fn missed_zero()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+	let heartbeat        = Heartbeat::new( Duration::from_millis(10) ).missed(0);
+
+	let (_       , _       , server_handle) = peer_listen( server, Arc::new( add_show_sum() ), AsyncStd, "server" ).await;
+	let (mut peer, mut evts, client_handle) = config_peer( client, "client", 1024, |peer| peer.set_heartbeat( heartbeat ) ).await;
+
+	match evts.next().await.unwrap()
+	{
+		PeerEvent::Latency(_) => {}
+		e                     => unreachable!( "Should be PeerEvent::Latency, got: {:?}", e ),
+	}
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	client_handle.await;
+	server_handle.await;
+}
@@ -0,0 +1,167 @@
+// Tests:
+//
+// - ✔ A call is described with its kind, the name of the service and the CBOR payload as JSON.
+// - ✔ ConnectionError frames are decoded.
+// - ✔ Payloads that aren't CBOR are given in hex.
+// - ✔ A byte stream of CborWF is decoded frame by frame, the last item being the error if there is one.
+// - ✔ The binary turns a capture into one JSON object per line, using an exported name table.
+//
+#![ cfg( feature = "inspect" ) ]
+
+mod common;
+
+use
+{
+	common     :: { import::{ *, assert_eq }    } ,
+	futures    :: { SinkExt, io::Cursor         } ,
+	serde_json :: { json                        } ,
+	std        :: { process::Command, fs        } ,
+};
+
+
+fn frame( sid: ServiceID, cid: u64, payload: &[u8] ) -> CborWF
+{
+	let mut wf = CborWF::with_capacity( payload.len() );
+
+	wf.set_sid( sid        );
+	wf.set_cid( cid.into() );
+	wf.write_all( payload ).expect( "write to wf" );
+
+	wf
+}
+
+
+
+// A call is described with its kind, the name of the service and the CBOR payload as JSON.
+//
+#[test]
+//
+fn inspect_call()
+{
+	let sid     = ServiceID::from( 0x5ca1ab1e );
+	let payload = serde_cbor::to_vec( &json!({ "a": 1, "b": [ "two" ] }) ).expect( "serialize" );
+
+	ServiceID::register_service( sid, "tests::Inspected" ).expect( "register" );
+
+	let value = inspect_frame( &frame( sid, 7, &payload ) );
+
+	assert_eq!( json!( "IncomingCall"                       ), value[ "kind"    ] );
+	assert_eq!( json!( format!( "{:032x}", 0x5ca1ab1e )     ), value[ "sid"     ] );
+	assert_eq!( json!( "tests::Inspected"                   ), value[ "service" ] );
+	assert_eq!( json!( 7                                    ), value[ "cid"     ] );
+	assert_eq!( json!({ "a": 1, "b": [ "two" ] }            ), value[ "payload" ] );
+}
+
+
+
+// ConnectionError frames are decoded.
+//
+#[test]
+//
+fn inspect_connection_error()
+{
+	let err     = ConnectionError::IncompatibleVersion{ version: 3 };
+	let payload = serde_cbor::to_vec( &err ).expect( "serialize" );
+	let value   = inspect_frame( &frame( ServiceID::null(), 0, &payload ) );
+
+	assert_eq!( json!( "ConnectionError"                       ), value[ "kind"    ] );
+	assert_eq!( json!( "thespis_remote::null"                  ), value[ "service" ] );
+	assert_eq!( json!({ "IncompatibleVersion": { "version": 3 } }), value[ "payload" ] );
+}
+
+
+
+// Payloads that aren't CBOR are given in hex.
+//
+#[test]
+//
+fn inspect_hex()
+{
+	let value = inspect_frame( &frame( ServiceID::from( 0xbad ), 0, &[ 0xff, 0x00 ] ) );
+
+	assert_eq!( json!( "IncomingSend" ), value[ "kind"    ] );
+	assert_eq!( json!( null           ), value[ "service" ] );
+	assert_eq!( json!( null           ), value[ "payload" ] );
+	assert_eq!( json!( "ff00"         ), value[ "hex"     ] );
+}
+
+
+
+// A byte stream of CborWF is decoded frame by frame, the last item being the error if there is one.
+//
+#[async_std::test]
+//
+async fn inspect_bytes()
+{
+	let mut wire    = Cursor::new( Vec::new() );
+	let mut encoder = Encoder::new( &mut wire, 1024 );
+
+	encoder.send( frame( ServiceID::from( 1 ), 0, &serde_cbor::to_vec( &5u8 ).expect( "serialize" ) ) ).await.expect( "encode" );
+	encoder.send( frame( ServiceID::from( 2 ), 3, &[]                                              ) ).await.expect( "encode" );
+
+	let mut wire = wire.into_inner();
+
+	let frames = inspect_stream( wire.clone(), 1024 );
+
+	assert_eq!( 2, frames.len() );
+	assert_eq!( json!( 5 ), frames[0].as_ref().expect( "a frame" )[ "payload" ] );
+	assert_eq!( json!( 3 ), frames[1].as_ref().expect( "a frame" )[ "cid"     ] );
+
+	// A length that is too big.
+	//
+	wire.extend_from_slice( &u64::MAX.to_le_bytes() );
+
+	let frames = inspect_stream( wire, 1024 );
+
+	assert_eq!( 3, frames.len() );
+	assert!( matches!( frames[2], Err( WireErr::MessageSizeExceeded{..} ) ) );
+}
+
+
+
+// The binary turns a capture into one JSON object per line, using an exported name table.
+//
+#[async_std::test]
+//
+async fn inspect_binary()
+{
+	let dir     = std::env::temp_dir().join( format!( "thespis_inspect_{}", std::process::id() ) );
+	let capture = dir.join( "peer.capture" );
+	let names   = dir.join( "names"        );
+
+	fs::create_dir_all( &dir ).expect( "create temp dir" );
+
+	let recorder = Recorder::new( fs::File::create( &capture ).expect( "create capture" ) ).expect( "create recorder" );
+	let ((incoming, outgoing), _other) = loopback::<CborWF>( 1024 );
+	let (_, mut outgoing) = recorder.tap( incoming, outgoing );
+
+	let sid = ServiceID::from( 0xfeed );
+	ServiceID::register_service( sid, "tests::Binary" ).expect( "register" );
+
+	outgoing.send( frame( sid, 0, &serde_cbor::to_vec( &"hello" ).expect( "serialize" ) ) ).await.expect( "send frame" );
+	recorder.flush().await;
+
+	drop( outgoing );
+	drop( recorder );
+
+	ServiceID::export_names( fs::File::create( &names ).expect( "create names" ) ).expect( "export names" );
+
+	let output = Command::new( env!( "CARGO_BIN_EXE_thespis_inspect" ) )
+
+		.arg( "--names" ).arg( &names )
+		.arg( &capture )
+		.output().expect( "run thespis_inspect" )
+	;
+
+	fs::remove_dir_all( &dir ).expect( "remove temp dir" );
+
+	assert!( output.status.success(), "{}", String::from_utf8_lossy( &output.stderr ) );
+
+	let stdout = String::from_utf8( output.stdout ).expect( "utf8" );
+	let lines: Vec<serde_json::Value> = stdout.lines().map( |l| serde_json::from_str( l ).expect( "json" ) ).collect();
+
+	assert_eq!( 1, lines.len() );
+	assert_eq!( json!( "Outgoing"      ), lines[0][ "direction" ] );
+	assert_eq!( json!( "tests::Binary" ), lines[0][ "service"   ] );
+	assert_eq!( json!( "hello"         ), lines[0][ "payload"   ] );
+}
@@ -0,0 +1,131 @@
+// Tests:
+//
+// - ✔ Peers on a loopback can call each other.
+// - ✔ Errors are the same as over the network: unknown services.
+// - ✔ The max size applies.
+// - ✔ Closing one end closes the other.
+//
+mod common;
+
+use common::{ *, import::{ *, assert_eq } };
+
+
+// Create a peer for one end of a loopback.
+//
+fn framed( end: ( LoopbackStream<CborWF>, LoopbackSink<CborWF> ), name: &str ) -> ( Peer, Mailbox<Peer>, WeakAddr<Peer> )
+{
+	let (incoming, outgoing) = end;
+
+	Peer::from_framed( name, incoming, outgoing, AsyncStd, None, None ).expect( "spawn peer" )
+}
+
+
+// The server provides Add and Show.
+//
+fn serve( peer: &mut Peer )
+{
+	peer.register_services( Arc::new( add_show_sum() ) );
+}
+
+
+
+// Peers on a loopback can call each other.
+//
+#[async_std::test]
+//
+async fn loopback_call()
+{
+	let (a, b) = loopback::<CborWF>( 1024 );
+
+	let (_       , _, server_handle) = start_peer( framed( a, "server" ), serve  ).await;
+	let (mut peer, _, client_handle) = start_peer( framed( b, "client" ), |_| {} ).await;
+
+	let mut addr = remotes::RemoteAddr::new( peer.clone() );
+
+	addr.send( Add(5) ).await.expect( "send Add" );
+	addr.send( Add(3) ).await.expect( "send Add" );
+
+	assert_eq!( 8, addr.call( Show ).await.expect( "call Show" ) );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	client_handle.await;
+	server_handle.await;
+}
+
+
+
+// Calling a service the remote doesn't provide gives the same error as over the network.
+//
+#[async_std::test]
+//
+async fn loopback_unknown_service()
+{
+	let (a, b) = loopback::<CborWF>( 1024 );
+
+	let (_       , _, server_handle) = start_peer( framed( a, "server" ), |_| {} ).await;
+	let (mut peer, _, client_handle) = start_peer( framed( b, "client" ), |_| {} ).await;
+
+	let mut addr = remotes::RemoteAddr::new( peer.clone() );
+
+	match addr.call( Show ).await
+	{
+		Err( PeerErr::Remote{ err: ConnectionError::UnknownService{ sid, .. }, .. } ) => assert_eq!( Some( <Show as remotes::Service>::sid() ), sid ),
+		e => unreachable!( "Should be ConnectionError::UnknownService, got: {:?}", e ),
+	}
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	client_handle.await;
+	server_handle.await;
+}
+
+
+
+// Messages bigger than the max size are refused.
+//
+#[async_std::test]
+//
+async fn loopback_max_size()
+{
+	let (a, b) = loopback::<CborWF>( 40 );
+
+	let (_       , _, server_handle) = start_peer( framed( a, "server" ), serve  ).await;
+	let (mut peer, _, client_handle) = start_peer( framed( b, "client" ), |_| {} ).await;
+
+	let mut wf = CborWF::with_capacity( 100 );
+	wf.set_sid( <Add as remotes::Service>::sid() );
+	wf.write_all( &[ 0; 100 ] ).expect( "write to wf" );
+
+	match peer.call( wf ).await.expect( "call peer" )
+	{
+		Err( PeerErr::WireFormat{ source: WireErr::MessageSizeExceeded{ max_size, .. }, .. } ) => assert_eq!( 40, max_size ),
+		e => unreachable!( "Should be WireErr::MessageSizeExceeded, got: {:?}", e ),
+	}
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	client_handle.await;
+	server_handle.await;
+}
+
+
+
+// Closing one end closes the other.
+//
+#[async_std::test]
+//
+async fn loopback_close()
+{
+	let (a, b) = loopback::<CborWF>( 1024 );
+
+	let (_       , mut server_evts, server_handle) = start_peer( framed( a, "server" ), serve  ).await;
+	let (mut peer, _            , client_handle) = start_peer( framed( b, "client" ), |_| {} ).await;
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	assert_eq!( PeerEvent::ClosedByRemote, server_evts.next().await.expect( "an event" ) );
+
+	client_handle.await;
+	server_handle.await;
+}
@@ -0,0 +1,131 @@
+// Tests:
+//
+// - ✔ Valid sends and calls get processed and answered.
+// - ✔ Calls to unknown services get a ConnectionError::UnknownService.
+// - ✔ Malformed payloads get a ConnectionError::Deserialize.
+// - ✔ Stray responses with unknown ConnIDs are dropped, the peer keeps working.
+// - ✔ Closing the mock remote closes the peer.
+//
+mod common;
+
+use common::{ *, import::{ *, assert_eq } };
+
+
+// Start a peer that provides Add and Show, connected to a mock remote.
+//
+async fn mock_remote() -> (MockRemote, Events<PeerEvent>, JoinHandle< MailboxEnd<Peer> >)
+{
+	let (mock, (incoming, outgoing)) = MockRemote::pair( 1024 );
+
+	let (mut peer, peer_mb, _) = Peer::from_framed( "server", incoming, outgoing, AsyncStd, None, None ).expect( "spawn peer" );
+
+	peer.register_services( Arc::new( add_show_sum() ) );
+
+	let evts   = peer.observe( ObserveConfig::default() ).await.expect( "pharos not closed" );
+	let handle = AsyncStd.spawn_handle( peer_mb.start(peer) ).expect( "start mailbox of Peer" );
+
+	(mock, evts, handle)
+}
+
+
+fn add () -> ServiceID { <Add  as remotes::Service>::sid() }
+fn show() -> ServiceID { <Show as remotes::Service>::sid() }
+
+
+
+// Valid sends and calls get processed and answered.
+//
+#[async_std::test]
+//
+async fn mock_valid()
+{
+	let (mut mock, _, handle) = mock_remote().await;
+
+	mock.send( add(), &Add(5) ).await.expect( "send Add" );
+	mock.send( add(), &Add(2) ).await.expect( "send Add" );
+
+	let cid = mock.call( show(), &Show ).await.expect( "call Show" );
+
+	assert_eq!( 7, mock.recv_response::<u64>( cid ).await );
+
+	mock.close().await.expect( "close mock" );
+	handle.await;
+}
+
+
+
+// Calls to unknown services get a ConnectionError::UnknownService.
+//
+#[async_std::test]
+//
+async fn mock_unknown_service()
+{
+	let (mut mock, _, handle) = mock_remote().await;
+
+	let sid = ServiceID::from( 0xdead_beef );
+	let cid = mock.call( sid, &Show ).await.expect( "call unknown service" );
+
+	assert_eq!( ConnectionError::UnknownService{ sid: Some( sid ), cid: Some( cid ) }, mock.recv_error().await );
+
+	mock.close().await.expect( "close mock" );
+	handle.await;
+}
+
+
+
+// Malformed payloads get a ConnectionError::Deserialize.
+//
+#[async_std::test]
+//
+async fn mock_malformed()
+{
+	let (mut mock, _, handle) = mock_remote().await;
+
+	let cid = ConnID::from( 9 );
+
+	mock.send_raw( MockRemote::<CborWF>::frame( show(), cid, &[ 0xff, 0xff, 0xff ] ) ).await.expect( "send malformed" );
+
+	assert_eq!( ConnectionError::Deserialize{ sid: Some( show() ), cid: Some( cid ) }, mock.recv_error().await );
+
+	mock.close().await.expect( "close mock" );
+	handle.await;
+}
+
+
+
+// Stray responses with unknown ConnIDs are dropped, the peer keeps working.
+//
+#[async_std::test]
+//
+async fn mock_stray_response()
+{
+	let (mut mock, _, handle) = mock_remote().await;
+
+	let stray = MockRemote::<CborWF>::message( ServiceID::full(), &5u64, ConnID::from( 12345 ) ).expect( "serialize" );
+
+	mock.send_raw( stray ).await.expect( "send stray response" );
+
+	let cid = mock.call( show(), &Show ).await.expect( "call Show" );
+
+	assert_eq!( 0, mock.recv_response::<u64>( cid ).await );
+
+	mock.close().await.expect( "close mock" );
+	handle.await;
+}
+
+
+
+// Closing the mock remote closes the peer.
+//
+#[async_std::test]
+//
+async fn mock_close()
+{
+	let (mock, mut evts, handle) = mock_remote().await;
+
+	mock.close().await.expect( "close mock" );
+
+	assert_eq!( PeerEvent::ClosedByRemote, evts.next().await.expect( "an event" ) );
+
+	handle.await;
+}
@@ -0,0 +1,342 @@
+// Tests:
+//
+// - ✔ Buffered sends are delivered after reconnecting and RemoteAddr keeps working.
+// - ✔ Outstanding calls are replayed with ReplayPolicy::Replay.
+// - ✔ Outstanding calls fail with ReplayPolicy::Fail.
+// - ✔ Replayed calls tell the remote their deadline.
+// - ✔ Replayed calls that don't fit in the buffer fail.
+// - ✔ The peer closes when all attempts fail.
+//
+mod common;
+
+use
+{
+	common        :: { *, import::{ *, assert_eq }                                         } ,
+	std           :: { io, sync::atomic::{ AtomicUsize, Ordering }                         } ,
+	futures       :: { channel::mpsc::{ unbounded, UnboundedSender }, lock::Mutex, SinkExt } ,
+	futures_timer :: { Delay                                                               } ,
+};
+
+
+// Answers Show with the number of calls received so far, after a delay.
+//
+#[ derive(Actor) ] struct Slow{ calls: Arc<AtomicUsize> }
+
+impl Handler<Show> for Slow
+{
+	fn handle( &mut self, _msg: Show ) -> Return<'_, i64> { async move
+	{
+		let calls = self.calls.fetch_add( 1, Ordering::SeqCst ) + 1;
+
+		Delay::new( Duration::from_millis(50) ).await;
+
+		calls as i64
+
+	}.boxed() }
+}
+
+
+service_map!
+(
+	namespace  : reconnect ;
+	wire_format: CborWF    ;
+	services   : Show      ;
+);
+
+
+
+// Create a client peer that will reconnect with endpoints sent on the returned channel.
+//
+async fn client( socket: Endpoint, policy: ReplayPolicy, buffer: usize ) -> (WeakAddr<Peer>, Events<PeerEvent>, UnboundedSender<Endpoint>)
+{
+	let (tx, rx) = unbounded::<Endpoint>();
+	let rx       = Arc::new( Mutex::new( rx ) );
+
+	let connect = move ||
+	{
+		let rx = rx.clone();
+
+		async move
+		{
+			rx.lock().await.next().await.ok_or_else( || io::Error::from( io::ErrorKind::NotConnected ) )
+		}
+	};
+
+	let reconnect = Reconnect::new( connect, 1024, 1024 )
+
+		.delay ( Duration::from_millis(10) )
+		.policy( policy                    )
+		.buffer( buffer                    )
+	;
+
+	let (mut peer, peer_mb, peer_addr) = CborWF::create_peer( "client", socket, 1024, 1024, AsyncStd, None, None ).expect( "spawn peer" );
+
+	peer.set_reconnect( reconnect );
+
+	let evts = peer.observe( ObserveConfig::default() ).await.expect( "pharos not closed" );
+
+	AsyncStd.spawn( peer_mb.start(peer).map(|_|()) ).expect( "start mailbox of Peer" );
+
+	(peer_addr, evts, tx)
+}
+
+
+
+// Buffered sends are delivered after reconnecting and RemoteAddr keeps working.
+//
+#[async_std::test]
+//
+async fn buffered_send()
+{
+	let (server, socket) = Endpoint::pair( 64, 64 );
+
+	let sm = Arc::new( add_show_sum() );
+
+	let (mut server_addr, _, _handle1) = peer_listen( server, sm.clone(), AsyncStd, "server1" ).await;
+	let (mut peer, mut evts, tx)       = client( socket, ReplayPolicy::Replay, 64 ).await;
+	let mut addr                       = remotes::RemoteAddr::new( peer.clone() );
+
+	addr.call( Add(1) ).await.expect( "call Add" );
+	assert_eq!( 1, addr.call( Show ).await.expect( "call Show" ) );
+
+	server_addr.send( CloseConnection{ remote: false, reason: "Connection lost.".to_string() } ).await.expect( "close server" );
+	assert_eq!( PeerEvent::Disconnected, evts.next().await.unwrap() );
+
+	// Buffered while disconnected.
+	//
+	addr.send( Add(5) ).await.expect( "send Add" );
+
+	let (server, socket) = Endpoint::pair( 64, 64 );
+	let (_, _, _handle2) = peer_listen( server, sm, AsyncStd, "server2" ).await;
+
+	tx.unbounded_send( socket ).expect( "provide new connection" );
+	assert_eq!( PeerEvent::Reconnected, evts.next().await.unwrap() );
+
+	assert_eq!( 6, addr.call( Show ).await.expect( "call Show" ) );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+}
+
+
+
+// Start a call on a Slow service and lose the connection while the server is processing it.
+//
+async fn lose_call( policy: ReplayPolicy ) -> (Result<i64, PeerErr>, Arc<AtomicUsize>, WeakAddr<Peer>)
+{
+	let calls = Arc::new( AtomicUsize::new(0) );
+	let slow  = Addr::builder( "slow" ).spawn( Slow{ calls: calls.clone() }, &AsyncStd ).expect( "spawn actor mailbox" );
+
+	let mut sm = reconnect::Services::new();
+	sm.register_handler::<Show>( slow.clone_box() );
+	let sm = Arc::new( sm );
+
+	let (server, socket) = Endpoint::pair( 64, 64 );
+
+	let (mut server_addr, _, _handle1) = peer_listen( server, sm.clone(), AsyncStd, "server1" ).await;
+	let (peer, mut evts, tx)           = client( socket, policy, 64 ).await;
+	let mut addr                       = reconnect::RemoteAddr::new( peer.clone() );
+
+	let call = AsyncStd.spawn_handle( async move { addr.call( Show ).await } ).expect( "spawn call" );
+
+	// Wait for the server to start processing the call.
+	//
+	while calls.load( Ordering::SeqCst ) == 0
+	{
+		Delay::new( Duration::from_millis(1) ).await;
+	}
+
+	server_addr.send( CloseConnection{ remote: false, reason: "Connection lost.".to_string() } ).await.expect( "close server" );
+	assert_eq!( PeerEvent::Disconnected, evts.next().await.unwrap() );
+
+	let (server, socket) = Endpoint::pair( 64, 64 );
+	let (_, _, _handle2) = peer_listen( server, sm, AsyncStd, "server2" ).await;
+
+	tx.unbounded_send( socket ).expect( "provide new connection" );
+	assert_eq!( PeerEvent::Reconnected, evts.next().await.unwrap() );
+
+	(call.await, calls, peer)
+}
+
+
+
+// Outstanding calls are replayed with ReplayPolicy::Replay.
+//
+#[async_std::test]
+//
+async fn replay()
+{
+	let (resp, calls, mut peer) = lose_call( ReplayPolicy::Replay ).await;
+
+	// The first server never got to answer, the second one processed the replayed call.
+	//
+	assert_eq!( 2, resp.expect( "replayed call" ) );
+	assert_eq!( 2, calls.load( Ordering::SeqCst ) );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+}
+
+
+
+// Outstanding calls fail with ReplayPolicy::Fail.
+//
+#[async_std::test]
+//
+async fn fail()
+{
+	let (resp, calls, mut peer) = lose_call( ReplayPolicy::Fail ).await;
+
+	assert!(matches!( resp, Err( PeerErr::ConnectionClosed{..} ) ));
+	assert_eq!( 1, calls.load( Ordering::SeqCst ) );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+}
+
+
+
+// Replayed calls tell the remote their deadline. When it has passed, the new server doesn't process the call.
+//
+#[async_std::test]
+//
+async fn replay_deadline()
+{
+	let calls = Arc::new( AtomicUsize::new(0) );
+	let slow  = Addr::builder( "slow" ).spawn( Slow{ calls: calls.clone() }, &AsyncStd ).expect( "spawn actor mailbox" );
+
+	let mut sm = reconnect::Services::new();
+	sm.register_handler::<Show>( slow.clone_box() );
+	let sm = Arc::new( sm );
+
+	let (server, socket) = Endpoint::pair( 64, 64 );
+
+	let (mut server_addr, _, _handle1) = peer_listen( server, sm.clone(), AsyncStd, "server1" ).await;
+	let (mut peer, mut evts, tx)       = client( socket, ReplayPolicy::Replay, 64 ).await;
+	let mut addr                       = reconnect::RemoteAddr::new( peer.clone() );
+	let timeout                        = Duration::from_millis(100);
+
+	let call = AsyncStd.spawn_handle( async move { addr.call_with_timeout( Show, timeout ).await } ).expect( "spawn call" );
+
+	while calls.load( Ordering::SeqCst ) == 0
+	{
+		Delay::new( Duration::from_millis(1) ).await;
+	}
+
+	server_addr.send( CloseConnection{ remote: false, reason: "Connection lost.".to_string() } ).await.expect( "close server" );
+	assert_eq!( PeerEvent::Disconnected, evts.next().await.unwrap() );
+
+	assert!(matches!( call.await, Err( PeerErr::Timeout{..} ) ));
+
+	let (server, socket) = Endpoint::pair( 64, 64 );
+	let (_, _, _handle2) = peer_listen( server, sm, AsyncStd, "server2" ).await;
+
+	tx.unbounded_send( socket ).expect( "provide new connection" );
+	assert_eq!( PeerEvent::Reconnected, evts.next().await.unwrap() );
+
+	// Give the second server the time to process the replayed call.
+	//
+	Delay::new( Duration::from_millis(100) ).await;
+
+	assert_eq!( 1, calls.load( Ordering::SeqCst ) );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+}
+
+
+
+// Replayed calls that don't fit in the buffer fail. The oldest ones are replayed.
+//
+#[async_std::test]
+//
+async fn replay_buffer_full()
+{
+	let calls = Arc::new( AtomicUsize::new(0) );
+	let slow  = Addr::builder( "slow" ).spawn( Slow{ calls: calls.clone() }, &AsyncStd ).expect( "spawn actor mailbox" );
+
+	let mut sm = reconnect::Services::new();
+	sm.register_handler::<Show>( slow.clone_box() );
+	let sm = Arc::new( sm );
+
+	let (server, socket) = Endpoint::pair( 64, 64 );
+
+	let (mut server_addr, _, _handle1) = peer_listen( server, sm.clone(), AsyncStd, "server1" ).await;
+	let (mut peer, mut evts, tx)       = client( socket, ReplayPolicy::Replay, 1 ).await;
+	let mut addr                       = reconnect::RemoteAddr::new( peer.clone() );
+	let mut addr2                      = addr.clone();
+
+	let first = AsyncStd.spawn_handle( async move { addr.call( Show ).await } ).expect( "spawn call" );
+
+	while calls.load( Ordering::SeqCst ) == 0
+	{
+		Delay::new( Duration::from_millis(1) ).await;
+	}
+
+	// The server is still processing the first call, so this one waits in the mailbox of Slow.
+	//
+	let second = AsyncStd.spawn_handle( async move { addr2.call( Show ).await } ).expect( "spawn call" );
+
+	Delay::new( Duration::from_millis(10) ).await;
+
+	server_addr.send( CloseConnection{ remote: false, reason: "Connection lost.".to_string() } ).await.expect( "close server" );
+	assert_eq!( PeerEvent::Disconnected, evts.next().await.unwrap() );
+
+	assert!(matches!( second.await, Err( PeerErr::ConnectionClosed{..} ) ));
+
+	let (server, socket) = Endpoint::pair( 64, 64 );
+	let (_, _, _handle2) = peer_listen( server, sm, AsyncStd, "server2" ).await;
+
+	tx.unbounded_send( socket ).expect( "provide new connection" );
+	assert_eq!( PeerEvent::Reconnected, evts.next().await.unwrap() );
+
+	assert!( first.await.is_ok() );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+}
+
+
+
+// The peer closes when all attempts fail.
+//
+#[async_std::test]
+//
+async fn attempts_exhausted()
+{
+	let (server, socket) = Endpoint::pair( 64, 64 );
+
+	let (mut server_addr, _, _handle) = peer_listen( server, Arc::new( add_show_sum() ), AsyncStd, "server" ).await;
+
+	let connect = ||
+	{
+		async { Err::<Endpoint, _>( io::Error::from( io::ErrorKind::ConnectionRefused ) ) }
+	};
+
+	let reconnect = Reconnect::new( connect, 1024, 1024 )
+
+		.delay   ( Duration::from_millis(1) )
+		.attempts( Some(2)                  )
+	;
+
+	let (mut peer, peer_mb, peer_addr) = CborWF::create_peer( "client", socket, 1024, 1024, AsyncStd, None, None ).expect( "spawn peer" );
+
+	peer.set_reconnect( reconnect );
+
+	let mut evts = peer.observe( ObserveConfig::default() ).await.expect( "pharos not closed" );
+
+	let handle = AsyncStd.spawn_handle( peer_mb.start(peer) ).expect( "start mailbox of Peer" );
+
+	server_addr.send( CloseConnection{ remote: false, reason: "Connection lost.".to_string() } ).await.expect( "close server" );
+
+	assert_eq!( PeerEvent::Disconnected, evts.next().await.unwrap() );
+
+	for _ in 0..2
+	{
+		match evts.next().await.unwrap()
+		{
+			PeerEvent::Error( PeerErr::WireFormat{ source: WireErr::Io{ kind: io::ErrorKind::ConnectionRefused }, .. } ) => {}
+			_ => unreachable!( "Should be PeerEvent::Error( PeerErr::WireFormat )" ),
+		}
+	}
+
+	assert_eq!( PeerEvent::ClosedByRemote, evts.next().await.unwrap() );
+
+	drop( peer_addr );
+	handle.await;
+}
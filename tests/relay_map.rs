@@ -112,8 +112,8 @@ async fn debug()
 	let txt = format!
 ("RelayMap, handler: ServiceHandler: Address: id: {}, name: \"relay_to_consumer\", services:
 {{
-	sid: 0x6440cfd17c374646
-	sid: 0xcdd3781867767588
+	sid: 0x0f2653e8cbaaf486d6acae336b4533d8
+	sid: 0xfd262d69df9a3ac3e2f10b58340b38c3
 }}",
 &id,
 );
@@ -6,7 +6,8 @@
 // - ✔ Test clone.
 // - ✔ Test Debug.
 // - Test ServiceID::Debug
-// - Test adding services at runtime.
+// - ✔ Test adding and removing services at runtime.
+// - ✔ A collision of service names is reported by every service map, not only the first.
 //
 
 
@@ -14,6 +15,7 @@ mod common;
 
 use common::*                                  ;
 use common::import::{ *, assert_eq, assert_ne };
+use futures::channel::oneshot                  ;
 
 mod a
 {
@@ -47,6 +49,18 @@ mod b
 	);
 }
 
+mod c
+{
+	use crate::*;
+
+	service_map!
+	(
+		namespace  : collide     ;
+		wire_format: CborWF ;
+		services   : Add         ;
+	);
+}
+
 
 // Verify that the same service, in a different namespace has different service id.
 //
@@ -122,9 +136,9 @@ async fn debug()
 	let txt = format!
 ("remotes::Services
 {{
-	Add  - sid: 0x6440cfd17c374646 - handler: id({}), name(for_debug)
-	Sub  - sid: 0xfb47ad8f2083c565 - handler: none
-	Show - sid: 0xcdd3781867767588 - handler: id({}), name(for_debug)
+	Add  - sid: 0x0f2653e8cbaaf486d6acae336b4533d8 - handler: id({}), name(for_debug)
+	Sub  - sid: 0x96eae60e53c7cd98a5ea404c31454ae9 - handler: none
+	Show - sid: 0xfd262d69df9a3ac3e2f10b58340b38c3 - handler: id({}), name(for_debug)
 }}",
 &id,
 &id,
@@ -147,7 +161,108 @@ async fn debug_service_id()
 
 	use remotes::Service;
 
-	assert_eq!( "ServiceID: remotes::Add (0x6440cfd17c374646)", format!( "{:?}", Add::sid() ) );
+	assert_eq!( "ServiceID: remotes::Add (0x0f2653e8cbaaf486d6acae336b4533d8)", format!( "{:?}", Add::sid() ) );
 }
 
 
+
+
+
+// Test adding and removing services at runtime.
+//
+#[async_std::test]
+//
+async fn add_remove_services()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+
+	let (added_tx  , added_rx  ) = oneshot::channel::<()>();
+	let (called_tx , called_rx ) = oneshot::channel::<()>();
+	let (removed_tx, removed_rx) = oneshot::channel::<()>();
+
+	let nodea = async move
+	{
+		// Start without exposing anything.
+		//
+		let (mut peera, mut evts, handle) = peer_listen( server, Arc::new( remotes::Services::new() ), AsyncStd, "nodea" ).await;
+
+		let sm: Arc<dyn ServiceMap> = Arc::new( add_show_sum() );
+
+		peera.send( AddServices{ sm: sm.clone() } ).await.expect( "send AddServices" );
+
+		match evts.next().await.unwrap()
+		{
+			PeerEvent::ServicesAdded( sids ) => assert_eq!( sids.len(), 2 ),
+			_                                => unreachable!( "Should be PeerEvent::ServicesAdded" ),
+		}
+
+		added_tx.send(()).expect( "signal added" );
+		called_rx.await.expect( "wait for call" );
+
+		// A different instance of a service map doesn't remove anything.
+		//
+		peera.send( RemoveServices{ sm: Arc::new( add_show_sum() ) } ).await.expect( "send RemoveServices" );
+		assert_eq!( PeerEvent::ServicesRemoved( Vec::new() ), evts.next().await.unwrap() );
+
+		peera.send( RemoveServices{ sm } ).await.expect( "send RemoveServices" );
+
+		match evts.next().await.unwrap()
+		{
+			PeerEvent::ServicesRemoved( sids ) => assert_eq!( sids.len(), 2 ),
+			_                                  => unreachable!( "Should be PeerEvent::ServicesRemoved" ),
+		}
+
+		removed_tx.send(()).expect( "signal removed" );
+
+		match evts.next().await.unwrap()
+		{
+			PeerEvent::Error( PeerErr::UnknownService{..} ) => {}
+			_ => unreachable!( "Should be PeerEvent::Error( PeerErr::UnknownService )" ),
+		}
+
+		handle.await;
+	};
+
+
+	let nodeb = async move
+	{
+		let (mut peera, _peera_evts) = peer_connect( client, AsyncStd, "nodeb_to_nodea" ).await;
+		let mut addr                 = remotes::RemoteAddr::new( peera.clone() );
+
+		added_rx.await.expect( "wait for services to be added" );
+
+		addr.call( Add(3) ).await.expect( "call Add" );
+		assert_eq!( 3, addr.call( Show ).await.expect( "call Show" ) );
+
+		called_tx.send(()).expect( "signal called" );
+		removed_rx.await.expect( "wait for services to be removed" );
+
+		match addr.call( Show ).await
+		{
+			Err( PeerErr::Remote{ err: ConnectionError::UnknownService{..}, .. } ) => {}
+			_ => unreachable!( "Should be ConnectionError::UnknownService" ),
+		}
+
+		peera.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+	};
+
+	join( nodea, nodeb ).await;
+}
+
+
+
+// The names are only registered by the first service map, but a collision is reported by every one of them.
+//
+#[test]
+//
+fn collision_every_time()
+{
+	let sid = <Add as c::collide::Service>::sid();
+
+	assert_eq!( Ok(()), ServiceID::register_service( sid, "tests::Other" ) );
+
+	let expect = WireErr::ServiceIDCollision{ sid, name: "collide::Add", registered: "tests::Other" };
+
+	assert_eq!( Some( expect.clone() ), c::collide::Services::try_new().err() );
+	assert_eq!( Some( expect         ), c::collide::Services::try_new().err() );
+}
@@ -0,0 +1,241 @@
+// Tests:
+//
+// - ✔ A streaming service delivers all items in order, followed by the end of the stream.
+// - ✔ An empty stream ends right away.
+// - ✔ Dropping the stream stops the remote from producing items.
+// - ✔ The remote stops producing items when the caller doesn't take them.
+// - ✔ A stream without end of stream frame times out and the call is canceled.
+//
+mod common;
+
+use
+{
+	common        :: { *, import::{ *, assert_eq }                             } ,
+	std           :: { time::Duration, sync::atomic::{ AtomicUsize, Ordering } } ,
+	futures_timer :: { Delay                                                   } ,
+	futures       :: { stream::{ self, BoxStream }                             } ,
+	serde         :: { Serialize, Deserialize                                  } ,
+};
+
+
+// Ask for the first n numbers, or for an endless stream if None.
+//
+#[ derive( Serialize, Deserialize, Debug ) ] pub struct Count( pub Option<usize> );
+
+impl Message for Count { type Return = BoxStream<'static, usize>; }
+
+
+// Sends one item and then nothing, without ever ending.
+//
+#[ derive( Serialize, Deserialize, Debug ) ] pub struct Hang;
+
+impl Message for Hang { type Return = BoxStream<'static, usize>; }
+
+
+#[ derive(Actor) ] struct Counter{ produced: Arc<AtomicUsize> }
+
+impl Handler<Count> for Counter
+{
+	#[async_fn] fn handle( &mut self, msg: Count ) -> BoxStream<'static, usize>
+	{
+		match msg.0
+		{
+			Some(n) =>
+			{
+				let produced = self.produced.clone();
+
+				stream::iter( 0..n ).inspect( move |_| { produced.fetch_add( 1, Ordering::SeqCst ); } ).boxed()
+			}
+
+			None =>
+			{
+				let produced = self.produced.clone();
+
+				stream::iter( 0.. ).then( move |i|
+				{
+					produced.fetch_add( 1, Ordering::SeqCst );
+
+					Delay::new( Duration::from_millis(1) ).map( move |_| i )
+
+				}).boxed()
+			}
+		}
+	}
+}
+
+impl Handler<Hang> for Counter
+{
+	#[async_fn] fn handle( &mut self, _msg: Hang ) -> BoxStream<'static, usize>
+	{
+		let produced = self.produced.clone();
+
+		stream::once( async { 0 } ).chain( stream::pending() ).inspect( move |_|
+		{
+			produced.fetch_add( 1, Ordering::SeqCst );
+
+		}).boxed()
+	}
+}
+
+
+
+service_map!
+(
+	namespace  : streams ;
+	wire_format: CborWF  ;
+	services   : Add     ;
+	streams    : Count, Hang ;
+);
+
+
+
+// Connect to a server that has a Counter.
+//
+async fn connect() -> (streams::RemoteAddr, WeakAddr<Peer>, Arc<AtomicUsize>, JoinHandle< MailboxEnd<Peer> >)
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+
+	let produced = Arc::new( AtomicUsize::new(0) );
+	let counter  = Addr::builder( "counter" ).spawn( Counter{ produced: produced.clone() }, &AsyncStd ).expect( "spawn actor mailbox" );
+
+	let mut sm = streams::Services::new();
+	sm.register_stream_handler::<Count>( counter.clone_box() );
+	sm.register_stream_handler::<Hang >( counter.clone_box() );
+
+	let (_, _, handle) = peer_listen( server, Arc::new( sm ), AsyncStd, "server" ).await;
+	let (peer, _)      = peer_connect( client, AsyncStd, "client" ).await;
+
+	(streams::RemoteAddr::new( peer.clone() ), peer, produced, handle)
+}
+
+
+
+// A streaming service delivers all items in order, followed by the end of the stream.
+// An empty stream ends right away.
+//
+#[async_std::test]
+//
+async fn stream_items()
+{
+	let (mut addr, mut peer, _, handle) = connect().await;
+
+	let items: Vec<usize> = addr.call_stream( Count( Some(5) ) ).await.expect( "call Count" )
+
+		.map( |item| item.expect( "stream item" ) )
+		.collect().await
+	;
+
+	assert_eq!( vec![ 0, 1, 2, 3, 4 ], items );
+
+	let mut empty = addr.call_stream( Count( Some(0) ) ).await.expect( "call Count" );
+
+	assert!( empty.next().await.is_none() );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	handle.await;
+}
+
+
+
+// Dropping the stream stops the remote from producing items.
+//
+#[async_std::test]
+//
+async fn drop_stream()
+{
+	let (mut addr, mut peer, produced, handle) = connect().await;
+
+	let mut items = addr.call_stream( Count( None ) ).await.expect( "call Count" );
+
+	assert_eq!( 0, items.next().await.unwrap().expect( "stream item" ) );
+	assert_eq!( 1, items.next().await.unwrap().expect( "stream item" ) );
+
+	drop( items );
+
+	// Give the cancellation time to get through.
+	//
+	Delay::new( Duration::from_millis(50) ).await;
+
+	let stopped = produced.load( Ordering::SeqCst );
+
+	Delay::new( Duration::from_millis(50) ).await;
+
+	assert_eq!( stopped, produced.load( Ordering::SeqCst ) );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	handle.await;
+}
+
+
+
+// The remote stops producing items when the caller doesn't take them.
+//
+#[async_std::test]
+//
+async fn stream_credit()
+{
+	let (mut addr, mut peer, produced, handle) = connect().await;
+
+	let total     = 10 * STREAM_CREDIT;
+	let mut items = addr.call_stream( Count( Some( total ) ) ).await.expect( "call Count" );
+
+	assert_eq!( 0, items.next().await.unwrap().expect( "stream item" ) );
+
+	// Once the credit is used up and the buffers between the peers are full, the producer waits.
+	//
+	Delay::new( Duration::from_millis(300) ).await;
+
+	assert!( produced.load( Ordering::SeqCst ) < total );
+
+	// Taking items gives the credit back.
+	//
+	let rest: Vec<usize> = items.map( |item| item.expect( "stream item" ) ).collect().await;
+
+	assert_eq!( ( 1..total ).collect::<Vec<_>>(), rest );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	handle.await;
+}
+
+
+
+// A stream without end of stream frame times out and the call is canceled.
+//
+#[async_std::test]
+//
+async fn stream_timeout()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+
+	let produced = Arc::new( AtomicUsize::new(0) );
+	let counter  = Addr::builder( "counter" ).spawn( Counter{ produced: produced.clone() }, &AsyncStd ).expect( "spawn actor mailbox" );
+
+	let mut sm = streams::Services::new();
+	sm.register_stream_handler::<Hang>( counter.clone_box() );
+
+	let (_, _, handle) = peer_listen( server, Arc::new( sm ), AsyncStd, "server" ).await;
+
+	let (mut client, client_mb, client_addr) = CborWF::create_peer( "client", client, 1024, 1024, AsyncStd, None, None ).expect( "spawn peer" );
+
+	client.set_timeout( Duration::from_millis(100) );
+
+	let client_handle = AsyncStd.spawn_handle( client_mb.start(client) ).expect( "start mailbox of Peer" );
+
+	let mut addr  = streams::RemoteAddr::new( client_addr.clone() );
+	let mut items = addr.call_stream( Hang ).await.expect( "call Hang" );
+
+	assert_eq!( 0, items.next().await.unwrap().expect( "stream item" ) );
+
+	assert!( matches!( items.next().await, Some( Err( PeerErr::Timeout{..} ) ) ) );
+	assert!( items.next().await.is_none() );
+
+	let mut client_addr = client_addr;
+
+	client_addr.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	client_handle.await;
+	handle.await;
+}
@@ -0,0 +1,184 @@
+// Tests:
+//
+// - ✔ Mutually authenticated peers can call each other and see the certificate of the remote.
+// - ✔ The handshake fails if the certificate of the server is not trusted.
+// - ✔ The handshake fails if the server requires a client certificate and the client has none.
+//
+#![ cfg( feature = "tls" ) ]
+
+mod common;
+
+use
+{
+	common :: { *, import::{ *, assert_eq }                        } ,
+	rcgen  :: { CertificateParams, KeyPair, IsCa, BasicConstraints } ,
+
+	thespis_remote::tls::rustls ::
+	{
+		ClientConfig, ServerConfig, RootCertStore                                    ,
+		server    :: { WebPkiClientVerifier                                        } ,
+		pki_types :: { CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName } ,
+	},
+};
+
+
+// A certificate authority and a certificate for "server" and "client" signed by it.
+//
+struct Pki
+{
+	ca    : CertificateDer<'static>,
+	server: ( CertificateDer<'static>, PrivateKeyDer<'static> ),
+	client: ( CertificateDer<'static>, PrivateKeyDer<'static> ),
+}
+
+
+impl Pki
+{
+	fn new() -> Self
+	{
+		let ca_key     = KeyPair::generate().expect( "generate key" );
+		let mut params = CertificateParams::new( Vec::<String>::new() ).expect( "ca params" );
+		params.is_ca   = IsCa::Ca( BasicConstraints::Unconstrained );
+
+		let ca = params.self_signed( &ca_key ).expect( "self sign ca" );
+
+		let leaf = |name: &str|
+		{
+			let key  = KeyPair::generate().expect( "generate key" );
+			let cert = CertificateParams::new( vec![ name.to_string() ] ).expect( "leaf params" )
+
+				.signed_by( &key, &ca, &ca_key ).expect( "sign leaf" )
+			;
+
+			( cert.der().clone(), PrivateKeyDer::Pkcs8( PrivatePkcs8KeyDer::from( key.serialize_der() ) ) )
+		};
+
+		Self { server: leaf( "server" ), client: leaf( "client" ), ca: ca.der().clone() }
+	}
+
+
+	fn roots( &self ) -> Arc<RootCertStore>
+	{
+		let mut roots = RootCertStore::empty();
+		roots.add( self.ca.clone() ).expect( "add ca" );
+
+		Arc::new( roots )
+	}
+
+
+	// A server that requires a client certificate signed by our CA.
+	//
+	fn server( &self ) -> Tls
+	{
+		let verifier = WebPkiClientVerifier::builder( self.roots() ).build().expect( "client verifier" );
+
+		let config = ServerConfig::builder()
+
+			.with_client_cert_verifier( verifier )
+			.with_single_cert( vec![ self.server.0.clone() ], self.server.1.clone_key() )
+			.expect( "server config" )
+		;
+
+		Tls::Server{ config: Arc::new( config ) }
+	}
+
+
+	// A client that trusts `roots` and presents our client certificate if `auth` is true.
+	//
+	fn client( &self, roots: Arc<RootCertStore>, auth: bool ) -> Tls
+	{
+		let builder = ClientConfig::builder().with_root_certificates( roots );
+
+		let config = match auth
+		{
+			true  => builder.with_client_auth_cert( vec![ self.client.0.clone() ], self.client.1.clone_key() ).expect( "client config" ),
+			false => builder.with_no_client_auth(),
+		};
+
+		Tls::Client{ config: Arc::new( config ), server_name: ServerName::try_from( "server" ).expect( "server name" ) }
+	}
+}
+
+
+
+// Mutually authenticated peers can call each other and see the certificate of the remote.
+//
+#[async_std::test]
+//
+async fn mutual_auth()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+	let pki              = Pki::new();
+
+	let (server, client) = join
+	(
+		pki.server(                    ).create_peer::<CborWF>( "server", server, 1024, 1024, AsyncStd, None, None ),
+		pki.client( pki.roots(), true  ).create_peer::<CborWF>( "client", client, 1024, 1024, AsyncStd, None, None ),
+	).await;
+
+	let (mut server, server_mb, _        ) = server.expect( "server handshake" );
+	let (    client, client_mb, mut peer ) = client.expect( "client handshake" );
+
+	assert_eq!( &pki.client.0, server.identity().expect( "client identity" ).end_entity() );
+	assert_eq!( &pki.server.0, client.identity().expect( "server identity" ).end_entity() );
+
+	server.register_services( Arc::new( add_show_sum() ) );
+
+	let server_handle = AsyncStd.spawn_handle( server_mb.start( server ) ).expect( "start server" );
+	let client_handle = AsyncStd.spawn_handle( client_mb.start( client ) ).expect( "start client" );
+
+	let mut addr = remotes::RemoteAddr::new( peer.clone() );
+
+	addr.call( Add(5) ).await.expect( "call Add" );
+
+	assert_eq!( 5, addr.call( Show ).await.expect( "call Show" ) );
+
+	peer.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	client_handle.await;
+	server_handle.await;
+}
+
+
+
+// The handshake fails if the certificate of the server is not trusted.
+//
+#[async_std::test]
+//
+async fn untrusted_server()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+	let pki              = Pki::new();
+	let other            = Pki::new();
+
+	let (server, client) = join
+	(
+		pki.server(                      ).create_peer::<CborWF>( "server", server, 1024, 1024, AsyncStd, None, None ),
+		pki.client( other.roots(), true  ).create_peer::<CborWF>( "client", client, 1024, 1024, AsyncStd, None, None ),
+	).await;
+
+	assert!( matches!( server, Err( PeerErr::Tls{..} ) ) );
+	assert!( matches!( client, Err( PeerErr::Tls{..} ) ) );
+}
+
+
+
+// The handshake fails if the server requires a client certificate and the client has none.
+//
+#[async_std::test]
+//
+async fn missing_client_cert()
+{
+	let (server, client) = Endpoint::pair( 64, 64 );
+	let pki              = Pki::new();
+
+	let (server, _client) = join
+	(
+		pki.server(                    ).create_peer::<CborWF>( "server", server, 1024, 1024, AsyncStd, None, None ),
+		pki.client( pki.roots(), false ).create_peer::<CborWF>( "client", client, 1024, 1024, AsyncStd, None, None ),
+	).await;
+
+	// With TLS 1.3 the client considers the handshake done before the server verified it.
+	//
+	assert!( matches!( server, Err( PeerErr::Tls{..} ) ) );
+}
@@ -0,0 +1,118 @@
+// Tests:
+//
+// - ✔ Peers using VarintWF can call each other.
+// - ✔ Messages are fragmented to frames that fit the max size, even though the header size varies.
+//
+mod common;
+
+use
+{
+	common :: { *, import::{ *, assert_eq } } ,
+	serde  :: { Serialize, Deserialize      } ,
+};
+
+
+#[ derive( Serialize, Deserialize, Debug ) ] pub struct Upload( String );
+
+impl Message for Upload { type Return = usize; }
+
+
+#[ derive(Actor) ] struct Store;
+
+impl Handler<Upload> for Store
+{
+	#[async_fn] fn handle( &mut self, msg: Upload ) -> usize
+	{
+		msg.0.len()
+	}
+}
+
+
+mod varint
+{
+	use super::*;
+
+	service_map!
+	(
+		namespace  : remotes           ;
+		wire_format: VarintWF          ;
+		services   : Add, Show, Upload ;
+	);
+}
+
+
+
+// Start a server with a VarintWF peer that provides Add, Show and Upload and fragments messages bigger than
+// 1024 bytes.
+//
+async fn server( socket: Endpoint ) -> JoinHandle< MailboxEnd<Peer<VarintWF>> >
+{
+	let sum   = Addr::builder( "sum"   ).spawn( Sum(5), &AsyncStd ).expect( "spawn actor mailbox" );
+	let store = Addr::builder( "store" ).spawn( Store , &AsyncStd ).expect( "spawn actor mailbox" );
+
+	let mut sm = varint::remotes::Services::new();
+	sm.register_handler::<Add   >( sum.clone_box()   );
+	sm.register_handler::<Show  >( sum.clone_box()   );
+	sm.register_handler::<Upload>( store.clone_box() );
+
+	let (mut peer, peer_mb, _) = VarintWF::create_peer( "server", socket, 1024, 1024, AsyncStd, None, None ).expect( "spawn peer" );
+
+	peer.register_services( Arc::new( sm ) );
+	peer.set_fragmentation( Fragmentation::new( 1024, 64 * 1024 ) );
+
+	AsyncStd.spawn_handle( peer_mb.start(peer) ).expect( "start mailbox of Peer" )
+}
+
+
+
+// Peers using VarintWF can call each other.
+//
+#[async_std::test]
+//
+async fn varint_wf_peers()
+{
+	let (server_sock, client_sock) = Endpoint::pair( 64, 64 );
+
+	let handle = server( server_sock ).await;
+
+	let (peer, peer_mb, mut peer_addr) = VarintWF::create_peer( "client", client_sock, 1024, 1024, AsyncStd, None, None ).expect( "spawn peer" );
+	let _client = AsyncStd.spawn_handle( peer_mb.start(peer) ).expect( "start mailbox of Peer" );
+
+	let mut addr = varint::remotes::RemoteAddr::new( peer_addr.clone() );
+
+	addr.send( Add(2) ).await.expect( "send Add" );
+
+	assert_eq!( 7, addr.call( Show ).await.expect( "call Show" ) );
+
+	peer_addr.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	handle.await;
+}
+
+
+
+// Fragments are as big as the max frame size allows. With a varint header, the header is bigger for
+// big cids and lengths, and the fragments must still fit.
+//
+#[async_std::test]
+//
+async fn varint_wf_fragments()
+{
+	let (server_sock, client_sock) = Endpoint::pair( 64, 64 );
+	let fragmentation              = Fragmentation::new( 1024, 64 * 1024 );
+
+	let handle = server( server_sock ).await;
+
+	let (mut peer, peer_mb, mut peer_addr) = VarintWF::create_peer( "client", client_sock, 1024, 1024, AsyncStd, None, None ).expect( "spawn peer" );
+	peer.set_fragmentation( fragmentation );
+
+	let _client = AsyncStd.spawn_handle( peer_mb.start(peer) ).expect( "start mailbox of Peer" );
+
+	let mut addr = varint::remotes::RemoteAddr::new( peer_addr.clone() );
+
+	assert_eq!( 10_000, addr.call( Upload( "x".repeat( 10_000 ) ) ).await.expect( "call Upload" ) );
+
+	peer_addr.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	handle.await;
+}
@@ -0,0 +1,130 @@
+// Tests:
+//
+// - ✔ Peers can call each other over a WebSocket, one frame per WebSocket message.
+// - ✔ The max size applies to incoming WebSocket messages.
+//
+mod common;
+
+use
+{
+	common            :: { *, import::{ *, assert_eq }                 } ,
+	futures           :: { future::ready, Sink, SinkExt, Stream        } ,
+	async_std         :: { net::{ TcpListener, TcpStream }             } ,
+	async_tungstenite :: { WebSocketStream, accept_async, client_async } ,
+	async_tungstenite :: { tungstenite::{ Message, Error as WsError }  } ,
+};
+
+
+// Binary WebSocket messages as a Sink/Stream of bytes. The stream ends on the first error, other kinds of
+// messages are skipped.
+//
+fn messages( ws: WebSocketStream<TcpStream> )
+
+	-> impl Sink< Vec<u8>, Error = WsError > + Stream< Item = Vec<u8> > + Unpin + Send + 'static
+{
+	ws
+		.with      ( |data: Vec<u8>| ready( Ok::<_, WsError>( Message::Binary( data ) ) ) )
+		.take_while( |msg| ready( msg.is_ok() ) )
+		.filter_map( |msg| ready( match msg
+		{
+			Ok( Message::Binary( data ) ) => Some( data ),
+			_                             => None,
+		}))
+}
+
+
+// Accept a WebSocket connection on the loopback interface and create a peer for it that provides
+// Add and Show.
+//
+async fn server() -> (SocketAddr, JoinHandle< (Events<PeerEvent>, JoinHandle< MailboxEnd<Peer> >) >)
+{
+	let listener = TcpListener::bind( "127.0.0.1:0" ).await.expect( "bind listener" );
+	let local    = listener.local_addr().expect( "local address" );
+
+	let handle = AsyncStd.spawn_handle( async move
+	{
+		let (tcp, _) = listener.accept().await.expect( "accept connection" );
+		let ws       = accept_async( tcp ).await.expect( "WebSocket handshake" );
+
+		let (mut peer, peer_mb, _) = CborWF::create_peer_messages( "server", messages( ws ), 1024, 1024, AsyncStd, None, None ).expect( "spawn peer" );
+
+		let evts = peer.observe( ObserveConfig::default() ).await.expect( "pharos not closed" );
+
+		peer.register_services( Arc::new( add_show_sum() ) );
+
+		let handle = AsyncStd.spawn_handle( peer_mb.start(peer) ).expect( "start mailbox of Peer" );
+
+		(evts, handle)
+
+	}).expect( "spawn server" );
+
+	(local, handle)
+}
+
+
+// Connect to the server over a WebSocket.
+//
+async fn connect( addr: SocketAddr ) -> WebSocketStream<TcpStream>
+{
+	let tcp     = TcpStream::connect( addr ).await.expect( "connect" );
+	let (ws, _) = client_async( format!( "ws://{addr}" ), tcp ).await.expect( "WebSocket handshake" );
+
+	ws
+}
+
+
+
+// Peers can call each other over a WebSocket.
+//
+#[async_std::test]
+//
+async fn ws_peers()
+{
+	let (addr, server) = server().await;
+
+	let ws = connect( addr ).await;
+
+	let (peer, peer_mb, mut peer_addr) = CborWF::create_peer_messages( "client", messages( ws ), 1024, 1024, AsyncStd, None, None ).expect( "spawn peer" );
+	let client = AsyncStd.spawn_handle( peer_mb.start(peer) ).expect( "start mailbox of Peer" );
+
+	let mut remote = remotes::RemoteAddr::new( peer_addr.clone() );
+
+	remote.send( Add(5) ).await.expect( "send Add" );
+
+	assert_eq!( 5, remote.call( Show ).await.expect( "call Show" ) );
+
+	peer_addr.send( CloseConnection{ remote: false, reason: "Program end.".to_string() } ).await.expect( "close connection" );
+
+	let (_, server) = server.await;
+
+	client.await;
+	server.await;
+}
+
+
+
+// The max size applies to incoming WebSocket messages.
+//
+#[async_std::test]
+//
+async fn ws_max_size()
+{
+	let (addr, server) = server().await;
+
+	let mut ws = connect( addr ).await;
+
+	// Header and a payload that is too big.
+	//
+	let mut data = vec![ 0u8; 24 ];
+	data.extend_from_slice( &[ 0; 2000 ] );
+
+	ws.send( Message::Binary( data ) ).await.expect( "send message" );
+
+	let (mut evts, _server) = server.await;
+
+	match evts.next().await.expect( "an event" )
+	{
+		PeerEvent::Error( PeerErr::WireFormat{ source: WireErr::MessageSizeExceeded{ max_size, .. }, .. } ) => assert_eq!( 1024, max_size ),
+		e => unreachable!( "Should be WireErr::MessageSizeExceeded, got: {:?}", e ),
+	}
+}